@@ -1,9 +1,33 @@
-use super::parse::CallDef;
+use super::parse::{CallDef, CallReceiver};
+use crate::utils::type_to_string;
 use quote::quote;
 
 /// See the `fn call` docs at the `lib.rs` of this crate for a high level definition.
 pub fn expand_call(def: CallDef) -> proc_macro2::TokenStream {
-	let CallDef { pallet_struct, methods } = def;
+	let CallDef { pallet_type, generics, methods } = def;
+	let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
+
+	// The generic parameter of the pallet config, usually `T`. Parsing checks there is one.
+	let config = &generics.type_params().next().expect("checked by parsing").ident;
+	// These are the generic parameters of `Call`, which uses them in a hidden variant so that they
+	// are all used, even when no argument does.
+	let type_params = generics.type_params().map(|param| &param.ident).collect::<Vec<_>>();
+	// These are the generics `Call` is declared with. The parameters after the config, like the
+	// instance parameter `I`, default to `()`, so that the calls of the default instance of a pallet
+	// are written `Call<T>`, like the pallet.
+	let mut call_generics = generics.clone();
+	call_generics.where_clause = None;
+	for param in call_generics.type_params_mut().skip(1) {
+		if param.default.is_none() {
+			param.eq_token = Some(Default::default());
+			param.default = Some(syn::parse_quote!(()));
+		}
+	}
+	// These are the bounds of the `where` clause of the `impl` block, which every implementation on
+	// `Call` repeats.
+	let predicates = where_clause
+		.map(|clause| clause.predicates.iter().collect::<Vec<_>>())
+		.unwrap_or_default();
 
 	// This is a vector of all the callable function names.
 	let fn_name = methods.iter().map(|method| &method.name).collect::<Vec<_>>();
@@ -23,6 +47,74 @@ pub fn expand_call(def: CallDef) -> proc_macro2::TokenStream {
 		.map(|method| method.args.iter().map(|(_, type_)| type_.clone()).collect::<Vec<_>>())
 		.collect::<Vec<_>>();
 
+	// This is a vector of the expressions calling each of the functions in `fn_name`, with all of
+	// its arguments. Note that we assume the first argument of every call, after what the function
+	// is called on, is the `caller`. The result of the function is converted into a
+	// `DispatchResultWithPostInfo`, so functions can return either kind of result.
+	let fn_call = methods
+		.iter()
+		.zip(&args_name)
+		.map(|(method, args_name)| {
+			let name = &method.name;
+			match method.receiver {
+				// The function is called on the pallet, which the runtime gives us through `AsMut`.
+				CallReceiver::Pallet => quote! {
+					AsMut::<#pallet_type>::as_mut(runtime).#name(caller, #( #args_name ),*)
+				},
+				// The function is called with the whole runtime.
+				CallReceiver::Runtime => quote! {
+					<#pallet_type>::#name(runtime, caller, #( #args_name ),*)
+				},
+			}
+		})
+		.collect::<Vec<_>>();
+
+	// This is a vector of the weight of each of the functions in `fn_name`. Functions without a
+	// `#[weight(..)]` attribute use the default weight defined in `support`.
+	let weight = methods
+		.iter()
+		.map(|method| match &method.weight {
+			Some(weight) => quote!(#weight),
+			None => quote!(crate::support::DEFAULT_WEIGHT),
+		})
+		.collect::<Vec<_>>();
+
+	// This is a vector of the index of each of the functions in `fn_name`, as given by
+	// `#[call_index(..)]`, which is how calls are told apart once encoded.
+	let fn_index = methods.iter().map(|method| method.index).collect::<Vec<_>>();
+
+	// This is a nested vector of the types of the arguments written as strings, and a vector of the
+	// doc comments of each of the functions in `fn_name`, which describe the calls in the metadata.
+	let args_type_name = methods
+		.iter()
+		.map(|method| method.args.iter().map(|(_, type_)| type_to_string(type_)).collect::<Vec<_>>())
+		.collect::<Vec<_>>();
+	let fn_docs = methods.iter().map(|method| &method.docs).collect::<Vec<_>>();
+
+	// The buffer calls are encoded to. It is hygienic, so it cannot clash with the arguments of the
+	// functions, which are bound by the same name.
+	let dest = syn::Ident::new("dest", proc_macro2::Span::mixed_site());
+
+	// This is a flat vector of all the argument types, used to bound the trait implementations of
+	// `Call` on exactly the types it contains.
+	let all_args_type = args_type.iter().flatten().collect::<Vec<_>>();
+	let bounded = |bound: proc_macro2::TokenStream| {
+		quote!(where #( #predicates, )* #( #all_args_type: #bound, )*)
+	};
+	let clone_bounds = bounded(quote!(Clone));
+	let debug_bounds = bounded(quote!(core::fmt::Debug));
+	let encode_bounds = bounded(quote!(crate::codec::Encode));
+	let decode_bounds = bounded(quote!(crate::codec::Decode));
+	let eq_bounds = bounded(quote!(PartialEq));
+	let full_eq_bounds = bounded(quote!(Eq));
+
+	// The arguments of `other` when comparing two calls, so they do not clash with the arguments of
+	// `self`.
+	let other_args_name = args_name
+		.iter()
+		.map(|names| names.iter().map(|name| quote::format_ident!("other_{}", name)).collect())
+		.collect::<Vec<Vec<_>>>();
+
 	// This quote block creates an `enum Call` which contains all the calls exposed by our pallet,
 	// and the `Dispatch` trait logic to route a `caller` to access those functions.
 	let dispatch_impl = quote! {
@@ -30,31 +122,158 @@ pub fn expand_call(def: CallDef) -> proc_macro2::TokenStream {
 		//
 		// The parsed function names will be `snake_case`, and that will show up in the enum.
 		#[allow(non_camel_case_types)]
-		pub enum Call<T: Config> {
+		pub enum Call #call_generics #where_clause {
 			#(
 				#fn_name { #( #args_name: #args_type),* },
 			)*
+			#[doc(hidden)]
+			__Ignore(core::marker::PhantomData<( #( #type_params, )* )>, core::convert::Infallible),
 		}
 
 		// Dispatch logic at the pallet level, mapping each of the items in the `Call` enum to the
 		// appropriate function call with all arguments, including the `caller`.
-		impl<T: Config> crate::support::Dispatch for #pallet_struct<T> {
-			type Caller = T::AccountId;
-			type Call = Call<T>;
+		//
+		// Calls are dispatched with access to the whole runtime `T`, which is how functions taking
+		// `runtime: &mut T` can reach other pallets.
+		impl #impl_generics Call #type_generics #where_clause {
+			pub fn dispatch(
+				self,
+				runtime: &mut #config,
+				caller: <#config as crate::system::Config>::AccountId,
+			) -> crate::support::DispatchResultWithPostInfo
+			where
+				#config: AsMut<#pallet_type>,
+			{
+				match self {
+					#(
+						Call::#fn_name { #( #args_name ),* } => {
+							#fn_call.map(Into::into).map_err(Into::into)
+						},
+					)*
+					Call::__Ignore(_, never) => match never {},
+				}
+			}
+		}
 
-			fn dispatch(&mut self, caller: Self::Caller, call: Self::Call) -> crate::support::DispatchResult {
-				match call {
+		impl #impl_generics Call #type_generics #where_clause {
+			/// The name of the callable function this call is for.
+			pub fn call_name(&self) -> &'static str {
+				match self {
+					#(
+						Call::#fn_name { .. } => stringify!(#fn_name),
+					)*
+					Call::__Ignore(_, never) => match *never {},
+				}
+			}
+
+			/// The description of each call, in the order of their index.
+			pub fn metadata() -> Vec<crate::metadata::CallMetadata> {
+				vec![
+					#(
+						crate::metadata::CallMetadata {
+							name: stringify!(#fn_name),
+							index: #fn_index,
+							args: vec![
+								#(
+									crate::metadata::ArgMetadata {
+										name: stringify!(#args_name),
+										ty: #args_type_name,
+									}
+								),*
+							],
+							docs: vec![ #( #fn_docs ),* ],
+						}
+					),*
+				]
+			}
+		}
+
+		// The weight of each call, as declared with `#[weight(..)]` on the callable functions. The
+		// arguments of the call are in scope, by reference, so the weight can depend on them.
+		impl #impl_generics crate::support::GetDispatchInfo for Call #type_generics #where_clause {
+			#[allow(unused_variables)]
+			fn weight(&self) -> crate::support::Weight {
+				match self {
+					#(
+						Call::#fn_name { #( #args_name ),* } => #weight,
+					)*
+					Call::__Ignore(_, never) => match *never {},
+				}
+			}
+		}
+
+		// `Clone` is implemented by hand, because `#[derive(Clone)]` would require `T: Clone`, while
+		// we only need the types of the arguments to be `Clone`.
+		impl #impl_generics Clone for Call #type_generics #clone_bounds {
+			fn clone(&self) -> Self {
+				match self {
+					#(
+						Call::#fn_name { #( #args_name ),* } => Call::#fn_name {
+							#( #args_name: #args_name.clone() ),*
+						},
+					)*
+					Call::__Ignore(_, never) => match *never {},
+				}
+			}
+		}
+
+		// `Debug` is implemented by hand, for the same reason as `Clone`.
+		impl #impl_generics core::fmt::Debug for Call #type_generics #debug_bounds {
+			fn fmt(&self, formatter: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+				match self {
+					#(
+						Call::#fn_name { #( #args_name ),* } => formatter
+							.debug_struct(stringify!(#fn_name))
+							#( .field(stringify!(#args_name), #args_name) )*
+							.finish(),
+					)*
+					Call::__Ignore(_, never) => match *never {},
+				}
+			}
+		}
+
+		// `PartialEq` and `Eq` are implemented by hand, for the same reason as `Clone`.
+		impl #impl_generics PartialEq for Call #type_generics #eq_bounds {
+			fn eq(&self, other: &Self) -> bool {
+				match (self, other) {
+					#(
+						(
+							Call::#fn_name { #( #args_name ),* },
+							Call::#fn_name { #( #args_name: #other_args_name ),* },
+						) => true #( && #args_name == #other_args_name )*,
+					)*
+					_ => false,
+				}
+			}
+		}
+
+		impl #impl_generics Eq for Call #type_generics #full_eq_bounds {}
+
+		// Calls are encoded as the index of the function, followed by its arguments in order.
+		impl #impl_generics crate::codec::Encode for Call #type_generics #encode_bounds {
+			fn encode_to(&self, #dest: &mut Vec<u8>) {
+				match self {
 					#(
 						Call::#fn_name { #( #args_name ),* } => {
-							self.#fn_name(
-								// Note that we assume the first argument of every call is the `caller`.
-								caller,
-								#( #args_name ),*
-							)?;
+							crate::codec::Encode::encode_to(&#fn_index, #dest);
+							#( crate::codec::Encode::encode_to(#args_name, #dest); )*
 						},
 					)*
+					Call::__Ignore(_, never) => match *never {},
+				}
+			}
+		}
+
+		impl #impl_generics crate::codec::Decode for Call #type_generics #decode_bounds {
+			fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+				match <u8 as crate::codec::Decode>::decode(input)? {
+					#(
+						#fn_index => Ok(Call::#fn_name {
+							#( #args_name: crate::codec::Decode::decode(input)? ),*
+						}),
+					)*
+					_ => Err("invalid call index"),
 				}
-				Ok(())
 			}
 		}
 	};
@@ -1,19 +1,16 @@
-use quote::ToTokens;
+use crate::utils::get_doc_literals;
 use syn::spanned::Spanned;
 
-// Custom keywords we match to when parsing the calls in a pallet.
-mod keyword {
-	syn::custom_keyword!(T);
-	syn::custom_keyword!(AccountId);
-}
-
 /// This object will collect all the information we need to keep while parsing the callable
 /// functions.
 #[derive(Debug)]
 pub struct CallDef {
-	/// This is the name of the pallet struct where the callable functions are implemented. We
-	/// mostly assume it is `Pallet`.
-	pub pallet_struct: syn::Ident,
+	/// This is the type of the pallet struct where the callable functions are implemented, like
+	/// `Pallet<T>` or `Pallet<T, I>`.
+	pub pallet_type: syn::Type,
+	/// The generics of the `impl` block, usually `<T: Config>`, including its `where` clause. The
+	/// `Call` enum has the same generics.
+	pub generics: syn::Generics,
 	/// This is a list of the callable functions exposed by this pallet. See `CallVariantDef`.
 	pub methods: Vec<CallVariantDef>,
 }
@@ -23,12 +20,37 @@ pub struct CallDef {
 pub struct CallVariantDef {
 	/// The function name.
 	pub name: syn::Ident,
-	/// Information on args of the function: `(name, type)`.
+	/// The index of the function, as given by `#[call_index(..)]`. Encoded calls start with it.
+	pub index: u8,
+	/// What the function is called on. See `CallReceiver`.
+	pub receiver: CallReceiver,
+	/// Information on args of the function: `(name, type)`. Arguments which are destructured are
+	/// named after their position, like `arg1`.
 	pub args: Vec<(syn::Ident, Box<syn::Type>)>,
+	/// The weight of the function, as given by `#[weight(..)]`. `None` when the attribute is not
+	/// used, in which case the default weight applies.
+	pub weight: Option<syn::Expr>,
+	/// The doc comments of the function, one line per item.
+	pub docs: Vec<String>,
+}
+
+/// The first argument of a callable function, which determines what the function is called on.
+#[derive(Debug)]
+pub enum CallReceiver {
+	/// The function takes some variant of `self`, and only has access to its own pallet.
+	Pallet,
+	/// The function takes `runtime: &mut T`, where `T` is the first generic parameter of the pallet,
+	/// and has access to every pallet in the runtime. This is how pallets which depend on other
+	/// pallets are written.
+	Runtime,
 }
 
 impl CallDef {
-	pub fn try_from(item: syn::Item) -> syn::Result<Self> {
+	/// Parse the callable functions of a pallet.
+	///
+	/// Helper attributes like `#[weight(..)]` are removed from `item` while parsing, since they
+	/// are not real attributes and would not compile otherwise.
+	pub fn try_from(item: &mut syn::Item) -> syn::Result<Self> {
 		// First we check that we are parsing an `impl`.
 		let item_impl = if let syn::Item::Impl(item) = item {
 			item
@@ -36,59 +58,83 @@ impl CallDef {
 			return Err(syn::Error::new(item.span(), "Invalid pallet::call, expected item impl"))
 		};
 
-		// Extract the name of the struct. We mostly assume it is `Pallet`, but we can handle it
+		// Extract the type of the struct. We mostly assume it is `Pallet<T>`, but we can handle it
 		// when it isn't.
-		let pallet_struct = match &*item_impl.self_ty {
-			syn::Type::Path(tp) => tp.path.segments.first().unwrap().ident.clone(),
-			_ => panic!("not supported tokens"),
+		let pallet_type = match &*item_impl.self_ty {
+			syn::Type::Path(type_path) if type_path.qself.is_none() => *item_impl.self_ty.clone(),
+			ty => {
+				let msg = "Invalid pallet::call, expected the pallet struct like `Pallet<T>`";
+				return Err(syn::Error::new(ty.span(), msg))
+			},
+		};
+
+		// The generic parameter of the pallet config, usually `T`, is the runtime the calls are
+		// dispatched on.
+		let generics = item_impl.generics.clone();
+		let config = match generics.type_params().next() {
+			Some(param) => param.ident.clone(),
+			None => {
+				let msg =
+					"Invalid pallet::call, expected a generic parameter like `impl<T: Config>`";
+				return Err(syn::Error::new(item_impl.generics.span(), msg))
+			},
 		};
 
 		// Here is where we will store all the callable functions.
 		let mut methods = vec![];
-		for item in item_impl.items {
+		for item in item_impl.items.iter_mut() {
 			if let syn::ImplItem::Fn(method) = item {
 				// Here is where we will store all the args for each callable functions.
 				let mut args = vec![];
 
-				// First argument should be some variant of `self`.
-				match method.sig.inputs.first() {
-					Some(syn::FnArg::Receiver(_)) => {},
-					_ => {
-						let msg = "Invalid call, first argument must be a variant of self";
-						return Err(syn::Error::new(method.sig.span(), msg))
-					},
-				}
-
-				// The second argument should be the `caller: T::AccountId` argument.
-				match method.sig.inputs.iter().skip(1).next() {
+				// First argument should be some variant of `self`, or `runtime: &mut T`.
+				let receiver = match method.sig.inputs.first() {
+					Some(syn::FnArg::Receiver(_)) => CallReceiver::Pallet,
 					Some(syn::FnArg::Typed(arg)) => {
-						// Here we specifically check that this argument is as we expect for
-						// `caller: T::AccountId`.
-						check_caller_arg(arg)?;
+						check_runtime_arg(arg, &config)?;
+						CallReceiver::Runtime
 					},
 					_ => {
-						let msg = "Invalid call, second argument should be `caller: T::AccountId`";
+						let msg = "Invalid call, first argument must be a variant of self, or \
+							`runtime: &mut T`";
 						return Err(syn::Error::new(method.sig.span(), msg))
 					},
+				};
+
+				// The second argument should be the caller, like `caller: T::AccountId`. Its type
+				// is checked by the compiler when the call is dispatched.
+				if method.sig.inputs.len() < 2 {
+					let msg = "Invalid call, second argument should be `caller: T::AccountId`";
+					return Err(syn::Error::new(method.sig.span(), msg))
+				}
+
+				// Calls are dispatched as a `DispatchResult` or a `DispatchResultWithPostInfo`,
+				// which the result of the function is converted into.
+				if let syn::ReturnType::Default = method.sig.output {
+					let msg = "Invalid call, expected a return type like `DispatchResult`";
+					return Err(syn::Error::new(method.sig.span(), msg))
 				}
 
 				let fn_name = method.sig.ident.clone();
+				let weight = take_weight_attr(method)?;
+				let index = take_call_index_attr(method)?;
+				let docs = get_doc_literals(&method.attrs);
 
 				// Parsing the rest of the args. Skipping 2 for `self` and `caller`.
-				for arg in method.sig.inputs.iter().skip(2) {
+				for (position, arg) in method.sig.inputs.iter().enumerate().skip(2) {
 					// All arguments should be typed.
 					let arg = if let syn::FnArg::Typed(arg) = arg {
 						arg
 					} else {
-						unreachable!("All args should be typed.");
+						let msg = "Invalid pallet::call, only the first argument can be `self`";
+						return Err(syn::Error::new(arg.span(), msg))
 					};
 
-					// Extract the name of the argument.
-					let arg_ident = if let syn::Pat::Ident(pat) = &*arg.pat {
-						pat.ident.clone()
-					} else {
-						let msg = "Invalid pallet::call, argument must be ident";
-						return Err(syn::Error::new(arg.pat.span(), msg))
+					// Extract the name of the argument, ignoring `mut`. Arguments which are
+					// destructured are named after their position.
+					let arg_ident = match &*arg.pat {
+						syn::Pat::Ident(pat) => pat.ident.clone(),
+						pat => quote::format_ident!("arg{}", position - 1, span = pat.span()),
 					};
 
 					// Store the argument name and the argument type for generating code.
@@ -96,46 +142,222 @@ impl CallDef {
 				}
 
 				// Store all the function name and the arg data for the function.
-				methods.push(CallVariantDef { name: fn_name, args });
+				methods.push(CallVariantDef { name: fn_name, index, receiver, args, weight, docs });
+			}
+		}
+
+		// The indices of the functions must be exactly `0..methods.len()`, so that no index is used
+		// twice and none is skipped. We keep the functions ordered by their index.
+		methods.sort_by_key(|method| method.index);
+		for (expected, method) in methods.iter().enumerate() {
+			if usize::from(method.index) < expected {
+				let msg = format!("Invalid call, the call index {} is used twice", method.index);
+				return Err(syn::Error::new(method.name.span(), msg))
+			}
+			if usize::from(method.index) > expected {
+				let msg = format!("Invalid call, the call index {} is missing", expected);
+				return Err(syn::Error::new(method.name.span(), msg))
 			}
 		}
 
 		// Return all callable functions for this pallet.
-		Ok(Self { pallet_struct, methods })
+		Ok(Self { pallet_type, generics, methods })
 	}
 }
 
-/// Check caller arg is exactly: `caller: T::AccountId`.
+/// Remove the `#[weight(..)]` attribute from a callable function, and return its expression.
 ///
-/// This is kept strict to keep the code simple.
-pub fn check_caller_arg(arg: &syn::PatType) -> syn::Result<()> {
-	pub struct CheckDispatchableFirstArg;
-	impl syn::parse::Parse for CheckDispatchableFirstArg {
-		fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
-			input.parse::<keyword::T>()?;
-			input.parse::<syn::Token![::]>()?;
-			input.parse::<keyword::AccountId>()?;
-			Ok(Self)
+/// Only a single `#[weight(..)]` attribute is allowed per function.
+fn take_weight_attr(method: &mut syn::ImplItemFn) -> syn::Result<Option<syn::Expr>> {
+	let mut weight = None;
+	let mut result = Ok(());
+	method.attrs.retain(|attr| {
+		if !attr.path().is_ident("weight") {
+			return true
 		}
-	}
+		if weight.is_some() {
+			let msg = "Invalid call, only one `#[weight(..)]` attribute is allowed";
+			result = Err(syn::Error::new(attr.span(), msg));
+		}
+		match attr.parse_args::<syn::Expr>() {
+			Ok(expr) => weight = Some(expr),
+			Err(e) => result = Err(e),
+		}
+		false
+	});
+	result.map(|_| weight)
+}
 
-	// This checks the arg name is `caller` or `_caller`.
-	if let syn::Pat::Ident(ident) = &*arg.pat {
-		// We also support the name as `_caller` for when the variable is unused.
-		if &ident.ident != "caller" && &ident.ident != "_caller" {
-			let msg = "Invalid name for second parameter: expected `caller: T::AccountId`";
-			return Err(syn::Error::new(ident.span(), msg))
+/// Remove the `#[call_index(..)]` attribute from a callable function, and return its index.
+///
+/// Every callable function must have exactly one `#[call_index(..)]` attribute, so that reordering
+/// the functions does not change how their calls are encoded.
+fn take_call_index_attr(method: &mut syn::ImplItemFn) -> syn::Result<u8> {
+	let mut index = None;
+	let mut result = Ok(());
+	method.attrs.retain(|attr| {
+		if !attr.path().is_ident("call_index") {
+			return true
+		}
+		if index.is_some() {
+			let msg = "Invalid call, only one `#[call_index(..)]` attribute is allowed";
+			result = Err(syn::Error::new(attr.span(), msg));
 		}
+		match attr.parse_args::<syn::LitInt>().and_then(|lit| lit.base10_parse::<u8>()) {
+			Ok(value) => index = Some(value),
+			Err(e) => result = Err(e),
+		}
+		false
+	});
+	result?;
+	index.ok_or_else(|| {
+		let msg = "Invalid call, missing `#[call_index(..)]` attribute";
+		syn::Error::new(method.sig.ident.span(), msg)
+	})
+}
+
+/// Check runtime arg is like `runtime: &mut T`, where `T` is the generic parameter of the pallet
+/// config, given in `config`.
+pub fn check_runtime_arg(arg: &syn::PatType, config: &syn::Ident) -> syn::Result<()> {
+	let msg = format!(
+		"Invalid first parameter: expected a variant of self, or `runtime: &mut {}`",
+		config
+	);
+
+	// This checks the arg is bound to a name, like `runtime`.
+	if !matches!(&*arg.pat, syn::Pat::Ident(_)) {
+		return Err(syn::Error::new(arg.pat.span(), msg))
+	}
+
+	// This checks the type is `&mut T`.
+	let elem = match &*arg.ty {
+		syn::Type::Reference(reference) if reference.mutability.is_some() => &reference.elem,
+		ty => return Err(syn::Error::new(ty.span(), msg)),
+	};
+	match &**elem {
+		syn::Type::Path(path) if path.qself.is_none() && path.path.is_ident(config) => Ok(()),
+		ty => Err(syn::Error::new(ty.span(), msg)),
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::CallDef;
+
+	fn parse_error(mut item: syn::Item) -> String {
+		CallDef::try_from(&mut item).unwrap_err().to_string()
+	}
+
+	#[test]
+	fn call_indices_are_checked() {
+		let mut item: syn::Item = syn::parse_quote! {
+			impl<T: Config> Pallet<T> {
+				#[call_index(1)]
+				pub fn b(&mut self, caller: T::AccountId) -> DispatchResult { Ok(()) }
+				#[call_index(0)]
+				#[weight(10)]
+				pub fn a(&mut self, caller: T::AccountId, amount: u32) -> DispatchResult { Ok(()) }
+			}
+		};
+		let def = CallDef::try_from(&mut item).unwrap();
+		let methods = def.methods.iter().map(|m| (m.name.to_string(), m.index)).collect::<Vec<_>>();
+		assert_eq!(methods, vec![("a".to_string(), 0), ("b".to_string(), 1)]);
+		// The helper attributes are removed.
+		assert!(!quote::quote!(#item).to_string().contains("call_index"));
+
+		assert_eq!(
+			parse_error(syn::parse_quote! {
+				impl<T: Config> Pallet<T> {
+					pub fn a(&mut self, caller: T::AccountId) -> DispatchResult { Ok(()) }
+				}
+			}),
+			"Invalid call, missing `#[call_index(..)]` attribute"
+		);
+		assert_eq!(
+			parse_error(syn::parse_quote! {
+				impl<T: Config> Pallet<T> {
+					#[call_index(0)]
+					pub fn a(&mut self, caller: T::AccountId) -> DispatchResult { Ok(()) }
+					#[call_index(0)]
+					pub fn b(&mut self, caller: T::AccountId) -> DispatchResult { Ok(()) }
+				}
+			}),
+			"Invalid call, the call index 0 is used twice"
+		);
+		assert_eq!(
+			parse_error(syn::parse_quote! {
+				impl<T: Config> Pallet<T> {
+					#[call_index(0)]
+					pub fn a(&mut self, caller: T::AccountId) -> DispatchResult { Ok(()) }
+					#[call_index(2)]
+					pub fn b(&mut self, caller: T::AccountId) -> DispatchResult { Ok(()) }
+				}
+			}),
+			"Invalid call, the call index 1 is missing"
+		);
+		assert_eq!(
+			parse_error(syn::parse_quote! {
+				impl<T: Config> Pallet<T> {
+					#[call_index(256)]
+					pub fn a(&mut self, caller: T::AccountId) -> DispatchResult { Ok(()) }
+				}
+			}),
+			"number too large to fit in target type"
+		);
 	}
 
-	// This checks the type is `T::AccountId` with `CheckDispatchableFirstArg`
-	let ty = &arg.ty;
-	syn::parse2::<CheckDispatchableFirstArg>(ty.to_token_stream()).map_err(|e| {
-		let msg = "Invalid type for second parameter: expected `caller: T::AccountId`";
-		let mut err = syn::Error::new(ty.span(), msg);
-		err.combine(e);
-		err
-	})?;
+	#[test]
+	fn signatures_are_relaxed() {
+		let mut item: syn::Item = syn::parse_quote! {
+			impl<T: Config<I>, I: 'static> Pallet<T, I>
+			where
+				T::Balance: Copy,
+			{
+				#[call_index(0)]
+				pub fn a(
+					runtime: &mut T,
+					mut caller: <T as system::Config>::AccountId,
+					mut amount: T::Balance,
+					(from, to): (u32, u32),
+				) -> DispatchResultWithPostInfo {
+					Ok(().into())
+				}
+			}
+		};
+		let def = CallDef::try_from(&mut item).unwrap();
+		let pallet_type = &def.pallet_type;
+		assert_eq!(quote::quote!(#pallet_type).to_string(), "Pallet < T , I >");
+		assert_eq!(def.generics.type_params().count(), 2);
+		assert!(def.generics.where_clause.is_some());
+		let args = def.methods[0].args.iter().map(|(name, _)| name.to_string()).collect::<Vec<_>>();
+		assert_eq!(args, vec!["amount", "arg2"]);
 
-	Ok(())
+		assert_eq!(
+			parse_error(syn::parse_quote! {
+				impl<T: Config> <T as Config>::Pallet {
+					#[call_index(0)]
+					pub fn a(&mut self, caller: T::AccountId) -> DispatchResult { Ok(()) }
+				}
+			}),
+			"Invalid pallet::call, expected the pallet struct like `Pallet<T>`"
+		);
+		assert_eq!(
+			parse_error(syn::parse_quote! {
+				impl<T: Config> Pallet<T> {
+					#[call_index(0)]
+					pub fn a(runtime: &mut U, caller: T::AccountId) -> DispatchResult { Ok(()) }
+				}
+			}),
+			"Invalid first parameter: expected a variant of self, or `runtime: &mut T`"
+		);
+		assert_eq!(
+			parse_error(syn::parse_quote! {
+				impl<T: Config> Pallet<T> {
+					#[call_index(0)]
+					pub fn a(&mut self, caller: T::AccountId) {}
+				}
+			}),
+			"Invalid call, expected a return type like `DispatchResult`"
+		);
+	}
 }
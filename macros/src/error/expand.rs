@@ -0,0 +1,101 @@
+use super::parse::ErrorDef;
+use quote::quote;
+
+/// See the `fn error` docs at the `lib.rs` of this crate for a high level definition.
+pub fn expand_error(def: ErrorDef) -> proc_macro2::TokenStream {
+	let ErrorDef { error_enum, generics, variants } = def;
+	let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
+
+	// The generic parameter of the pallet config, usually `T`. Parsing checks there is one.
+	let config = &generics.type_params().next().expect("checked by parsing").ident;
+
+	// These are vectors of the name, the index and the doc comments of each error.
+	let name = variants.iter().map(|variant| &variant.name).collect::<Vec<_>>();
+	let index = variants.iter().map(|variant| variant.index).collect::<Vec<_>>();
+	let docs = variants.iter().map(|variant| &variant.docs).collect::<Vec<_>>();
+
+	quote! {
+		impl #impl_generics #error_enum #type_generics #where_clause {
+			/// The index of the error in the pallet.
+			pub fn index(&self) -> u8 {
+				match self {
+					#( Self::#name => #index, )*
+					Self::__Ignore(_, never) => match *never {},
+				}
+			}
+
+			/// The name of the error, which is its message in a `support::ModuleError`.
+			pub fn name(&self) -> &'static str {
+				match self {
+					#( Self::#name => stringify!(#name), )*
+					Self::__Ignore(_, never) => match *never {},
+				}
+			}
+
+			/// The error with the index `index`, if any.
+			pub fn from_index(index: u8) -> Option<Self> {
+				match index {
+					#( #index => Some(Self::#name), )*
+					_ => None,
+				}
+			}
+
+			/// The description of each error, in the order of their index.
+			pub fn metadata() -> Vec<crate::metadata::ErrorMetadata> {
+				vec![
+					#(
+						crate::metadata::ErrorMetadata {
+							name: stringify!(#name),
+							index: #index,
+							docs: vec![ #( #docs ),* ],
+						}
+					),*
+				]
+			}
+		}
+
+		// Errors are identified by the index of their pallet in the runtime, and their index in the
+		// pallet. Outside of a runtime, only their name is kept.
+		impl #impl_generics From<#error_enum #type_generics> for crate::support::DispatchError
+		#where_clause
+		{
+			fn from(error: #error_enum #type_generics) -> Self {
+				let pallet = <
+					<#config as crate::system::Config>::PalletIndex as crate::support::PalletIndex
+				>::index::<Pallet #type_generics>();
+				match pallet {
+					Some(index) => crate::support::DispatchError::Module(crate::support::ModuleError {
+						index,
+						error: error.index(),
+						message: error.name(),
+					}),
+					None => crate::support::DispatchError::Other(error.name()),
+				}
+			}
+		}
+
+		// These traits are implemented by hand, because deriving them would require them on the
+		// generic parameters of the enum.
+		impl #impl_generics Clone for #error_enum #type_generics #where_clause {
+			fn clone(&self) -> Self {
+				*self
+			}
+		}
+
+		impl #impl_generics Copy for #error_enum #type_generics #where_clause {}
+
+		impl #impl_generics PartialEq for #error_enum #type_generics #where_clause {
+			fn eq(&self, other: &Self) -> bool {
+				self.index() == other.index()
+			}
+		}
+
+		impl #impl_generics Eq for #error_enum #type_generics #where_clause {}
+
+		impl #impl_generics core::fmt::Debug for #error_enum #type_generics #where_clause {
+			fn fmt(&self, formatter: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+				formatter.write_str(self.name())
+			}
+		}
+	}
+}
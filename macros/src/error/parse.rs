@@ -0,0 +1,107 @@
+use crate::utils::get_doc_literals;
+use syn::spanned::Spanned;
+
+/// This object will collect all the information we need to keep while parsing the error enum.
+#[derive(Debug)]
+pub struct ErrorDef {
+	/// This is the name of the error enum. We mostly assume it is `Error`.
+	pub error_enum: syn::Ident,
+	/// The generics of the error enum, usually `<T: Config>`. They are the generics of the pallet
+	/// struct too.
+	pub generics: syn::Generics,
+	/// This is the list of the errors of the pallet, in the order of their index. See
+	/// `ErrorVariantDef`.
+	pub variants: Vec<ErrorVariantDef>,
+}
+
+/// This is the metadata we keep about each error of the pallet.
+#[derive(Debug)]
+pub struct ErrorVariantDef {
+	/// The name of the variant, which is also the message of the error.
+	pub name: syn::Ident,
+	/// The index of the error in the pallet, which is its position in the enum.
+	pub index: u8,
+	/// The doc comments of the variant, one line per item.
+	pub docs: Vec<String>,
+}
+
+impl ErrorDef {
+	/// Parse the error enum of a pallet.
+	pub fn try_from(item: &syn::Item) -> syn::Result<Self> {
+		// First we check that we are parsing an `enum`.
+		let item_enum = if let syn::Item::Enum(item) = item {
+			item
+		} else {
+			return Err(syn::Error::new(item.span(), "Invalid error, expected item enum"))
+		};
+
+		// The enum must be generic over the pallet, so that it can find the index of the pallet in
+		// the runtime.
+		if item_enum.generics.type_params().next().is_none() {
+			let msg = "Invalid error, expected a generic parameter like `Error<T: Config>`";
+			return Err(syn::Error::new(item_enum.ident.span(), msg))
+		}
+
+		let mut variants = vec![];
+		for (index, variant) in item_enum.variants.iter().enumerate() {
+			if !matches!(variant.fields, syn::Fields::Unit) {
+				let msg = "Invalid error, expected variants without fields";
+				return Err(syn::Error::new(variant.fields.span(), msg))
+			}
+			let index = u8::try_from(index).map_err(|_| {
+				syn::Error::new(
+					variant.span(),
+					"Invalid error, a pallet can have at most 256 errors",
+				)
+			})?;
+			variants.push(ErrorVariantDef {
+				name: variant.ident.clone(),
+				index,
+				docs: get_doc_literals(&variant.attrs),
+			});
+		}
+
+		Ok(Self {
+			error_enum: item_enum.ident.clone(),
+			generics: item_enum.generics.clone(),
+			variants,
+		})
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::ErrorDef;
+
+	#[test]
+	fn errors_are_parsed() {
+		let item: syn::Item = syn::parse_quote! {
+			pub enum Error<T: Config> {
+				/// The account does not have enough funds.
+				InsufficientBalance,
+				Overflow,
+			}
+		};
+		let def = ErrorDef::try_from(&item).unwrap();
+		let names = def.variants.iter().map(|variant| variant.name.to_string()).collect::<Vec<_>>();
+		assert_eq!(names, vec!["InsufficientBalance", "Overflow"]);
+		assert_eq!(def.variants[1].index, 1);
+		assert_eq!(def.variants[0].docs, vec!["The account does not have enough funds."]);
+
+		let item: syn::Item = syn::parse_quote! {
+			pub enum Error<T: Config> {
+				InsufficientBalance { needed: u128 },
+			}
+		};
+		let error = ErrorDef::try_from(&item).unwrap_err().to_string();
+		assert_eq!(error, "Invalid error, expected variants without fields");
+
+		let item: syn::Item = syn::parse_quote! {
+			pub enum Error {
+				InsufficientBalance,
+			}
+		};
+		let error = ErrorDef::try_from(&item).unwrap_err().to_string();
+		assert_eq!(error, "Invalid error, expected a generic parameter like `Error<T: Config>`");
+	}
+}
@@ -0,0 +1,169 @@
+use super::parse::EventDef;
+use crate::utils::type_to_string;
+use quote::quote;
+
+/// See the `fn event` docs at the `lib.rs` of this crate for a high level definition.
+pub fn expand_event(def: EventDef) -> proc_macro2::TokenStream {
+	let EventDef { event_enum, generics, variants } = def;
+	let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
+
+	// The generic parameter of the pallet config, usually `T`. Parsing checks there is one.
+	let config = &generics.type_params().next().expect("checked by parsing").ident;
+
+	// This is a vector of all the event names.
+	let name = variants.iter().map(|variant| &variant.name).collect::<Vec<_>>();
+
+	// These are nested vectors of the names and types of the fields of each event in `name`.
+	let field_name = variants
+		.iter()
+		.map(|variant| variant.fields.iter().map(|(name, _)| name).collect::<Vec<_>>())
+		.collect::<Vec<_>>();
+	let field_type = variants
+		.iter()
+		.map(|variant| variant.fields.iter().map(|(_, ty)| ty).collect::<Vec<_>>())
+		.collect::<Vec<_>>();
+
+	// The index of each event in `name`, which identifies it in the encoding of the event.
+	let index = (0..variants.len()).map(|index| index as u8).collect::<Vec<_>>();
+
+	// The fields of `other` when comparing two events, so they do not clash with the fields of
+	// `self`.
+	let other_field_name = field_name
+		.iter()
+		.map(|names| names.iter().map(|name| quote::format_ident!("other_{}", name)).collect())
+		.collect::<Vec<Vec<_>>>();
+
+	// This is a nested vector of the types of the fields written as strings, and a vector of the
+	// doc comments of each event in `name`, which describe the events in the metadata.
+	let field_type_name = field_type
+		.iter()
+		.map(|types| types.iter().map(|ty| type_to_string(ty)).collect::<Vec<_>>())
+		.collect::<Vec<_>>();
+	let docs = variants.iter().map(|variant| &variant.docs).collect::<Vec<_>>();
+
+	// The trait implementations of `Event` are bounded on exactly the types it contains, on top of
+	// the bounds of the enum itself.
+	let predicates = where_clause.map(|clause| clause.predicates.iter().collect::<Vec<_>>());
+	let predicates = predicates.unwrap_or_default();
+	let all_field_type = field_type.iter().flatten().collect::<Vec<_>>();
+	let bounded = |bound: proc_macro2::TokenStream| quote!(where #( #predicates, )* #( #all_field_type: #bound, )*);
+	let clone_bounds = bounded(quote!(Clone));
+	let debug_bounds = bounded(quote!(core::fmt::Debug));
+	let eq_bounds = bounded(quote!(PartialEq));
+	let full_eq_bounds = bounded(quote!(Eq));
+	let encode_bounds = bounded(quote!(crate::codec::Encode));
+	let decode_bounds = bounded(quote!(crate::codec::Decode));
+
+	quote! {
+		impl #impl_generics #event_enum #type_generics #where_clause {
+			/// The description of each event of the pallet.
+			pub fn metadata() -> Vec<crate::metadata::EventMetadata> {
+				vec![
+					#(
+						crate::metadata::EventMetadata {
+							name: stringify!(#name),
+							fields: vec![
+								#(
+									crate::metadata::ArgMetadata {
+										name: stringify!(#field_name),
+										ty: #field_type_name,
+									}
+								),*
+							],
+							docs: vec![ #( #docs ),* ],
+						}
+					),*
+				]
+			}
+		}
+
+		impl #impl_generics Pallet #type_generics #where_clause {
+			/// Deposit an event of this pallet in the system pallet sharing its backend, which keeps
+			/// it until the next block is initialized.
+			pub fn deposit_event(&mut self, event: #event_enum #type_generics)
+			where
+				<#config as crate::system::Config>::RuntimeEvent: From<#event_enum #type_generics>,
+			{
+				crate::system::Pallet::<#config>::deposit_event_in(self.storage_backend(), event.into());
+			}
+		}
+
+		// Runtimes which keep no events, with `()` as their `RuntimeEvent`, drop the events of the
+		// pallet.
+		impl #impl_generics From<#event_enum #type_generics> for () #where_clause {
+			fn from(_event: #event_enum #type_generics) {}
+		}
+
+		// These traits are implemented by hand, because deriving them would require them on the
+		// generic parameters of the enum, while we only need them on the types of the fields.
+		impl #impl_generics Clone for #event_enum #type_generics #clone_bounds {
+			fn clone(&self) -> Self {
+				match self {
+					#(
+						Self::#name { #( #field_name ),* } => Self::#name {
+							#( #field_name: #field_name.clone() ),*
+						},
+					)*
+					Self::__Ignore(_, never) => match *never {},
+				}
+			}
+		}
+
+		impl #impl_generics core::fmt::Debug for #event_enum #type_generics #debug_bounds {
+			fn fmt(&self, formatter: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+				match self {
+					#(
+						Self::#name { #( #field_name ),* } => formatter
+							.debug_struct(stringify!(#name))
+							#( .field(stringify!(#field_name), #field_name) )*
+							.finish(),
+					)*
+					Self::__Ignore(_, never) => match *never {},
+				}
+			}
+		}
+
+		impl #impl_generics PartialEq for #event_enum #type_generics #eq_bounds {
+			fn eq(&self, other: &Self) -> bool {
+				match (self, other) {
+					#(
+						(
+							Self::#name { #( #field_name ),* },
+							Self::#name { #( #field_name: #other_field_name ),* },
+						) => true #( && #field_name == #other_field_name )*,
+					)*
+					_ => false,
+				}
+			}
+		}
+
+		impl #impl_generics Eq for #event_enum #type_generics #full_eq_bounds {}
+
+		impl #impl_generics crate::codec::Encode for #event_enum #type_generics #encode_bounds {
+			fn encode_to(&self, dest: &mut Vec<u8>) {
+				match self {
+					#(
+						Self::#name { #( #field_name ),* } => {
+							crate::codec::Encode::encode_to(&#index, dest);
+							#( crate::codec::Encode::encode_to(#field_name, dest); )*
+						},
+					)*
+					Self::__Ignore(_, never) => match *never {},
+				}
+			}
+		}
+
+		impl #impl_generics crate::codec::Decode for #event_enum #type_generics #decode_bounds {
+			fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+				match <u8 as crate::codec::Decode>::decode(input)? {
+					#(
+						#index => Ok(Self::#name {
+							#( #field_name: crate::codec::Decode::decode(input)?, )*
+						}),
+					)*
+					_ => Err("invalid event index"),
+				}
+			}
+		}
+	}
+}
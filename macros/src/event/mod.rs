@@ -0,0 +1,28 @@
+pub mod expand;
+pub mod parse;
+
+/// See the `fn event` docs at the `lib.rs` of this crate for a high level definition.
+pub fn event(
+	_attr: proc_macro::TokenStream,
+	item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+	let mut item_enum = syn::parse_macro_input!(item as syn::Item);
+
+	// First we parse the events of the pallet...
+	let generated: proc_macro::TokenStream = match parse::EventDef::try_from(&item_enum) {
+		// ..then we generate our new code.
+		Ok(def) => expand::expand_event(def).into(),
+		Err(e) => e.to_compile_error().into(),
+	};
+
+	// Our macro only adds new code, so our final product contains all of our old code too, plus a
+	// hidden variant using the generic parameters of the enum.
+	if let syn::Item::Enum(item_enum) = &mut item_enum {
+		crate::utils::add_phantom_variant(item_enum);
+	}
+	let mut finished: proc_macro::TokenStream = quote::quote!(#item_enum).into();
+
+	// Add our generated code to the end, and return the final result.
+	finished.extend(generated);
+	return finished;
+}
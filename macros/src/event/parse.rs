@@ -0,0 +1,107 @@
+use crate::utils::get_doc_literals;
+use syn::spanned::Spanned;
+
+/// This object will collect all the information we need to keep while parsing the event enum.
+#[derive(Debug)]
+pub struct EventDef {
+	/// This is the name of the event enum. We mostly assume it is `Event`.
+	pub event_enum: syn::Ident,
+	/// The generics of the event enum, usually `<T: Config>`. They are the generics of the pallet
+	/// struct too.
+	pub generics: syn::Generics,
+	/// This is the list of the events of the pallet. See `EventVariantDef`.
+	pub variants: Vec<EventVariantDef>,
+}
+
+/// This is the metadata we keep about each event of the pallet.
+#[derive(Debug)]
+pub struct EventVariantDef {
+	/// The name of the variant.
+	pub name: syn::Ident,
+	/// Information on the fields of the variant: `(name, type)`.
+	pub fields: Vec<(syn::Ident, syn::Type)>,
+	/// The doc comments of the variant, one line per item.
+	pub docs: Vec<String>,
+}
+
+impl EventDef {
+	/// Parse the event enum of a pallet.
+	pub fn try_from(item: &syn::Item) -> syn::Result<Self> {
+		// First we check that we are parsing an `enum`.
+		let item_enum = if let syn::Item::Enum(item) = item {
+			item
+		} else {
+			return Err(syn::Error::new(item.span(), "Invalid event, expected item enum"))
+		};
+
+		// The enum must be generic over the pallet, so that it can be deposited in the system
+		// pallet of the runtime.
+		if item_enum.generics.type_params().next().is_none() {
+			let msg = "Invalid event, expected a generic parameter like `Event<T: Config>`";
+			return Err(syn::Error::new(item_enum.ident.span(), msg))
+		}
+
+		let mut variants = vec![];
+		for variant in item_enum.variants.iter() {
+			let fields = match &variant.fields {
+				syn::Fields::Named(fields) => fields
+					.named
+					.iter()
+					.map(|field| {
+						(field.ident.clone().expect("named fields have an ident"), field.ty.clone())
+					})
+					.collect(),
+				syn::Fields::Unit => vec![],
+				syn::Fields::Unnamed(fields) => {
+					let msg = "Invalid event, expected variants with named fields";
+					return Err(syn::Error::new(fields.span(), msg))
+				},
+			};
+			variants.push(EventVariantDef {
+				name: variant.ident.clone(),
+				fields,
+				docs: get_doc_literals(&variant.attrs),
+			});
+		}
+
+		Ok(Self {
+			event_enum: item_enum.ident.clone(),
+			generics: item_enum.generics.clone(),
+			variants,
+		})
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::EventDef;
+
+	#[test]
+	fn events_are_parsed() {
+		let item: syn::Item = syn::parse_quote! {
+			pub enum Event<T: Config> {
+				/// Funds were transferred.
+				Transfer { from: T::AccountId, to: T::AccountId, amount: T::Balance },
+				Reset,
+			}
+		};
+		let def = EventDef::try_from(&item).unwrap();
+		assert_eq!(def.variants[0].name.to_string(), "Transfer");
+		let fields = def.variants[0]
+			.fields
+			.iter()
+			.map(|(name, _)| name.to_string())
+			.collect::<Vec<_>>();
+		assert_eq!(fields, vec!["from", "to", "amount"]);
+		assert_eq!(def.variants[0].docs, vec!["Funds were transferred."]);
+		assert!(def.variants[1].fields.is_empty());
+
+		let item: syn::Item = syn::parse_quote! {
+			pub enum Event<T: Config> {
+				Transfer(T::AccountId, T::AccountId),
+			}
+		};
+		let error = EventDef::try_from(&item).unwrap_err().to_string();
+		assert_eq!(error, "Invalid event, expected variants with named fields");
+	}
+}
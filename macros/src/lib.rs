@@ -1,6 +1,41 @@
 mod call;
+mod error;
+mod event;
+mod pallet_info;
 mod runtime;
+mod storage;
+mod utils;
 
+/// Expand the callable functions of a pallet.
+///
+/// This generates:
+/// - `enum Call` - an enum with one variant per callable function, holding its arguments. It has
+///   the same generics as the `impl` block, like `Call<T>` or `Call<T, I>` for a pallet with an
+///   instance parameter, which defaults to `()`. It has a hidden variant which can never be
+///   constructed.
+/// - `fn Call::dispatch()` - which routes each `Call` variant to the function it represents. Calls
+///   are dispatched on the whole runtime `T`, which must give access to the pallet through `AsMut`.
+///   Functions return a `support::DispatchResult`, or a `support::DispatchResultWithPostInfo` to
+///   give back the weight they actually consumed.
+/// - implements the trait `support::GetDispatchInfo` on `Call`. The weight of a function is given
+///   with the `#[weight(..)]` attribute, and defaults to `support::DEFAULT_WEIGHT`. The expression
+///   can use the arguments of the function, by reference, like `#[weight(call.weight())]`.
+/// - implements `Clone`, `Debug`, `PartialEq`, `Eq`, `codec::Encode` and `codec::Decode` on `Call`,
+///   as long as the types of all the arguments implement them. Calls are encoded as the index of
+///   the function in the pallet, followed by the arguments.
+/// - `fn Call::call_name()` - which gives the name of the function a call is for.
+/// - `fn Call::metadata()` - which describes each callable function: its name, its index, the names
+///   and types of its arguments, and its doc comments.
+///
+/// Callable functions take some variant of `self` as their first argument. Functions which need
+/// other pallets of the runtime instead take `runtime: &mut T`, and reach any pallet through the
+/// `AsRef` and `AsMut` implementations generated by `#[macros::runtime]`. In both cases, the second
+/// argument is the caller, like `caller: T::AccountId`. The other arguments can be `mut` or
+/// destructured, in which case they are named after their position in `Call`, like `arg2`.
+///
+/// Every callable function must have a `#[call_index(..)]` attribute, giving the index its calls are
+/// encoded with. The indices of a pallet must go from 0 up, without duplicates or gaps, so that
+/// reordering the functions never changes the encoding of their calls.
 #[proc_macro_attribute]
 pub fn call(
 	attr: proc_macro::TokenStream,
@@ -9,22 +44,178 @@ pub fn call(
 	call::call(attr, item)
 }
 
+/// Expand the events of a pallet.
+///
+/// The event enum must be generic over the pallet config, like `Event<T: Config>`, and its variants
+/// must have named fields, or none. This generates:
+/// - `fn Pallet::deposit_event()` - which deposits an event in the system pallet sharing the
+///   storage backend of the pallet. The `RuntimeEvent` of the runtime must convert from the event,
+///   as generated by `#[macros::runtime]`, or be `()`, which drops the event.
+/// - implements `Clone`, `Debug`, `PartialEq`, `Eq`, `codec::Encode` and `codec::Decode` on the
+///   event enum, as long as the types of all the fields implement them. Events are encoded as their
+///   index in the enum, followed by their fields.
+/// - `fn Event::metadata()` - which describes each event: its name, the names and types of its
+///   fields, and its doc comments.
+///
+/// The pallet struct must be named `Pallet`, with the same generics as the event enum. A hidden
+/// variant is added to the enum, so that it uses its generic parameters. It can never be
+/// constructed.
+#[proc_macro_attribute]
+pub fn event(
+	attr: proc_macro::TokenStream,
+	item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+	event::event(attr, item)
+}
+
+/// Expand the errors of a pallet.
+///
+/// The error enum must be generic over the pallet config, like `Error<T: Config>`, and its variants
+/// must have no fields. Each error is identified by its position in the enum. This generates:
+/// - `fn Error::index()`, `fn Error::name()` and `fn Error::from_index()` - which convert between
+///   an error and its index in the pallet.
+/// - implements `From<Error>` on `support::DispatchError`. The error becomes a
+///   `support::ModuleError`, with the index of the pallet given by the `PalletIndex` of the system
+///   config. Pallets outside of a runtime have no index, and their errors become
+///   `DispatchError::Other`.
+/// - implements `Clone`, `Copy`, `Debug`, `PartialEq` and `Eq` on the error enum. Errors are
+///   printed as their name.
+/// - `fn Error::metadata()` - which describes each error: its name, its index, and its doc
+///   comments.
+///
+/// The pallet struct must be named `Pallet`, with the same generics as the error enum. A hidden
+/// variant is added to the enum, so that it uses its generic parameters. It can never be
+/// constructed.
+#[proc_macro_attribute]
+pub fn error(
+	attr: proc_macro::TokenStream,
+	item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+	error::error(attr, item)
+}
+
+/// Describe a pallet struct for the metadata of the runtime.
+///
+/// This implements the trait `metadata::PalletInfo` on the pallet, which gives its doc comments and
+/// its storage items. Each field of the pallet struct is a storage item, described by its name, its
+/// kind, the types of its keys and values, and its doc comments. The `storage_version` field added
+/// by `#[macros::storage]` is left out.
+#[proc_macro_derive(PalletInfo)]
+pub fn pallet_info(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+	pallet_info::pallet_info(item)
+}
+
+/// Expand the storage of a pallet.
+///
+/// Every field of the pallet struct is a storage item: a `storage::StorageValue`, a
+/// `storage::StorageMap` or a `storage::StorageDoubleMap`. Storage items are handles to a
+/// `storage::Backend`, which the pallets of a runtime share. Each item keeps its entries under its
+/// own prefix, made of the name of the pallet and the name of the field. This generates:
+/// - `fn new_in()` - which binds the storage items to a backend, under the given pallet name. Items
+///   which are not in the backend yet start at their default value: maps start empty, and values
+///   start with the value given by the `#[default(..)]` attribute of the field, or
+///   `Default::default()`.
+/// - `fn new()` - which creates the pallet in a backend of its own, named after its module.
+/// - `fn storage_backend()` - which gives the backend the pallet is bound to.
+/// - `fn encode_storage()` and `fn decode_storage()` - which add the state of the pallet to a
+///   `storage::EncodedStorage`, and load it back. The types of the keys and values of the items
+///   must implement `codec::Encode` and `codec::Decode`.
+/// - a getter function for each field with a `#[getter(name)]` attribute. Getters return a clone
+///   of the value. For maps, they take a reference to each key, and return the `#[default(..)]`
+///   value for missing keys, or an `Option` if the field has no `#[default(..)]`.
+/// - implements the trait `support::GetStorageVersion` on the pallet. The storage version of the
+///   code is given by `#[macros::storage(version = ..)]`, or `0`. The storage version of the state
+///   is kept in a `storage_version` field added to the pallet struct, which the migrations of the
+///   pallet must bump. It starts at the version of the code for a new pallet, and at `0` for a
+///   backend which has no storage version for the pallet.
+///
+/// `#[macros::storage]` must come before the derives of the pallet struct, so that they do not see
+/// the helper attributes.
+#[proc_macro_attribute]
+pub fn storage(
+	attr: proc_macro::TokenStream,
+	item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+	storage::storage(attr, item)
+}
+
 /// Expand the `Runtime` definition.
 ///
+/// The system pallet is the field marked with `#[system]`, or else the field named `system`, and
+/// can be anywhere in the struct. Every other field is a pallet, whose type is written like
+/// `balances::Pallet<Self>`: its `Call`, `Event` and `Error` are found in the same module, with the
+/// same generic arguments. A pallet can be included several times, as long as each instance has a
+/// different type, like `balances::Pallet<Self, Instance2>`. Each instance is named after its
+/// field in the generated enums.
+///
+/// The blocks of the runtime are `types::Block`, unless given with
+/// `#[macros::runtime(block = ..)]`. The types of their header and extrinsics are found through
+/// `support::BlockFormat`.
+///
 /// This generates function implementations on `Runtime`:
-/// - `fn new()` - which generates a new instance of the runtime, by instantiating all the pallets
-///   included in the runtime.
-/// - `fn execute_block()` - which handles basic logic for executing a block of extrinsics. It does
-///   basic actions like incrementing the block number and checking the block to be executed has a
-///   valid block number.
+/// - `fn new()` - which generates a new instance of the runtime, by binding all the pallets
+///   included in the runtime to a new `storage::Backend`, at the storage versions of their code
+///   and the `SPEC_VERSION` of the system config. Each pallet is named after its field, and system
+///   after `system::PALLET_NAME`. `fn new_in()` binds them to an existing backend instead, where a
+///   state which was never upgraded is at spec version 0.
+/// - `fn execute_block()` - which handles basic logic for executing a block of extrinsics. It checks
+///   the block fits within the block limits of the system pallet, and that its roots match its
+///   extrinsics and the resulting state. The runtime must implement `Debug`, which the state root
+///   is computed from. It returns a `support::BlockReceipt` with the receipt of each extrinsic.
+/// - `fn initialize_block()` - which starts a block. It does basic actions like incrementing the
+///   block number, clearing the events of the last block and checking the block follows the last
+///   one. The runtime must implement `support::OnInitialize`, which is executed at the start of
+///   each block, before any extrinsic. The weight it returns is mandatory: it never makes the
+///   block invalid, but it is taken from the weight left for the extrinsics of the block.
+///   The state is upgraded before the hook runs.
+/// - `fn upgrade()` - which upgrades the state to the `SPEC_VERSION` of the system config. The
+///   runtime must implement `support::OnRuntimeUpgrade`, which is given the spec version of the
+///   state and `SPEC_VERSION`. Every pallet must then be at the storage version of its code. The
+///   block is invalid if the upgrade fails, or if the state is at a newer spec version.
+/// - `fn apply_extrinsic()` - which executes a single extrinsic of a block. It checks the caller
+///   through `support::CheckSignature` and its nonce, and charges the fee of the extrinsic through
+///   `support::TransactionPayment`. The runtime must implement both traits. It returns a
+///   `support::ExtrinsicReceipt`, with the events the system pallet recorded during the dispatch.
+/// - `fn finalize_block()` - which ends a block, checking its state root.
 ///
 /// This also generates code needed for dispatching calls to the pallets:
 /// - Note: For simplicity, we assume that the system pallet is not callable.
 /// - `enum RuntimeCall` - an "outer"-enum representing the accumulation of all possible calls to
 ///   all pallets. The system pallet is not included.
-/// - implements the trait `support::Dispatch` to dispatch calls to the appropriate pallet. Basic
-///   logic like incrementing the nonce of the user is included in the generated code. The system
-///   pallet is not included.
+/// - implements the trait `support::Dispatch` to dispatch calls to the appropriate pallet. The
+///   system pallet is not included. Calls are first checked with `support::CallFilter`, which the
+///   runtime must implement, and fail with `DispatchError::CallFiltered` if it rejects them.
+/// - implements `support::GetDispatchInfo`, `Clone`, `Debug`, `PartialEq`, `Eq`, `codec::Encode`
+///   and `codec::Decode` on `RuntimeCall`, by forwarding to the calls of each pallet. Runtime calls
+///   are encoded as the index of the pallet in the runtime, followed by the call of the pallet.
+/// - `fn RuntimeCall::pallet_name()` and `fn RuntimeCall::call_name()` - which give the name of the
+///   pallet and the function a call is for, e.g. to log or filter calls.
+/// - implements `AsRef` and `AsMut` on `Runtime` for each pallet, including system, so pallets can
+///   access the other pallets they depend on.
+///
+/// This also generates code gathering the events and errors of the pallets:
+/// - `enum RuntimeEvent` - an "outer"-enum of the events of the pallets marked with `#[event]`,
+///   which must declare them with `#[macros::event]`. Each event of a pallet converts into it.
+/// - `enum RuntimeError` - an "outer"-enum of the errors of all pallets, with
+///   `fn RuntimeError::from_dispatch_error()` to find the error a `support::ModuleError` stands for.
+/// - implements `support::PalletIndex` on `Runtime`, which must be the `PalletIndex` of its system
+///   config, so that the errors of the pallets know the index of their pallet.
+///
+/// Every pallet but system must have a `#[pallet_index(..)]` attribute, giving the index its calls
+/// and errors are encoded with. The indices must go from 0 up, without duplicates or gaps, so that
+/// reordering the pallets never changes the encoding of their calls.
+///
+/// This also implements `Clone` on `Runtime`, which copies the backend of the runtime and binds the
+/// pallets of the copy to it, so that the copy has a state of its own. `fn encode_storage()` and
+/// `fn decode_storage()` encode the state of every pallet, and load a runtime from it, which is
+/// how the state is stored on disk. `RuntimeEvent` implements `codec::Encode` and `codec::Decode`
+/// like `RuntimeCall`.
+///
+/// Finally, this generates `fn metadata()` on `Runtime`, which describes every pallet: its index,
+/// its doc comments and storage items through `metadata::PalletInfo`, and its calls, events and
+/// errors through `Call::metadata()`, `Event::metadata()` and `Error::metadata()`. Every pallet
+/// must declare its storage with `#[macros::storage]` and derive `PalletInfo`, and declare its
+/// errors with `#[macros::error]`.
 #[proc_macro_attribute]
 pub fn runtime(
 	attr: proc_macro::TokenStream,
@@ -0,0 +1,47 @@
+use super::parse::PalletInfoDef;
+use crate::storage::parse::StorageKind;
+use quote::quote;
+
+/// See the `fn pallet_info` docs at the `lib.rs` of this crate for a high level definition.
+pub fn expand_pallet_info(def: PalletInfoDef) -> proc_macro2::TokenStream {
+	let PalletInfoDef { pallet_struct, generics, docs, storage } = def;
+	let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
+
+	// These are vectors of the description of each storage item.
+	let storage_name = storage.iter().map(|item| &item.name).collect::<Vec<_>>();
+	let storage_kind = storage
+		.iter()
+		.map(|item| match item.kind {
+			StorageKind::Value => quote!(crate::metadata::StorageKind::Value),
+			StorageKind::Map => quote!(crate::metadata::StorageKind::Map),
+			StorageKind::DoubleMap => quote!(crate::metadata::StorageKind::DoubleMap),
+		})
+		.collect::<Vec<_>>();
+	let storage_keys = storage.iter().map(|item| &item.keys).collect::<Vec<_>>();
+	let storage_value = storage.iter().map(|item| &item.value).collect::<Vec<_>>();
+	let storage_docs = storage.iter().map(|item| &item.docs).collect::<Vec<_>>();
+
+	quote! {
+		impl #impl_generics crate::metadata::PalletInfo for #pallet_struct #type_generics
+		#where_clause
+		{
+			fn docs() -> Vec<&'static str> {
+				vec![ #( #docs ),* ]
+			}
+
+			fn storage() -> Vec<crate::metadata::StorageMetadata> {
+				vec![
+					#(
+						crate::metadata::StorageMetadata {
+							name: stringify!(#storage_name),
+							kind: #storage_kind,
+							keys: vec![ #( #storage_keys ),* ],
+							value: #storage_value,
+							docs: vec![ #( #storage_docs ),* ],
+						}
+					),*
+				]
+			}
+		}
+	}
+}
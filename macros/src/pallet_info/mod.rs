@@ -0,0 +1,15 @@
+pub mod expand;
+pub mod parse;
+
+/// See the `fn pallet_info` docs at the `lib.rs` of this crate for a high level definition.
+pub fn pallet_info(item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+	let item_struct = syn::parse_macro_input!(item as syn::Item);
+
+	// First we parse the pallet struct...
+	match parse::PalletInfoDef::try_from(item_struct) {
+		// ..then we generate our new code. A derive macro only adds new code, so this is all we
+		// return.
+		Ok(def) => expand::expand_pallet_info(def).into(),
+		Err(e) => e.to_compile_error().into(),
+	}
+}
@@ -0,0 +1,101 @@
+use crate::{
+	storage::parse::{parse_storage_type, StorageKind},
+	utils::{get_doc_literals, type_to_string},
+};
+use syn::spanned::Spanned;
+
+/// This object will collect all the information we need to keep while parsing the pallet struct.
+#[derive(Debug)]
+pub struct PalletInfoDef {
+	/// This is the name of the pallet struct. We mostly assume it is `Pallet`.
+	pub pallet_struct: syn::Ident,
+	/// The generics of the pallet struct, usually `<T: Config>`.
+	pub generics: syn::Generics,
+	/// The doc comments of the pallet struct, one line per item.
+	pub docs: Vec<String>,
+	/// This is the list of the storage items of the pallet. See `StorageDef`.
+	pub storage: Vec<StorageDef>,
+}
+
+/// This is the metadata we keep about each storage item, which is a field of the pallet struct.
+#[derive(Debug)]
+pub struct StorageDef {
+	/// The name of the field.
+	pub name: syn::Ident,
+	/// What kind of storage item it is. See `StorageKind`.
+	pub kind: StorageKind,
+	/// The types of the keys of the item, as written in the source code.
+	pub keys: Vec<String>,
+	/// The type of the values of the item, as written in the source code.
+	pub value: String,
+	/// The doc comments of the field, one line per item.
+	pub docs: Vec<String>,
+}
+
+impl PalletInfoDef {
+	pub fn try_from(item: syn::Item) -> syn::Result<Self> {
+		// First we check that we are parsing a `struct` with named fields.
+		let item_struct = if let syn::Item::Struct(item) = item {
+			item
+		} else {
+			return Err(syn::Error::new(item.span(), "Invalid pallet info, expected item struct"))
+		};
+		let fields = if let syn::Fields::Named(fields) = item_struct.fields {
+			fields
+		} else {
+			let msg = "Invalid pallet info, expected a struct with named fields";
+			return Err(syn::Error::new(item_struct.fields.span(), msg))
+		};
+
+		// The storage version added by `#[macros::storage]` is not one of the storage items the
+		// pallet declares.
+		let mut storage = vec![];
+		for field in fields.named {
+			let name = field.ident.expect("named fields have an ident");
+			if name == "storage_version" {
+				continue
+			}
+			let (kind, mut types) = parse_storage_type(&field.ty)?;
+			let value = types.pop().expect("storage types have a value type");
+			storage.push(StorageDef {
+				name,
+				kind,
+				keys: types.iter().map(type_to_string).collect(),
+				value: type_to_string(&value),
+				docs: get_doc_literals(&field.attrs),
+			});
+		}
+
+		Ok(Self {
+			pallet_struct: item_struct.ident,
+			generics: item_struct.generics,
+			docs: get_doc_literals(&item_struct.attrs),
+			storage,
+		})
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::PalletInfoDef;
+	use crate::storage::parse::StorageKind;
+
+	#[test]
+	fn storage_items_are_described() {
+		let item: syn::Item = syn::parse_quote! {
+			/// The pallet.
+			pub struct Pallet<T: Config> {
+				/// The balances.
+				balances: StorageMap<T::AccountId, T::Balance>,
+				storage_version: crate::storage::StorageValue<crate::support::StorageVersion>,
+			}
+		};
+		let def = PalletInfoDef::try_from(item).unwrap();
+		assert_eq!(def.docs, vec!["The pallet."]);
+		assert_eq!(def.storage.len(), 1);
+		assert_eq!(def.storage[0].kind, StorageKind::Map);
+		assert_eq!(def.storage[0].keys, vec!["T::AccountId"]);
+		assert_eq!(def.storage[0].value, "T::Balance");
+		assert_eq!(def.storage[0].docs, vec!["The balances."]);
+	}
+}
@@ -1,46 +1,236 @@
-use super::parse::RuntimeDef;
+use super::parse::{RuntimeDef, SystemDef};
 use quote::quote;
 
 /// See the `fn runtime` docs at the `lib.rs` of this crate for a high level definition.
 pub fn expand_runtime(def: RuntimeDef) -> proc_macro2::TokenStream {
-	let RuntimeDef { runtime_struct, pallets } = def;
+	let RuntimeDef { runtime_struct, system, block, pallets } = def;
+	let SystemDef { name: system_name, module: system_module, ty: system_type } = system;
+
+	// These are the types the blocks of the runtime are made of, and the types of their receipts.
+	let header_type = quote!(<#block as crate::support::BlockFormat>::Header);
+	let extrinsic_type = quote!(<#block as crate::support::BlockFormat>::Extrinsic);
+	let balance_type = quote!(<#block as crate::support::BlockFormat>::Balance);
+	let event_type = quote!(<#runtime_struct as #system_module::Config>::RuntimeEvent);
+	let block_number_type = quote!(<#runtime_struct as #system_module::Config>::BlockNumber);
+	let block_receipt_type =
+		quote!(crate::support::BlockReceipt<#block_number_type, #balance_type, #event_type>);
 
 	// This is a vector of all the pallet names, not including system.
-	let pallet_names = pallets.iter().map(|(name, _)| name.clone()).collect::<Vec<_>>();
+	let pallet_names = pallets.iter().map(|pallet| pallet.name.clone()).collect::<Vec<_>>();
 	// This is a vector of all the pallet types, not including system.
-	let pallet_types = pallets.iter().map(|(_, type_)| type_.clone()).collect::<Vec<_>>();
+	let pallet_types = pallets.iter().map(|pallet| pallet.ty.clone()).collect::<Vec<_>>();
+
+	// These are vectors of the `Call` and `Error` types of each pallet, like
+	// `balances::Call<Runtime>`. They take the same generic arguments as the pallet, so each
+	// instance of a pallet has its own.
+	let call_types = pallets
+		.iter()
+		.map(|pallet| {
+			let (module, args) = (&pallet.module, &pallet.args);
+			quote!(#module::Call<#( #args ),*>)
+		})
+		.collect::<Vec<_>>();
+	let error_types = pallets
+		.iter()
+		.map(|pallet| {
+			let (module, args) = (&pallet.module, &pallet.args);
+			quote!(#module::Error<#( #args ),*>)
+		})
+		.collect::<Vec<_>>();
+
+	// This is a vector of the index of each pallet, as given by `#[pallet_index(..)]`, which is how
+	// calls to different pallets are told apart once encoded.
+	let pallet_index = pallets.iter().map(|pallet| pallet.index).collect::<Vec<_>>();
+
+	// These are vectors of the names and the `Event` types of the pallets which deposit events, as
+	// given by `#[event]`.
+	let event_pallets = pallets.iter().filter(|pallet| pallet.event).collect::<Vec<_>>();
+	let event_pallet_names = event_pallets.iter().map(|pallet| &pallet.name).collect::<Vec<_>>();
+	let event_pallet_index = event_pallets.iter().map(|pallet| pallet.index).collect::<Vec<_>>();
+	let event_pallet_types = event_pallets
+		.iter()
+		.map(|pallet| {
+			let (module, args) = (&pallet.module, &pallet.args);
+			quote!(#module::Event<#( #args ),*>)
+		})
+		.collect::<Vec<_>>();
+	// This is a vector of the expressions describing the events of each pallet for the metadata.
+	let pallet_events = pallets
+		.iter()
+		.map(|pallet| {
+			let (module, args) = (&pallet.module, &pallet.args);
+			if pallet.event {
+				quote!(#module::Event::<#( #args ),*>::metadata())
+			} else {
+				quote!(Vec::new())
+			}
+		})
+		.collect::<Vec<_>>();
 
 	// This quote block implements functions on the `Runtime` struct.
 	let runtime_impl = quote! {
 		impl #runtime_struct {
-			// Create a new instance of the main Runtime, by creating a new instance of each pallet.
+			// Create a new instance of the main Runtime, by creating a new instance of each pallet in
+			// a new storage backend. The state starts at the spec version of the runtime, and every
+			// pallet at the storage version of its code, since there is nothing to migrate.
 			fn new() -> Self {
+				use crate::support::GetStorageVersion;
+
+				let mut runtime = Self::new_in(&crate::storage::Backend::new());
+				runtime.#system_name.set_spec_version(<#runtime_struct as #system_module::Config>::SPEC_VERSION);
+				runtime.#system_name.set_on_chain_storage_version(<#system_type>::IN_CODE_STORAGE_VERSION);
+				#(
+					runtime.#pallet_names.set_on_chain_storage_version(<#pallet_types>::IN_CODE_STORAGE_VERSION);
+				)*
+				runtime
+			}
+
+			// Bind each pallet to `backend`, under the name of its field. The system pallet is bound
+			// under its own name, so that the other pallets find it to deposit their events.
+			fn new_in(backend: &crate::storage::Backend) -> Self {
 				Self {
 					// Since system is not included in the list of pallets, we manually add it here.
-					system: <system::Pallet::<Self>>::new(),
+					#system_name: <#system_type>::new_in(backend, #system_module::PALLET_NAME),
 					#(
-						#pallet_names: <#pallet_types>::new()
+						#pallet_names: <#pallet_types>::new_in(backend, stringify!(#pallet_names))
 					),*
 				}
 			}
 
-			// Execute a block of extrinsics. Increments the block number.
-			fn execute_block(&mut self, block: types::Block) -> crate::support::DispatchResult {
-				self.system.inc_block_number();
-				if block.header.block_number != self.system.block_number() {
+			// Execute a block. The block must follow the last executed block, fit within the block
+			// limits of the system pallet, and its roots must match its extrinsics and the state
+			// they lead to. Extrinsics which fail to dispatch are recorded in the receipt of the
+			// block, but an invalid extrinsic makes the whole block invalid.
+			//
+			// The block is executed as it goes, so the state must be discarded if an error is
+			// returned.
+			fn execute_block(
+				&mut self,
+				block: #block,
+			) -> Result<#block_receipt_type, &'static str> {
+				let crate::support::Block { header, extrinsics } = block;
+				if crate::support::hash_of(&extrinsics) != header.extrinsics_root {
+					return Err(&"extrinsics root does not match the extrinsics")
+				}
+				if extrinsics.len() > <#runtime_struct as #system_module::Config>::MAXIMUM_BLOCK_LENGTH as usize {
+					return Err(&"block has too many extrinsics")
+				}
+
+				// The weight consumed when the block is initialized is mandatory: it never makes the
+				// block invalid, but it is taken from the weight left for the extrinsics.
+				let initialization_weight = self.initialize_block(&header)?;
+				let budget = <#runtime_struct as #system_module::Config>::MAXIMUM_BLOCK_WEIGHT
+					.saturating_sub(initialization_weight);
+				let weight = extrinsics.iter().fold(0, |weight: crate::support::Weight, extrinsic| {
+					weight.saturating_add(crate::support::GetDispatchInfo::weight(&extrinsic.call))
+				});
+				if weight > budget {
+					return Err(&"block is too heavy")
+				}
+				let initialization_events = self.#system_name.events();
+				let mut receipts = Vec::new();
+				for extrinsic in extrinsics {
+					let receipt = self.apply_extrinsic(extrinsic).map_err(|_| "block contains an invalid extrinsic")?;
+					receipts.push(receipt);
+				}
+				self.finalize_block(&header)?;
+				Ok(crate::support::BlockReceipt {
+					block_number: header.block_number,
+					initialization_events,
+					extrinsics: receipts,
+				})
+			}
+
+			// Start a block. Increments the block number, clears the events of the previous block,
+			// upgrades the state to the spec version of the runtime, and runs the `OnInitialize` hook
+			// of the runtime before any extrinsic. Returns the weight consumed by the hook.
+			fn initialize_block(
+				&mut self,
+				header: &#header_type,
+			) -> Result<crate::support::Weight, &'static str> {
+				self.#system_name.inc_block_number();
+				if header.block_number != self.#system_name.block_number() {
 					return Err(&"block number does not match what is expected")
 				}
-				for (i, support::Extrinsic { caller, call }) in block.extrinsics.into_iter().enumerate() {
-					self.system.inc_nonce(&caller);
-					let _res = self.dispatch(caller, call).map_err(|e| {
-						eprintln!(
-							"Extrinsic Error\n\tBlock Number: {}\n\tExtrinsic Number: {}\n\tError: {}",
-							block.header.block_number, i, e
-						)
-					});
+				if header.parent_hash != self.#system_name.parent_hash() {
+					return Err(&"parent hash does not match the last block")
+				}
+				self.#system_name.reset_events();
+				self.upgrade()?;
+				Ok(crate::support::OnInitialize::on_initialize(self, header.block_number))
+			}
+
+			// Upgrade the state to the spec version of the runtime, by running the migrations of the
+			// runtime added since the spec version of the state. Every pallet, including system, must
+			// then be at the storage version of its code.
+			fn upgrade(&mut self) -> Result<(), &'static str> {
+				use crate::support::GetStorageVersion;
+
+				let from = self.#system_name.spec_version();
+				let to = <#runtime_struct as #system_module::Config>::SPEC_VERSION;
+				if from > to {
+					return Err(&"state was upgraded by a newer runtime")
+				}
+				if from < to {
+					crate::support::OnRuntimeUpgrade::try_on_runtime_upgrade(self, from, to)?;
+					self.#system_name.set_spec_version(to);
+				}
+
+				let migrated = self.#system_name.on_chain_storage_version()
+					== <#system_type>::IN_CODE_STORAGE_VERSION
+					#(
+						&& self.#pallet_names.on_chain_storage_version()
+							== <#pallet_types>::IN_CODE_STORAGE_VERSION
+					)*;
+				if !migrated {
+					return Err(&"a pallet was not migrated to its storage version")
 				}
 				Ok(())
 			}
+
+			// Execute a single extrinsic. The caller must be able to sign it, its nonce must match
+			// the extrinsic, and it pays the fee of the extrinsic before it is dispatched. The nonce
+			// is incremented and the fee is kept even if the dispatch fails.
+			fn apply_extrinsic(
+				&mut self,
+				extrinsic: #extrinsic_type,
+			) -> crate::support::ApplyExtrinsicResult<#balance_type, #event_type> {
+				use crate::support::TransactionValidityError;
+
+				let crate::support::Extrinsic { caller, nonce, tip, call } = extrinsic;
+				if !<#runtime_struct as crate::support::CheckSignature<_>>::check_signature(&caller) {
+					return Err(TransactionValidityError::BadSigner)
+				}
+				match nonce.cmp(&self.#system_name.nonce(&caller)) {
+					core::cmp::Ordering::Less => return Err(TransactionValidityError::Stale),
+					core::cmp::Ordering::Greater => return Err(TransactionValidityError::Future),
+					core::cmp::Ordering::Equal => {},
+				}
+				let fee = crate::support::TransactionPayment::compute_fee(self, &call, tip);
+				crate::support::TransactionPayment::withdraw_fee(self, &caller, fee)
+					.map_err(|_| TransactionValidityError::Payment)?;
+				self.#system_name.inc_nonce(&caller);
+
+				// The receipt records the weight the call actually consumed, if it gives it back.
+				let declared_weight = crate::support::GetDispatchInfo::weight(&call);
+				let first_event = self.#system_name.events().len();
+				let (result, weight) = match crate::support::Dispatch::dispatch(self, caller, call) {
+					Ok(post_info) => (Ok(()), post_info.calc_actual_weight(declared_weight)),
+					Err(error) => (Err(error.error), error.post_info.calc_actual_weight(declared_weight)),
+				};
+				let events = self.#system_name.events().split_off(first_event);
+				Ok(crate::support::ExtrinsicReceipt { result, weight, fee, events })
+			}
+
+			// End a block. The state must match the state root of the header, which becomes the
+			// parent of the next block.
+			fn finalize_block(&mut self, header: &#header_type) -> Result<(), &'static str> {
+				if crate::support::hash_of(self) != header.state_root {
+					return Err(&"state root does not match the state")
+				}
+				self.#system_name.set_parent_hash(crate::support::hash_of(header));
+				Ok(())
+			}
 		}
 	};
 
@@ -50,13 +240,14 @@ pub fn expand_runtime(def: RuntimeDef) -> proc_macro2::TokenStream {
 		// Note that it is just an accumulation of the calls exposed by each pallet.
 		//
 		// The parsed function names will be `snake_case`, and that will show up in the enum.
+		#[derive(Clone, Debug, PartialEq, Eq)]
 		#[allow(non_camel_case_types)]
 		pub enum RuntimeCall {
-			#( #pallet_names(#pallet_names::Call<#runtime_struct>) ),*
+			#( #pallet_names(#call_types) ),*
 		}
 
 		impl crate::support::Dispatch for #runtime_struct {
-			type Caller = <Runtime as system::Config>::AccountId;
+			type Caller = <#runtime_struct as #system_module::Config>::AccountId;
 			type Call = RuntimeCall;
 			// Dispatch a call on behalf of a caller. Increments the caller's nonce.
 			//
@@ -67,17 +258,255 @@ pub fn expand_runtime(def: RuntimeDef) -> proc_macro2::TokenStream {
 				&mut self,
 				caller: Self::Caller,
 				runtime_call: Self::Call,
-			) -> crate::support::DispatchResult {
+			) -> crate::support::DispatchResultWithPostInfo {
+				// Calls rejected by the filter of the runtime never reach their pallet.
+				if !crate::support::CallFilter::contains(self, &runtime_call) {
+					return Err(crate::support::DispatchError::CallFiltered.into());
+				}
+
 				// This match statement will allow us to correctly route `RuntimeCall`s
 				// to the appropriate pallet level call.
 				match runtime_call {
+					#(
+						RuntimeCall::#pallet_names(call) => call.dispatch(self, caller),
+					)*
+				}
+			}
+		}
+
+		impl crate::support::GetDispatchInfo for RuntimeCall {
+			fn weight(&self) -> crate::support::Weight {
+				match self {
+					#(
+						RuntimeCall::#pallet_names(call) => crate::support::GetDispatchInfo::weight(call)
+					),*
+				}
+			}
+		}
+
+		impl RuntimeCall {
+			/// The name of the pallet this call is for, as named in the runtime.
+			pub fn pallet_name(&self) -> &'static str {
+				match self {
+					#(
+						RuntimeCall::#pallet_names(_) => stringify!(#pallet_names),
+					)*
+				}
+			}
+
+			/// The name of the callable function this call is for.
+			pub fn call_name(&self) -> &'static str {
+				match self {
+					#(
+						RuntimeCall::#pallet_names(call) => call.call_name(),
+					)*
+				}
+			}
+		}
+
+		// Runtime calls are encoded as the index of the pallet in the runtime, not counting system,
+		// followed by the encoded call of the pallet.
+		impl crate::codec::Encode for RuntimeCall {
+			fn encode_to(&self, dest: &mut Vec<u8>) {
+				match self {
 					#(
 						RuntimeCall::#pallet_names(call) => {
-							self.#pallet_names.dispatch(caller, call)?;
+							crate::codec::Encode::encode_to(&#pallet_index, dest);
+							crate::codec::Encode::encode_to(call, dest);
 						}
 					),*
 				}
-				Ok(())
+			}
+		}
+
+		impl crate::codec::Decode for RuntimeCall {
+			fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+				match <u8 as crate::codec::Decode>::decode(input)? {
+					#(
+						#pallet_index => Ok(RuntimeCall::#pallet_names(crate::codec::Decode::decode(input)?)),
+					)*
+					_ => Err("invalid pallet index"),
+				}
+			}
+		}
+	};
+
+	// This quote block implements the `RuntimeEvent` and `RuntimeError` enums, which gather the events
+	// and the errors of the pallets.
+	let event_impl = quote! {
+		// These are all the events deposited in the system pallet.
+		// Note that it is just an accumulation of the events of each pallet which has `#[event]`.
+		#[derive(Clone, Debug, PartialEq, Eq)]
+		#[allow(non_camel_case_types)]
+		pub enum RuntimeEvent {
+			#( #event_pallet_names(#event_pallet_types) ),*
+		}
+
+		#(
+			impl From<#event_pallet_types> for RuntimeEvent {
+				fn from(event: #event_pallet_types) -> Self {
+					RuntimeEvent::#event_pallet_names(event)
+				}
+			}
+		)*
+
+		// Runtime events are encoded like runtime calls, as the index of their pallet followed by the
+		// encoded event of the pallet.
+		impl crate::codec::Encode for RuntimeEvent {
+			fn encode_to(&self, dest: &mut Vec<u8>) {
+				match *self {
+					#(
+						RuntimeEvent::#event_pallet_names(ref event) => {
+							crate::codec::Encode::encode_to(&#event_pallet_index, dest);
+							crate::codec::Encode::encode_to(event, dest);
+						}
+					),*
+				}
+			}
+		}
+
+		impl crate::codec::Decode for RuntimeEvent {
+			fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+				match <u8 as crate::codec::Decode>::decode(input)? {
+					#(
+						#event_pallet_index => Ok(RuntimeEvent::#event_pallet_names(crate::codec::Decode::decode(input)?)),
+					)*
+					_ => Err("invalid pallet index"),
+				}
+			}
+		}
+
+		// These are all the errors the calls of the runtime can fail with.
+		// Note that it is just an accumulation of the errors of each pallet.
+		#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+		#[allow(non_camel_case_types)]
+		pub enum RuntimeError {
+			#( #pallet_names(#error_types) ),*
+		}
+
+		impl RuntimeError {
+			// Find the error of a pallet which `error` stands for, if it is a `ModuleError`.
+			pub fn from_dispatch_error(error: &crate::support::DispatchError) -> Option<Self> {
+				let crate::support::DispatchError::Module(error) = error else { return None };
+				match error.index {
+					#(
+						#pallet_index => <#error_types>::from_index(error.error)
+							.map(RuntimeError::#pallet_names),
+					)*
+					_ => None,
+				}
+			}
+		}
+
+		// The errors of the pallets are identified by the index of their pallet in the runtime.
+		impl crate::support::PalletIndex for #runtime_struct {
+			fn index<P: 'static>() -> Option<u8> {
+				let pallet = core::any::TypeId::of::<P>();
+				#(
+					if pallet == core::any::TypeId::of::<#pallet_types>() {
+						return Some(#pallet_index)
+					}
+				)*
+				None
+			}
+		}
+	};
+
+	// This quote block describes the pallets of the runtime, so that tools outside of the runtime can
+	// discover its calls, storage, events and errors.
+	let metadata_impl = quote! {
+		impl #runtime_struct {
+			pub fn metadata() -> crate::metadata::RuntimeMetadata {
+				use crate::metadata::PalletInfo;
+
+				crate::metadata::RuntimeMetadata {
+					pallets: vec![
+						// The system pallet is not callable, so it has no index.
+						crate::metadata::PalletMetadata {
+							name: stringify!(#system_name),
+							index: None,
+							docs: <#system_type>::docs(),
+							calls: Vec::new(),
+							storage: <#system_type>::storage(),
+							events: Vec::new(),
+							errors: Vec::new(),
+						},
+						#(
+							crate::metadata::PalletMetadata {
+								name: stringify!(#pallet_names),
+								index: Some(#pallet_index),
+								docs: <#pallet_types>::docs(),
+								calls: <#call_types>::metadata(),
+								storage: <#pallet_types>::storage(),
+								events: #pallet_events,
+								errors: <#error_types>::metadata(),
+							}
+						),*
+					],
+				}
+			}
+		}
+	};
+
+	// This quote block gives access to each pallet of the runtime through `AsRef` and `AsMut`. This
+	// is how pallets reach the other pallets they depend on.
+	let access_impl = quote! {
+		impl AsRef<#system_type> for #runtime_struct {
+			fn as_ref(&self) -> &#system_type {
+				&self.#system_name
+			}
+		}
+
+		impl AsMut<#system_type> for #runtime_struct {
+			fn as_mut(&mut self) -> &mut #system_type {
+				&mut self.#system_name
+			}
+		}
+
+		#(
+			impl AsRef<#pallet_types> for #runtime_struct {
+				fn as_ref(&self) -> &#pallet_types {
+					&self.#pallet_names
+				}
+			}
+
+			impl AsMut<#pallet_types> for #runtime_struct {
+				fn as_mut(&mut self) -> &mut #pallet_types {
+					&mut self.#pallet_names
+				}
+			}
+		)*
+	};
+
+	// The pallets are handles to the storage backend of the runtime, so a copy of the runtime binds
+	// its pallets to a copy of the backend. Copying the pallets would share the backend instead.
+	let clone_impl = quote! {
+		impl Clone for #runtime_struct {
+			fn clone(&self) -> Self {
+				Self::new_in(&self.#system_name.storage_backend().snapshot())
+			}
+		}
+	};
+
+	// This quote block encodes the state of the runtime, which is how it is stored on disk. Each
+	// pallet adds the entries of its storage items, under the keys they have in the backend.
+	let storage_impl = quote! {
+		impl #runtime_struct {
+			// Encode the state of the runtime.
+			pub fn encode_storage(&self) -> crate::storage::EncodedStorage {
+				let mut storage = crate::storage::EncodedStorage::new();
+				self.#system_name.encode_storage(&mut storage);
+				#( self.#pallet_names.encode_storage(&mut storage); )*
+				storage
+			}
+
+			// Load a state encoded by `encode_storage`. The items which are not in `storage` are at
+			// their default value, and the entries which belong to no item are dropped.
+			pub fn decode_storage(storage: &crate::storage::EncodedStorage) -> Result<Self, &'static str> {
+				let mut runtime = Self::new_in(&crate::storage::Backend::new());
+				runtime.#system_name.decode_storage(storage)?;
+				#( runtime.#pallet_names.decode_storage(storage)?; )*
+				Ok(runtime)
 			}
 		}
 	};
@@ -85,7 +514,12 @@ pub fn expand_runtime(def: RuntimeDef) -> proc_macro2::TokenStream {
 	// We combine and return all the generated code.
 	quote! {
 		#dispatch_impl
+		#event_impl
 		#runtime_impl
+		#access_impl
+		#clone_impl
+		#storage_impl
+		#metadata_impl
 	}
 	.into()
 }
@@ -5,13 +5,55 @@ use syn::spanned::Spanned;
 pub struct RuntimeDef {
 	/// This is the name of the struct used by the user. We mostly assume it is `Runtime`.
 	pub runtime_struct: syn::Ident,
-	/// This is the list of pallets included in the `Runtime` struct. We omit `system` from this
-	/// list, but during parsing we check that system exists.
-	pub pallets: Vec<(syn::Ident, syn::Type)>,
+	/// This is the system pallet of the runtime. See `SystemDef`.
+	pub system: SystemDef,
+	/// The type of the blocks the runtime executes, as given by `#[macros::runtime(block = ..)]`.
+	/// It is `types::Block` by default.
+	pub block: syn::Type,
+	/// This is the list of pallets included in the `Runtime` struct, ordered by their index. We omit
+	/// `system` from this list, but during parsing we check that system exists.
+	pub pallets: Vec<PalletDef>,
+}
+
+/// This is the metadata we keep about the system pallet of the runtime.
+#[derive(Debug)]
+pub struct SystemDef {
+	/// The name of the field holding the system pallet, usually `system`.
+	pub name: syn::Ident,
+	/// The module of the system pallet, like `system`, where its `Pallet` and `Config` are found.
+	pub module: syn::Path,
+	/// The type of the system pallet, like `system::Pallet<Self>`.
+	pub ty: syn::Type,
+}
+
+/// This is the metadata we keep about each pallet in the runtime.
+#[derive(Debug)]
+pub struct PalletDef {
+	/// The name of the field holding the pallet. Different instances of the same pallet are told
+	/// apart by it.
+	pub name: syn::Ident,
+	/// The module of the pallet, like `balances`, where its `Call`, `Event` and `Error` are found.
+	pub module: syn::Path,
+	/// The generic arguments of the pallet, like `Runtime, Instance2`, with `Self` replaced by the
+	/// runtime struct. `Call`, `Event` and `Error` take the same arguments.
+	pub args: Vec<syn::GenericArgument>,
+	/// The type of the pallet, like `balances::Pallet<Self>`.
+	pub ty: syn::Type,
+	/// The index of the pallet, as given by `#[pallet_index(..)]`. Encoded runtime calls start with
+	/// it.
+	pub index: u8,
+	/// Whether the pallet deposits events, as given by `#[event]`.
+	pub event: bool,
 }
 
 impl RuntimeDef {
-	pub fn try_from(item: syn::Item) -> syn::Result<Self> {
+	/// Parse the `Runtime` struct, and the arguments of `#[macros::runtime(..)]` in `attr`.
+	///
+	/// The `#[pallet_index(..)]`, `#[event]` and `#[system]` helper attributes are removed from
+	/// `item` while parsing, since they are not real attributes and would not compile otherwise.
+	pub fn try_from(attr: proc_macro2::TokenStream, item: &mut syn::Item) -> syn::Result<Self> {
+		let block = parse_runtime_args(attr)?;
+
 		// First we check that we are parsing a `struct`.
 		let item_struct = if let syn::Item::Struct(item) = item {
 			item
@@ -19,49 +61,379 @@ impl RuntimeDef {
 			return Err(syn::Error::new(item.span(), "Invalid runtime, expected item struct"))
 		};
 
-		// We check that the `Runtime` includes the `system` pallet as the first item.
-		check_system(&item_struct)?;
+		let runtime_struct = item_struct.ident.clone();
 
-		let runtime_struct = item_struct.ident;
+		// We find the `system` pallet, which is where the runtime keeps its block number, nonces and
+		// events.
+		let system_position = find_system(item_struct)?;
 
-		// Here is where we will store a list of all the pallets.
+		// Here is where we will store a list of all the pallets, and the system pallet.
 		let mut pallets = vec![];
-		// We skip `system`, which we ensure is the first field in `check_system`.
-		for field in item_struct.fields.into_iter().skip(1) {
-			if let Some(ident) = field.ident {
-				pallets.push((ident, field.ty))
+		let mut system = None;
+		for (position, field) in item_struct.fields.iter_mut().enumerate() {
+			let name = field.ident.clone().expect("checked by `find_system`");
+			if position == system_position {
+				let (module, _) = split_pallet_type(&field.ty, &runtime_struct)?;
+				system = Some(SystemDef { name, module, ty: field.ty.clone() });
+				continue
 			}
+			let index = take_pallet_index_attr(field)?;
+			let event = take_flag_attr(field, "event")?;
+			let (module, args) = split_pallet_type(&field.ty, &runtime_struct)?;
+			pallets.push(PalletDef { name, module, args, ty: field.ty.clone(), index, event });
 		}
+		let system = system.expect("`find_system` returns the position of a field");
 
-		Ok(Self { runtime_struct, pallets })
+		// The indices of the pallets must be exactly `0..pallets.len()`, so that no index is used
+		// twice and none is skipped. We keep the pallets ordered by their index.
+		pallets.sort_by_key(|pallet| pallet.index);
+		for (expected, pallet) in pallets.iter().enumerate() {
+			if usize::from(pallet.index) < expected {
+				let msg = format!("Invalid runtime, the pallet index {} is used twice", pallet.index);
+				return Err(syn::Error::new(pallet.name.span(), msg))
+			}
+			if usize::from(pallet.index) > expected {
+				let msg = format!("Invalid runtime, the pallet index {} is missing", expected);
+				return Err(syn::Error::new(pallet.name.span(), msg))
+			}
+		}
+
+		// Each pallet is reached through `AsRef` and `AsMut` on its type, so several instances of
+		// the same pallet must have different types, usually through an instance parameter.
+		for (i, pallet) in pallets.iter().enumerate() {
+			let ty = &pallet.ty;
+			let ty = quote::quote!(#ty).to_string();
+			if pallets[..i].iter().any(|other| {
+				let other = &other.ty;
+				quote::quote!(#other).to_string() == ty
+			}) {
+				let msg = "Invalid runtime, each instance of a pallet must have a different type, \
+					like `balances::Pallet<Self, Instance2>`";
+				return Err(syn::Error::new(pallet.ty.span(), msg))
+			}
+		}
+
+		Ok(Self { runtime_struct, system, block, pallets })
 	}
 }
 
-/// This function checks that the `system` pallet is the first pallet included in the `Runtime`
-/// struct. We make many assumptions about the `system` pallet in order to keep these macros simple.
-/// For example, we assume that the system pallet has no callable functions, and that it contains
-/// specific functions like incrementing the block number and a user's nonce.
+/// Parse the arguments of `#[macros::runtime(..)]`, and return the block type of the runtime.
 ///
-/// You can consider these macros to be tightly coupled to the logic of the `system` pallet.
-fn check_system(item_struct: &syn::ItemStruct) -> syn::Result<()> {
-	// Extract the name of the first field in the `Runtime` struct.
-	let first_field_name = if let Some(first_field) = item_struct.fields.iter().next() {
-		if let Some(field_name) = &first_field.ident {
-			field_name.to_string()
+/// The only supported argument is `block = ..`, which defaults to `types::Block`.
+fn parse_runtime_args(attr: proc_macro2::TokenStream) -> syn::Result<syn::Type> {
+	let mut block = None;
+	let parser = syn::meta::parser(|meta| {
+		if meta.path.is_ident("block") {
+			block = Some(meta.value()?.parse::<syn::Type>()?);
+			Ok(())
 		} else {
-			let msg = "first field is expected to have the name system";
-			return Err(syn::Error::new(item_struct.span(), msg))
+			Err(meta.error("Invalid runtime, the only supported argument is `block = ..`"))
 		}
-	} else {
+	});
+	syn::parse::Parser::parse2(parser, attr)?;
+	Ok(block.unwrap_or_else(|| syn::parse_quote!(types::Block)))
+}
+
+/// Split the type of a pallet, like `balances::Pallet<Self, Instance2>`, into the module of the
+/// pallet and its generic arguments. `Self` is replaced by the runtime struct in the arguments, so
+/// they can be used outside of the `impl` blocks of the runtime.
+fn split_pallet_type(
+	ty: &syn::Type,
+	runtime_struct: &syn::Ident,
+) -> syn::Result<(syn::Path, Vec<syn::GenericArgument>)> {
+	let msg = "Invalid runtime, expected the type of the pallet like `balances::Pallet<Self>`";
+	let syn::Type::Path(type_path) = ty else { return Err(syn::Error::new(ty.span(), msg)) };
+	if type_path.qself.is_some() || type_path.path.segments.len() < 2 {
+		return Err(syn::Error::new(ty.span(), msg))
+	}
+
+	let mut module = type_path.path.clone();
+	let pallet = module.segments.pop().expect("checked above").into_value();
+	// Popping leaves a trailing `::`, which we remove.
+	let last = module.segments.pop().expect("checked above").into_value();
+	module.segments.push(last);
+
+	let args = match pallet.arguments {
+		syn::PathArguments::AngleBracketed(args) => args
+			.args
+			.into_iter()
+			.map(|arg| {
+				let tokens = crate::utils::replace_self(quote::quote!(#arg), runtime_struct);
+				syn::parse2(tokens)
+			})
+			.collect::<syn::Result<_>>()?,
+		_ => return Err(syn::Error::new(ty.span(), msg)),
+	};
+	Ok((module, args))
+}
+
+/// Remove the `#[pallet_index(..)]` attribute from a field of the `Runtime` struct, and return its
+/// index.
+///
+/// Every pallet but system must have exactly one `#[pallet_index(..)]` attribute, so that reordering
+/// the pallets does not change how their calls are encoded.
+fn take_pallet_index_attr(field: &mut syn::Field) -> syn::Result<u8> {
+	let mut index = None;
+	let mut result = Ok(());
+	field.attrs.retain(|attr| {
+		if !attr.path().is_ident("pallet_index") {
+			return true
+		}
+		if index.is_some() {
+			let msg = "Invalid runtime, only one `#[pallet_index(..)]` attribute is allowed";
+			result = Err(syn::Error::new(attr.span(), msg));
+		}
+		match attr.parse_args::<syn::LitInt>().and_then(|lit| lit.base10_parse::<u8>()) {
+			Ok(value) => index = Some(value),
+			Err(e) => result = Err(e),
+		}
+		false
+	});
+	result?;
+	index.ok_or_else(|| {
+		let msg = "Invalid runtime, missing `#[pallet_index(..)]` attribute";
+		syn::Error::new(field.span(), msg)
+	})
+}
+
+/// Remove the `#[<name>]` attribute, like `#[event]`, from a field of the `Runtime` struct, and
+/// return whether it was there.
+fn take_flag_attr(field: &mut syn::Field, name: &str) -> syn::Result<bool> {
+	let mut found = false;
+	let mut result = Ok(());
+	field.attrs.retain(|attr| {
+		if !attr.path().is_ident(name) {
+			return true
+		}
+		if found {
+			let msg = format!("Invalid runtime, only one `#[{}]` attribute is allowed", name);
+			result = Err(syn::Error::new(attr.span(), msg));
+		}
+		if let Err(e) = attr.meta.require_path_only() {
+			result = Err(e);
+		}
+		found = true;
+		false
+	});
+	result.map(|_| found)
+}
+
+/// This function finds the `system` pallet in the `Runtime` struct, and returns its position among
+/// the fields. It is the field marked with `#[system]`, which is removed, or else the field named
+/// `system`. We make many assumptions about the `system` pallet in order to keep these macros
+/// simple. For example, we assume that the system pallet has no callable functions, and that it
+/// contains specific functions like incrementing the block number and a user's nonce.
+///
+/// You can consider these macros to be tightly coupled to the logic of the `system` pallet.
+fn find_system(item_struct: &mut syn::ItemStruct) -> syn::Result<usize> {
+	if item_struct.fields.is_empty() {
 		let msg = "runtime struct is expected to have fields";
 		return Err(syn::Error::new(item_struct.span(), msg))
+	}
+	if let Some(field) = item_struct.fields.iter().find(|field| field.ident.is_none()) {
+		let msg = "runtime struct is expected to have named fields";
+		return Err(syn::Error::new(field.span(), msg))
+	}
+
+	// Look for the field marked with `#[system]`, falling back to the field named `system`.
+	let mut marked = vec![];
+	for (position, field) in item_struct.fields.iter_mut().enumerate() {
+		if take_flag_attr(field, "system")? {
+			marked.push(position);
+		}
+	}
+	let position = match marked[..] {
+		[position] => position,
+		[] => item_struct
+			.fields
+			.iter()
+			.position(|field| field.ident.as_ref().is_some_and(|ident| ident == "system"))
+			.ok_or_else(|| {
+				let msg = "Invalid runtime, expected a field named `system`, or a field marked with \
+					`#[system]`";
+				syn::Error::new(item_struct.ident.span(), msg)
+			})?,
+		[_, second, ..] => {
+			let field = item_struct.fields.iter().nth(second).expect("found above");
+			let msg = "Invalid runtime, only one field can be marked with `#[system]`";
+			return Err(syn::Error::new(field.span(), msg))
+		},
 	};
 
-	// Check if the first field is named "system"
-	if first_field_name != "system" {
-		let msg = "first field is expected to be named system";
-		return Err(syn::Error::new(item_struct.span(), msg))
+	// The system pallet is not callable, so it has no index.
+	let system = item_struct.fields.iter().nth(position).expect("found above");
+	if let Some(attr) = system.attrs.iter().find(|attr| attr.path().is_ident("pallet_index")) {
+		let msg = "the system pallet is not callable, so it cannot have a `#[pallet_index(..)]`";
+		return Err(syn::Error::new(attr.span(), msg))
 	}
 
-	Ok(())
+	Ok(position)
+}
+
+#[cfg(test)]
+mod test {
+	use super::RuntimeDef;
+
+	fn parse_error(mut item: syn::Item) -> String {
+		RuntimeDef::try_from(Default::default(), &mut item).unwrap_err().to_string()
+	}
+
+	#[test]
+	fn pallet_indices_are_checked() {
+		let mut item: syn::Item = syn::parse_quote! {
+			pub struct Runtime {
+				system: system::Pallet<Self>,
+				#[pallet_index(1)]
+				#[event]
+				balances: balances::Pallet<Self>,
+				#[pallet_index(0)]
+				proof_of_existence: proof_of_existence::Pallet<Self>,
+			}
+		};
+		let def = RuntimeDef::try_from(Default::default(), &mut item).unwrap();
+		let pallets =
+			def.pallets.iter().map(|p| (p.name.to_string(), p.index, p.event)).collect::<Vec<_>>();
+		assert_eq!(
+			pallets,
+			vec![("proof_of_existence".to_string(), 0, false), ("balances".to_string(), 1, true)]
+		);
+		// The helper attributes are removed.
+		assert!(!quote::quote!(#item).to_string().contains("pallet_index"));
+		assert!(!quote::quote!(#item).to_string().contains("event"));
+
+		assert_eq!(
+			parse_error(syn::parse_quote! {
+				pub struct Runtime {
+					system: system::Pallet<Self>,
+					balances: balances::Pallet<Self>,
+				}
+			}),
+			"Invalid runtime, missing `#[pallet_index(..)]` attribute"
+		);
+		assert_eq!(
+			parse_error(syn::parse_quote! {
+				pub struct Runtime {
+					system: system::Pallet<Self>,
+					#[pallet_index(0)]
+					balances: balances::Pallet<Self>,
+					#[pallet_index(0)]
+					assets: assets::Pallet<Self>,
+				}
+			}),
+			"Invalid runtime, the pallet index 0 is used twice"
+		);
+		assert_eq!(
+			parse_error(syn::parse_quote! {
+				pub struct Runtime {
+					system: system::Pallet<Self>,
+					#[pallet_index(1)]
+					balances: balances::Pallet<Self>,
+				}
+			}),
+			"Invalid runtime, the pallet index 0 is missing"
+		);
+		assert_eq!(
+			parse_error(syn::parse_quote! {
+				pub struct Runtime {
+					#[pallet_index(0)]
+					system: system::Pallet<Self>,
+				}
+			}),
+			"the system pallet is not callable, so it cannot have a `#[pallet_index(..)]`"
+		);
+	}
+
+	#[test]
+	fn system_and_block_are_configurable() {
+		let mut item: syn::Item = syn::parse_quote! {
+			pub struct Runtime {
+				#[pallet_index(0)]
+				balances: balances::Pallet<Self>,
+				#[system]
+				chain: frame::system::Pallet<Self>,
+			}
+		};
+		let attr = quote::quote!(block = types::OpaqueBlock);
+		let def = RuntimeDef::try_from(attr, &mut item).unwrap();
+		assert_eq!(def.system.name.to_string(), "chain");
+		let module = &def.system.module;
+		assert_eq!(quote::quote!(#module).to_string(), "frame :: system");
+		let block = &def.block;
+		assert_eq!(quote::quote!(#block).to_string(), "types :: OpaqueBlock");
+		assert_eq!(def.pallets.len(), 1);
+		assert!(!quote::quote!(#item).to_string().contains("# [system]"));
+
+		// The block type is `types::Block` by default.
+		let mut item: syn::Item = syn::parse_quote! {
+			pub struct Runtime {
+				system: system::Pallet<Self>,
+			}
+		};
+		let def = RuntimeDef::try_from(Default::default(), &mut item).unwrap();
+		let block = &def.block;
+		assert_eq!(quote::quote!(#block).to_string(), "types :: Block");
+
+		assert_eq!(
+			parse_error(syn::parse_quote! {
+				pub struct Runtime {
+					chain: system::Pallet<Self>,
+				}
+			}),
+			"Invalid runtime, expected a field named `system`, or a field marked with `#[system]`"
+		);
+		let mut item: syn::Item = syn::parse_quote! {
+			pub struct Runtime {
+				system: system::Pallet<Self>,
+			}
+		};
+		let error = RuntimeDef::try_from(quote::quote!(header = types::Header), &mut item);
+		assert_eq!(
+			error.unwrap_err().to_string(),
+			"Invalid runtime, the only supported argument is `block = ..`"
+		);
+	}
+
+	#[test]
+	fn pallets_can_have_instances() {
+		let mut item: syn::Item = syn::parse_quote! {
+			pub struct Runtime {
+				system: system::Pallet<Self>,
+				#[pallet_index(0)]
+				balances: balances::Pallet<Self>,
+				#[pallet_index(1)]
+				dollars: balances::Pallet<Self, balances::Instance2>,
+			}
+		};
+		let def = RuntimeDef::try_from(Default::default(), &mut item).unwrap();
+		let dollars = &def.pallets[1];
+		assert_eq!(dollars.name.to_string(), "dollars");
+		let (module, args) = (&dollars.module, &dollars.args);
+		assert_eq!(quote::quote!(#module).to_string(), "balances");
+		// `Self` is replaced by the runtime struct.
+		assert_eq!(quote::quote!(#( #args ),*).to_string(), "Runtime , balances :: Instance2");
+
+		assert_eq!(
+			parse_error(syn::parse_quote! {
+				pub struct Runtime {
+					system: system::Pallet<Self>,
+					#[pallet_index(0)]
+					balances: balances::Pallet<Self>,
+					#[pallet_index(1)]
+					dollars: balances::Pallet<Self>,
+				}
+			}),
+			"Invalid runtime, each instance of a pallet must have a different type, like \
+			 `balances::Pallet<Self, Instance2>`"
+		);
+		assert_eq!(
+			parse_error(syn::parse_quote! {
+				pub struct Runtime {
+					system: system::Pallet<Self>,
+					#[pallet_index(0)]
+					balances: Balances,
+				}
+			}),
+			"Invalid runtime, expected the type of the pallet like `balances::Pallet<Self>`"
+		);
+	}
 }
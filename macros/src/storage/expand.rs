@@ -0,0 +1,147 @@
+use super::parse::{StorageDef, StorageKind};
+use quote::quote;
+
+/// See the `fn storage` docs at the `lib.rs` of this crate for a high level definition.
+pub fn expand_storage(def: StorageDef) -> proc_macro2::TokenStream {
+	let StorageDef { pallet_struct, generics, items, version } = def;
+	let (impl_generics, type_generics, where_clause) = generics.split_for_impl();
+
+	// This is a vector of the expressions binding each storage item to the backend, under its name.
+	// Values start with their default value, unless the backend already has one.
+	let item_name = items.iter().map(|item| &item.name).collect::<Vec<_>>();
+	let item_init = items
+		.iter()
+		.map(|item| {
+			let name = item.name.to_string();
+			match (item.kind, &item.default) {
+				(StorageKind::Value, Some(default)) => {
+					quote!(crate::storage::StorageValue::new(backend, pallet, #name, #default))
+				},
+				(StorageKind::Value, None) => quote! {
+					crate::storage::StorageValue::new(backend, pallet, #name, Default::default())
+				},
+				(StorageKind::Map, _) => quote!(crate::storage::StorageMap::new(backend, pallet, #name)),
+				(StorageKind::DoubleMap, _) => {
+					quote!(crate::storage::StorageDoubleMap::new(backend, pallet, #name))
+				},
+			}
+		})
+		.collect::<Vec<_>>();
+
+	// This is a vector of the getter functions of the storage items which have a `#[getter(..)]`.
+	// Getters of maps return the default value of the item for missing keys, or `None` if the item
+	// has no default value.
+	let getters = items
+		.iter()
+		.filter_map(|item| {
+			let getter = item.getter.as_ref()?;
+			let name = &item.name;
+			let value = &item.value;
+			let docs = &item.docs;
+			let key = (0..item.keys.len())
+				.map(|i| quote::format_ident!("key{}", i + 1))
+				.collect::<Vec<_>>();
+			let key_type = &item.keys;
+			let getter = match (item.kind, &item.default) {
+				(StorageKind::Value, _) => quote! {
+					pub fn #getter(&self) -> #value {
+						self.#name.get()
+					}
+				},
+				(_, Some(default)) => quote! {
+					pub fn #getter(&self, #( #key: &#key_type ),*) -> #value {
+						self.#name.get(#( #key ),*).unwrap_or_else(|| #default)
+					}
+				},
+				(_, None) => quote! {
+					pub fn #getter(&self, #( #key: &#key_type ),*) -> Option<#value> {
+						self.#name.get(#( #key ),*)
+					}
+				},
+			};
+			Some(quote! {
+				#( #[doc = #docs] )*
+				#getter
+			})
+		})
+		.collect::<Vec<_>>();
+
+	// The types of the keys and values of all the items, which must be encodable for the state of
+	// the pallet to be encoded.
+	let item_type = items.iter().flat_map(|item| item.keys.iter().chain([&item.value])).collect::<Vec<_>>();
+
+	quote! {
+		impl #impl_generics #pallet_struct #type_generics #where_clause {
+			/// Create a new instance of the pallet, with every storage item at its default value, in
+			/// a backend of its own. The pallet is named after its module. The state of a new pallet
+			/// is at the storage version of the code, since there is nothing to migrate.
+			pub fn new() -> Self {
+				use crate::support::GetStorageVersion;
+
+				let name = module_path!().rsplit("::").next().unwrap_or_default();
+				let mut pallet = Self::new_in(&crate::storage::Backend::new(), name);
+				pallet.set_on_chain_storage_version(Self::IN_CODE_STORAGE_VERSION);
+				pallet
+			}
+
+			/// Bind the storage items of the pallet to `backend`, under the name `pallet`. Items
+			/// which are not in the backend yet start at their default value, and a state without
+			/// a storage version is at version 0.
+			pub fn new_in(backend: &crate::storage::Backend, pallet: &str) -> Self {
+				Self {
+					#( #item_name: #item_init, )*
+					storage_version: crate::storage::StorageValue::new(
+						backend,
+						pallet,
+						"storage_version",
+						crate::support::StorageVersion::default(),
+					),
+				}
+			}
+
+			/// The backend the storage items of the pallet are bound to.
+			pub fn storage_backend(&self) -> &crate::storage::Backend {
+				self.storage_version.backend()
+			}
+
+			/// Add the state of the pallet, encoded, to `storage`.
+			pub fn encode_storage(&self, storage: &mut crate::storage::EncodedStorage)
+			where
+				#( #item_type: crate::codec::Encode, )*
+			{
+				#( self.#item_name.encode_into(storage); )*
+				self.storage_version.encode_into(storage);
+			}
+
+			/// Set the state of the pallet from `storage`. Items which are not in `storage` are left
+			/// as they are.
+			pub fn decode_storage(
+				&mut self,
+				storage: &crate::storage::EncodedStorage,
+			) -> Result<(), &'static str>
+			where
+				#( #item_type: crate::codec::Decode, )*
+			{
+				#( self.#item_name.decode_from(storage)?; )*
+				self.storage_version.decode_from(storage)
+			}
+
+			#( #getters )*
+		}
+
+		impl #impl_generics crate::support::GetStorageVersion for #pallet_struct #type_generics
+		#where_clause
+		{
+			const IN_CODE_STORAGE_VERSION: crate::support::StorageVersion =
+				crate::support::StorageVersion(#version);
+
+			fn on_chain_storage_version(&self) -> crate::support::StorageVersion {
+				self.storage_version.get()
+			}
+
+			fn set_on_chain_storage_version(&mut self, version: crate::support::StorageVersion) {
+				self.storage_version.put(version);
+			}
+		}
+	}
+}
@@ -0,0 +1,28 @@
+pub mod expand;
+pub mod parse;
+
+/// See the `fn storage` docs at the `lib.rs` of this crate for a high level definition.
+pub fn storage(
+	attr: proc_macro::TokenStream,
+	item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+	let mut item_struct = syn::parse_macro_input!(item as syn::Item);
+
+	// First we parse the storage items of the pallet and the arguments of the macro. Parsing removes
+	// the helper attributes like `#[getter(..)]` from `item_struct`, and adds the field keeping the
+	// storage version...
+	let def = parse::StorageDef::try_from(attr.into(), &mut item_struct);
+	let generated: proc_macro::TokenStream = match def {
+		// ..then we generate our new code.
+		Ok(def) => expand::expand_storage(def).into(),
+		Err(e) => e.to_compile_error().into(),
+	};
+
+	// Our macro only adds new code, so our final product contains all of our old code too, minus
+	// the helper attributes and plus the `storage_version` field.
+	let mut finished: proc_macro::TokenStream = quote::quote!(#item_struct).into();
+
+	// Add our generated code to the end, and return the final result.
+	finished.extend(generated);
+	return finished;
+}
@@ -0,0 +1,249 @@
+use crate::utils::get_doc_literals;
+use syn::spanned::Spanned;
+
+/// This object will collect all the information we need to keep while parsing the pallet struct.
+#[derive(Debug)]
+pub struct StorageDef {
+	/// This is the name of the pallet struct. We mostly assume it is `Pallet`.
+	pub pallet_struct: syn::Ident,
+	/// The generics of the pallet struct, usually `<T: Config>`.
+	pub generics: syn::Generics,
+	/// This is the list of the storage items of the pallet, one per field. See `StorageItemDef`.
+	pub items: Vec<StorageItemDef>,
+	/// The storage version of the pallet, as given by `#[macros::storage(version = ..)]`.
+	pub version: u16,
+}
+
+/// This is the metadata we keep about each storage item of the pallet.
+#[derive(Debug)]
+pub struct StorageItemDef {
+	/// The name of the field holding the storage item.
+	pub name: syn::Ident,
+	/// What kind of storage item it is. See `StorageKind`.
+	pub kind: StorageKind,
+	/// The types of the keys of the item: none for a value, one for a map and two for a double map.
+	pub keys: Vec<syn::Type>,
+	/// The type of the values of the item.
+	pub value: syn::Type,
+	/// The name of the getter function, as given by `#[getter(..)]`.
+	pub getter: Option<syn::Ident>,
+	/// The default value of the item, as given by `#[default(..)]`. `None` when the attribute is not
+	/// used.
+	pub default: Option<syn::Expr>,
+	/// The doc comments of the field, one line per item, which the getter is documented with.
+	pub docs: Vec<String>,
+}
+
+/// The type of a storage item.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageKind {
+	/// `StorageValue<V>`
+	Value,
+	/// `StorageMap<K, V>`
+	Map,
+	/// `StorageDoubleMap<K1, K2, V>`
+	DoubleMap,
+}
+
+impl StorageDef {
+	/// Parse the storage items of a pallet struct, and the arguments of `#[macros::storage(..)]` in
+	/// `attr`.
+	///
+	/// Helper attributes like `#[getter(..)]` are removed from `item` while parsing, since they are
+	/// not real attributes and would not compile otherwise. The `storage_version` field, which keeps
+	/// the storage version of the state of the pallet, is added to `item`.
+	pub fn try_from(attr: proc_macro2::TokenStream, item: &mut syn::Item) -> syn::Result<Self> {
+		let version = parse_storage_args(attr)?;
+
+		// First we check that we are parsing a `struct` with named fields.
+		let item_struct = if let syn::Item::Struct(item) = item {
+			item
+		} else {
+			return Err(syn::Error::new(item.span(), "Invalid storage, expected item struct"))
+		};
+		let fields = if let syn::Fields::Named(fields) = &mut item_struct.fields {
+			fields
+		} else {
+			let msg = "Invalid storage, expected a struct with named fields";
+			return Err(syn::Error::new(item_struct.fields.span(), msg))
+		};
+
+		let mut items = vec![];
+		for field in fields.named.iter_mut() {
+			if field.ident.as_ref().is_some_and(|name| name == "storage_version") {
+				let msg = "Invalid storage, `storage_version` is reserved for the storage version";
+				return Err(syn::Error::new(field.span(), msg))
+			}
+			let (kind, mut types) = parse_storage_type(&field.ty)?;
+			let value = types.pop().expect("storage types have a value type");
+			let getter = take_attr::<syn::Ident>(field, "getter")?;
+			let default = take_attr::<syn::Expr>(field, "default")?;
+			items.push(StorageItemDef {
+				name: field.ident.clone().expect("named fields have an ident"),
+				kind,
+				keys: types,
+				value,
+				getter,
+				default,
+				docs: get_doc_literals(&field.attrs),
+			});
+		}
+		fields.named.push(syn::parse_quote! {
+			/// The storage version of the state of the pallet.
+			storage_version: crate::storage::StorageValue<crate::support::StorageVersion>
+		});
+
+		Ok(Self {
+			pallet_struct: item_struct.ident.clone(),
+			generics: item_struct.generics.clone(),
+			items,
+			version,
+		})
+	}
+}
+
+/// Parse the arguments of `#[macros::storage(..)]`, and return the storage version of the pallet.
+///
+/// The only supported argument is `version = ..`, which defaults to `0`.
+fn parse_storage_args(attr: proc_macro2::TokenStream) -> syn::Result<u16> {
+	let mut version = None;
+	let parser = syn::meta::parser(|meta| {
+		if meta.path.is_ident("version") {
+			version = Some(meta.value()?.parse::<syn::LitInt>()?.base10_parse::<u16>()?);
+			Ok(())
+		} else {
+			Err(meta.error("Invalid storage, the only supported argument is `version = ..`"))
+		}
+	});
+	syn::parse::Parser::parse2(parser, attr)?;
+	Ok(version.unwrap_or(0))
+}
+
+/// Get the kind of the storage item of type `ty`, and its generic arguments: the types of its keys,
+/// followed by the type of its values.
+pub fn parse_storage_type(ty: &syn::Type) -> syn::Result<(StorageKind, Vec<syn::Type>)> {
+	let msg = "Invalid storage, expected a `StorageValue`, `StorageMap` or `StorageDoubleMap`";
+	let segment = match ty {
+		syn::Type::Path(path) => path.path.segments.last(),
+		_ => None,
+	}
+	.ok_or_else(|| syn::Error::new(ty.span(), msg))?;
+
+	let (kind, arity) = match segment.ident.to_string().as_str() {
+		"StorageValue" => (StorageKind::Value, 1),
+		"StorageMap" => (StorageKind::Map, 2),
+		"StorageDoubleMap" => (StorageKind::DoubleMap, 3),
+		_ => return Err(syn::Error::new(ty.span(), msg)),
+	};
+	let types = match &segment.arguments {
+		syn::PathArguments::AngleBracketed(args) => args
+			.args
+			.iter()
+			.filter_map(|arg| match arg {
+				syn::GenericArgument::Type(ty) => Some(ty.clone()),
+				_ => None,
+			})
+			.collect::<Vec<_>>(),
+		_ => vec![],
+	};
+	if types.len() != arity {
+		let msg = format!("Invalid storage, `{}` expects {} type arguments", segment.ident, arity);
+		return Err(syn::Error::new(segment.arguments.span(), msg))
+	}
+	Ok((kind, types))
+}
+
+/// Remove the helper attribute `#[name(..)]` from a field, and return its argument.
+///
+/// Only a single attribute of each name is allowed per field.
+fn take_attr<A: syn::parse::Parse>(field: &mut syn::Field, name: &str) -> syn::Result<Option<A>> {
+	let mut value = None;
+	let mut result = Ok(());
+	field.attrs.retain(|attr| {
+		if !attr.path().is_ident(name) {
+			return true
+		}
+		if value.is_some() {
+			let msg = format!("Invalid storage, only one `#[{}(..)]` attribute is allowed", name);
+			result = Err(syn::Error::new(attr.span(), msg));
+		}
+		match attr.parse_args::<A>() {
+			Ok(arg) => value = Some(arg),
+			Err(e) => result = Err(e),
+		}
+		false
+	});
+	result.map(|_| value)
+}
+
+#[cfg(test)]
+mod test {
+	use super::{StorageDef, StorageKind};
+
+	#[test]
+	fn storage_items_are_parsed() {
+		let mut item: syn::Item = syn::parse_quote! {
+			/// The pallet.
+			pub struct Pallet<T: Config> {
+				/// The balances.
+				#[getter(balance)]
+				#[default(T::Balance::zero())]
+				balances: StorageMap<T::AccountId, T::Balance>,
+				locks: crate::storage::StorageDoubleMap<T::AccountId, LockIdentifier, T::Balance>,
+			}
+		};
+		let def = StorageDef::try_from(quote::quote!(), &mut item).unwrap();
+		assert_eq!(def.version, 0);
+		assert_eq!(def.items[0].kind, StorageKind::Map);
+		assert_eq!(def.items[0].getter.as_ref().map(ToString::to_string), Some("balance".into()));
+		assert!(def.items[0].default.is_some());
+		assert_eq!(def.items[0].docs, vec!["The balances."]);
+		assert_eq!(def.items[1].kind, StorageKind::DoubleMap);
+		assert_eq!(def.items[1].keys.len(), 2);
+		assert!(def.items[1].getter.is_none());
+		// The helper attributes are removed, and the storage version is kept in its own field.
+		assert!(!quote::quote!(#item).to_string().contains("getter"));
+		assert!(quote::quote!(#item).to_string().contains("storage_version"));
+		assert_eq!(def.items.len(), 2);
+
+		let mut item: syn::Item = syn::parse_quote! {
+			pub struct Pallet<T: Config> {
+				balances: StorageMap<T::AccountId, T::Balance>,
+			}
+		};
+		let def = StorageDef::try_from(quote::quote!(version = 2), &mut item).unwrap();
+		assert_eq!(def.version, 2);
+		let error = StorageDef::try_from(quote::quote!(revision = 2), &mut item).unwrap_err();
+		assert_eq!(
+			error.to_string(),
+			"Invalid storage, the only supported argument is `version = ..`"
+		);
+
+		let mut item: syn::Item = syn::parse_quote! {
+			pub struct Pallet<T: Config> {
+				storage_version: StorageValue<u16>,
+			}
+		};
+		let error = StorageDef::try_from(quote::quote!(), &mut item).unwrap_err().to_string();
+		assert_eq!(error, "Invalid storage, `storage_version` is reserved for the storage version");
+
+		let mut item: syn::Item = syn::parse_quote! {
+			pub struct Pallet<T: Config> {
+				balances: BTreeMap<T::AccountId, T::Balance>,
+			}
+		};
+		let error = StorageDef::try_from(quote::quote!(), &mut item).unwrap_err().to_string();
+		assert_eq!(
+			error,
+			"Invalid storage, expected a `StorageValue`, `StorageMap` or `StorageDoubleMap`"
+		);
+
+		let mut item: syn::Item = syn::parse_quote! {
+			pub struct Pallet<T: Config> {
+				balances: StorageMap<T::Balance>,
+			}
+		};
+		let error = StorageDef::try_from(quote::quote!(), &mut item).unwrap_err().to_string();
+		assert_eq!(error, "Invalid storage, `StorageMap` expects 2 type arguments");
+	}
+}
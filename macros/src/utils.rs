@@ -0,0 +1,70 @@
+use quote::ToTokens;
+
+/// Get the lines of the doc comments in `attrs`, without the space which follows `///`.
+pub fn get_doc_literals(attrs: &[syn::Attribute]) -> Vec<String> {
+	attrs
+		.iter()
+		.filter_map(|attr| match &attr.meta {
+			syn::Meta::NameValue(meta) if meta.path.is_ident("doc") => match &meta.value {
+				syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(doc), .. }) => {
+					let doc = doc.value();
+					Some(doc.strip_prefix(' ').unwrap_or(&doc).to_string())
+				},
+				_ => None,
+			},
+			_ => None,
+		})
+		.collect()
+}
+
+/// Write `ty` the way it is usually written in source code, like `Vec<T::AccountId>`, rather than
+/// with a space between every token.
+pub fn type_to_string(ty: &syn::Type) -> String {
+	ty.to_token_stream()
+		.to_string()
+		.replace(" :: ", "::")
+		.replace(":: ", "::")
+		.replace(" <", "<")
+		.replace("< ", "<")
+		.replace(" >", ">")
+		.replace(" ,", ",")
+		.replace("& ", "&")
+		.replace("( ", "(")
+		.replace(" )", ")")
+		.replace("[ ", "[")
+		.replace(" ]", "]")
+		.replace(" ;", ";")
+}
+
+/// Add a hidden variant to a generic enum of a pallet, which uses its generic parameters even when
+/// no other variant does. It holds `Infallible`, so it can never be constructed.
+pub fn add_phantom_variant(item_enum: &mut syn::ItemEnum) {
+	let params = item_enum.generics.type_params().map(|param| &param.ident).collect::<Vec<_>>();
+	item_enum.variants.push(syn::parse_quote! {
+		#[doc(hidden)]
+		__Ignore(core::marker::PhantomData<( #( #params, )* )>, core::convert::Infallible)
+	});
+}
+
+/// Replace every `Self` in `tokens` with `ident`, so that types written inside an `impl` block can
+/// be used outside of it.
+pub fn replace_self(
+	tokens: proc_macro2::TokenStream,
+	ident: &syn::Ident,
+) -> proc_macro2::TokenStream {
+	tokens
+		.into_iter()
+		.map(|token| match token {
+			proc_macro2::TokenTree::Ident(self_ident) if self_ident == "Self" => {
+				proc_macro2::TokenTree::Ident(ident.clone())
+			},
+			proc_macro2::TokenTree::Group(group) => {
+				let mut replaced =
+					proc_macro2::Group::new(group.delimiter(), replace_self(group.stream(), ident));
+				replaced.set_span(group.span());
+				proc_macro2::TokenTree::Group(replaced)
+			},
+			token => token,
+		})
+		.collect()
+}
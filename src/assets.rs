@@ -0,0 +1,452 @@
+use core::fmt::Debug;
+use num::traits::{CheckedAdd, CheckedSub, Zero};
+
+use crate::{
+	codec::{Decode, Encode},
+	storage::{StorageDoubleMap, StorageMap},
+	support::{DispatchError, DispatchResult, EnsureOrigin},
+};
+
+/// Assets share the `Balance` type of the balances pallet, and so its numeric bounds.
+pub trait Config: crate::balances::Config<RuntimeEvent: From<Event<Self>>> {
+	/// The type used to identify an asset.
+	type AssetId: Debug + Encode + Ord + Copy;
+	/// The origin allowed to create assets, since creating an asset requires no deposit.
+	type CreateOrigin: EnsureOrigin<Self::AccountId>;
+}
+
+/// The first key of an approval: `(asset, owner)`.
+type ApprovalKey<T> = (<T as Config>::AssetId, <T as crate::system::Config>::AccountId);
+
+/// The details of an asset.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AssetDetails<AccountId, Balance> {
+	/// The account which can mint, burn, freeze and thaw the asset, and set its metadata.
+	pub admin: AccountId,
+	/// The total amount of the asset held by all accounts.
+	pub supply: Balance,
+}
+
+/// The metadata of an asset, which is only informative.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AssetMetadata {
+	pub name: String,
+	pub symbol: String,
+	/// The number of decimals used to display an amount of the asset.
+	pub decimals: u8,
+}
+
+impl<AccountId: Encode, Balance: Encode> Encode for AssetDetails<AccountId, Balance> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.admin.encode_to(dest);
+		self.supply.encode_to(dest);
+	}
+}
+
+impl<AccountId: Decode, Balance: Decode> Decode for AssetDetails<AccountId, Balance> {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(AssetDetails { admin: AccountId::decode(input)?, supply: Balance::decode(input)? })
+	}
+}
+
+impl Encode for AssetMetadata {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.name.encode_to(dest);
+		self.symbol.encode_to(dest);
+		self.decimals.encode_to(dest);
+	}
+}
+
+impl Decode for AssetMetadata {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(AssetMetadata {
+			name: String::decode(input)?,
+			symbol: String::decode(input)?,
+			decimals: u8::decode(input)?,
+		})
+	}
+}
+
+/// This is the Assets Module.
+/// It manages fungible assets other than the native balance, each identified by an `AssetId`.
+#[macros::storage]
+#[derive(Debug, macros::PalletInfo)]
+pub struct Pallet<T: Config> {
+	/// The details of each asset.
+	assets: StorageMap<T::AssetId, AssetDetails<T::AccountId, T::Balance>>,
+	/// The balance of each account, for each asset.
+	#[getter(balance)]
+	#[default(T::Balance::zero())]
+	accounts: StorageDoubleMap<T::AssetId, T::AccountId, T::Balance>,
+	/// The amount a delegate can transfer on behalf of an owner.
+	approvals: StorageDoubleMap<ApprovalKey<T>, T::AccountId, T::Balance>,
+	/// The accounts which cannot transfer an asset.
+	frozen: StorageDoubleMap<T::AssetId, T::AccountId, ()>,
+	/// The metadata of each asset.
+	metadata: StorageMap<T::AssetId, AssetMetadata>,
+}
+
+/// The events of this pallet.
+#[macros::event]
+pub enum Event<T: Config> {
+	/// The asset `id` was created, administrated by `admin`.
+	Created { id: T::AssetId, admin: T::AccountId },
+	/// `amount` of the asset `id` was created in the account of `owner`.
+	Issued { id: T::AssetId, owner: T::AccountId, amount: T::Balance },
+	/// `amount` of the asset `id` was destroyed from the account of `owner`.
+	Burned { id: T::AssetId, owner: T::AccountId, amount: T::Balance },
+	/// `amount` of the asset `id` was transferred from `from` to `to`.
+	Transferred { id: T::AssetId, from: T::AccountId, to: T::AccountId, amount: T::Balance },
+	/// `delegate` can transfer up to `amount` of the asset `id` on behalf of `owner`.
+	ApprovedTransfer {
+		id: T::AssetId,
+		owner: T::AccountId,
+		delegate: T::AccountId,
+		amount: T::Balance,
+	},
+	/// `who` can no longer transfer the asset `id`.
+	Frozen { id: T::AssetId, who: T::AccountId },
+	/// `who` can transfer the asset `id` again.
+	Thawed { id: T::AssetId, who: T::AccountId },
+	/// The metadata of the asset `id` was set.
+	MetadataSet { id: T::AssetId, name: String, symbol: String, decimals: u8 },
+}
+
+/// The errors of this pallet.
+#[macros::error]
+pub enum Error<T: Config> {
+	/// The asset does not exist.
+	UnknownAsset,
+	/// The caller is not the admin of this asset.
+	NoPermission,
+	/// The account is frozen.
+	Frozen,
+	/// The account does not have enough funds.
+	BalanceLow,
+	/// An arithmetic operation overflowed.
+	Overflow,
+	/// The asset already exists.
+	AssetExists,
+	/// The amount exceeds the approved amount.
+	Unapproved,
+}
+
+impl<T: Config> Pallet<T> {
+	/// Get the details of an asset, if it exists.
+	pub fn asset(&self, id: &T::AssetId) -> Option<AssetDetails<T::AccountId, T::Balance>> {
+		self.assets.get(id)
+	}
+
+	/// Get the total supply of the asset `id`, returning zero if it does not exist.
+	pub fn total_supply(&self, id: &T::AssetId) -> T::Balance {
+		self.assets.get(id).map_or(T::Balance::zero(), |asset| asset.supply)
+	}
+
+	/// Get the amount of the asset `id` which `delegate` can transfer on behalf of `owner`.
+	pub fn allowance(
+		&self,
+		id: &T::AssetId,
+		owner: &T::AccountId,
+		delegate: &T::AccountId,
+	) -> T::Balance {
+		self.approvals.get(&(*id, owner.clone()), delegate).unwrap_or(T::Balance::zero())
+	}
+
+	/// Get the approvals `owner` gave for the asset `id`, as `(delegate, amount)` pairs.
+	pub fn approvals(&self, id: &T::AssetId, owner: &T::AccountId) -> Vec<(T::AccountId, T::Balance)> {
+		self.approvals.iter_prefix(&(*id, owner.clone())).collect()
+	}
+
+	/// Get the metadata of the asset `id`, if any.
+	pub fn metadata(&self, id: &T::AssetId) -> Option<AssetMetadata> {
+		self.metadata.get(id)
+	}
+
+	/// Check whether `who` is frozen for the asset `id`.
+	pub fn is_frozen(&self, id: &T::AssetId, who: &T::AccountId) -> bool {
+		self.frozen.contains_key(id, who)
+	}
+
+	/// Get the details of the asset `id`, checking that `who` is its admin.
+	fn ensure_admin(
+		&self,
+		id: &T::AssetId,
+		who: &T::AccountId,
+	) -> Result<AssetDetails<T::AccountId, T::Balance>, DispatchError> {
+		let asset = self.assets.get(id).ok_or(Error::<T>::UnknownAsset)?;
+		if asset.admin != *who {
+			return Err(Error::<T>::NoPermission.into());
+		}
+		Ok(asset)
+	}
+
+	/// Move `amount` of the asset `id` from `from` to `to`, if `from` is not frozen.
+	fn do_transfer(
+		&mut self,
+		id: T::AssetId,
+		from: T::AccountId,
+		to: T::AccountId,
+		amount: T::Balance,
+	) -> DispatchResult {
+		if !self.assets.contains_key(&id) {
+			return Err(Error::<T>::UnknownAsset.into());
+		}
+		if self.is_frozen(&id, &from) {
+			return Err(Error::<T>::Frozen.into());
+		}
+
+		let new_from_balance =
+			self.balance(&id, &from).checked_sub(&amount).ok_or(Error::<T>::BalanceLow)?;
+		if from == to {
+			return Ok(());
+		}
+		let new_to_balance = self.balance(&id, &to).checked_add(&amount).ok_or(Error::<T>::Overflow)?;
+
+		self.accounts.insert(id, from.clone(), new_from_balance);
+		self.accounts.insert(id, to.clone(), new_to_balance);
+		self.deposit_event(Event::Transferred { id, from, to, amount });
+
+		Ok(())
+	}
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	/// Create the asset `id`, administrated by `admin`. Only `T::CreateOrigin` can create assets.
+	#[call_index(0)]
+	pub fn create(&mut self, caller: T::AccountId, id: T::AssetId, admin: T::AccountId) -> DispatchResult {
+		T::CreateOrigin::ensure_origin(&caller)?;
+		if self.assets.contains_key(&id) {
+			return Err(Error::<T>::AssetExists.into());
+		}
+		self.assets.insert(id, AssetDetails { admin: admin.clone(), supply: T::Balance::zero() });
+		self.deposit_event(Event::Created { id, admin });
+		Ok(())
+	}
+
+	/// Create `amount` of the asset `id` in the account of `beneficiary`.
+	/// Only the admin of the asset can mint.
+	#[call_index(1)]
+	pub fn mint(
+		&mut self,
+		caller: T::AccountId,
+		id: T::AssetId,
+		beneficiary: T::AccountId,
+		amount: T::Balance,
+	) -> DispatchResult {
+		let asset = self.ensure_admin(&id, &caller)?;
+		let new_supply = asset.supply.checked_add(&amount).ok_or(Error::<T>::Overflow)?;
+		let new_balance = self.balance(&id, &beneficiary).checked_add(&amount).ok_or(Error::<T>::Overflow)?;
+
+		self.assets.insert(id, AssetDetails { supply: new_supply, ..asset });
+		self.accounts.insert(id, beneficiary.clone(), new_balance);
+		self.deposit_event(Event::Issued { id, owner: beneficiary, amount });
+		Ok(())
+	}
+
+	/// Destroy `amount` of the asset `id` from the account of `who`.
+	/// Only the admin of the asset can burn.
+	#[call_index(2)]
+	pub fn burn(
+		&mut self,
+		caller: T::AccountId,
+		id: T::AssetId,
+		who: T::AccountId,
+		amount: T::Balance,
+	) -> DispatchResult {
+		let asset = self.ensure_admin(&id, &caller)?;
+		let new_supply = asset.supply.checked_sub(&amount).ok_or(Error::<T>::BalanceLow)?;
+		let new_balance = self.balance(&id, &who).checked_sub(&amount).ok_or(Error::<T>::BalanceLow)?;
+
+		self.assets.insert(id, AssetDetails { supply: new_supply, ..asset });
+		self.accounts.insert(id, who.clone(), new_balance);
+		self.deposit_event(Event::Burned { id, owner: who, amount });
+		Ok(())
+	}
+
+	/// Transfer `amount` of the asset `id` from the caller to `target`.
+	#[call_index(3)]
+	pub fn transfer(
+		&mut self,
+		caller: T::AccountId,
+		id: T::AssetId,
+		target: T::AccountId,
+		amount: T::Balance,
+	) -> DispatchResult {
+		self.do_transfer(id, caller, target, amount)
+	}
+
+	/// Allow `delegate` to transfer up to `amount` of the asset `id` on behalf of the caller,
+	/// replacing any previous approval.
+	#[call_index(4)]
+	pub fn approve_transfer(
+		&mut self,
+		caller: T::AccountId,
+		id: T::AssetId,
+		delegate: T::AccountId,
+		amount: T::Balance,
+	) -> DispatchResult {
+		if !self.assets.contains_key(&id) {
+			return Err(Error::<T>::UnknownAsset.into());
+		}
+		self.approvals.insert((id, caller.clone()), delegate.clone(), amount);
+		self.deposit_event(Event::ApprovedTransfer { id, owner: caller, delegate, amount });
+		Ok(())
+	}
+
+	/// Transfer `amount` of the asset `id` from `owner` to `destination`, using the approval
+	/// `owner` gave to the caller.
+	#[call_index(5)]
+	pub fn transfer_approved(
+		&mut self,
+		caller: T::AccountId,
+		id: T::AssetId,
+		owner: T::AccountId,
+		destination: T::AccountId,
+		amount: T::Balance,
+	) -> DispatchResult {
+		let new_allowance = self
+			.allowance(&id, &owner, &caller)
+			.checked_sub(&amount)
+			.ok_or(Error::<T>::Unapproved)?;
+		self.do_transfer(id, owner.clone(), destination, amount)?;
+
+		if new_allowance.is_zero() {
+			self.approvals.remove(&(id, owner), &caller);
+		} else {
+			self.approvals.insert((id, owner), caller, new_allowance);
+		}
+		Ok(())
+	}
+
+	/// Prevent `who` from transferring the asset `id`.
+	/// Only the admin of the asset can freeze an account.
+	#[call_index(6)]
+	pub fn freeze(&mut self, caller: T::AccountId, id: T::AssetId, who: T::AccountId) -> DispatchResult {
+		self.ensure_admin(&id, &caller)?;
+		self.frozen.insert(id, who.clone(), ());
+		self.deposit_event(Event::Frozen { id, who });
+		Ok(())
+	}
+
+	/// Allow a frozen `who` to transfer the asset `id` again.
+	/// Only the admin of the asset can thaw an account.
+	#[call_index(7)]
+	pub fn thaw(&mut self, caller: T::AccountId, id: T::AssetId, who: T::AccountId) -> DispatchResult {
+		self.ensure_admin(&id, &caller)?;
+		self.frozen.remove(&id, &who);
+		self.deposit_event(Event::Thawed { id, who });
+		Ok(())
+	}
+
+	/// Set the metadata of the asset `id`.
+	/// Only the admin of the asset can set its metadata.
+	#[call_index(8)]
+	pub fn set_metadata(
+		&mut self,
+		caller: T::AccountId,
+		id: T::AssetId,
+		name: String,
+		symbol: String,
+		decimals: u8,
+	) -> DispatchResult {
+		self.ensure_admin(&id, &caller)?;
+		let metadata = AssetMetadata { name: name.clone(), symbol: symbol.clone(), decimals };
+		self.metadata.insert(id, metadata);
+		self.deposit_event(Event::MetadataSet { id, name, symbol, decimals });
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::support;
+
+	struct TestConfig;
+
+	impl crate::system::Config for TestConfig {
+		type AccountId = &'static str;
+		type BlockNumber = u32;
+		type Nonce = u32;
+		type RuntimeEvent = ();
+		type PalletIndex = ();
+		const MAXIMUM_BLOCK_WEIGHT: crate::support::Weight = u64::MAX;
+		const MAXIMUM_BLOCK_LENGTH: u32 = u32::MAX;
+		const SPEC_VERSION: u32 = 1;
+	}
+
+	impl crate::balances::Config for TestConfig {
+		type Balance = u128;
+	}
+
+	struct EnsureRoot;
+
+	impl support::EnsureOrigin<&'static str> for EnsureRoot {
+		fn ensure_origin(caller: &&'static str) -> support::DispatchResult {
+			if *caller == "root" { Ok(()) } else { Err(support::DispatchError::BadOrigin) }
+		}
+	}
+
+	impl super::Config for TestConfig {
+		type AssetId = u32;
+		type CreateOrigin = EnsureRoot;
+	}
+
+	#[test]
+	fn create_mint_burn() {
+		let mut assets = super::Pallet::<TestConfig>::new();
+		assert_eq!(assets.mint("alice", 1, "bob", 10), Err(super::Error::<TestConfig>::UnknownAsset.into()));
+		assert_eq!(assets.create("alice", 1, "alice"), Err(support::DispatchError::BadOrigin));
+		assert_eq!(assets.create("root", 1, "alice"), Ok(()));
+		assert_eq!(assets.create("root", 1, "bob"), Err(super::Error::<TestConfig>::AssetExists.into()));
+		assert_eq!(assets.asset(&1).map(|asset| asset.admin), Some("alice"));
+
+		assert_eq!(assets.mint("bob", 1, "bob", 10), Err(super::Error::<TestConfig>::NoPermission.into()));
+		assert_eq!(assets.mint("alice", 1, "bob", 100), Ok(()));
+		assert_eq!(assets.balance(&1, &"bob"), 100);
+		assert_eq!(assets.total_supply(&1), 100);
+
+		assert_eq!(assets.burn("alice", 1, "bob", 101), Err(super::Error::<TestConfig>::BalanceLow.into()));
+		assert_eq!(assets.burn("alice", 1, "bob", 40), Ok(()));
+		assert_eq!(assets.balance(&1, &"bob"), 60);
+		assert_eq!(assets.total_supply(&1), 60);
+
+		assert_eq!(assets.set_metadata("bob", 1, "Token".into(), "TKN".into(), 12), Err(super::Error::<TestConfig>::NoPermission.into()));
+		assert_eq!(assets.set_metadata("alice", 1, "Token".into(), "TKN".into(), 12), Ok(()));
+		assert_eq!(assets.metadata(&1).map(|metadata| metadata.decimals), Some(12));
+	}
+
+	#[test]
+	fn transfers_and_approvals() {
+		let mut assets = super::Pallet::<TestConfig>::new();
+		let _res = assets.create("root", 1, "alice");
+		let _res = assets.mint("alice", 1, "bob", 100);
+
+		assert_eq!(assets.transfer("bob", 2, "charlie", 10), Err(super::Error::<TestConfig>::UnknownAsset.into()));
+		assert_eq!(assets.transfer("bob", 1, "charlie", 101), Err(super::Error::<TestConfig>::BalanceLow.into()));
+		assert_eq!(assets.transfer("bob", 1, "charlie", 10), Ok(()));
+		assert_eq!(assets.balance(&1, &"bob"), 90);
+		assert_eq!(assets.balance(&1, &"charlie"), 10);
+
+		assert_eq!(assets.approve_transfer("bob", 1, "dave", 50), Ok(()));
+		assert_eq!(assets.allowance(&1, &"bob", &"dave"), 50);
+		assert_eq!(assets.approve_transfer("bob", 1, "eve", 5), Ok(()));
+		assert_eq!(assets.approvals(&1, &"bob"), vec![("dave", 50), ("eve", 5)]);
+		assert_eq!(assets.approvals(&1, &"dave"), vec![]);
+		assert_eq!(assets.transfer_approved("dave", 1, "bob", "dave", 60), Err(super::Error::<TestConfig>::Unapproved.into()));
+		assert_eq!(assets.transfer_approved("dave", 1, "bob", "dave", 30), Ok(()));
+		assert_eq!(assets.allowance(&1, &"bob", &"dave"), 20);
+		assert_eq!(assets.balance(&1, &"dave"), 30);
+		assert_eq!(assets.balance(&1, &"bob"), 60);
+
+		assert_eq!(assets.freeze("bob", 1, "bob"), Err(super::Error::<TestConfig>::NoPermission.into()));
+		assert_eq!(assets.freeze("alice", 1, "bob"), Ok(()));
+		assert!(assets.is_frozen(&1, &"bob"));
+		assert_eq!(assets.transfer("bob", 1, "charlie", 10), Err(super::Error::<TestConfig>::Frozen.into()));
+		assert_eq!(assets.transfer_approved("dave", 1, "bob", "dave", 10), Err(super::Error::<TestConfig>::Frozen.into()));
+		// Frozen accounts can still receive the asset.
+		assert_eq!(assets.transfer("charlie", 1, "bob", 10), Ok(()));
+		assert_eq!(assets.thaw("alice", 1, "bob"), Ok(()));
+		assert_eq!(assets.transfer("bob", 1, "charlie", 10), Ok(()));
+	}
+}
@@ -1,56 +1,225 @@
-use std::collections::BTreeMap;
 use num::traits::{CheckedAdd, CheckedSub, Zero};
 
-use crate::RuntimeCall;
+use crate::storage::{StorageDoubleMap, StorageMap};
 
-pub trait Config: crate::system::Config{
-    type Balance: Zero + CheckedSub + CheckedAdd + Copy;
+/// An identifier for a lock. Each pallet locking funds uses its own identifier.
+pub type LockIdentifier = [u8; 8];
+
+pub trait Config<I: 'static = ()>:
+	Sized + crate::system::Config<RuntimeEvent: From<Event<Self, I>>>
+{
+    type Balance: Zero + CheckedSub + CheckedAdd + Ord + Copy;
 }
 
-#[derive(Debug)]
-pub struct Pallet<T: Config> {
-    balances: BTreeMap<T::AccountId, T::Balance>,
+/// This is the Balances Module.
+/// It keeps track of how much balance each account has, and lets accounts transfer it.
+/// A runtime can include it several times, one per currency, with a different instance `I` each
+/// time. The default instance is `()`.
+#[macros::storage]
+#[derive(Debug, macros::PalletInfo)]
+pub struct Pallet<T: Config<I>, I: 'static = ()> {
+	/// The free balance of each account.
+	#[getter(balance)]
+	#[default(T::Balance::zero())]
+    balances: StorageMap<T::AccountId, T::Balance>,
+	/// The funds of each account which cannot be transferred, by lock identifier.
+	/// Locks overlap: the locked amount of an account is the largest of its locks.
+	locks: StorageDoubleMap<T::AccountId, LockIdentifier, T::Balance>,
+	/// The amount a spender can transfer from the account of an owner: `(owner, spender)`.
+	#[getter(allowance)]
+	#[default(T::Balance::zero())]
+	allowances: StorageDoubleMap<T::AccountId, T::AccountId, T::Balance>,
+	/// The funds of each account which are set aside, e.g. as a deposit. They are not part of the
+	/// balance of the account until they are unreserved.
+	#[getter(reserved_balance)]
+	#[default(T::Balance::zero())]
+	reserved: StorageMap<T::AccountId, T::Balance>,
 }
 
-impl<T: Config> Pallet<T>{
-    pub fn new() -> Self {
-		Self {balances: BTreeMap::new()}
-	}
+/// The events of this pallet.
+#[macros::event]
+pub enum Event<T: Config<I>, I: 'static = ()> {
+	/// `amount` was transferred from `from` to `to`.
+	Transfer { from: T::AccountId, to: T::AccountId, amount: T::Balance },
+	/// `amount` of new funds were created in the account of `who`.
+	Minted { who: T::AccountId, amount: T::Balance },
+	/// `amount` of the funds of `who` were destroyed.
+	Burned { who: T::AccountId, amount: T::Balance },
+}
+
+/// The errors of this pallet.
+#[macros::error]
+pub enum Error<T: Config<I>, I: 'static = ()> {
+	/// An arithmetic operation overflowed.
+	Overflow,
+	/// The account does not have enough funds.
+	InsufficientBalance,
+	/// The funds of the account are locked.
+	FundsLocked,
+	/// The amount exceeds the allowance of the caller.
+	AllowanceExceeded,
+}
 
+impl<T: Config<I>, I: 'static> Pallet<T, I>{
 	pub fn set_balance(&mut self, who: &T::AccountId, amount: T::Balance) {
 		self.balances.insert(who.clone(), amount);
 	}
 
-	pub fn balance(&self, who: &T::AccountId) -> T::Balance {
-		/* Return the balance of `who`, returning zero if `None`. */
-		*self.balances.get(who).unwrap_or(&T::Balance::zero())
+	/// Create `amount` of new funds in the account of `who`.
+	pub fn mint(&mut self, who: &T::AccountId, amount: T::Balance) -> crate::support::DispatchResult {
+		let new_balance = self.balance(who).checked_add(&amount).ok_or(Error::<T, I>::Overflow)?;
+		self.balances.insert(who.clone(), new_balance);
+		self.deposit_event(Event::Minted { who: who.clone(), amount });
+		Ok(())
 	}
-}
 
-#[macros::call]
-impl<T: Config> Pallet<T>{
-    /// Transfer `amount` from one account to another.
-	/// This function verifies that `from` has at least `amount` balance to transfer,
-	/// and that no mathematical overflows occur.
-	pub fn transfer(
+	/// Destroy `amount` of the funds of `who`. Locked funds can be burned, so callers should update
+	/// their locks accordingly.
+	pub fn burn(&mut self, who: &T::AccountId, amount: T::Balance) -> crate::support::DispatchResult {
+		let new_balance = self.balance(who).checked_sub(&amount).ok_or(Error::<T, I>::InsufficientBalance)?;
+		self.balances.insert(who.clone(), new_balance);
+		self.deposit_event(Event::Burned { who: who.clone(), amount });
+		Ok(())
+	}
+
+	/// Move `amount` from the balance of `who` to its reserved funds. Locked funds cannot be
+	/// reserved.
+	pub fn reserve(&mut self, who: &T::AccountId, amount: T::Balance) -> crate::support::DispatchResult {
+		let new_balance = self.balance(who).checked_sub(&amount).ok_or(Error::<T, I>::InsufficientBalance)?;
+		if new_balance < self.locked(who) {
+			return Err(Error::<T, I>::FundsLocked.into());
+		}
+		let new_reserved = self.reserved_balance(who).checked_add(&amount).ok_or(Error::<T, I>::Overflow)?;
+
+		self.balances.insert(who.clone(), new_balance);
+		self.reserved.insert(who.clone(), new_reserved);
+		Ok(())
+	}
+
+	/// Move up to `amount` from the reserved funds of `who` back to its balance. Returns the amount
+	/// which was unreserved, which is zero if the balance would overflow.
+	pub fn unreserve(&mut self, who: &T::AccountId, amount: T::Balance) -> T::Balance {
+		let amount = amount.min(self.reserved_balance(who));
+		let Some(new_balance) = self.balance(who).checked_add(&amount) else {
+			return T::Balance::zero();
+		};
+
+		self.slash_reserved(who, amount);
+		self.balances.insert(who.clone(), new_balance);
+		amount
+	}
+
+	/// Destroy up to `amount` of the reserved funds of `who`. Returns the amount which was
+	/// destroyed.
+	pub fn slash_reserved(&mut self, who: &T::AccountId, amount: T::Balance) -> T::Balance {
+		let reserved = self.reserved_balance(who);
+		let amount = amount.min(reserved);
+		let new_reserved = reserved.checked_sub(&amount).unwrap_or(T::Balance::zero());
+		if new_reserved.is_zero() {
+			self.reserved.remove(who);
+		} else {
+			self.reserved.insert(who.clone(), new_reserved);
+		}
+		amount
+	}
+
+	/// Get the amount of the balance of `who` which is locked, and cannot be transferred.
+	pub fn locked(&self, who: &T::AccountId) -> T::Balance {
+		self.locks
+			.iter_prefix(who)
+			.map(|(_, amount)| amount)
+			.max()
+			.unwrap_or(T::Balance::zero())
+	}
+
+	/// Lock `amount` of the balance of `who` under the lock `id`, replacing any previous amount
+	/// of that lock.
+	pub fn set_lock(&mut self, id: LockIdentifier, who: &T::AccountId, amount: T::Balance) {
+		self.locks.insert(who.clone(), id, amount);
+	}
+
+	/// Remove the lock `id` from the balance of `who`.
+	pub fn remove_lock(&mut self, id: LockIdentifier, who: &T::AccountId) {
+		self.locks.remove(who, &id);
+	}
+
+	/// Move `amount` from `from` to `to`, checking that `from` has enough funds which are not
+	/// locked, and that no mathematical overflows occur. A transfer to `from` itself changes
+	/// nothing once it is checked.
+	fn do_transfer(
 		&mut self,
-		caller: T::AccountId,
+		from: T::AccountId,
 		to: T::AccountId,
 		amount: T::Balance,
 	) -> crate::support::DispatchResult {
-        let caller_balance: T::Balance = self.balance(&caller);
+        let from_balance: T::Balance = self.balance(&from);
         let to_balance: T::Balance = self.balance(&to);
 
-        let new_caller_balance = caller_balance.checked_sub(&amount).ok_or("Not enough funds.")?;
-        let new_to_balance = to_balance.checked_add(&amount).ok_or("Overflow.")?;
+        let new_from_balance = from_balance.checked_sub(&amount).ok_or(Error::<T, I>::InsufficientBalance)?;
+        if new_from_balance < self.locked(&from) {
+            return Err(Error::<T, I>::FundsLocked.into());
+        }
+        if from == to {
+            return Ok(());
+        }
+        let new_to_balance = to_balance.checked_add(&amount).ok_or(Error::<T, I>::Overflow)?;
 
-        self.balances.insert(caller.clone(), new_caller_balance);
+        self.balances.insert(from.clone(), new_from_balance);
         self.balances.insert(to.clone(), new_to_balance);
+		self.deposit_event(Event::Transfer { from, to, amount });
 
 		Ok(())
 	}
 }
 
+#[macros::call]
+impl<T: Config<I>, I: 'static> Pallet<T, I>{
+    /// Transfer `amount` from one account to another.
+	/// This function verifies that `from` has at least `amount` balance to transfer, without
+	/// touching its locked funds, and that no mathematical overflows occur.
+	#[call_index(0)]
+	pub fn transfer(
+		&mut self,
+		caller: T::AccountId,
+		to: T::AccountId,
+		amount: T::Balance,
+	) -> crate::support::DispatchResult {
+		self.do_transfer(caller, to, amount)
+	}
+
+	/// Allow `spender` to transfer up to `amount` from the caller's account with `transfer_from`,
+	/// replacing any previous allowance.
+	#[call_index(1)]
+	pub fn approve(
+		&mut self,
+		caller: T::AccountId,
+		spender: T::AccountId,
+		amount: T::Balance,
+	) -> crate::support::DispatchResult {
+		if amount.is_zero() {
+			self.allowances.remove(&caller, &spender);
+		} else {
+			self.allowances.insert(caller, spender, amount);
+		}
+		Ok(())
+	}
+
+	/// Transfer `amount` from `owner` to `to`, using the allowance `owner` gave to the caller.
+	/// The allowance is decreased by `amount`.
+	#[call_index(2)]
+	pub fn transfer_from(
+		&mut self,
+		caller: T::AccountId,
+		owner: T::AccountId,
+		to: T::AccountId,
+		amount: T::Balance,
+	) -> crate::support::DispatchResult {
+		let new_allowance =
+			self.allowance(&owner, &caller).checked_sub(&amount).ok_or(Error::<T, I>::AllowanceExceeded)?;
+		self.do_transfer(owner.clone(), to, amount)?;
+		self.approve(owner, caller, new_allowance)
+	}
+}
 
 #[cfg(test)]
 mod tests {
@@ -60,6 +229,11 @@ mod tests {
         type AccountId = String;
 		type BlockNumber = u32;
 		type Nonce = u32;
+		type RuntimeEvent = ();
+		type PalletIndex = ();
+		const MAXIMUM_BLOCK_WEIGHT: crate::support::Weight = u64::MAX;
+		const MAXIMUM_BLOCK_LENGTH: u32 = u32::MAX;
+		const SPEC_VERSION: u32 = 1;
     }
 	impl super::Config for TestConfig {
 		type Balance = u128;
@@ -91,11 +265,231 @@ mod tests {
         balances.set_balance(&"alice".to_string(), 100);
         balances.set_balance(&"bob".to_string(), 0);
 
-        assert_eq!(balances.transfer("alice".to_string(), "bob".to_string(), 1000), Err("Not enough funds."));
+        assert_eq!(balances.transfer("alice".to_string(), "bob".to_string(), 1000), Err(super::Error::<TestConfig>::InsufficientBalance.into()));
         assert_eq!(balances.transfer("alice".to_string(), "bob".to_string(), 10), Ok(()));
 
         assert_eq!(balances.balance(&"alice".to_string()), 90);
         assert_eq!(balances.balance(&"bob".to_string()), 10);
 	}
 
-}
\ No newline at end of file
+	#[test]
+	fn self_transfer() {
+		let mut balances = super::Pallet::<TestConfig>::new();
+		balances.set_balance(&"alice".to_string(), 100);
+
+		assert_eq!(balances.transfer("alice".to_string(), "alice".to_string(), 1000), Err(super::Error::<TestConfig>::InsufficientBalance.into()));
+		assert_eq!(balances.transfer("alice".to_string(), "alice".to_string(), 60), Ok(()));
+		assert_eq!(balances.balance(&"alice".to_string()), 100);
+
+		// Spending an allowance on the owner does not create funds either.
+		assert_eq!(balances.approve("alice".to_string(), "alice".to_string(), 50), Ok(()));
+		assert_eq!(balances.transfer_from("alice".to_string(), "alice".to_string(), "alice".to_string(), 50), Ok(()));
+		assert_eq!(balances.balance(&"alice".to_string()), 100);
+	}
+
+	#[test]
+	fn locked_funds() {
+		let mut balances = super::Pallet::<TestConfig>::new();
+		balances.set_balance(&"alice".to_string(), 100);
+
+		// The largest lock applies.
+		balances.set_lock(*b"lock_one", &"alice".to_string(), 30);
+		balances.set_lock(*b"lock_two", &"alice".to_string(), 60);
+		assert_eq!(balances.locked(&"alice".to_string()), 60);
+
+		assert_eq!(balances.transfer("alice".to_string(), "bob".to_string(), 50), Err(super::Error::<TestConfig>::FundsLocked.into()));
+		assert_eq!(balances.transfer("alice".to_string(), "bob".to_string(), 40), Ok(()));
+
+		balances.remove_lock(*b"lock_two", &"alice".to_string());
+		assert_eq!(balances.locked(&"alice".to_string()), 30);
+		assert_eq!(balances.transfer("alice".to_string(), "bob".to_string(), 30), Ok(()));
+		assert_eq!(balances.balance(&"alice".to_string()), 30);
+
+		balances.remove_lock(*b"lock_one", &"alice".to_string());
+		assert_eq!(balances.locked(&"alice".to_string()), 0);
+	}
+
+	#[test]
+	fn allowances() {
+		let mut balances = super::Pallet::<TestConfig>::new();
+		balances.set_balance(&"alice".to_string(), 100);
+
+		assert_eq!(balances.transfer_from("bob".to_string(), "alice".to_string(), "charlie".to_string(), 10), Err(super::Error::<TestConfig>::AllowanceExceeded.into()));
+		assert_eq!(balances.approve("alice".to_string(), "bob".to_string(), 50), Ok(()));
+		assert_eq!(balances.allowance(&"alice".to_string(), &"bob".to_string()), 50);
+
+		assert_eq!(balances.transfer_from("bob".to_string(), "alice".to_string(), "charlie".to_string(), 60), Err(super::Error::<TestConfig>::AllowanceExceeded.into()));
+		assert_eq!(balances.transfer_from("bob".to_string(), "alice".to_string(), "charlie".to_string(), 30), Ok(()));
+		assert_eq!(balances.allowance(&"alice".to_string(), &"bob".to_string()), 20);
+		assert_eq!(balances.balance(&"alice".to_string()), 70);
+		assert_eq!(balances.balance(&"charlie".to_string()), 30);
+
+		// The allowance is not spent when the transfer fails.
+		balances.set_lock(*b"lock_one", &"alice".to_string(), 60);
+		assert_eq!(balances.transfer_from("bob".to_string(), "alice".to_string(), "charlie".to_string(), 20), Err(super::Error::<TestConfig>::FundsLocked.into()));
+		assert_eq!(balances.allowance(&"alice".to_string(), &"bob".to_string()), 20);
+
+		assert_eq!(balances.approve("alice".to_string(), "bob".to_string(), 0), Ok(()));
+		assert_eq!(balances.allowance(&"alice".to_string(), &"bob".to_string()), 0);
+	}
+
+	#[test]
+	fn reserved_funds() {
+		let mut balances = super::Pallet::<TestConfig>::new();
+		balances.set_balance(&"alice".to_string(), 100);
+		balances.set_lock(*b"lock_one", &"alice".to_string(), 50);
+
+		assert_eq!(balances.reserve(&"alice".to_string(), 60), Err(super::Error::<TestConfig>::FundsLocked.into()));
+		assert_eq!(balances.reserve(&"alice".to_string(), 40), Ok(()));
+		assert_eq!(balances.balance(&"alice".to_string()), 60);
+		assert_eq!(balances.reserved_balance(&"alice".to_string()), 40);
+		assert_eq!(balances.transfer("alice".to_string(), "bob".to_string(), 20), Err(super::Error::<TestConfig>::FundsLocked.into()));
+
+		assert_eq!(balances.unreserve(&"alice".to_string(), 10), 10);
+		assert_eq!(balances.slash_reserved(&"alice".to_string(), 100), 30);
+		assert_eq!(balances.unreserve(&"alice".to_string(), 10), 0);
+		assert_eq!(balances.balance(&"alice".to_string()), 70);
+		assert_eq!(balances.reserved_balance(&"alice".to_string()), 0);
+
+		// Funds stay reserved if unreserving them would overflow the balance.
+		assert_eq!(balances.reserve(&"alice".to_string(), 10), Ok(()));
+		balances.set_balance(&"alice".to_string(), u128::MAX);
+		assert_eq!(balances.unreserve(&"alice".to_string(), 10), 0);
+		assert_eq!(balances.balance(&"alice".to_string()), u128::MAX);
+		assert_eq!(balances.reserved_balance(&"alice".to_string()), 10);
+	}
+
+	/// The instance of the pallet keeping a second currency.
+	#[derive(Debug)]
+	struct Dollars;
+
+	type Extrinsic = crate::support::Extrinsic<String, u32, u128, RuntimeCall>;
+	type Block = crate::support::Block<crate::support::Header<u32>, Extrinsic>;
+
+	/// A runtime with two currencies, each kept by its own instance of the pallet.
+	#[derive(Debug)]
+	#[macros::runtime(block = Block)]
+	struct Runtime {
+		system: crate::system::Pallet<Self>,
+		#[pallet_index(0)]
+		#[event]
+		balances: super::Pallet<Self>,
+		#[pallet_index(1)]
+		#[event]
+		dollars: super::Pallet<Self, Dollars>,
+	}
+
+	impl crate::system::Config for Runtime {
+		type AccountId = String;
+		type BlockNumber = u32;
+		type Nonce = u32;
+		type RuntimeEvent = RuntimeEvent;
+		type PalletIndex = Runtime;
+		const MAXIMUM_BLOCK_WEIGHT: crate::support::Weight = u64::MAX;
+		const MAXIMUM_BLOCK_LENGTH: u32 = u32::MAX;
+		const SPEC_VERSION: u32 = 1;
+	}
+
+	impl super::Config for Runtime {
+		type Balance = u128;
+	}
+
+	impl super::Config<Dollars> for Runtime {
+		type Balance = u128;
+	}
+
+	impl crate::support::CallFilter<RuntimeCall> for Runtime {
+		fn contains(&self, _call: &RuntimeCall) -> bool {
+			true
+		}
+	}
+
+	impl crate::support::TransactionPayment<String, u128, RuntimeCall> for Runtime {
+		fn compute_fee(&self, _call: &RuntimeCall, tip: u128) -> u128 {
+			tip
+		}
+
+		fn withdraw_fee(&mut self, who: &String, fee: u128) -> crate::support::DispatchResult {
+			self.balances.burn(who, fee)
+		}
+	}
+
+	impl crate::support::CheckSignature<String> for Runtime {
+		fn check_signature(_who: &String) -> bool {
+			true
+		}
+	}
+
+	impl crate::support::OnInitialize<u32> for Runtime {
+		fn on_initialize(&mut self, _block_number: u32) -> crate::support::Weight {
+			0
+		}
+	}
+
+	impl crate::support::OnRuntimeUpgrade for Runtime {
+		fn on_runtime_upgrade(&mut self, _from: u32, _to: u32) {}
+	}
+
+	#[test]
+	fn instances_are_separate() {
+		let mut runtime = Runtime::new();
+		runtime.balances.set_balance(&"alice".to_string(), 100);
+		runtime.dollars.set_balance(&"alice".to_string(), 50);
+
+		let extrinsics = [
+			RuntimeCall::balances(super::Call::transfer { to: "bob".to_string(), amount: 30 }),
+			RuntimeCall::dollars(super::Call::transfer { to: "bob".to_string(), amount: 20 }),
+			RuntimeCall::dollars(super::Call::transfer { to: "bob".to_string(), amount: 70 }),
+		]
+		.into_iter()
+		.zip(0..)
+		.map(|(call, nonce)| Extrinsic { caller: "alice".to_string(), nonce, tip: 0, call })
+		.collect::<Vec<_>>();
+		let mut header = crate::support::Header {
+			block_number: 1,
+			parent_hash: runtime.system.parent_hash(),
+			extrinsics_root: crate::support::hash_of(&extrinsics),
+			state_root: 0,
+		};
+		// The state root is the hash of the state the block leads to, which executing the block on
+		// a copy of the runtime gives, before its roots are checked.
+		let mut copy = runtime.clone();
+		let block = Block { header: header.clone(), extrinsics: extrinsics.clone() };
+		assert_eq!(copy.execute_block(block).err(), Some("state root does not match the state"));
+		header.state_root = crate::support::hash_of(&copy);
+
+		let receipt = runtime.execute_block(Block { header, extrinsics }).unwrap();
+		let events = receipt.extrinsics.iter().map(|receipt| receipt.events.clone()).collect::<Vec<_>>();
+		assert_eq!(
+			events,
+			vec![
+				vec![RuntimeEvent::balances(super::Event::Transfer {
+					from: "alice".to_string(),
+					to: "bob".to_string(),
+					amount: 30,
+				})],
+				vec![RuntimeEvent::dollars(super::Event::Transfer {
+					from: "alice".to_string(),
+					to: "bob".to_string(),
+					amount: 20,
+				})],
+				vec![],
+			]
+		);
+		// The error is reported by the instance which failed.
+		let error = receipt.extrinsics[2].result.unwrap_err();
+		assert_eq!(
+			RuntimeError::from_dispatch_error(&error),
+			Some(RuntimeError::dollars(super::Error::InsufficientBalance))
+		);
+
+		// Each instance keeps its balances under its own name in the storage backend.
+		assert_eq!(runtime.balances.balance(&"alice".to_string()), 70);
+		assert_eq!(runtime.balances.balance(&"bob".to_string()), 30);
+		assert_eq!(runtime.dollars.balance(&"alice".to_string()), 30);
+		assert_eq!(runtime.dollars.balance(&"bob".to_string()), 20);
+
+		let pallets = Runtime::metadata().pallets.iter().map(|pallet| pallet.name).collect::<Vec<_>>();
+		assert_eq!(pallets, vec!["system", "balances", "dollars"]);
+	}
+}
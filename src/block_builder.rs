@@ -0,0 +1,277 @@
+use crate::{
+	support::{self, GetDispatchInfo},
+	system, types, Runtime,
+};
+
+/// Why an extrinsic was not included in the block being built.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BuildError {
+	/// The extrinsic is invalid, e.g. its nonce is wrong or its caller cannot pay the fee.
+	Invalid(support::TransactionValidityError),
+	/// The extrinsic does not fit within the limits of the block.
+	ExhaustsResources,
+}
+
+/// Builds the next block on top of the current state of a runtime.
+///
+/// Extrinsics are applied one by one to a copy of the state, so invalid extrinsics can be left out.
+/// The runtime itself is not modified: the finalized block must be executed with
+/// `Runtime::execute_block`.
+pub struct BlockBuilder {
+	/// The state of the runtime, as the block is being built.
+	runtime: Runtime,
+	/// The header of the block, whose roots are filled in when the block is finalized.
+	header: types::Header,
+	/// The extrinsics included so far.
+	extrinsics: Vec<types::Extrinsic>,
+	/// The total weight of the block so far: the weight consumed when it was initialized, and the
+	/// weight of the extrinsics included so far.
+	weight: support::Weight,
+}
+
+impl BlockBuilder {
+	/// Start building the block which follows the last block executed by `runtime`. Fails if the
+	/// state cannot be upgraded to the spec version of the runtime.
+	pub fn new(runtime: &Runtime) -> Result<Self, &'static str> {
+		let mut runtime = runtime.clone();
+		let header = support::Header {
+			block_number: runtime.system.block_number() + 1,
+			parent_hash: runtime.system.parent_hash(),
+			extrinsics_root: 0,
+			state_root: 0,
+		};
+		let weight = runtime.initialize_block(&header)?;
+		Ok(Self { runtime, header, extrinsics: Vec::new(), weight })
+	}
+
+	/// Apply `extrinsic`, and include it in the block. Returns its receipt, or why it was not
+	/// included.
+	pub fn push(&mut self, extrinsic: types::Extrinsic) -> Result<types::ExtrinsicReceipt, BuildError> {
+		if self.extrinsics.len() >= <Runtime as system::Config>::MAXIMUM_BLOCK_LENGTH as usize {
+			return Err(BuildError::ExhaustsResources);
+		}
+		let weight = self
+			.weight
+			.checked_add(extrinsic.call.weight())
+			.filter(|weight| *weight <= <Runtime as system::Config>::MAXIMUM_BLOCK_WEIGHT)
+			.ok_or(BuildError::ExhaustsResources)?;
+
+		let receipt =
+			self.runtime.apply_extrinsic(extrinsic.clone()).map_err(BuildError::Invalid)?;
+		self.weight = weight;
+		self.extrinsics.push(extrinsic);
+		Ok(receipt)
+	}
+
+	/// Seal the block, computing the roots of its header.
+	pub fn finalize(self) -> types::Block {
+		let header = support::Header {
+			extrinsics_root: support::hash_of(&self.extrinsics),
+			state_root: support::hash_of(&self.runtime),
+			..self.header
+		};
+		support::Block { header, extrinsics: self.extrinsics }
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{BlockBuilder, BuildError};
+	use crate::{
+		balances, scheduler,
+		support::{self, GetStorageVersion, StorageVersion, TransactionValidityError},
+		system, types, Runtime, RuntimeCall, RuntimeEvent,
+	};
+
+	fn transfer(caller: &str, nonce: types::Nonce, amount: types::Balance) -> types::Extrinsic {
+		support::Extrinsic {
+			caller: caller.to_string(),
+			nonce,
+			tip: 0,
+			call: RuntimeCall::balances(balances::Call::transfer { to: "bob".to_string(), amount }),
+		}
+	}
+
+	#[test]
+	fn built_blocks_are_executed() {
+		let mut runtime = Runtime::new();
+		runtime.balances.set_balance(&"alice".to_string(), 100);
+
+		let mut builder = BlockBuilder::new(&runtime).unwrap();
+		assert_eq!(builder.header.block_number, 1);
+		assert_eq!(builder.push(transfer("alice", 0, 10)).map(|receipt| receipt.result), Ok(Ok(())));
+		assert_eq!(
+			builder.push(transfer("alice", 0, 10)),
+			Err(BuildError::Invalid(TransactionValidityError::Stale))
+		);
+		assert_eq!(
+			builder.push(transfer("charlie", 0, 10)),
+			Err(BuildError::Invalid(TransactionValidityError::Payment))
+		);
+		// The extrinsic is included, and pays its fee, even though the transfer fails.
+		assert_eq!(
+			builder.push(transfer("alice", 1, 1_000)).map(|receipt| receipt.result),
+			Ok(Err(support::DispatchError::from(balances::Error::<Runtime>::InsufficientBalance)))
+		);
+		let block = builder.finalize();
+		assert_eq!(block.extrinsics.len(), 2);
+
+		// The runtime is untouched until the block is executed.
+		assert_eq!(runtime.balances.balance(&"bob".to_string()), 0);
+		assert!(runtime.execute_block(block.clone()).is_ok());
+		assert_eq!(runtime.balances.balance(&"bob".to_string()), 10);
+		assert_eq!(runtime.balances.balance(&"alice".to_string()), 88);
+
+		// The next block builds on top of it.
+		let builder = BlockBuilder::new(&runtime).unwrap();
+		assert_eq!(builder.header.block_number, 2);
+		assert_eq!(builder.finalize().header.parent_hash, support::hash_of(&block.header));
+	}
+
+	#[test]
+	fn tampered_blocks_are_rejected() {
+		let mut runtime = Runtime::new();
+		runtime.balances.set_balance(&"alice".to_string(), 100);
+		let mut builder = BlockBuilder::new(&runtime).unwrap();
+		assert!(builder.push(transfer("alice", 0, 10)).is_ok());
+		let block = builder.finalize();
+
+		let mut tampered = block.clone();
+		tampered.extrinsics[0] = transfer("alice", 0, 20);
+		assert_eq!(
+			Runtime::new().execute_block(tampered),
+			Err("extrinsics root does not match the extrinsics")
+		);
+
+		let mut tampered = block.clone();
+		tampered.header.state_root += 1;
+		let mut other = Runtime::new();
+		other.balances.set_balance(&"alice".to_string(), 100);
+		assert_eq!(other.execute_block(tampered), Err("state root does not match the state"));
+
+		let mut tampered = block;
+		tampered.header.parent_hash += 1;
+		assert_eq!(
+			runtime.execute_block(tampered),
+			Err("parent hash does not match the last block")
+		);
+	}
+
+	#[test]
+	fn blocks_are_limited() {
+		let mut runtime = Runtime::new();
+		runtime.balances.set_balance(&"alice".to_string(), 1_000);
+		let mut builder = BlockBuilder::new(&runtime).unwrap();
+		let mut nonce = 0;
+		while builder.push(transfer("alice", nonce, 1)).is_ok() {
+			nonce += 1;
+		}
+		assert_eq!(builder.push(transfer("alice", nonce, 1)), Err(BuildError::ExhaustsResources));
+		assert!(runtime.execute_block(builder.finalize()).is_ok());
+		assert_eq!(runtime.balances.balance(&"bob".to_string()), nonce as types::Balance);
+	}
+
+	#[test]
+	fn runtime_upgrades_run_at_the_next_block() {
+		let spec_version = <Runtime as system::Config>::SPEC_VERSION;
+		let mut runtime = Runtime::new();
+		assert_eq!(runtime.system.spec_version(), spec_version);
+
+		// The state was left by the previous version of the runtime.
+		runtime.system.set_spec_version(spec_version - 1);
+		let block = BlockBuilder::new(&runtime).unwrap().finalize();
+		assert!(runtime.execute_block(block).is_ok());
+		assert_eq!(runtime.system.spec_version(), spec_version);
+
+		// The state was left by a newer version of the runtime, which this one cannot undo.
+		runtime.system.set_spec_version(spec_version + 1);
+		assert!(matches!(
+			BlockBuilder::new(&runtime),
+			Err("state was upgraded by a newer runtime")
+		));
+
+		// Every pallet must be at the storage version of its code once the state is upgraded.
+		runtime.system.set_spec_version(spec_version - 1);
+		runtime.vesting.set_on_chain_storage_version(StorageVersion(1));
+		assert!(matches!(
+			BlockBuilder::new(&runtime),
+			Err("a pallet was not migrated to its storage version")
+		));
+		runtime.system.set_spec_version(spec_version);
+		assert!(matches!(
+			BlockBuilder::new(&runtime),
+			Err("a pallet was not migrated to its storage version")
+		));
+	}
+
+	#[test]
+	fn executed_blocks_have_receipts() {
+		let mut runtime = Runtime::new();
+		runtime.balances.set_balance(&"alice".to_string(), 100);
+
+		let mut builder = BlockBuilder::new(&runtime).unwrap();
+		let schedule = support::Extrinsic {
+			caller: "alice".to_string(),
+			nonce: 0,
+			tip: 2,
+			call: RuntimeCall::scheduler(scheduler::Call::schedule {
+				when: 2,
+				maybe_periodic: None,
+				priority: 0,
+				call: Box::new(RuntimeCall::balances(balances::Call::transfer {
+					to: "bob".to_string(),
+					amount: 1_000,
+				})),
+			}),
+		};
+		assert!(builder.push(schedule).is_ok());
+		assert!(builder.push(transfer("alice", 1, 1_000)).is_ok());
+		assert!(builder.push(transfer("alice", 2, 10)).is_ok());
+
+		let receipt = runtime.execute_block(builder.finalize()).unwrap();
+		assert_eq!(receipt.block_number, 1);
+		assert_eq!(
+			receipt.extrinsics,
+			vec![
+				// Scheduling the transfer pays for dispatching it.
+				support::ExtrinsicReceipt {
+					result: Ok(()),
+					weight: 2 * support::DEFAULT_WEIGHT,
+					fee: 4,
+					events: vec![],
+				},
+				support::ExtrinsicReceipt {
+					result: Err(support::DispatchError::from(balances::Error::<Runtime>::InsufficientBalance)),
+					weight: support::DEFAULT_WEIGHT,
+					fee: 1,
+					events: vec![],
+				},
+				// The events deposited by the pallets during the call are kept with it.
+				support::ExtrinsicReceipt {
+					result: Ok(()),
+					weight: support::DEFAULT_WEIGHT,
+					fee: 1,
+					events: vec![RuntimeEvent::balances(balances::Event::Transfer {
+						from: "alice".to_string(),
+						to: "bob".to_string(),
+						amount: 10,
+					})],
+				},
+			]
+		);
+
+		// The scheduled transfer is dispatched when block 2 is initialized, and its weight counts
+		// toward the weight of the block.
+		let builder = BlockBuilder::new(&runtime).unwrap();
+		assert_eq!(builder.weight, support::DEFAULT_WEIGHT);
+		let receipt = runtime.execute_block(builder.finalize()).unwrap();
+		assert_eq!(
+			receipt.initialization_events,
+			vec![RuntimeEvent::scheduler(scheduler::Event::Dispatched {
+				origin: "alice".to_string(),
+				result: Err(support::DispatchError::from(balances::Error::<Runtime>::InsufficientBalance)),
+			})]
+		);
+		assert!(receipt.extrinsics.is_empty());
+	}
+}
@@ -0,0 +1,386 @@
+use std::{fs, path::PathBuf};
+
+use crate::{
+	balances,
+	codec::{Decode, Encode},
+	genesis::GenesisConfig,
+	json::Value,
+	node::Node,
+	proof_of_existence, rpc,
+	support::{self, Hash},
+	types, Runtime, RuntimeCall,
+};
+
+/// The data directory used when `--base-path` is not given.
+const DEFAULT_BASE_PATH: &str = "chain-data";
+
+/// How to use the command line.
+pub const USAGE: &str = "\
+Usage: dotcodeschool-rust-state-machine [--base-path <dir>] <command>
+
+Commands:
+  init --chain-spec <dev|file>    Start a new chain in the data directory
+  import-blocks <file>            Import the blocks of a file, as written by export-blocks
+  export-blocks <file>            Write the genesis config and all the blocks of the chain to a file
+  submit-tx --from <account> [--nonce <n>] [--tip <n>] <call>
+                                  Submit a transaction to the pool
+  query balance <account>         Print the balance of an account
+  query claim <content>           Print the owner of a claim
+  block <n|0xhash>                Print the block with the number n, or with the hash of its header
+  produce-blocks [--count <n>]    Produce blocks from the transactions of the pool
+  rpc [--port <n>]                Serve JSON-RPC requests over HTTP on localhost
+  metadata                        Print the metadata of the runtime as JSON
+
+Calls:
+  transfer <to> <amount>
+  create-claim <content>
+  revoke-claim <content>";
+
+/// The parsed command line.
+#[derive(Debug)]
+pub struct Cli {
+	/// The data directory of the chain.
+	pub base_path: PathBuf,
+	pub command: Command,
+}
+
+/// A subcommand of the command line.
+#[derive(Debug)]
+pub enum Command {
+	/// Start a new chain from a chain spec, or from the development chain spec with `dev`.
+	Init {
+		chain_spec: String,
+	},
+	ImportBlocks {
+		file: PathBuf,
+	},
+	ExportBlocks {
+		file: PathBuf,
+	},
+	/// Submit a transaction. Without a nonce, the next nonce of the caller is used.
+	SubmitTx {
+		caller: types::AccountId,
+		nonce: Option<types::Nonce>,
+		tip: types::Balance,
+		call: RuntimeCall,
+	},
+	QueryBalance {
+		who: types::AccountId,
+	},
+	QueryClaim {
+		claim: String,
+	},
+	Block {
+		id: BlockId,
+	},
+	ProduceBlocks {
+		count: u32,
+	},
+	/// Serve the chain over JSON-RPC, until the process is stopped.
+	Rpc {
+		port: u16,
+	},
+	/// Print the metadata of the runtime, which does not depend on the chain.
+	Metadata,
+}
+
+/// How a block is designated on the command line.
+#[derive(Debug, PartialEq, Eq)]
+pub enum BlockId {
+	Number(types::BlockNumber),
+	/// The hash of the header of the block, written in hexadecimal with a `0x` prefix.
+	Hash(Hash),
+}
+
+impl Cli {
+	/// Parse the command line, without the name of the program.
+	pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, &'static str> {
+		let mut args = args.into_iter().collect::<Vec<_>>();
+		let base_path =
+			take_option(&mut args, "--base-path")?.unwrap_or_else(|| DEFAULT_BASE_PATH.to_string());
+
+		let command = match args.first().map(String::as_str) {
+			Some("init") => {
+				let chain_spec =
+					take_option(&mut args, "--chain-spec")?.ok_or("missing --chain-spec")?;
+				Command::Init { chain_spec }
+			},
+			Some("submit-tx") => {
+				let caller = take_option(&mut args, "--from")?.ok_or("missing --from")?;
+				let nonce = take_option(&mut args, "--nonce")?
+					.map(|nonce| nonce.parse().map_err(|_| "invalid nonce"))
+					.transpose()?;
+				let tip = take_option(&mut args, "--tip")?
+					.map(|tip| tip.parse().map_err(|_| "invalid tip"))
+					.transpose()?
+					.unwrap_or(0);
+				check_no_options(&args)?;
+				let call = parse_call(&args[1..])?;
+				Command::SubmitTx { caller, nonce, tip, call }
+			},
+			Some("produce-blocks") => {
+				let count = take_option(&mut args, "--count")?
+					.map(|count| count.parse().map_err(|_| "invalid count"))
+					.transpose()?
+					.unwrap_or(1);
+				Command::ProduceBlocks { count }
+			},
+			Some("rpc") => {
+				let port = take_option(&mut args, "--port")?
+					.map(|port| port.parse().map_err(|_| "invalid port"))
+					.transpose()?
+					.unwrap_or(rpc::DEFAULT_PORT);
+				Command::Rpc { port }
+			},
+			_ => {
+				check_no_options(&args)?;
+				match &args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+					["import-blocks", file] => Command::ImportBlocks { file: file.into() },
+					["export-blocks", file] => Command::ExportBlocks { file: file.into() },
+					["query", "balance", who] => Command::QueryBalance { who: who.to_string() },
+					["query", "claim", claim] => Command::QueryClaim { claim: claim.to_string() },
+					["block", id] => Command::Block { id: parse_block_id(id)? },
+					["metadata"] => Command::Metadata,
+					[] => return Err("missing command"),
+					_ => return Err("unknown command"),
+				}
+			},
+		};
+		check_no_options(&args)?;
+		match &command {
+			Command::Init { .. } | Command::ProduceBlocks { .. } | Command::Rpc { .. }
+				if args.len() > 1 =>
+			{
+				Err("unexpected argument")
+			},
+			_ => Ok(Cli { base_path: base_path.into(), command }),
+		}
+	}
+
+	/// Run the command against the chain of the data directory, printing its outcome.
+	pub fn run(self) -> Result<(), String> {
+		if let Command::Init { chain_spec } = &self.command {
+			let genesis = if chain_spec == "dev" {
+				GenesisConfig::dev()
+			} else {
+				let spec = fs::read_to_string(chain_spec).map_err(|e| e.to_string())?;
+				GenesisConfig::from_chain_spec(&spec)?
+			};
+			Node::init(&self.base_path, genesis).map_err(|e| e.to_string())?;
+			println!("Started a new chain in {}", self.base_path.display());
+			return Ok(());
+		}
+		if let Command::Metadata = self.command {
+			println!("{}", Value::from(&Runtime::metadata()));
+			return Ok(());
+		}
+
+		let mut node = Node::open(&self.base_path).map_err(|e| e.to_string())?;
+		match self.command {
+			Command::Init { .. } | Command::Metadata => unreachable!("handled above"),
+			Command::ImportBlocks { file } => {
+				let bytes = fs::read(&file).map_err(|e| e.to_string())?;
+				let (genesis, blocks) = <(GenesisConfig, Vec<types::Block>)>::decode_all(&bytes)?;
+				if &genesis != node.database().genesis() {
+					return Err("the blocks belong to a chain with another genesis".to_string());
+				}
+				for block in blocks {
+					let number = block.header.block_number;
+					match node.import_block(block).map_err(|e| e.to_string())? {
+						Some(receipt) => report(&receipt),
+						None => println!("Block {} is already known", number),
+					}
+				}
+			},
+			Command::ExportBlocks { file } => {
+				let head = node.database().head().map_or(0, |head| head.block_number);
+				let blocks = (1..=head)
+					.filter_map(|number| node.database().block(number).cloned())
+					.collect::<Vec<_>>();
+				let genesis = node.database().genesis().clone();
+				println!("Exporting {} blocks to {}", blocks.len(), file.display());
+				fs::write(&file, (genesis, blocks).encode()).map_err(|e| e.to_string())?;
+			},
+			Command::SubmitTx { caller, nonce, tip, call } => {
+				let nonce = nonce.unwrap_or_else(|| node.next_nonce(&caller));
+				let extrinsic = support::Extrinsic { caller: caller.clone(), nonce, tip, call };
+				node.submit(extrinsic).map_err(|e| e.to_string())?;
+				println!("Submitted the transaction {} of {}", nonce, caller);
+			},
+			Command::QueryBalance { who } => {
+				println!("{}", node.runtime().balances.balance(&who));
+			},
+			Command::QueryClaim { claim } => {
+				match node.runtime().proof_of_existence.get_claim(claim.as_str()) {
+					Some(owner) => println!("{}", owner),
+					None => println!("The claim does not exist"),
+				}
+			},
+			Command::Block { id } => {
+				let block = match id {
+					BlockId::Number(number) => node.database().block(number),
+					BlockId::Hash(hash) => node.database().block_by_hash(hash),
+				};
+				match block {
+					Some(block) => println!("{:#?}", block),
+					None => return Err("the block does not exist".to_string()),
+				}
+			},
+			Command::ProduceBlocks { count } => {
+				for _ in 0..count {
+					report(&node.produce_block().map_err(|e| e.to_string())?);
+				}
+				if !node.pool().is_empty() {
+					println!("{} transactions are still waiting in the pool", node.pool().len());
+				}
+			},
+			Command::Rpc { port } => {
+				let server = rpc::RpcServer::bind(node, port).map_err(|e| e.to_string())?;
+				let address = server.local_addr().map_err(|e| e.to_string())?;
+				println!("Serving JSON-RPC on http://{}", address);
+				server.run().map_err(|e| e.to_string())?;
+			},
+		}
+		Ok(())
+	}
+}
+
+/// Remove the option `name` and its value from `args`, and return the value.
+fn take_option(args: &mut Vec<String>, name: &str) -> Result<Option<String>, &'static str> {
+	let Some(index) = args.iter().position(|arg| arg == name) else { return Ok(None) };
+	if index + 1 >= args.len() {
+		return Err("missing value for an option");
+	}
+	let value = args.remove(index + 1);
+	args.remove(index);
+	Ok(Some(value))
+}
+
+/// Check every option of `args` was taken.
+fn check_no_options(args: &[String]) -> Result<(), &'static str> {
+	match args.iter().any(|arg| arg.starts_with("--")) {
+		true => Err("unknown option"),
+		false => Ok(()),
+	}
+}
+
+/// Parse a block number, or the hash of a block prefixed with `0x`.
+fn parse_block_id(id: &str) -> Result<BlockId, &'static str> {
+	match id.strip_prefix("0x") {
+		Some(hash) => Hash::from_str_radix(hash, 16)
+			.map(BlockId::Hash)
+			.map_err(|_| "invalid block hash"),
+		None => id.parse().map(BlockId::Number).map_err(|_| "invalid block number"),
+	}
+}
+
+/// Parse the call of a transaction.
+fn parse_call(args: &[String]) -> Result<RuntimeCall, &'static str> {
+	match &args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+		["transfer", to, amount] => Ok(RuntimeCall::balances(balances::Call::transfer {
+			to: to.to_string(),
+			amount: amount.parse().map_err(|_| "invalid amount")?,
+		})),
+		["create-claim", claim] => {
+			Ok(RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+				claim: claim.to_string(),
+			}))
+		},
+		["revoke-claim", claim] => {
+			Ok(RuntimeCall::proof_of_existence(proof_of_existence::Call::revoke_claim {
+				claim: claim.to_string(),
+			}))
+		},
+		[] => Err("missing call"),
+		_ => Err("unknown call"),
+	}
+}
+
+/// Print the failed extrinsics and the events of a block which was just imported.
+fn report(receipt: &types::BlockReceipt) {
+	println!(
+		"Imported block {} with {} extrinsics",
+		receipt.block_number,
+		receipt.extrinsics.len()
+	);
+	for event in &receipt.initialization_events {
+		println!("Event\n\tBlock Number: {}\n\tEvent: {:?}", receipt.block_number, event);
+	}
+	for (i, extrinsic) in receipt.extrinsics.iter().enumerate() {
+		if let Err(e) = extrinsic.result {
+			println!(
+				"Extrinsic Error\n\tBlock Number: {}\n\tExtrinsic Number: {}\n\tError: {}",
+				receipt.block_number, i, e
+			);
+		}
+		for event in &extrinsic.events {
+			println!(
+				"Event\n\tBlock Number: {}\n\tExtrinsic Number: {}\n\tEvent: {:?}",
+				receipt.block_number, i, event
+			);
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{BlockId, Cli, Command};
+	use crate::{balances, RuntimeCall};
+
+	fn parse(args: &str) -> Result<Cli, &'static str> {
+		Cli::parse(args.split_whitespace().map(str::to_string))
+	}
+
+	#[test]
+	fn commands_are_parsed() {
+		let cli = parse("--base-path /tmp/chain init --chain-spec dev").unwrap();
+		assert_eq!(cli.base_path.to_str(), Some("/tmp/chain"));
+		assert!(matches!(cli.command, Command::Init { chain_spec } if chain_spec == "dev"));
+
+		let cli = parse("submit-tx transfer bob 10 --from alice --tip 2").unwrap();
+		assert_eq!(cli.base_path.to_str(), Some(super::DEFAULT_BASE_PATH));
+		assert_eq!(
+			format!("{:?}", cli.command),
+			"SubmitTx { caller: \"alice\", nonce: None, tip: 2, call: balances(transfer { to: \
+			 \"bob\", amount: 10 }) }"
+		);
+		let Command::SubmitTx { call, .. } = cli.command else { panic!("not a transaction") };
+		let transfer = balances::Call::transfer { to: "bob".to_string(), amount: 10 };
+		assert_eq!(call, RuntimeCall::balances(transfer));
+		assert_eq!((call.pallet_name(), call.call_name()), ("balances", "transfer"));
+
+		assert!(matches!(
+			parse("produce-blocks --count 3").unwrap().command,
+			Command::ProduceBlocks { count: 3 }
+		));
+		assert!(matches!(
+			parse("query claim hello").unwrap().command,
+			Command::QueryClaim { claim } if claim == "hello"
+		));
+		assert!(matches!(parse("metadata").unwrap().command, Command::Metadata));
+		assert!(matches!(parse("rpc --port 9000").unwrap().command, Command::Rpc { port: 9000 }));
+		assert!(matches!(
+			parse("block 7").unwrap().command,
+			Command::Block { id: BlockId::Number(7) }
+		));
+		assert!(matches!(
+			parse("block 0xff").unwrap().command,
+			Command::Block { id: BlockId::Hash(255) }
+		));
+	}
+
+	#[test]
+	fn invalid_commands_are_rejected() {
+		assert_eq!(parse("").err(), Some("missing command"));
+		assert_eq!(parse("mine").err(), Some("unknown command"));
+		assert_eq!(parse("init").err(), Some("missing --chain-spec"));
+		assert_eq!(parse("init --chain-spec").err(), Some("missing value for an option"));
+		assert_eq!(parse("block 7 --verbose yes").err(), Some("unknown option"));
+		assert_eq!(parse("block seven").err(), Some("invalid block number"));
+		assert_eq!(parse("block 0xzz").err(), Some("invalid block hash"));
+		assert_eq!(parse("submit-tx transfer bob 10").err(), Some("missing --from"));
+		assert_eq!(parse("submit-tx --from alice stake 10").err(), Some("unknown call"));
+		assert_eq!(parse("produce-blocks now").err(), Some("unexpected argument"));
+		assert_eq!(parse("rpc --port high").err(), Some("invalid port"));
+	}
+}
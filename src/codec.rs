@@ -0,0 +1,304 @@
+use std::collections::BTreeMap;
+
+/// A simple binary encoding, used to store blocks and states on disk.
+///
+/// Integers are encoded as little endian bytes of their full width, and sequences like `String`
+/// and `Vec` are prefixed with their length as a `u32`. Enums are encoded as the index of their
+/// variant as a `u8`, followed by their fields. Nothing describes the encoded types, so a value
+/// must be decoded as the same type it was encoded from.
+pub trait Encode {
+	/// Append the encoding of `self` to `dest`.
+	fn encode_to(&self, dest: &mut Vec<u8>);
+
+	/// Get the encoding of `self`.
+	fn encode(&self) -> Vec<u8> {
+		let mut dest = Vec::new();
+		self.encode_to(&mut dest);
+		dest
+	}
+}
+
+/// The reverse of `Encode`.
+pub trait Decode: Sized {
+	/// Decode a value from the start of `input`, and advance `input` past it.
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str>;
+
+	/// Decode a value from all of `input`. Any bytes left over are an error.
+	fn decode_all(mut input: &[u8]) -> Result<Self, &'static str> {
+		let value = Self::decode(&mut input)?;
+		if !input.is_empty() {
+			return Err("input has bytes left over");
+		}
+		Ok(value)
+	}
+}
+
+/// Take the next `len` bytes of `input`.
+fn take<'a>(input: &mut &'a [u8], len: usize) -> Result<&'a [u8], &'static str> {
+	if input.len() < len {
+		return Err("not enough data to decode");
+	}
+	let (bytes, rest) = input.split_at(len);
+	*input = rest;
+	Ok(bytes)
+}
+
+macro_rules! impl_for_integers {
+	( $( $int:ty ),* ) => {
+		$(
+			impl Encode for $int {
+				fn encode_to(&self, dest: &mut Vec<u8>) {
+					dest.extend_from_slice(&self.to_le_bytes());
+				}
+			}
+
+			impl Decode for $int {
+				fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+					let bytes = take(input, core::mem::size_of::<$int>())?;
+					Ok(<$int>::from_le_bytes(bytes.try_into().expect("the length was checked")))
+				}
+			}
+		)*
+	};
+}
+
+impl_for_integers!(u8, u16, u32, u64, u128);
+
+impl Encode for bool {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		(*self as u8).encode_to(dest);
+	}
+}
+
+impl Decode for bool {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		match u8::decode(input)? {
+			0 => Ok(false),
+			1 => Ok(true),
+			_ => Err("invalid bool"),
+		}
+	}
+}
+
+impl Encode for () {
+	fn encode_to(&self, _dest: &mut Vec<u8>) {}
+}
+
+impl Decode for () {
+	fn decode(_input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(())
+	}
+}
+
+/// Encode the length of a sequence.
+fn encode_len(len: usize, dest: &mut Vec<u8>) {
+	u32::try_from(len)
+		.expect("sequences are shorter than `u32::MAX`")
+		.encode_to(dest);
+}
+
+impl Encode for str {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		encode_len(self.len(), dest);
+		dest.extend_from_slice(self.as_bytes());
+	}
+}
+
+impl Encode for String {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.as_str().encode_to(dest);
+	}
+}
+
+impl Decode for String {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		let len = u32::decode(input)? as usize;
+		let bytes = take(input, len)?;
+		String::from_utf8(bytes.to_vec()).map_err(|_| "invalid utf8 string")
+	}
+}
+
+impl Encode for &str {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		(**self).encode_to(dest);
+	}
+}
+
+impl<const N: usize> Encode for [u8; N] {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		dest.extend_from_slice(self);
+	}
+}
+
+impl<const N: usize> Decode for [u8; N] {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(take(input, N)?.try_into().expect("the length was checked"))
+	}
+}
+
+impl<T: Encode> Encode for Vec<T> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		encode_len(self.len(), dest);
+		for item in self {
+			item.encode_to(dest);
+		}
+	}
+}
+
+impl<T: Decode> Decode for Vec<T> {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		let len = u32::decode(input)? as usize;
+		// The length is not trusted to preallocate, since it could be anything.
+		let mut items = Vec::new();
+		for _ in 0..len {
+			items.push(T::decode(input)?);
+		}
+		Ok(items)
+	}
+}
+
+impl<T: Encode> Encode for Option<T> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		match self {
+			None => 0u8.encode_to(dest),
+			Some(value) => {
+				1u8.encode_to(dest);
+				value.encode_to(dest);
+			},
+		}
+	}
+}
+
+impl<T: Decode> Decode for Option<T> {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		match u8::decode(input)? {
+			0 => Ok(None),
+			1 => Ok(Some(T::decode(input)?)),
+			_ => Err("invalid option"),
+		}
+	}
+}
+
+impl<T: Encode, E: Encode> Encode for Result<T, E> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		match self {
+			Ok(value) => {
+				0u8.encode_to(dest);
+				value.encode_to(dest);
+			},
+			Err(error) => {
+				1u8.encode_to(dest);
+				error.encode_to(dest);
+			},
+		}
+	}
+}
+
+impl<T: Decode, E: Decode> Decode for Result<T, E> {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		match u8::decode(input)? {
+			0 => Ok(Ok(T::decode(input)?)),
+			1 => Ok(Err(E::decode(input)?)),
+			_ => Err("invalid result"),
+		}
+	}
+}
+
+/// Maps are encoded like a `Vec` of their entries, in the order of their keys.
+impl<K: Encode, V: Encode> Encode for BTreeMap<K, V> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		encode_len(self.len(), dest);
+		for (key, value) in self {
+			key.encode_to(dest);
+			value.encode_to(dest);
+		}
+	}
+}
+
+impl<K: Decode + Ord, V: Decode> Decode for BTreeMap<K, V> {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(Vec::<(K, V)>::decode(input)?.into_iter().collect())
+	}
+}
+
+impl<T: Encode + ?Sized> Encode for Box<T> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		(**self).encode_to(dest);
+	}
+}
+
+impl<T: Decode> Decode for Box<T> {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(Box::new(T::decode(input)?))
+	}
+}
+
+impl<A: Encode, B: Encode> Encode for (A, B) {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.0.encode_to(dest);
+		self.1.encode_to(dest);
+	}
+}
+
+impl<A: Decode, B: Decode> Decode for (A, B) {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok((A::decode(input)?, B::decode(input)?))
+	}
+}
+
+impl<A: Encode, B: Encode, C: Encode> Encode for (A, B, C) {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.0.encode_to(dest);
+		self.1.encode_to(dest);
+		self.2.encode_to(dest);
+	}
+}
+
+impl<A: Decode, B: Decode, C: Decode> Decode for (A, B, C) {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok((A::decode(input)?, B::decode(input)?, C::decode(input)?))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{Decode, Encode};
+
+	#[test]
+	fn values_round_trip() {
+		let value: Vec<(String, Option<u128>)> =
+			vec![("alice".to_string(), Some(100)), ("bob".to_string(), None)];
+		let encoded = value.encode();
+		assert_eq!(&encoded[..4], &2u32.to_le_bytes());
+		assert_eq!(Vec::<(String, Option<u128>)>::decode_all(&encoded), Ok(value));
+
+		let claim = "my claim";
+		assert_eq!(String::decode_all(&claim.encode()), Ok(claim.to_string()));
+		assert_eq!(bool::decode_all(&[2]), Err("invalid bool"));
+		assert_eq!(u32::decode_all(&[1, 0]), Err("not enough data to decode"));
+		assert_eq!(u8::decode_all(&[1, 0]), Err("input has bytes left over"));
+	}
+
+	#[test]
+	fn calls_round_trip() {
+		use crate::{balances, scheduler, support, RuntimeCall};
+
+		let call = RuntimeCall::scheduler(scheduler::Call::schedule {
+			when: 10,
+			maybe_periodic: Some((5, 2)),
+			priority: 1,
+			call: Box::new(RuntimeCall::balances(balances::Call::transfer {
+				to: "bob".to_string(),
+				amount: 42,
+			})),
+		});
+		let encoded = call.encode();
+		// The scheduler is the third pallet, and `schedule` its first function.
+		assert_eq!(&encoded[..2], &[2, 0]);
+		let decoded = RuntimeCall::decode_all(&encoded).unwrap();
+		assert_eq!(support::hash_of(&decoded), support::hash_of(&call));
+
+		assert_eq!(RuntimeCall::decode_all(&[2, 200]).err(), Some("invalid call index"));
+		assert_eq!(RuntimeCall::decode_all(&[200]).err(), Some("invalid pallet index"));
+	}
+}
@@ -0,0 +1,410 @@
+use std::{
+	collections::BTreeMap,
+	fs::{File, OpenOptions},
+	io::{self, Read, Write},
+	path::Path,
+};
+
+use crate::{
+	codec::{Decode, Encode},
+	genesis::GenesisConfig,
+	storage::EncodedStorage,
+	support::{self, Hash},
+	types, Runtime,
+};
+
+/// The version of the format of the database, which is the first byte of the genesis record. It is
+/// bumped whenever the encoding of the records changes, e.g. the encoding of blocks or of a state.
+const FORMAT_VERSION: u8 = 1;
+
+/// The record holding the genesis config and the genesis state of the chain, which is always the
+/// first record.
+const GENESIS_RECORD: u8 = 0;
+/// A record holding an imported block, and the changes it made to the state.
+const BLOCK_RECORD: u8 = 1;
+
+/// The changes a block made to the encoded state: the new value of each entry it changed, or
+/// `None` for the entries it removed.
+type StateDiff = Vec<(Vec<u8>, Option<Vec<u8>>)>;
+
+/// Why the database could not be used.
+#[derive(Debug)]
+pub enum DatabaseError {
+	/// The file of the database could not be read or written.
+	Io(io::Error),
+	/// The file of the database does not hold what is expected.
+	Corrupted(&'static str),
+	/// The block does not follow the head of the chain, or it is invalid on top of its state.
+	InvalidBlock(&'static str),
+}
+
+impl core::fmt::Display for DatabaseError {
+	fn fmt(&self, formatter: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		match self {
+			DatabaseError::Io(error) => write!(formatter, "{}", error),
+			DatabaseError::Corrupted(error) => write!(formatter, "corrupted database: {}", error),
+			DatabaseError::InvalidBlock(error) => write!(formatter, "invalid block: {}", error),
+		}
+	}
+}
+
+impl From<io::Error> for DatabaseError {
+	fn from(error: io::Error) -> Self {
+		DatabaseError::Io(error)
+	}
+}
+
+/// A chain stored on disk, as a single append-only file.
+///
+/// The file is a sequence of records, each made of its kind as a `u8`, the length of its payload as
+/// a `u32`, and its encoded payload. The first record holds the version of the format, the genesis
+/// config and the genesis state. The others hold the imported blocks in order, so the last one is
+/// the head of the chain, each with the changes it made to the state.
+///
+/// The state at the head is kept encoded, and updated with the changes of each block as they are
+/// read, so opening the database does not execute any block.
+pub struct Database {
+	/// The file records are appended to.
+	file: File,
+	genesis: GenesisConfig,
+	/// The state at the head of the chain.
+	state: EncodedStorage,
+	/// The imported blocks, where the block number `n` is at index `n - 1`.
+	blocks: Vec<types::Block>,
+	/// The number of each imported block, by the hash of its header.
+	numbers: BTreeMap<Hash, types::BlockNumber>,
+}
+
+impl Database {
+	/// Create the database of a new chain at `path`, starting from `genesis`. Fails if a file already
+	/// exists at `path`.
+	pub fn create(path: &Path, genesis: GenesisConfig) -> Result<Self, DatabaseError> {
+		let mut file = OpenOptions::new().append(true).create_new(true).open(path)?;
+		let state = genesis.build().encode_storage();
+		let mut payload = FORMAT_VERSION.encode();
+		genesis.encode_to(&mut payload);
+		state.encode_to(&mut payload);
+		write_record(&mut file, GENESIS_RECORD, &payload)?;
+		Ok(Database { file, genesis, state, blocks: Vec::new(), numbers: BTreeMap::new() })
+	}
+
+	/// Open the database at `path`.
+	///
+	/// A record which was only partly written, e.g. because the node stopped while importing a
+	/// block, is discarded.
+	pub fn open(path: &Path) -> Result<Self, DatabaseError> {
+		let mut file = OpenOptions::new().read(true).append(true).open(path)?;
+		let mut bytes = Vec::new();
+		file.read_to_end(&mut bytes)?;
+
+		let mut input = &bytes[..];
+		let (kind, payload) = read_record(&mut input)
+			.ok_or(DatabaseError::Corrupted("the database has no genesis config"))?;
+		if kind != GENESIS_RECORD {
+			return Err(DatabaseError::Corrupted("the database has no genesis config"));
+		}
+		let (version, payload) = payload
+			.split_first()
+			.ok_or(DatabaseError::Corrupted("the database has no genesis config"))?;
+		if *version != FORMAT_VERSION {
+			return Err(DatabaseError::Corrupted("unsupported database format"));
+		}
+		let (genesis, state) = <(GenesisConfig, EncodedStorage)>::decode_all(payload)
+			.map_err(DatabaseError::Corrupted)?;
+
+		let mut database = Database { file, genesis, state, blocks: Vec::new(), numbers: BTreeMap::new() };
+		let mut end = bytes.len() - input.len();
+		while let Some((kind, payload)) = read_record(&mut input) {
+			if kind != BLOCK_RECORD {
+				return Err(DatabaseError::Corrupted("unknown record"));
+			}
+			let (block, diff) = <(types::Block, StateDiff)>::decode_all(payload)
+				.map_err(DatabaseError::Corrupted)?;
+			database.check_next(&block.header).map_err(DatabaseError::Corrupted)?;
+			apply_diff(&mut database.state, diff);
+			database.insert(block);
+			end = bytes.len() - input.len();
+		}
+
+		if end < bytes.len() {
+			database.file.set_len(end as u64)?;
+		}
+		Ok(database)
+	}
+
+	/// Get the genesis config of the chain.
+	pub fn genesis(&self) -> &GenesisConfig {
+		&self.genesis
+	}
+
+	/// Get the header of the last imported block, or `None` if no block was imported yet.
+	pub fn head(&self) -> Option<&types::Header> {
+		self.blocks.last().map(|block| &block.header)
+	}
+
+	/// Get the imported block with the number `number`.
+	pub fn block(&self, number: types::BlockNumber) -> Option<&types::Block> {
+		let index = usize::try_from(number).ok()?.checked_sub(1)?;
+		self.blocks.get(index)
+	}
+
+	/// Get the imported block whose header hashes to `hash`.
+	pub fn block_by_hash(&self, hash: Hash) -> Option<&types::Block> {
+		self.numbers.get(&hash).and_then(|number| self.block(*number))
+	}
+
+	/// Store `block`, which becomes the new head of the chain, with `runtime` as the state it leads
+	/// to. The block must follow the head, and it must already be executed: the state is only
+	/// checked to be finalized with the block.
+	pub fn import_block(&mut self, block: types::Block, runtime: &Runtime) -> Result<(), DatabaseError> {
+		self.check_next(&block.header).map_err(DatabaseError::InvalidBlock)?;
+		if runtime.system.parent_hash() != support::hash_of(&block.header) {
+			return Err(DatabaseError::InvalidBlock("state is not finalized with the block"));
+		}
+		let state = runtime.encode_storage();
+		let mut payload = block.encode();
+		state_diff(&self.state, &state).encode_to(&mut payload);
+		write_record(&mut self.file, BLOCK_RECORD, &payload)?;
+		self.state = state;
+		self.insert(block);
+		Ok(())
+	}
+
+	/// Load the runtime at the head of the chain from its stored state, which is checked against the
+	/// state root of the head.
+	pub fn runtime(&self) -> Result<Runtime, DatabaseError> {
+		let mut runtime = Runtime::decode_storage(&self.state).map_err(DatabaseError::Corrupted)?;
+		if let Some(head) = self.head() {
+			// The state root is taken before the block is finalized, which only records the hash of
+			// its header as the parent of the next block.
+			let hash = support::hash_of(head);
+			let finalized = runtime.system.parent_hash() == hash;
+			runtime.system.set_parent_hash(head.parent_hash);
+			if !finalized || support::hash_of(&runtime) != head.state_root {
+				return Err(DatabaseError::Corrupted("state does not match the head of the chain"));
+			}
+			runtime.system.set_parent_hash(hash);
+		}
+		Ok(runtime)
+	}
+
+	/// Check that a block with `header` can be imported on top of the head.
+	fn check_next(&self, header: &types::Header) -> Result<(), &'static str> {
+		let (number, hash) = match self.head() {
+			Some(head) => (head.block_number, support::hash_of(head)),
+			None => (0, 0),
+		};
+		if header.block_number != number + 1 {
+			return Err("block does not follow the head of the chain");
+		}
+		if header.parent_hash != hash {
+			return Err("parent hash does not match the head of the chain");
+		}
+		Ok(())
+	}
+
+	fn insert(&mut self, block: types::Block) {
+		self.numbers.insert(support::hash_of(&block.header), block.header.block_number);
+		self.blocks.push(block);
+	}
+}
+
+/// The changes which turn the state `old` into the state `new`.
+fn state_diff(old: &EncodedStorage, new: &EncodedStorage) -> StateDiff {
+	let changed = new
+		.iter()
+		.filter(|(key, value)| old.get(*key) != Some(*value))
+		.map(|(key, value)| (key.clone(), Some(value.clone())));
+	let removed = old.keys().filter(|key| !new.contains_key(*key)).map(|key| (key.clone(), None));
+	changed.chain(removed).collect()
+}
+
+fn apply_diff(state: &mut EncodedStorage, diff: StateDiff) {
+	for (key, value) in diff {
+		match value {
+			Some(value) => state.insert(key, value),
+			None => state.remove(&key),
+		};
+	}
+}
+
+/// Append a record to `file`. The record is written at once, so it is either fully written or cut
+/// short, which `read_record` detects.
+fn write_record(file: &mut File, kind: u8, payload: &[u8]) -> io::Result<()> {
+	let len = u32::try_from(payload.len())
+		.map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "record is too large"))?;
+	let mut record = Vec::with_capacity(payload.len() + 5);
+	kind.encode_to(&mut record);
+	len.encode_to(&mut record);
+	record.extend_from_slice(payload);
+	file.write_all(&record)?;
+	file.sync_data()
+}
+
+/// Read the next record of `input`, as its kind and its payload. Returns `None` at the end of the
+/// input, or if the last record was cut short.
+fn read_record<'a>(input: &mut &'a [u8]) -> Option<(u8, &'a [u8])> {
+	let mut rest = *input;
+	let kind = u8::decode(&mut rest).ok()?;
+	let len = u32::decode(&mut rest).ok()? as usize;
+	let payload = rest.get(..len)?;
+	*input = &rest[len..];
+	Some((kind, payload))
+}
+
+#[cfg(test)]
+mod test {
+	use std::{fs, path::PathBuf};
+
+	use super::{Database, DatabaseError};
+	use crate::{
+		balances, block_builder::BlockBuilder, genesis::GenesisConfig, support, system, types,
+		Runtime, RuntimeCall,
+	};
+
+	/// A path for the database of a test, which does not exist yet.
+	fn path(name: &str) -> PathBuf {
+		let path = std::env::temp_dir().join(format!("database-{}-{}", name, std::process::id()));
+		let _ = fs::remove_file(&path);
+		path
+	}
+
+	fn genesis() -> GenesisConfig {
+		GenesisConfig { balances: vec![("alice".to_string(), 100)] }
+	}
+
+	/// Build and execute the next block on top of `runtime`, with a transfer from alice to bob.
+	fn next_block(runtime: &mut Runtime) -> types::Block {
+		let mut builder = BlockBuilder::new(runtime).unwrap();
+		let extrinsic = support::Extrinsic {
+			caller: "alice".to_string(),
+			nonce: runtime.system.nonce(&"alice".to_string()),
+			tip: 0,
+			call: RuntimeCall::balances(balances::Call::transfer {
+				to: "bob".to_string(),
+				amount: 10,
+			}),
+		};
+		assert!(builder.push(extrinsic).is_ok());
+		let block = builder.finalize();
+		assert!(runtime.execute_block(block.clone()).is_ok());
+		block
+	}
+
+	#[test]
+	fn chain_is_resumed() {
+		let path = path("resumed");
+		let mut database = Database::create(&path, genesis()).unwrap();
+		assert!(Database::create(&path, genesis()).is_err());
+
+		let mut runtime = database.runtime().unwrap();
+		let block_1 = next_block(&mut runtime);
+		database.import_block(block_1.clone(), &runtime).unwrap();
+		let block_2 = next_block(&mut runtime);
+		// The state must be the one the block leads to.
+		assert!(matches!(
+			database.import_block(block_2.clone(), &database.runtime().unwrap()),
+			Err(DatabaseError::InvalidBlock(_))
+		));
+		database.import_block(block_2.clone(), &runtime).unwrap();
+		assert!(matches!(
+			database.import_block(block_1.clone(), &runtime),
+			Err(DatabaseError::InvalidBlock(_))
+		));
+		drop(database);
+
+		let database = Database::open(&path).unwrap();
+		assert_eq!(database.genesis(), &genesis());
+		assert_eq!(database.head().map(|head| head.block_number), Some(2));
+		assert_eq!(database.block(1).map(support::hash_of), Some(support::hash_of(&block_1)));
+		assert_eq!(
+			database.block_by_hash(support::hash_of(&block_2.header)).map(support::hash_of),
+			Some(support::hash_of(&block_2))
+		);
+		assert!(database.block(3).is_none());
+
+		let resumed = database.runtime().unwrap();
+		assert_eq!(support::hash_of(&resumed), support::hash_of(&runtime));
+		assert_eq!(resumed.balances.balance(&"bob".to_string()), 20);
+		fs::remove_file(&path).unwrap();
+	}
+
+	#[test]
+	fn partly_written_blocks_are_discarded() {
+		let path = path("partly-written");
+		let mut database = Database::create(&path, genesis()).unwrap();
+		let mut runtime = database.runtime().unwrap();
+		let block = next_block(&mut runtime);
+		database.import_block(block, &runtime).unwrap();
+		let len = fs::metadata(&path).unwrap().len();
+		let block = next_block(&mut runtime);
+		database.import_block(block, &runtime).unwrap();
+		drop(database);
+
+		// Cut the last block short.
+		fs::OpenOptions::new()
+			.write(true)
+			.open(&path)
+			.unwrap()
+			.set_len(len + 10)
+			.unwrap();
+		let mut database = Database::open(&path).unwrap();
+		assert_eq!(database.head().map(|head| head.block_number), Some(1));
+		assert_eq!(fs::metadata(&path).unwrap().len(), len);
+
+		// The chain goes on from the last full block.
+		let mut runtime = database.runtime().unwrap();
+		let block = next_block(&mut runtime);
+		database.import_block(block, &runtime).unwrap();
+		drop(database);
+		assert_eq!(Database::open(&path).unwrap().head().map(|head| head.block_number), Some(2));
+		fs::remove_file(&path).unwrap();
+	}
+
+	#[test]
+	fn other_formats_are_rejected() {
+		let path = path("format");
+		drop(Database::create(&path, genesis()).unwrap());
+
+		// The version of the format follows the kind and the length of the genesis record.
+		let mut bytes = fs::read(&path).unwrap();
+		bytes[5] += 1;
+		fs::write(&path, bytes).unwrap();
+		assert!(matches!(
+			Database::open(&path),
+			Err(DatabaseError::Corrupted("unsupported database format"))
+		));
+		fs::remove_file(&path).unwrap();
+	}
+
+	#[test]
+	fn chain_is_resumed_across_runtime_upgrades() {
+		let path = path("upgrade");
+		let mut database = Database::create(&path, genesis()).unwrap();
+		let spec_version = <Runtime as system::Config>::SPEC_VERSION;
+
+		// A block imported by the previous version of the runtime, which left the state at its own
+		// spec version.
+		let mut runtime = database.runtime().unwrap();
+		let mut block = next_block(&mut runtime);
+		runtime.system.set_spec_version(spec_version - 1);
+		runtime.system.set_parent_hash(block.header.parent_hash);
+		block.header.state_root = support::hash_of(&runtime);
+		runtime.system.set_parent_hash(support::hash_of(&block.header));
+		database.import_block(block, &runtime).unwrap();
+		drop(database);
+
+		// The state is loaded as it was stored, and the next block upgrades it.
+		let mut database = Database::open(&path).unwrap();
+		let mut runtime = database.runtime().unwrap();
+		assert_eq!(runtime.system.spec_version(), spec_version - 1);
+		let block = next_block(&mut runtime);
+		database.import_block(block, &runtime).unwrap();
+		drop(database);
+
+		let runtime = Database::open(&path).unwrap().runtime().unwrap();
+		assert_eq!(runtime.system.spec_version(), spec_version);
+		assert_eq!(runtime.balances.balance(&"bob".to_string()), 20);
+		fs::remove_file(&path).unwrap();
+	}
+}
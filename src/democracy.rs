@@ -0,0 +1,708 @@
+use core::fmt::Debug;
+use num::traits::{CheckedAdd, CheckedDiv, CheckedMul, Zero};
+use std::collections::BTreeMap;
+
+use crate::{
+	balances::{self, LockIdentifier},
+	codec::{Decode, Encode},
+	storage::{StorageDoubleMap, StorageMap, StorageValue},
+	support::{DispatchResult, GetDispatchInfo},
+	system,
+};
+
+/// The identifier of the lock this pallet keeps on the funds used to vote.
+const DEMOCRACY_ID: LockIdentifier = *b"democrac";
+
+/// The index of a public proposal.
+pub type PropIndex = u32;
+
+/// The index of a referendum.
+pub type ReferendumIndex = u32;
+
+pub trait Config:
+	balances::Config<
+		AccountId: Debug,
+		Balance: CheckedMul + CheckedDiv + From<u32> + Debug,
+		BlockNumber: Debug,
+		RuntimeEvent: From<Event<Self>>,
+	>
+	+ Sized
+	+ AsRef<system::Pallet<Self>>
+	+ AsMut<balances::Pallet<Self>>
+	+ AsMut<Pallet<Self>>
+{
+	/// The aggregated call type of the runtime, which proposals dispatch once enacted.
+	type RuntimeCall: GetDispatchInfo + Clone + Debug;
+	/// The minimum deposit which must be reserved to make a proposal.
+	const MINIMUM_DEPOSIT: Self::Balance;
+	/// The number of blocks between two referenda being launched from the public proposals.
+	const LAUNCH_PERIOD: Self::BlockNumber;
+	/// The number of blocks a referendum is open for voting.
+	const VOTING_PERIOD: Self::BlockNumber;
+	/// The number of blocks between a referendum passing and its proposal being enacted.
+	const ENACTMENT_PERIOD: Self::BlockNumber;
+	/// The number of blocks funds stay locked after the end of a referendum, for each lock period of
+	/// the conviction they voted with.
+	const VOTE_LOCKING_PERIOD: Self::BlockNumber;
+}
+
+/// How long a voter is willing to lock their funds after the end of a referendum. The longer the
+/// funds are locked, the more the vote is worth.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Conviction {
+	/// 0.1x votes, unlocked at the end of the referendum.
+	None,
+	/// 1x votes, locked for 1 lock period.
+	Locked1x,
+	/// 2x votes, locked for 2 lock periods.
+	Locked2x,
+	/// 3x votes, locked for 4 lock periods.
+	Locked3x,
+	/// 4x votes, locked for 8 lock periods.
+	Locked4x,
+	/// 5x votes, locked for 16 lock periods.
+	Locked5x,
+	/// 6x votes, locked for 32 lock periods.
+	Locked6x,
+}
+
+impl Encode for Conviction {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		(*self as u8).encode_to(dest);
+	}
+}
+
+impl Decode for Conviction {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		match u8::decode(input)? {
+			0 => Ok(Conviction::None),
+			1 => Ok(Conviction::Locked1x),
+			2 => Ok(Conviction::Locked2x),
+			3 => Ok(Conviction::Locked3x),
+			4 => Ok(Conviction::Locked4x),
+			5 => Ok(Conviction::Locked5x),
+			6 => Ok(Conviction::Locked6x),
+			_ => Err("invalid conviction"),
+		}
+	}
+}
+
+impl Conviction {
+	/// The number of lock periods funds stay locked after the end of the referendum.
+	pub fn lock_periods(self) -> u32 {
+		match self {
+			Conviction::None => 0,
+			Conviction::Locked1x => 1,
+			Conviction::Locked2x => 2,
+			Conviction::Locked3x => 4,
+			Conviction::Locked4x => 8,
+			Conviction::Locked5x => 16,
+			Conviction::Locked6x => 32,
+		}
+	}
+
+	/// The votes `balance` is worth with this conviction.
+	pub fn votes<Balance: CheckedMul + CheckedDiv + From<u32> + Zero>(self, balance: Balance) -> Balance {
+		let votes = match self {
+			Conviction::None => balance.checked_div(&Balance::from(10)),
+			Conviction::Locked1x => Some(balance),
+			Conviction::Locked2x => balance.checked_mul(&Balance::from(2)),
+			Conviction::Locked3x => balance.checked_mul(&Balance::from(3)),
+			Conviction::Locked4x => balance.checked_mul(&Balance::from(4)),
+			Conviction::Locked5x => balance.checked_mul(&Balance::from(5)),
+			Conviction::Locked6x => balance.checked_mul(&Balance::from(6)),
+		};
+		votes.unwrap_or(Balance::zero())
+	}
+}
+
+/// The vote of an account in a referendum.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AccountVote<Balance> {
+	/// Whether the vote is in favor of the proposal.
+	pub aye: bool,
+	/// How long `balance` stays locked after the end of the referendum.
+	pub conviction: Conviction,
+	/// The funds voting, which are locked.
+	pub balance: Balance,
+}
+
+impl<Balance: Encode> Encode for AccountVote<Balance> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.aye.encode_to(dest);
+		self.conviction.encode_to(dest);
+		self.balance.encode_to(dest);
+	}
+}
+
+impl<Balance: Decode> Decode for AccountVote<Balance> {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(AccountVote {
+			aye: bool::decode(input)?,
+			conviction: Conviction::decode(input)?,
+			balance: Balance::decode(input)?,
+		})
+	}
+}
+
+/// A proposal waiting to be launched as a referendum.
+#[derive(Debug)]
+struct PublicProposal<T: Config> {
+	index: PropIndex,
+	/// The call to dispatch if the proposal is enacted.
+	proposal: T::RuntimeCall,
+	/// The deposit reserved by the proposer, and by every account which seconded the proposal.
+	deposit: T::Balance,
+	/// The proposer, followed by every account which seconded the proposal.
+	backers: Vec<T::AccountId>,
+}
+
+// Proposals and referenda implement `Clone` by hand, since deriving it would require `T: Clone`.
+impl<T: Config> Clone for PublicProposal<T> {
+	fn clone(&self) -> Self {
+		PublicProposal {
+			index: self.index,
+			proposal: self.proposal.clone(),
+			deposit: self.deposit,
+			backers: self.backers.clone(),
+		}
+	}
+}
+
+impl<T: Config> Encode for PublicProposal<T>
+where
+	T::RuntimeCall: Encode,
+	T::Balance: Encode,
+{
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.index.encode_to(dest);
+		self.proposal.encode_to(dest);
+		self.deposit.encode_to(dest);
+		self.backers.encode_to(dest);
+	}
+}
+
+impl<T: Config> Decode for PublicProposal<T>
+where
+	T::RuntimeCall: Decode,
+	T::AccountId: Decode,
+	T::Balance: Decode,
+{
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(PublicProposal {
+			index: PropIndex::decode(input)?,
+			proposal: T::RuntimeCall::decode(input)?,
+			deposit: T::Balance::decode(input)?,
+			backers: Vec::decode(input)?,
+		})
+	}
+}
+
+impl<T: Config> PublicProposal<T> {
+	/// The total deposit backing the proposal.
+	fn backing(&self) -> T::Balance {
+		let backers = T::Balance::from(self.backers.len() as u32);
+		self.deposit.checked_mul(&backers).unwrap_or(self.deposit)
+	}
+}
+
+/// A referendum open for voting.
+#[derive(Debug)]
+struct ReferendumInfo<T: Config> {
+	/// The block at which voting ends, and the votes are tallied.
+	end: T::BlockNumber,
+	/// The call to dispatch if the referendum passes.
+	proposal: T::RuntimeCall,
+	/// The vote of each account.
+	votes: BTreeMap<T::AccountId, AccountVote<T::Balance>>,
+}
+
+impl<T: Config> Clone for ReferendumInfo<T> {
+	fn clone(&self) -> Self {
+		ReferendumInfo { end: self.end, proposal: self.proposal.clone(), votes: self.votes.clone() }
+	}
+}
+
+impl<T: Config> Encode for ReferendumInfo<T>
+where
+	T::RuntimeCall: Encode,
+	T::Balance: Encode,
+{
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.end.encode_to(dest);
+		self.proposal.encode_to(dest);
+		self.votes.encode_to(dest);
+	}
+}
+
+impl<T: Config> Decode for ReferendumInfo<T>
+where
+	T::RuntimeCall: Decode,
+	T::AccountId: Decode,
+	T::Balance: Decode,
+	T::BlockNumber: Decode,
+{
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(ReferendumInfo {
+			end: T::BlockNumber::decode(input)?,
+			proposal: T::RuntimeCall::decode(input)?,
+			votes: BTreeMap::decode(input)?,
+		})
+	}
+}
+
+/// This is the Democracy Module.
+/// It lets accounts make proposals, which are periodically launched as referenda. Referenda which
+/// pass are enacted after a delay, by dispatching their call as Root.
+#[macros::storage]
+#[derive(Debug, macros::PalletInfo)]
+pub struct Pallet<T: Config> {
+	/// The proposals waiting to be launched as referenda.
+	public_props: StorageValue<Vec<PublicProposal<T>>>,
+	/// The index of the next public proposal.
+	next_prop_index: StorageValue<PropIndex>,
+	/// The referenda open for voting.
+	referenda: StorageMap<ReferendumIndex, ReferendumInfo<T>>,
+	/// The index of the next referendum.
+	next_ref_index: StorageValue<ReferendumIndex>,
+	/// The block at which the next referendum is launched.
+	#[default(T::LAUNCH_PERIOD)]
+	next_launch: StorageValue<T::BlockNumber>,
+	/// The calls of the referenda which passed, by the block at which they are enacted.
+	dispatch_queue: StorageMap<T::BlockNumber, Vec<T::RuntimeCall>>,
+	/// The funds locked by each vote `(voter, referendum)`, with the block they can be unlocked at.
+	vote_locks: StorageDoubleMap<T::AccountId, ReferendumIndex, (T::BlockNumber, T::Balance)>,
+}
+
+/// The events of this pallet.
+#[macros::event]
+pub enum Event<T: Config> {
+	/// `proposer` made a public proposal, reserving `deposit`.
+	Proposed { index: PropIndex, proposer: T::AccountId, deposit: T::Balance },
+	/// `seconder` seconded a public proposal.
+	Seconded { index: PropIndex, seconder: T::AccountId },
+	/// `voter` voted in a referendum.
+	Voted { voter: T::AccountId, ref_index: ReferendumIndex, vote: AccountVote<T::Balance> },
+	/// A call enacted by democracy was dispatched as Root.
+	Enacted { result: DispatchResult },
+}
+
+/// The errors of this pallet.
+#[macros::error]
+pub enum Error<T: Config> {
+	/// The deposit is below the minimum.
+	DepositLow,
+	/// The proposal does not exist.
+	UnknownProposal,
+	/// The account does not have enough funds to vote.
+	InsufficientFunds,
+	/// The referendum is not ongoing.
+	ReferendumNotOngoing,
+	/// The proposal is too heavy to be enacted within a block.
+	TooHeavy,
+}
+
+impl<T: Config> Pallet<T> {
+	/// Get the indices of the proposals waiting to be launched, with the accounts backing them.
+	pub fn public_props(&self) -> Vec<(PropIndex, Vec<T::AccountId>)> {
+		self.public_props.get().iter().map(|prop| (prop.index, prop.backers.clone())).collect()
+	}
+
+	/// Get the vote of `who` in a referendum open for voting, if any.
+	pub fn vote_of(
+		&self,
+		ref_index: ReferendumIndex,
+		who: &T::AccountId,
+	) -> Option<AccountVote<T::Balance>> {
+		self.referenda.get(&ref_index)?.votes.get(who).cloned()
+	}
+
+	/// Get the `(ayes, nays)` of a referendum open for voting, weighted by conviction.
+	pub fn tally(&self, ref_index: ReferendumIndex) -> Option<(T::Balance, T::Balance)> {
+		let referendum = self.referenda.get(&ref_index)?;
+		let tally = referendum.votes.values().fold(
+			(T::Balance::zero(), T::Balance::zero()),
+			|(ayes, nays), vote| {
+				let votes = vote.conviction.votes(vote.balance);
+				if vote.aye {
+					(ayes.checked_add(&votes).unwrap_or(ayes), nays)
+				} else {
+					(ayes, nays.checked_add(&votes).unwrap_or(nays))
+				}
+			},
+		);
+		Some(tally)
+	}
+
+	/// Launch and tally referenda, and take the calls which are enacted at block `now`. The calls
+	/// should be dispatched as Root.
+	///
+	/// Every `T::LAUNCH_PERIOD` blocks, the public proposal with the most backing becomes a
+	/// referendum, and the deposits backing it are returned. A referendum passes if it has more
+	/// ayes than nays when voting ends, and its call is enacted `T::ENACTMENT_PERIOD` blocks later.
+	pub fn on_initialize(runtime: &mut T, now: T::BlockNumber) -> Vec<T::RuntimeCall> {
+		let democracy: &mut Pallet<T> = runtime.as_mut();
+
+		let mut unreserved = Vec::new();
+		if now >= democracy.next_launch.get() {
+			democracy.next_launch.put(now.checked_add(&T::LAUNCH_PERIOD).unwrap_or(now));
+			if let Some(prop) = democracy.take_best_proposal() {
+				let end = now.checked_add(&T::VOTING_PERIOD).unwrap_or(now);
+				let referendum = ReferendumInfo { end, proposal: prop.proposal, votes: BTreeMap::new() };
+				democracy.referenda.insert(democracy.next_ref_index.get(), referendum);
+				democracy.next_ref_index.mutate(|index| *index += 1);
+				unreserved.extend(prop.backers.into_iter().map(|backer| (backer, prop.deposit)));
+			}
+		}
+
+		let ended = democracy
+			.referenda
+			.iter()
+			.filter(|(_, referendum)| referendum.end <= now)
+			.map(|(index, _)| index)
+			.collect::<Vec<_>>();
+		for index in ended {
+			let Some((ayes, nays)) = democracy.tally(index) else { continue };
+			let Some(referendum) = democracy.referenda.remove(&index) else { continue };
+			if ayes > nays {
+				let when = now.checked_add(&T::ENACTMENT_PERIOD).unwrap_or(now);
+				democracy
+					.dispatch_queue
+					.mutate(when, |calls| calls.get_or_insert_with(Vec::new).push(referendum.proposal));
+			}
+		}
+
+		let due = democracy.dispatch_queue.range(..=now).map(|(when, _)| when).collect::<Vec<_>>();
+		let calls = due
+			.into_iter()
+			.flat_map(|when| democracy.dispatch_queue.remove(&when).unwrap_or_default())
+			.collect();
+
+		let balances: &mut balances::Pallet<T> = runtime.as_mut();
+		for (backer, deposit) in unreserved {
+			balances.unreserve(&backer, deposit);
+		}
+		calls
+	}
+
+	/// Remove the public proposal with the most backing. Among equally backed proposals, the oldest
+	/// is taken.
+	fn take_best_proposal(&mut self) -> Option<PublicProposal<T>> {
+		let (position, _) = self
+			.public_props
+			.get()
+			.iter()
+			.enumerate()
+			.rev()
+			.max_by(|(_, a), (_, b)| a.backing().cmp(&b.backing()))?;
+		Some(self.public_props.mutate(|props| props.remove(position)))
+	}
+
+	/// The block at which funds voting in a referendum which ends at block `end` are unlocked.
+	fn unlock_at(end: T::BlockNumber, conviction: Conviction) -> T::BlockNumber {
+		(0..conviction.lock_periods())
+			.try_fold(end, |at, _| at.checked_add(&T::VOTE_LOCKING_PERIOD))
+			.unwrap_or(end)
+	}
+
+	/// Update the lock on the funds of `who` to the largest amount still locked by one of its votes.
+	fn update_lock(runtime: &mut T, who: &T::AccountId) {
+		let democracy: &mut Pallet<T> = runtime.as_mut();
+		let locked = democracy.vote_locks.iter_prefix(who).map(|(_, (_, balance))| balance).max();
+
+		let balances: &mut balances::Pallet<T> = runtime.as_mut();
+		match locked {
+			Some(locked) => balances.set_lock(DEMOCRACY_ID, who, locked),
+			None => balances.remove_lock(DEMOCRACY_ID, who),
+		}
+	}
+}
+
+// The calls are boxed, since `Call` would otherwise contain itself through `T::RuntimeCall`.
+#[allow(clippy::boxed_local)]
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	/// Propose `proposal` to be launched as a referendum, reserving `deposit` from the caller.
+	/// Proposals heavier than a block are rejected, since they are enacted when a block is
+	/// initialized, whatever their weight.
+	#[call_index(0)]
+	pub fn propose(
+		runtime: &mut T,
+		caller: T::AccountId,
+		proposal: Box<T::RuntimeCall>,
+		deposit: T::Balance,
+	) -> DispatchResult {
+		if deposit < T::MINIMUM_DEPOSIT {
+			return Err(Error::<T>::DepositLow.into());
+		}
+		if proposal.weight() > T::MAXIMUM_BLOCK_WEIGHT {
+			return Err(Error::<T>::TooHeavy.into());
+		}
+		let balances: &mut balances::Pallet<T> = runtime.as_mut();
+		balances.reserve(&caller, deposit)?;
+
+		let democracy: &mut Pallet<T> = runtime.as_mut();
+		let index = democracy.next_prop_index.get();
+		democracy.next_prop_index.mutate(|index| *index += 1);
+		let prop = PublicProposal { index, proposal: *proposal, deposit, backers: vec![caller.clone()] };
+		democracy.public_props.mutate(|props| props.push(prop));
+		democracy.deposit_event(Event::Proposed { index, proposer: caller, deposit });
+		Ok(())
+	}
+
+	/// Second a public proposal, reserving the same deposit as its proposer. A proposal can be
+	/// seconded more than once by the same account.
+	#[call_index(1)]
+	pub fn second(runtime: &mut T, caller: T::AccountId, proposal: PropIndex) -> DispatchResult {
+		let democracy: &mut Pallet<T> = runtime.as_mut();
+		let deposit = democracy
+			.public_props
+			.get()
+			.iter()
+			.find(|prop| prop.index == proposal)
+			.ok_or(Error::<T>::UnknownProposal)?
+			.deposit;
+
+		let balances: &mut balances::Pallet<T> = runtime.as_mut();
+		balances.reserve(&caller, deposit)?;
+
+		let democracy: &mut Pallet<T> = runtime.as_mut();
+		democracy.public_props.mutate(|props| {
+			if let Some(prop) = props.iter_mut().find(|prop| prop.index == proposal) {
+				prop.backers.push(caller.clone());
+			}
+		});
+		democracy.deposit_event(Event::Seconded { index: proposal, seconder: caller });
+		Ok(())
+	}
+
+	/// Vote in a referendum with `balance`, which stays locked until the end of the referendum, and
+	/// for as many lock periods as `conviction` requires. A new vote replaces the previous one.
+	#[call_index(2)]
+	pub fn vote(
+		runtime: &mut T,
+		caller: T::AccountId,
+		ref_index: ReferendumIndex,
+		aye: bool,
+		conviction: Conviction,
+		balance: T::Balance,
+	) -> DispatchResult {
+		let balances: &mut balances::Pallet<T> = runtime.as_mut();
+		if balance > balances.balance(&caller) {
+			return Err(Error::<T>::InsufficientFunds.into());
+		}
+
+		let democracy: &mut Pallet<T> = runtime.as_mut();
+		let mut referendum =
+			democracy.referenda.get(&ref_index).ok_or(Error::<T>::ReferendumNotOngoing)?;
+		let vote = AccountVote { aye, conviction, balance };
+		referendum.votes.insert(caller.clone(), vote);
+		let unlock_at = Self::unlock_at(referendum.end, conviction);
+		democracy.referenda.insert(ref_index, referendum);
+		democracy.vote_locks.insert(caller.clone(), ref_index, (unlock_at, balance));
+		democracy.deposit_event(Event::Voted { voter: caller.clone(), ref_index, vote });
+
+		Self::update_lock(runtime, &caller);
+		Ok(())
+	}
+
+	/// Unlock the funds of `target` which are no longer locked by any of its votes.
+	#[call_index(3)]
+	pub fn unlock(runtime: &mut T, _caller: T::AccountId, target: T::AccountId) -> DispatchResult {
+		let now = AsRef::<system::Pallet<T>>::as_ref(runtime).block_number();
+		let democracy: &mut Pallet<T> = runtime.as_mut();
+		let expired = democracy
+			.vote_locks
+			.iter_prefix(&target)
+			.filter(|(_, (unlock_at, _))| *unlock_at <= now)
+			.map(|(ref_index, _)| ref_index)
+			.collect::<Vec<_>>();
+		for ref_index in expired {
+			democracy.vote_locks.remove(&target, &ref_index);
+		}
+
+		Self::update_lock(runtime, &target);
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::Conviction;
+	use crate::{
+		balances,
+		support::{GetDispatchInfo, Weight},
+		system,
+	};
+
+	struct TestRuntime {
+		system: system::Pallet<Self>,
+		balances: balances::Pallet<Self>,
+		democracy: super::Pallet<Self>,
+	}
+
+	impl system::Config for TestRuntime {
+		type AccountId = &'static str;
+		type BlockNumber = u32;
+		type Nonce = u32;
+		type RuntimeEvent = ();
+		type PalletIndex = ();
+		const MAXIMUM_BLOCK_WEIGHT: Weight = 100;
+		const MAXIMUM_BLOCK_LENGTH: u32 = u32::MAX;
+		const SPEC_VERSION: u32 = 1;
+	}
+
+	// Proposals are plain numbers, which weigh as much as their value.
+	impl GetDispatchInfo for u32 {
+		fn weight(&self) -> Weight {
+			*self as Weight
+		}
+	}
+
+	impl balances::Config for TestRuntime {
+		type Balance = u128;
+	}
+
+	impl super::Config for TestRuntime {
+		type RuntimeCall = u32;
+		const MINIMUM_DEPOSIT: u128 = 10;
+		const LAUNCH_PERIOD: u32 = 5;
+		const VOTING_PERIOD: u32 = 3;
+		const ENACTMENT_PERIOD: u32 = 2;
+		const VOTE_LOCKING_PERIOD: u32 = 4;
+	}
+
+	impl AsRef<system::Pallet<Self>> for TestRuntime {
+		fn as_ref(&self) -> &system::Pallet<Self> {
+			&self.system
+		}
+	}
+
+	impl AsMut<balances::Pallet<Self>> for TestRuntime {
+		fn as_mut(&mut self) -> &mut balances::Pallet<Self> {
+			&mut self.balances
+		}
+	}
+
+	impl AsMut<super::Pallet<Self>> for TestRuntime {
+		fn as_mut(&mut self) -> &mut super::Pallet<Self> {
+			&mut self.democracy
+		}
+	}
+
+	fn new_runtime() -> TestRuntime {
+		let mut runtime = TestRuntime {
+			system: system::Pallet::new(),
+			balances: balances::Pallet::new(),
+			democracy: super::Pallet::new(),
+		};
+		for who in ["alice", "bob", "charlie"] {
+			runtime.balances.set_balance(&who, 100);
+		}
+		runtime
+	}
+
+	/// Advance to block `n`, returning the calls enacted on the way.
+	fn run_to_block(runtime: &mut TestRuntime, n: u32) -> Vec<u32> {
+		let mut enacted = Vec::new();
+		while runtime.system.block_number() < n {
+			runtime.system.inc_block_number();
+			let now = runtime.system.block_number();
+			enacted.extend(super::Pallet::on_initialize(runtime, now));
+		}
+		enacted
+	}
+
+	#[test]
+	fn conviction_votes() {
+		assert_eq!(Conviction::None.votes(100u128), 10);
+		assert_eq!(Conviction::Locked1x.votes(100u128), 100);
+		assert_eq!(Conviction::Locked6x.votes(100u128), 600);
+		assert_eq!(super::Pallet::<TestRuntime>::unlock_at(10, Conviction::None), 10);
+		assert_eq!(super::Pallet::<TestRuntime>::unlock_at(10, Conviction::Locked3x), 26);
+	}
+
+	#[test]
+	fn propose_and_second() {
+		let mut runtime = new_runtime();
+		assert_eq!(
+			super::Pallet::propose(&mut runtime, "alice", Box::new(1), 5),
+			Err(super::Error::<TestRuntime>::DepositLow.into())
+		);
+		assert_eq!(
+			super::Pallet::propose(&mut runtime, "alice", Box::new(101), 10),
+			Err(super::Error::<TestRuntime>::TooHeavy.into())
+		);
+		assert_eq!(super::Pallet::propose(&mut runtime, "alice", Box::new(1), 10), Ok(()));
+		assert_eq!(super::Pallet::propose(&mut runtime, "bob", Box::new(2), 20), Ok(()));
+		assert_eq!(super::Pallet::second(&mut runtime, "charlie", 0), Ok(()));
+		assert_eq!(super::Pallet::second(&mut runtime, "charlie", 0), Ok(()));
+		assert_eq!(super::Pallet::second(&mut runtime, "charlie", 2), Err(super::Error::<TestRuntime>::UnknownProposal.into()));
+		assert_eq!(runtime.balances.reserved_balance(&"alice"), 10);
+		assert_eq!(runtime.balances.reserved_balance(&"charlie"), 20);
+
+		// Proposal 0 has a backing of 30 against 20, so it is launched first, and its deposits are
+		// returned.
+		run_to_block(&mut runtime, 5);
+		assert_eq!(runtime.democracy.public_props(), vec![(1, vec!["bob"])]);
+		assert!(runtime.democracy.tally(0).is_some());
+		assert_eq!(runtime.balances.reserved_balance(&"alice"), 0);
+		assert_eq!(runtime.balances.balance(&"charlie"), 100);
+		assert_eq!(runtime.balances.reserved_balance(&"bob"), 20);
+	}
+
+	#[test]
+	fn referendum_is_enacted() {
+		let mut runtime = new_runtime();
+		assert_eq!(super::Pallet::propose(&mut runtime, "alice", Box::new(42), 10), Ok(()));
+		assert_eq!(
+			super::Pallet::vote(&mut runtime, "bob", 0, true, Conviction::Locked1x, 50),
+			Err(super::Error::<TestRuntime>::ReferendumNotOngoing.into())
+		);
+		run_to_block(&mut runtime, 5);
+
+		// Bob's conviction outweighs charlie's larger balance.
+		assert_eq!(super::Pallet::vote(&mut runtime, "bob", 0, true, Conviction::Locked2x, 50), Ok(()));
+		assert_eq!(super::Pallet::vote(&mut runtime, "charlie", 0, false, Conviction::None, 90), Ok(()));
+		assert_eq!(
+			super::Pallet::vote(&mut runtime, "alice", 0, true, Conviction::None, 200),
+			Err(super::Error::<TestRuntime>::InsufficientFunds.into())
+		);
+		assert_eq!(runtime.democracy.tally(0), Some((100, 9)));
+		assert_eq!(runtime.balances.locked(&"bob"), 50);
+
+		// Voting ends at block 8, and the proposal is enacted at block 10.
+		assert_eq!(run_to_block(&mut runtime, 9), vec![]);
+		assert_eq!(runtime.democracy.tally(0), None);
+		assert_eq!(run_to_block(&mut runtime, 10), vec![42]);
+	}
+
+	#[test]
+	fn rejected_referendum_is_not_enacted() {
+		let mut runtime = new_runtime();
+		assert_eq!(super::Pallet::propose(&mut runtime, "alice", Box::new(42), 10), Ok(()));
+		run_to_block(&mut runtime, 5);
+		assert_eq!(super::Pallet::vote(&mut runtime, "bob", 0, false, Conviction::Locked1x, 10), Ok(()));
+		assert_eq!(run_to_block(&mut runtime, 20), vec![]);
+	}
+
+	#[test]
+	fn votes_unlock_after_conviction() {
+		let mut runtime = new_runtime();
+		assert_eq!(super::Pallet::propose(&mut runtime, "alice", Box::new(42), 10), Ok(()));
+		run_to_block(&mut runtime, 5);
+		assert_eq!(super::Pallet::vote(&mut runtime, "bob", 0, true, Conviction::Locked1x, 60), Ok(()));
+		assert_eq!(super::Pallet::vote(&mut runtime, "charlie", 0, true, Conviction::None, 70), Ok(()));
+
+		// The referendum ends at block 8: charlie is unlocked, bob stays locked for one more period.
+		run_to_block(&mut runtime, 8);
+		assert_eq!(super::Pallet::unlock(&mut runtime, "alice", "charlie"), Ok(()));
+		assert_eq!(super::Pallet::unlock(&mut runtime, "alice", "bob"), Ok(()));
+		assert_eq!(runtime.balances.locked(&"charlie"), 0);
+		assert_eq!(runtime.balances.locked(&"bob"), 60);
+
+		run_to_block(&mut runtime, 12);
+		assert_eq!(super::Pallet::unlock(&mut runtime, "bob", "bob"), Ok(()));
+		assert_eq!(runtime.balances.locked(&"bob"), 0);
+	}
+}
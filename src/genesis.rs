@@ -0,0 +1,92 @@
+use crate::{
+	codec::{Decode, Encode},
+	types, Runtime,
+};
+
+/// The initial state of a chain, before its first block.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GenesisConfig {
+	/// The accounts which start with some balance.
+	pub balances: Vec<(types::AccountId, types::Balance)>,
+}
+
+impl GenesisConfig {
+	/// The genesis config of a development chain, where a few well-known accounts have funds.
+	pub fn dev() -> Self {
+		let balances = ["alice", "bob", "charlie"];
+		GenesisConfig { balances: balances.iter().map(|who| (who.to_string(), 1_000)).collect() }
+	}
+
+	/// Parse a chain spec: one entry per line, like `balance alice 100`. Empty lines and lines
+	/// starting with `#` are ignored.
+	pub fn from_chain_spec(spec: &str) -> Result<Self, &'static str> {
+		let mut genesis = GenesisConfig::default();
+		for line in spec.lines().map(str::trim) {
+			if line.is_empty() || line.starts_with('#') {
+				continue;
+			}
+			match line.split_whitespace().collect::<Vec<_>>()[..] {
+				["balance", who, amount] => {
+					let amount = amount.parse().map_err(|_| "invalid balance in chain spec")?;
+					genesis.balances.push((who.to_string(), amount));
+				},
+				_ => return Err("invalid entry in chain spec"),
+			}
+		}
+		Ok(genesis)
+	}
+
+	/// Build the runtime in its genesis state.
+	pub fn build(&self) -> Runtime {
+		let mut runtime = Runtime::new();
+		for (who, amount) in &self.balances {
+			runtime.balances.set_balance(who, *amount);
+		}
+		runtime
+	}
+}
+
+impl Encode for GenesisConfig {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.balances.encode_to(dest);
+	}
+}
+
+impl Decode for GenesisConfig {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(GenesisConfig { balances: Vec::decode(input)? })
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::GenesisConfig;
+	use crate::{support::GetStorageVersion, system, vesting, Runtime};
+
+	#[test]
+	fn chain_spec_is_parsed() {
+		let spec = "# The initial balances.\nbalance alice 100\n\n  balance bob 5\n";
+		let genesis = GenesisConfig::from_chain_spec(spec).unwrap();
+		assert_eq!(genesis.balances, vec![("alice".to_string(), 100), ("bob".to_string(), 5)]);
+		assert_eq!(genesis.build().balances.balance(&"bob".to_string()), 5);
+
+		assert_eq!(
+			GenesisConfig::from_chain_spec("balance alice lots"),
+			Err("invalid balance in chain spec")
+		);
+		assert_eq!(
+			GenesisConfig::from_chain_spec("stake alice 100"),
+			Err("invalid entry in chain spec")
+		);
+	}
+
+	#[test]
+	fn genesis_state_is_at_the_spec_version_of_the_runtime() {
+		let runtime = GenesisConfig::default().build();
+		assert_eq!(runtime.system.spec_version(), <Runtime as system::Config>::SPEC_VERSION);
+		assert_eq!(
+			runtime.vesting.on_chain_storage_version(),
+			<vesting::Pallet<Runtime>>::IN_CODE_STORAGE_VERSION
+		);
+	}
+}
@@ -0,0 +1,553 @@
+use num::traits::{CheckedAdd, Zero};
+
+use crate::{
+	balances,
+	codec::{Decode, Encode},
+	storage::{StorageMap, StorageValue},
+	support::{DispatchResult, EnsureOrigin},
+};
+
+/// The index of a registrar.
+pub type RegistrarIndex = u32;
+
+pub trait Config:
+	balances::Config<RuntimeEvent: From<Event<Self>>>
+	+ Sized
+	+ AsMut<balances::Pallet<Self>>
+	+ AsMut<Pallet<Self>>
+{
+	/// The origin allowed to add registrars.
+	type RegistrarOrigin: EnsureOrigin<Self::AccountId>;
+	/// The deposit reserved for each identity.
+	const BASIC_DEPOSIT: Self::Balance;
+	/// The maximum number of registrars.
+	const MAX_REGISTRARS: u32;
+	/// The maximum length of each field of an identity, in bytes, since the deposit does not depend
+	/// on the size of the identity.
+	const MAX_FIELD_LENGTH: u32;
+}
+
+/// The human-readable information of an identity. Fields can be left empty.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct IdentityInfo {
+	pub display: String,
+	pub legal: String,
+	pub email: String,
+	pub web: String,
+}
+
+impl Encode for IdentityInfo {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.display.encode_to(dest);
+		self.legal.encode_to(dest);
+		self.email.encode_to(dest);
+		self.web.encode_to(dest);
+	}
+}
+
+impl Decode for IdentityInfo {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(IdentityInfo {
+			display: String::decode(input)?,
+			legal: String::decode(input)?,
+			email: String::decode(input)?,
+			web: String::decode(input)?,
+		})
+	}
+}
+
+/// The judgement of a registrar on an identity.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Judgement<Balance> {
+	/// A judgement was requested, and the fee is reserved until the registrar provides it.
+	FeePaid(Balance),
+	/// The registrar cannot tell whether the identity is correct.
+	Unknown,
+	/// The identity looks correct, but the registrar did not check it in depth.
+	Reasonable,
+	/// The registrar checked the identity, and it is correct.
+	KnownGood,
+	/// The identity was correct, but it is no longer.
+	OutOfDate,
+	/// The identity is of low quality, or imprecise.
+	LowQuality,
+	/// The identity is wrong.
+	Erroneous,
+}
+
+impl<Balance: Encode> Encode for Judgement<Balance> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		match self {
+			Judgement::FeePaid(fee) => {
+				0u8.encode_to(dest);
+				fee.encode_to(dest);
+			},
+			Judgement::Unknown => 1u8.encode_to(dest),
+			Judgement::Reasonable => 2u8.encode_to(dest),
+			Judgement::KnownGood => 3u8.encode_to(dest),
+			Judgement::OutOfDate => 4u8.encode_to(dest),
+			Judgement::LowQuality => 5u8.encode_to(dest),
+			Judgement::Erroneous => 6u8.encode_to(dest),
+		}
+	}
+}
+
+impl<Balance: Decode> Decode for Judgement<Balance> {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		match u8::decode(input)? {
+			0 => Ok(Judgement::FeePaid(Balance::decode(input)?)),
+			1 => Ok(Judgement::Unknown),
+			2 => Ok(Judgement::Reasonable),
+			3 => Ok(Judgement::KnownGood),
+			4 => Ok(Judgement::OutOfDate),
+			5 => Ok(Judgement::LowQuality),
+			6 => Ok(Judgement::Erroneous),
+			_ => Err("invalid judgement"),
+		}
+	}
+}
+
+/// A registrar, who can provide judgements on identities for a fee.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RegistrarInfo<AccountId, Balance> {
+	pub account: AccountId,
+	pub fee: Balance,
+}
+
+impl<AccountId: Encode, Balance: Encode> Encode for RegistrarInfo<AccountId, Balance> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.account.encode_to(dest);
+		self.fee.encode_to(dest);
+	}
+}
+
+impl<AccountId: Decode, Balance: Decode> Decode for RegistrarInfo<AccountId, Balance> {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(RegistrarInfo { account: AccountId::decode(input)?, fee: Balance::decode(input)? })
+	}
+}
+
+/// The identity of an account, with the judgements of registrars on it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Registration<Balance> {
+	pub info: IdentityInfo,
+	/// The amount reserved for the identity.
+	pub deposit: Balance,
+	/// The judgement of each registrar which was asked for one, ordered by registrar.
+	pub judgements: Vec<(RegistrarIndex, Judgement<Balance>)>,
+}
+
+impl<Balance: Encode> Encode for Registration<Balance> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.info.encode_to(dest);
+		self.deposit.encode_to(dest);
+		self.judgements.encode_to(dest);
+	}
+}
+
+impl<Balance: Decode> Decode for Registration<Balance> {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(Registration {
+			info: IdentityInfo::decode(input)?,
+			deposit: Balance::decode(input)?,
+			judgements: Vec::decode(input)?,
+		})
+	}
+}
+
+/// This is the Identity Module.
+/// It lets accounts set a human-readable identity for a deposit, and registrars judge whether the
+/// identities are correct.
+#[macros::storage]
+#[derive(Debug, macros::PalletInfo)]
+pub struct Pallet<T: Config> {
+	/// The identity of each account.
+	identities: StorageMap<T::AccountId, Registration<T::Balance>>,
+	/// The registrars, by index.
+	registrars: StorageValue<Vec<RegistrarInfo<T::AccountId, T::Balance>>>,
+}
+
+/// The events of this pallet.
+#[macros::event]
+pub enum Event<T: Config> {
+	/// `account` was added as the registrar at `index`.
+	RegistrarAdded { index: RegistrarIndex, account: T::AccountId },
+	/// The fee of the registrar at `index` was set.
+	RegistrarFeeSet { index: RegistrarIndex, fee: T::Balance },
+	/// `who` set their identity.
+	IdentitySet { who: T::AccountId },
+	/// `who` cleared their identity, and `deposit` was returned to them.
+	IdentityCleared { who: T::AccountId, deposit: T::Balance },
+	/// `who` requested a judgement from the registrar at `registrar`.
+	JudgementRequested { who: T::AccountId, registrar: RegistrarIndex },
+	/// The registrar at `registrar` gave a judgement on the identity of `target`.
+	JudgementGiven {
+		target: T::AccountId,
+		registrar: RegistrarIndex,
+		judgement: Judgement<T::Balance>,
+	},
+}
+
+/// The errors of this pallet.
+#[macros::error]
+pub enum Error<T: Config> {
+	/// There are too many registrars.
+	TooManyRegistrars,
+	/// The registrar does not exist.
+	InvalidRegistrar,
+	/// The caller is not the registrar.
+	NotRegistrar,
+	/// The account has no identity.
+	NoIdentity,
+	/// The fee of the registrar is above the maximum.
+	FeeTooHigh,
+	/// A judgement is already requested.
+	JudgementRequested,
+	/// The identity was judged erroneous.
+	JudgedErroneous,
+	/// The judgement is invalid.
+	InvalidJudgement,
+	/// A judgement was not requested.
+	JudgementNotRequested,
+	/// A field of the identity is longer than `T::MAX_FIELD_LENGTH`.
+	FieldTooLong,
+}
+
+impl<T: Config> Pallet<T> {
+	/// Get the identity of `who`, if any.
+	pub fn identity(&self, who: &T::AccountId) -> Option<Registration<T::Balance>> {
+		self.identities.get(who)
+	}
+
+	/// Get a registrar, if it exists.
+	pub fn registrar(&self, index: RegistrarIndex) -> Option<RegistrarInfo<T::AccountId, T::Balance>> {
+		self.registrars.get().get(index as usize).cloned()
+	}
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	/// Add `account` as a registrar, with no fee.
+	#[call_index(0)]
+	pub fn add_registrar(&mut self, caller: T::AccountId, account: T::AccountId) -> DispatchResult {
+		T::RegistrarOrigin::ensure_origin(&caller)?;
+		if self.registrars.get().len() as u32 >= T::MAX_REGISTRARS {
+			return Err(Error::<T>::TooManyRegistrars.into());
+		}
+		let index = self.registrars.get().len() as RegistrarIndex;
+		let registrar = RegistrarInfo { account: account.clone(), fee: T::Balance::zero() };
+		self.registrars.mutate(|registrars| registrars.push(registrar));
+		self.deposit_event(Event::RegistrarAdded { index, account });
+		Ok(())
+	}
+
+	/// Set the fee of a registrar. Only the account of the registrar can set it.
+	#[call_index(1)]
+	pub fn set_fee(&mut self, caller: T::AccountId, index: RegistrarIndex, fee: T::Balance) -> DispatchResult {
+		self.registrars.mutate(|registrars| {
+			let registrar = registrars.get_mut(index as usize).ok_or(Error::<T>::InvalidRegistrar)?;
+			if registrar.account != caller {
+				return Err(Error::<T>::NotRegistrar);
+			}
+			registrar.fee = fee;
+			Ok(())
+		})?;
+		self.deposit_event(Event::RegistrarFeeSet { index, fee });
+		Ok(())
+	}
+
+	/// Set the identity of the caller. `T::BASIC_DEPOSIT` is reserved when the identity is first
+	/// set. The judgements provided on a previous identity are removed, but requested ones remain,
+	/// and so do `Erroneous` ones, which cannot be erased by changing the identity.
+	#[call_index(2)]
+	pub fn set_identity(runtime: &mut T, caller: T::AccountId, info: IdentityInfo) -> DispatchResult {
+		let fields = [&info.display, &info.legal, &info.email, &info.web];
+		if fields.iter().any(|field| field.len() > T::MAX_FIELD_LENGTH as usize) {
+			return Err(Error::<T>::FieldTooLong.into());
+		}
+
+		let identity: &mut Pallet<T> = runtime.as_mut();
+		if let Some(mut registration) = identity.identities.get(&caller) {
+			registration.info = info;
+			registration
+				.judgements
+				.retain(|(_, judgement)| matches!(judgement, Judgement::FeePaid(_) | Judgement::Erroneous));
+			identity.identities.insert(caller.clone(), registration);
+			identity.deposit_event(Event::IdentitySet { who: caller });
+			return Ok(());
+		}
+
+		let balances: &mut balances::Pallet<T> = runtime.as_mut();
+		balances.reserve(&caller, T::BASIC_DEPOSIT)?;
+
+		let registration = Registration { info, deposit: T::BASIC_DEPOSIT, judgements: Vec::new() };
+		let identity: &mut Pallet<T> = runtime.as_mut();
+		identity.identities.insert(caller.clone(), registration);
+		identity.deposit_event(Event::IdentitySet { who: caller });
+		Ok(())
+	}
+
+	/// Clear the identity of the caller. The deposit, and the fees of pending judgements, are
+	/// returned.
+	#[call_index(3)]
+	pub fn clear_identity(runtime: &mut T, caller: T::AccountId) -> DispatchResult {
+		let identity: &mut Pallet<T> = runtime.as_mut();
+		let registration = identity.identities.remove(&caller).ok_or(Error::<T>::NoIdentity)?;
+
+		let balances: &mut balances::Pallet<T> = runtime.as_mut();
+		balances.unreserve(&caller, registration.deposit);
+		for (_, judgement) in registration.judgements {
+			if let Judgement::FeePaid(fee) = judgement {
+				balances.unreserve(&caller, fee);
+			}
+		}
+		let deposit = registration.deposit;
+		let identity: &mut Pallet<T> = runtime.as_mut();
+		identity.deposit_event(Event::IdentityCleared { who: caller, deposit });
+		Ok(())
+	}
+
+	/// Request a judgement on the identity of the caller from a registrar. The fee of the registrar
+	/// is reserved, and must not exceed `max_fee`.
+	#[call_index(4)]
+	pub fn request_judgement(
+		runtime: &mut T,
+		caller: T::AccountId,
+		reg_index: RegistrarIndex,
+		max_fee: T::Balance,
+	) -> DispatchResult {
+		let identity: &mut Pallet<T> = runtime.as_mut();
+		let fee = identity.registrar(reg_index).ok_or(Error::<T>::InvalidRegistrar)?.fee;
+		if fee > max_fee {
+			return Err(Error::<T>::FeeTooHigh.into());
+		}
+		let registration = identity.identities.get(&caller).ok_or(Error::<T>::NoIdentity)?;
+		let position = match registration.judgements.binary_search_by_key(&reg_index, |(index, _)| *index) {
+			Ok(position) => match registration.judgements[position].1 {
+				Judgement::FeePaid(_) => return Err(Error::<T>::JudgementRequested.into()),
+				Judgement::Erroneous => return Err(Error::<T>::JudgedErroneous.into()),
+				_ => position,
+			},
+			Err(position) => position,
+		};
+
+		let balances: &mut balances::Pallet<T> = runtime.as_mut();
+		balances.reserve(&caller, fee)?;
+
+		let identity: &mut Pallet<T> = runtime.as_mut();
+		if let Some(mut registration) = identity.identities.get(&caller) {
+			match registration.judgements.get_mut(position) {
+				Some((index, judgement)) if *index == reg_index => *judgement = Judgement::FeePaid(fee),
+				_ => registration.judgements.insert(position, (reg_index, Judgement::FeePaid(fee))),
+			}
+			identity.identities.insert(caller.clone(), registration);
+		}
+		identity.deposit_event(Event::JudgementRequested { who: caller, registrar: reg_index });
+		Ok(())
+	}
+
+	/// Provide the judgement of a registrar on the identity of `target`, which must have requested
+	/// it. The reserved fee is paid to the registrar.
+	#[call_index(5)]
+	pub fn provide_judgement(
+		runtime: &mut T,
+		caller: T::AccountId,
+		reg_index: RegistrarIndex,
+		target: T::AccountId,
+		judgement: Judgement<T::Balance>,
+	) -> DispatchResult {
+		if matches!(judgement, Judgement::FeePaid(_)) {
+			return Err(Error::<T>::InvalidJudgement.into());
+		}
+		let identity: &mut Pallet<T> = runtime.as_mut();
+		let registrar = identity.registrar(reg_index).ok_or(Error::<T>::InvalidRegistrar)?;
+		if registrar.account != caller {
+			return Err(Error::<T>::NotRegistrar.into());
+		}
+		let registration = identity.identities.get(&target).ok_or(Error::<T>::NoIdentity)?;
+		let position = registration
+			.judgements
+			.iter()
+			.position(|(index, _)| *index == reg_index)
+			.ok_or(Error::<T>::JudgementNotRequested)?;
+		let Judgement::FeePaid(fee) = registration.judgements[position].1 else {
+			return Err(Error::<T>::JudgementNotRequested.into());
+		};
+
+		// Check that the registrar can be paid before changing anything.
+		let balances: &mut balances::Pallet<T> = runtime.as_mut();
+		let fee = fee.min(balances.reserved_balance(&target));
+		balances.balance(&caller).checked_add(&fee).ok_or(balances::Error::<T>::Overflow)?;
+
+		let fee = balances.slash_reserved(&target, fee);
+		// This cannot fail, since we checked that the balance of the registrar does not overflow.
+		let _res = balances.mint(&caller, fee);
+		let identity: &mut Pallet<T> = runtime.as_mut();
+		if let Some(mut registration) = identity.identities.get(&target) {
+			registration.judgements[position].1 = judgement;
+			identity.identities.insert(target.clone(), registration);
+		}
+		identity.deposit_event(Event::JudgementGiven { target, registrar: reg_index, judgement });
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{IdentityInfo, Judgement};
+	use crate::{balances, support};
+
+	struct TestRuntime {
+		balances: balances::Pallet<Self>,
+		identity: super::Pallet<Self>,
+	}
+
+	impl crate::system::Config for TestRuntime {
+		type AccountId = &'static str;
+		type BlockNumber = u32;
+		type Nonce = u32;
+		type RuntimeEvent = ();
+		type PalletIndex = ();
+		const MAXIMUM_BLOCK_WEIGHT: crate::support::Weight = u64::MAX;
+		const MAXIMUM_BLOCK_LENGTH: u32 = u32::MAX;
+		const SPEC_VERSION: u32 = 1;
+	}
+
+	impl balances::Config for TestRuntime {
+		type Balance = u128;
+	}
+
+	struct EnsureRoot;
+
+	impl support::EnsureOrigin<&'static str> for EnsureRoot {
+		fn ensure_origin(caller: &&'static str) -> support::DispatchResult {
+			if *caller == "root" { Ok(()) } else { Err(support::DispatchError::BadOrigin) }
+		}
+	}
+
+	impl super::Config for TestRuntime {
+		type RegistrarOrigin = EnsureRoot;
+		const BASIC_DEPOSIT: u128 = 10;
+		const MAX_REGISTRARS: u32 = 1;
+		const MAX_FIELD_LENGTH: u32 = 32;
+	}
+
+	impl AsMut<balances::Pallet<Self>> for TestRuntime {
+		fn as_mut(&mut self) -> &mut balances::Pallet<Self> {
+			&mut self.balances
+		}
+	}
+
+	impl AsMut<super::Pallet<Self>> for TestRuntime {
+		fn as_mut(&mut self) -> &mut super::Pallet<Self> {
+			&mut self.identity
+		}
+	}
+
+	fn new_runtime() -> TestRuntime {
+		let mut runtime = TestRuntime { balances: balances::Pallet::new(), identity: super::Pallet::new() };
+		runtime.balances.set_balance(&"alice", 100);
+		runtime
+	}
+
+	fn alice_info() -> IdentityInfo {
+		IdentityInfo { display: "Alice".to_string(), email: "alice@example.com".to_string(), ..Default::default() }
+	}
+
+	#[test]
+	fn set_and_clear_identity() {
+		let mut runtime = new_runtime();
+		assert_eq!(super::Pallet::set_identity(&mut runtime, "bob", alice_info()), Err(crate::balances::Error::<TestRuntime>::InsufficientBalance.into()));
+		let info = IdentityInfo { web: "a".repeat(33), ..alice_info() };
+		assert_eq!(super::Pallet::set_identity(&mut runtime, "alice", info), Err(super::Error::<TestRuntime>::FieldTooLong.into()));
+		assert_eq!(super::Pallet::set_identity(&mut runtime, "alice", alice_info()), Ok(()));
+		assert_eq!(runtime.balances.reserved_balance(&"alice"), 10);
+
+		// Updating the identity does not reserve another deposit.
+		let info = IdentityInfo { web: "alice.example.com".to_string(), ..alice_info() };
+		assert_eq!(super::Pallet::set_identity(&mut runtime, "alice", info.clone()), Ok(()));
+		assert_eq!(runtime.identity.identity(&"alice").map(|registration| registration.info), Some(info));
+		assert_eq!(runtime.balances.reserved_balance(&"alice"), 10);
+
+		assert_eq!(super::Pallet::clear_identity(&mut runtime, "alice"), Ok(()));
+		assert_eq!(super::Pallet::clear_identity(&mut runtime, "alice"), Err(super::Error::<TestRuntime>::NoIdentity.into()));
+		assert_eq!(runtime.balances.balance(&"alice"), 100);
+	}
+
+	#[test]
+	fn registrar_judgement() {
+		let mut runtime = new_runtime();
+		assert_eq!(runtime.identity.add_registrar("alice", "registrar"), Err(support::DispatchError::BadOrigin));
+		assert_eq!(runtime.identity.add_registrar("root", "registrar"), Ok(()));
+		assert_eq!(runtime.identity.add_registrar("root", "registrar"), Err(super::Error::<TestRuntime>::TooManyRegistrars.into()));
+		assert_eq!(runtime.identity.set_fee("alice", 0, 5), Err(super::Error::<TestRuntime>::NotRegistrar.into()));
+		assert_eq!(runtime.identity.set_fee("registrar", 0, 5), Ok(()));
+
+		assert_eq!(super::Pallet::request_judgement(&mut runtime, "alice", 0, 5), Err(super::Error::<TestRuntime>::NoIdentity.into()));
+		assert_eq!(super::Pallet::set_identity(&mut runtime, "alice", alice_info()), Ok(()));
+		assert_eq!(
+			super::Pallet::request_judgement(&mut runtime, "alice", 0, 4),
+			Err(super::Error::<TestRuntime>::FeeTooHigh.into())
+		);
+		assert_eq!(super::Pallet::request_judgement(&mut runtime, "alice", 0, 5), Ok(()));
+		assert_eq!(
+			super::Pallet::request_judgement(&mut runtime, "alice", 0, 5),
+			Err(super::Error::<TestRuntime>::JudgementRequested.into())
+		);
+		assert_eq!(runtime.balances.reserved_balance(&"alice"), 15);
+
+		assert_eq!(
+			super::Pallet::provide_judgement(&mut runtime, "alice", 0, "alice", Judgement::KnownGood),
+			Err(super::Error::<TestRuntime>::NotRegistrar.into())
+		);
+		// Nothing changes if the registrar cannot be paid.
+		runtime.balances.set_balance(&"registrar", u128::MAX);
+		assert_eq!(
+			super::Pallet::provide_judgement(&mut runtime, "registrar", 0, "alice", Judgement::KnownGood),
+			Err(balances::Error::<TestRuntime>::Overflow.into())
+		);
+		assert_eq!(
+			runtime.identity.identity(&"alice").map(|registration| registration.judgements.clone()),
+			Some(vec![(0, Judgement::FeePaid(5))])
+		);
+		assert_eq!(runtime.balances.reserved_balance(&"alice"), 15);
+		runtime.balances.set_balance(&"registrar", 0);
+		assert_eq!(
+			super::Pallet::provide_judgement(&mut runtime, "registrar", 0, "alice", Judgement::KnownGood),
+			Ok(())
+		);
+		assert_eq!(
+			super::Pallet::provide_judgement(&mut runtime, "registrar", 0, "alice", Judgement::KnownGood),
+			Err(super::Error::<TestRuntime>::JudgementNotRequested.into())
+		);
+		assert_eq!(
+			runtime.identity.identity(&"alice").map(|registration| registration.judgements.clone()),
+			Some(vec![(0, Judgement::KnownGood)])
+		);
+		assert_eq!(runtime.balances.balance(&"registrar"), 5);
+		assert_eq!(runtime.balances.reserved_balance(&"alice"), 10);
+
+		// Changing the identity removes the judgement.
+		assert_eq!(super::Pallet::set_identity(&mut runtime, "alice", IdentityInfo::default()), Ok(()));
+		assert_eq!(
+			runtime.identity.identity(&"alice").map(|registration| registration.judgements.clone()),
+			Some(vec![])
+		);
+
+		// Unless the identity was judged erroneous.
+		assert_eq!(super::Pallet::request_judgement(&mut runtime, "alice", 0, 5), Ok(()));
+		assert_eq!(
+			super::Pallet::provide_judgement(&mut runtime, "registrar", 0, "alice", Judgement::Erroneous),
+			Ok(())
+		);
+		assert_eq!(super::Pallet::set_identity(&mut runtime, "alice", alice_info()), Ok(()));
+		assert_eq!(
+			runtime.identity.identity(&"alice").map(|registration| registration.judgements.clone()),
+			Some(vec![(0, Judgement::Erroneous)])
+		);
+		assert_eq!(
+			super::Pallet::request_judgement(&mut runtime, "alice", 0, 5),
+			Err(super::Error::<TestRuntime>::JudgedErroneous.into())
+		);
+	}
+}
@@ -0,0 +1,368 @@
+use core::fmt;
+
+/// How deep arrays and objects can be nested in parsed JSON, so that parsing cannot overflow the
+/// stack.
+const MAX_DEPTH: usize = 32;
+
+/// A JSON value.
+///
+/// Numbers are kept as the text of their literal, so large integers like balances are not rounded.
+/// The fields of an object are kept in order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Value {
+	Null,
+	Bool(bool),
+	Number(String),
+	String(String),
+	Array(Vec<Value>),
+	Object(Vec<(String, Value)>),
+}
+
+impl Value {
+	/// Parse a JSON text.
+	pub fn parse(text: &str) -> Result<Value, &'static str> {
+		let mut parser = Parser { text: text.as_bytes(), position: 0 };
+		let value = parser.value(0)?;
+		parser.skip_whitespace();
+		if parser.position != parser.text.len() {
+			return Err("trailing characters after JSON value");
+		}
+		Ok(value)
+	}
+
+	/// Build an object from its fields.
+	pub fn object<const N: usize>(fields: [(&str, Value); N]) -> Value {
+		Value::Object(fields.into_iter().map(|(key, value)| (key.to_string(), value)).collect())
+	}
+
+	/// Get the field `key` of an object.
+	pub fn get(&self, key: &str) -> Option<&Value> {
+		match self {
+			Value::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, value)| value),
+			_ => None,
+		}
+	}
+
+	pub fn as_str(&self) -> Option<&str> {
+		match self {
+			Value::String(string) => Some(string),
+			_ => None,
+		}
+	}
+
+	/// Get the value of a number which is an unsigned integer.
+	pub fn as_u64(&self) -> Option<u64> {
+		match self {
+			Value::Number(number) => number.parse().ok(),
+			_ => None,
+		}
+	}
+}
+
+macro_rules! impl_from_integers {
+	( $( $int:ty ),* ) => {
+		$(
+			impl From<$int> for Value {
+				fn from(value: $int) -> Self {
+					Value::Number(value.to_string())
+				}
+			}
+		)*
+	};
+}
+
+impl_from_integers!(u8, u32, u64, u128, usize, i64);
+
+impl From<bool> for Value {
+	fn from(value: bool) -> Self {
+		Value::Bool(value)
+	}
+}
+
+impl From<&str> for Value {
+	fn from(value: &str) -> Self {
+		Value::String(value.to_string())
+	}
+}
+
+impl From<String> for Value {
+	fn from(value: String) -> Self {
+		Value::String(value)
+	}
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+	fn from(value: Option<T>) -> Self {
+		value.map_or(Value::Null, Into::into)
+	}
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+	fn from(values: Vec<T>) -> Self {
+		Value::Array(values.into_iter().map(Into::into).collect())
+	}
+}
+
+/// Values are displayed as compact JSON.
+impl fmt::Display for Value {
+	fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Value::Null => write!(formatter, "null"),
+			Value::Bool(value) => write!(formatter, "{}", value),
+			Value::Number(number) => write!(formatter, "{}", number),
+			Value::String(string) => write_string(formatter, string),
+			Value::Array(values) => {
+				write!(formatter, "[")?;
+				for (i, value) in values.iter().enumerate() {
+					if i > 0 {
+						write!(formatter, ",")?;
+					}
+					write!(formatter, "{}", value)?;
+				}
+				write!(formatter, "]")
+			},
+			Value::Object(fields) => {
+				write!(formatter, "{{")?;
+				for (i, (key, value)) in fields.iter().enumerate() {
+					if i > 0 {
+						write!(formatter, ",")?;
+					}
+					write_string(formatter, key)?;
+					write!(formatter, ":{}", value)?;
+				}
+				write!(formatter, "}}")
+			},
+		}
+	}
+}
+
+fn write_string(formatter: &mut fmt::Formatter<'_>, string: &str) -> fmt::Result {
+	write!(formatter, "\"")?;
+	for c in string.chars() {
+		match c {
+			'"' => write!(formatter, "\\\"")?,
+			'\\' => write!(formatter, "\\\\")?,
+			'\n' => write!(formatter, "\\n")?,
+			'\r' => write!(formatter, "\\r")?,
+			'\t' => write!(formatter, "\\t")?,
+			c if u32::from(c) < 0x20 => write!(formatter, "\\u{:04x}", u32::from(c))?,
+			c => write!(formatter, "{}", c)?,
+		}
+	}
+	write!(formatter, "\"")
+}
+
+/// A recursive descent parser over the bytes of a JSON text.
+struct Parser<'a> {
+	text: &'a [u8],
+	position: usize,
+}
+
+impl Parser<'_> {
+	fn peek(&self) -> Option<u8> {
+		self.text.get(self.position).copied()
+	}
+
+	fn next(&mut self) -> Option<u8> {
+		let byte = self.peek()?;
+		self.position += 1;
+		Some(byte)
+	}
+
+	fn skip_whitespace(&mut self) {
+		while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+			self.position += 1;
+		}
+	}
+
+	fn expect(&mut self, literal: &str) -> Result<(), &'static str> {
+		if !self.text[self.position..].starts_with(literal.as_bytes()) {
+			return Err("invalid JSON literal");
+		}
+		self.position += literal.len();
+		Ok(())
+	}
+
+	fn value(&mut self, depth: usize) -> Result<Value, &'static str> {
+		if depth > MAX_DEPTH {
+			return Err("JSON is nested too deeply");
+		}
+		self.skip_whitespace();
+		match self.peek().ok_or("unexpected end of JSON")? {
+			b'n' => self.expect("null").map(|_| Value::Null),
+			b't' => self.expect("true").map(|_| Value::Bool(true)),
+			b'f' => self.expect("false").map(|_| Value::Bool(false)),
+			b'"' => self.string().map(Value::String),
+			b'[' => {
+				self.position += 1;
+				let mut values = Vec::new();
+				self.skip_whitespace();
+				if self.peek() == Some(b']') {
+					self.position += 1;
+					return Ok(Value::Array(values));
+				}
+				loop {
+					values.push(self.value(depth + 1)?);
+					self.skip_whitespace();
+					match self.next() {
+						Some(b',') => continue,
+						Some(b']') => return Ok(Value::Array(values)),
+						_ => return Err("expected `,` or `]` in JSON array"),
+					}
+				}
+			},
+			b'{' => {
+				self.position += 1;
+				let mut fields = Vec::new();
+				self.skip_whitespace();
+				if self.peek() == Some(b'}') {
+					self.position += 1;
+					return Ok(Value::Object(fields));
+				}
+				loop {
+					self.skip_whitespace();
+					if self.peek() != Some(b'"') {
+						return Err("expected a key in JSON object");
+					}
+					let key = self.string()?;
+					self.skip_whitespace();
+					if self.next() != Some(b':') {
+						return Err("expected `:` in JSON object");
+					}
+					fields.push((key, self.value(depth + 1)?));
+					self.skip_whitespace();
+					match self.next() {
+						Some(b',') => continue,
+						Some(b'}') => return Ok(Value::Object(fields)),
+						_ => return Err("expected `,` or `}` in JSON object"),
+					}
+				}
+			},
+			b'-' | b'0'..=b'9' => self.number().map(Value::Number),
+			_ => Err("unexpected character in JSON"),
+		}
+	}
+
+	fn number(&mut self) -> Result<String, &'static str> {
+		let start = self.position;
+		if self.peek() == Some(b'-') {
+			self.position += 1;
+		}
+		let digits = |parser: &mut Self| {
+			let start = parser.position;
+			while matches!(parser.peek(), Some(b'0'..=b'9')) {
+				parser.position += 1;
+			}
+			parser.position > start
+		};
+		if !digits(self) {
+			return Err("invalid JSON number");
+		}
+		if self.peek() == Some(b'.') {
+			self.position += 1;
+			if !digits(self) {
+				return Err("invalid JSON number");
+			}
+		}
+		if matches!(self.peek(), Some(b'e' | b'E')) {
+			self.position += 1;
+			if matches!(self.peek(), Some(b'+' | b'-')) {
+				self.position += 1;
+			}
+			if !digits(self) {
+				return Err("invalid JSON number");
+			}
+		}
+		// The number is only made of ASCII characters.
+		Ok(String::from_utf8_lossy(&self.text[start..self.position]).into_owned())
+	}
+
+	fn string(&mut self) -> Result<String, &'static str> {
+		// Skip the opening quote.
+		self.position += 1;
+		let mut bytes = Vec::new();
+		loop {
+			match self.next().ok_or("unterminated JSON string")? {
+				b'"' => break,
+				b'\\' => match self.next().ok_or("unterminated JSON string")? {
+					b'"' => bytes.push(b'"'),
+					b'\\' => bytes.push(b'\\'),
+					b'/' => bytes.push(b'/'),
+					b'b' => bytes.push(0x08),
+					b'f' => bytes.push(0x0c),
+					b'n' => bytes.push(b'\n'),
+					b'r' => bytes.push(b'\r'),
+					b't' => bytes.push(b'\t'),
+					b'u' => {
+						let c = self.unicode_escape()?;
+						bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+					},
+					_ => return Err("invalid escape in JSON string"),
+				},
+				byte if byte < 0x20 => return Err("control character in JSON string"),
+				byte => bytes.push(byte),
+			}
+		}
+		String::from_utf8(bytes).map_err(|_| "invalid utf8 in JSON string")
+	}
+
+	/// Parse the `XXXX` of a `\uXXXX` escape, along with the low surrogate which follows it if it is
+	/// a high surrogate.
+	fn unicode_escape(&mut self) -> Result<char, &'static str> {
+		let high = self.hex4()?;
+		if !(0xd800..0xdc00).contains(&high) {
+			return char::from_u32(high).ok_or("invalid unicode escape in JSON string");
+		}
+		self.expect("\\u").map_err(|_| "invalid unicode escape in JSON string")?;
+		let low = self.hex4()?;
+		if !(0xdc00..0xe000).contains(&low) {
+			return Err("invalid unicode escape in JSON string");
+		}
+		char::from_u32(0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00))
+			.ok_or("invalid unicode escape in JSON string")
+	}
+
+	fn hex4(&mut self) -> Result<u32, &'static str> {
+		let digits = self
+			.text
+			.get(self.position..self.position + 4)
+			.ok_or("unterminated JSON string")?;
+		let digits =
+			core::str::from_utf8(digits).map_err(|_| "invalid unicode escape in JSON string")?;
+		let value =
+			u32::from_str_radix(digits, 16).map_err(|_| "invalid unicode escape in JSON string")?;
+		self.position += 4;
+		Ok(value)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::Value;
+
+	#[test]
+	fn json_round_trips() {
+		let text = r#"{"jsonrpc":"2.0","id":-1.5e3,"params":[null,true,"a \"quote\"\n",[],{}]}"#;
+		let value = Value::parse(text).unwrap();
+		assert_eq!(value.get("jsonrpc").and_then(Value::as_str), Some("2.0"));
+		assert_eq!(value.get("id"), Some(&Value::Number("-1.5e3".to_string())));
+		assert_eq!(value.to_string(), text);
+
+		let value =
+			Value::parse(" { \"big\" : 340282366920938463463374607431768211455 } ").unwrap();
+		assert_eq!(value, Value::object([("big", u128::MAX.into())]));
+		assert_eq!(Value::parse(r#""é😀""#), Ok(Value::from("é😀")));
+		assert_eq!(Value::from("\u{1}").to_string(), r#""\u0001""#);
+	}
+
+	#[test]
+	fn invalid_json_is_rejected() {
+		assert_eq!(Value::parse(""), Err("unexpected end of JSON"));
+		assert_eq!(Value::parse("[1,]"), Err("unexpected character in JSON"));
+		assert_eq!(Value::parse("{\"a\" 1}"), Err("expected `:` in JSON object"));
+		assert_eq!(Value::parse("01x"), Err("trailing characters after JSON value"));
+		assert_eq!(Value::parse("\"abc"), Err("unterminated JSON string"));
+		assert_eq!(Value::parse("tru"), Err("invalid JSON literal"));
+		assert_eq!(Value::parse(&"[".repeat(100)), Err("JSON is nested too deeply"));
+	}
+}
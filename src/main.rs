@@ -1,6 +1,27 @@
+mod assets;
 mod balances;
+mod block_builder;
+mod cli;
+mod codec;
+mod database;
+mod democracy;
+mod genesis;
+mod identity;
+mod json;
+mod metadata;
+mod nfts;
+mod node;
 mod proof_of_existence;
+mod rpc;
+mod runtime_api;
+mod scheduler;
+mod staking;
+mod storage;
 mod system;
+mod transaction_pool;
+mod treasury;
+mod tx_pause;
+mod vesting;
 mod support;
 
 use crate::support::Dispatch;
@@ -8,30 +29,86 @@ use crate::support::Dispatch;
 mod types {
 	pub type AccountId = String;
 	pub type Balance = u128;
+	pub type AssetId = u32;
+	pub type CollectionId = u32;
+	pub type ItemId = u32;
     pub type BlockNumber = u32;
     pub type Nonce = u32;
-    pub type Extrinsic = crate::support::Extrinsic<AccountId, crate::RuntimeCall>;
+    pub type Extrinsic = crate::support::Extrinsic<AccountId, Nonce, Balance, crate::RuntimeCall>;
     pub type Header = crate::support::Header<BlockNumber>;
     pub type Block = crate::support::Block<Header, Extrinsic>;
-    pub type Content = &'static str;
+    pub type ExtrinsicReceipt = crate::support::ExtrinsicReceipt<Balance, crate::RuntimeEvent>;
+    pub type BlockReceipt = crate::support::BlockReceipt<BlockNumber, Balance, crate::RuntimeEvent>;
+    pub type Tag = (AccountId, Nonce);
+    pub type Content = String;
+    pub type TaskName = String;
 
 }
 
+/// The account standing for the Root origin. Calls dispatched on its behalf are privileged, like
+/// the calls enacted by democracy.
+const ROOT: &str = "root";
+
+/// Only accepts calls dispatched as Root.
+pub struct EnsureRoot;
+
+impl support::EnsureOrigin<types::AccountId> for EnsureRoot {
+	fn ensure_origin(caller: &types::AccountId) -> support::DispatchResult {
+		if caller == ROOT { Ok(()) } else { Err(support::DispatchError::BadOrigin) }
+	}
+}
+
 // These are all the calls which are exposed to the world.
 // Note that it is just an accumulation of the calls exposed by each module.
 
 #[derive(Debug)]
-#[macros::runtime]
+#[macros::runtime(block = types::Block)]
 pub struct Runtime {
+    #[system]
     system: system::Pallet<Self>,
+    #[pallet_index(0)]
+    #[event]
     balances: balances::Pallet<Self>,
+    #[pallet_index(1)]
     proof_of_existence: proof_of_existence::Pallet<Self>,
+    #[pallet_index(2)]
+    #[event]
+    scheduler: scheduler::Pallet<Self>,
+    #[pallet_index(3)]
+    #[event]
+    vesting: vesting::Pallet<Self>,
+    #[pallet_index(4)]
+    #[event]
+    assets: assets::Pallet<Self>,
+    #[pallet_index(5)]
+    #[event]
+    nfts: nfts::Pallet<Self>,
+    #[pallet_index(6)]
+    #[event]
+    staking: staking::Pallet<Self>,
+    #[pallet_index(7)]
+    #[event]
+    democracy: democracy::Pallet<Self>,
+    #[pallet_index(8)]
+    #[event]
+    treasury: treasury::Pallet<Self>,
+    #[pallet_index(9)]
+    #[event]
+    identity: identity::Pallet<Self>,
+    #[pallet_index(10)]
+    #[event]
+    tx_pause: tx_pause::Pallet<Self>,
 }
 
 impl system::Config for Runtime {
 	type AccountId = types::AccountId;
 	type BlockNumber = types::BlockNumber;
 	type Nonce = types::Nonce;
+	type RuntimeEvent = RuntimeEvent;
+	type PalletIndex = Runtime;
+	const MAXIMUM_BLOCK_WEIGHT: support::Weight = 100 * support::DEFAULT_WEIGHT;
+	const MAXIMUM_BLOCK_LENGTH: u32 = 100;
+	const SPEC_VERSION: u32 = 1;
 }
 
 impl balances::Config for Runtime {
@@ -42,60 +119,164 @@ impl proof_of_existence::Config for Runtime {
 	type Content = types::Content;
 }
 
+impl scheduler::Config for Runtime {
+	type RuntimeCall = RuntimeCall;
+	type TaskName = types::TaskName;
+	const MAXIMUM_WEIGHT: support::Weight = 10 * support::DEFAULT_WEIGHT;
+}
+
+impl vesting::Config for Runtime {
+	const MIN_VESTED_TRANSFER: types::Balance = 10;
+	const MAX_VESTING_SCHEDULES: u32 = 8;
+}
+
+impl assets::Config for Runtime {
+	type AssetId = types::AssetId;
+	type CreateOrigin = EnsureRoot;
+}
+
+impl nfts::Config for Runtime {
+	type CollectionId = types::CollectionId;
+	type ItemId = types::ItemId;
+}
+
+/// Pays out 1% of the total stake every era.
+pub struct Inflation;
+
+impl staking::EraPayout<types::Balance> for Inflation {
+	fn era_payout(total_staked: types::Balance) -> types::Balance {
+		total_staked / 100
+	}
+}
+
+impl staking::Config for Runtime {
+	const BONDING_DURATION: types::BlockNumber = 5;
+	const ERA_LENGTH: types::BlockNumber = 10;
+	const VALIDATOR_COUNT: u32 = 2;
+	const MAX_NOMINATIONS: u32 = 4;
+	type EraPayout = Inflation;
+	type SlashOrigin = EnsureRoot;
+}
+
+impl democracy::Config for Runtime {
+	type RuntimeCall = RuntimeCall;
+	const MINIMUM_DEPOSIT: types::Balance = 10;
+	const LAUNCH_PERIOD: types::BlockNumber = 10;
+	const VOTING_PERIOD: types::BlockNumber = 10;
+	const ENACTMENT_PERIOD: types::BlockNumber = 5;
+	const VOTE_LOCKING_PERIOD: types::BlockNumber = 10;
+}
+
+impl treasury::Config for Runtime {
+	type ApproveOrigin = EnsureRoot;
+	type RejectOrigin = EnsureRoot;
+	const PROPOSAL_BOND: u8 = 5;
+	const PROPOSAL_BOND_MINIMUM: types::Balance = 1;
+	const SPEND_PERIOD: types::BlockNumber = 20;
+	const BURN: u8 = 1;
+
+	fn pot_account() -> types::AccountId {
+		"treasury".to_string()
+	}
+}
+
+impl identity::Config for Runtime {
+	type RegistrarOrigin = EnsureRoot;
+	const BASIC_DEPOSIT: types::Balance = 10;
+	const MAX_REGISTRARS: u32 = 20;
+	const MAX_FIELD_LENGTH: u32 = 64;
+}
+
+impl tx_pause::Config for Runtime {
+	type PauseOrigin = EnsureRoot;
+}
+
+/// Calls are not dispatched while they or their pallet are paused by `tx_pause`. The calls of
+/// `tx_pause` itself are never filtered, so that everything paused can be unpaused.
+impl support::CallFilter<RuntimeCall> for Runtime {
+	fn contains(&self, call: &RuntimeCall) -> bool {
+		matches!(call, RuntimeCall::tx_pause(_))
+			|| !self.tx_pause.is_paused(call.pallet_name(), call.call_name())
+	}
+}
+
+/// Extrinsics pay 1 for every `DEFAULT_WEIGHT` of their call, plus their tip. Fees go to the
+/// treasury.
+impl support::TransactionPayment<types::AccountId, types::Balance, RuntimeCall> for Runtime {
+	fn compute_fee(&self, call: &RuntimeCall, tip: types::Balance) -> types::Balance {
+		let weight = support::GetDispatchInfo::weight(call);
+		types::Balance::from(weight / support::DEFAULT_WEIGHT).saturating_add(tip)
+	}
+
+	fn withdraw_fee(&mut self, who: &types::AccountId, fee: types::Balance) -> support::DispatchResult {
+		let pot = <Runtime as treasury::Config>::pot_account();
+		self.balances.transfer(who.clone(), pot, fee).map_err(|_| "unable to pay fees".into())
+	}
+}
+
+impl support::CheckSignature<types::AccountId> for Runtime {
+	fn check_signature(who: &types::AccountId) -> bool {
+		who != ROOT
+	}
+}
+
+impl Runtime {
+	/// Dispatch `call` on behalf of `caller`, and return its result with the weight it consumed.
+	fn dispatch_weighed(
+		&mut self,
+		caller: types::AccountId,
+		call: RuntimeCall,
+	) -> (support::DispatchResult, support::Weight) {
+		let declared_weight = support::GetDispatchInfo::weight(&call);
+		match self.dispatch(caller, call) {
+			Ok(post_info) => (Ok(()), post_info.calc_actual_weight(declared_weight)),
+			Err(error) => (Err(error.error), error.post_info.calc_actual_weight(declared_weight)),
+		}
+	}
+}
+
+/// The calls dispatched by democracy and the scheduler count toward the weight of the block.
+impl support::OnInitialize<types::BlockNumber> for Runtime {
+	fn on_initialize(&mut self, block_number: types::BlockNumber) -> support::Weight {
+		let mut weight: support::Weight = 0;
+
+		// Start a new staking era, if the current one is over.
+		staking::Pallet::on_initialize(self, block_number);
+
+		// Pay out the approved treasury proposals, if the spend period is over.
+		treasury::Pallet::on_initialize(self, block_number);
+
+		// Dispatch the calls enacted by democracy as Root.
+		for call in democracy::Pallet::on_initialize(self, block_number) {
+			let (result, call_weight) = self.dispatch_weighed(ROOT.to_string(), call);
+			weight = weight.saturating_add(call_weight);
+			self.democracy.deposit_event(democracy::Event::Enacted { result });
+		}
+
+		// Dispatch the calls the scheduler has due for this block, on behalf of who scheduled them.
+		for (caller, call) in self.scheduler.take_due(block_number) {
+			let (result, call_weight) = self.dispatch_weighed(caller.clone(), call);
+			weight = weight.saturating_add(call_weight);
+			self.scheduler.deposit_event(scheduler::Event::Dispatched { origin: caller, result });
+		}
+
+		weight
+	}
+}
+
+/// The migrations of the pallets whose storage version was bumped, by the spec version which added
+/// them, in the order they must run. No pallet has bumped its storage version yet.
+impl support::OnRuntimeUpgrade for Runtime {
+	fn on_runtime_upgrade(&mut self, _from: u32, _to: u32) {}
+}
 
 fn main() {
-	let mut runtime = Runtime::new();
-	let alice = "alice".to_string();
-	let bob = "bob".to_string();
-    let charlie = "charlie".to_string();
-
-	// Initialize the system with some initial balance.
-	runtime.balances.set_balance(&alice, 100);
-
-    /*
-		TODO: Replace the logic above with a new `Block`.
-			- Set the block number to 1 in the `Header`.
-			- Move your existing transactions into extrinsic format, using the
-			  `Extrinsic` and `RuntimeCall`.
-	*/
-    let block_1 = types::Block {
-        header: support::Header { block_number: 1 },
-        extrinsics: vec![
-            support::Extrinsic {
-                caller: alice.clone(),
-                call: RuntimeCall::balances(balances::Call::transfer{ to: bob.clone(), amount: 66 }),
-            },            
-            support::Extrinsic {
-                caller: alice.clone(),
-                call: RuntimeCall::balances(balances::Call::transfer{ to: charlie.clone(), amount: 20 }),
-            },
-        ],
-    };
-    runtime.execute_block(block_1).expect("invalid block");
-
-    let block_2 = types::Block {
-        header: support::Header { block_number: 2 },
-        extrinsics: vec![
-            support::Extrinsic {
-                caller: alice.clone(),
-                call: RuntimeCall::balances(balances::Call::transfer{ to: bob.clone(), amount: 2 }),
-            },            
-            support::Extrinsic {
-                caller: alice.clone(),
-                call: RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {claim: "This is alice's first claim."}),
-            },      
-            support::Extrinsic {
-                caller: bob.clone(),
-                call: RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim{claim: "This is bob's first claim."}),
-            },            
-            support::Extrinsic {
-                caller: bob.clone(),
-                call: RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim{claim: "This is bob's second claim."}),
-            },
-        ],
-    };
-    runtime.execute_block(block_2).expect("invalid block");
-
-	// Simply print the debug format of our runtime state.                
-    println!("{:#?}", runtime);
+	let cli = cli::Cli::parse(std::env::args().skip(1)).unwrap_or_else(|e| {
+		eprintln!("error: {}\n\n{}", e, cli::USAGE);
+		std::process::exit(2);
+	});
+	if let Err(e) = cli.run() {
+		eprintln!("error: {}", e);
+		std::process::exit(1);
+	}
 }
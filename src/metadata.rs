@@ -0,0 +1,231 @@
+use crate::json::Value;
+
+/// A description of the runtime, generated by `#[macros::runtime]`, which lets tools outside of the
+/// runtime discover its pallets, their calls, their storage, their events and their errors.
+///
+/// It is converted to JSON with `json::Value::from`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RuntimeMetadata {
+	pub pallets: Vec<PalletMetadata>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PalletMetadata {
+	/// The name of the pallet in the runtime.
+	pub name: &'static str,
+	/// The index of the pallet, which encoded runtime calls start with. `None` for the system
+	/// pallet, which is not callable.
+	pub index: Option<u8>,
+	/// The doc comments of the pallet, one line per item.
+	pub docs: Vec<&'static str>,
+	pub calls: Vec<CallMetadata>,
+	pub storage: Vec<StorageMetadata>,
+	/// The events of the pallet. Empty if the pallet deposits no events.
+	pub events: Vec<EventMetadata>,
+	pub errors: Vec<ErrorMetadata>,
+}
+
+/// A callable function of a pallet, generated by `#[macros::call]`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CallMetadata {
+	pub name: &'static str,
+	/// The index of the function, which encoded calls of the pallet start with.
+	pub index: u8,
+	/// The arguments of the function, not counting the caller.
+	pub args: Vec<ArgMetadata>,
+	pub docs: Vec<&'static str>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ArgMetadata {
+	pub name: &'static str,
+	/// The type of the argument, as written in the source code of the pallet.
+	pub ty: &'static str,
+}
+
+/// A storage item of a pallet, generated by `#[macros::storage]`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StorageMetadata {
+	pub name: &'static str,
+	pub kind: StorageKind,
+	/// The types of the keys of the storage item, as written in the source code of the pallet.
+	pub keys: Vec<&'static str>,
+	/// The type of the values of the storage item, as written in the source code of the pallet.
+	pub value: &'static str,
+	pub docs: Vec<&'static str>,
+}
+
+/// An event of a pallet, generated by `#[macros::event]`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EventMetadata {
+	pub name: &'static str,
+	/// The fields of the event, with the types written in the source code of the pallet.
+	pub fields: Vec<ArgMetadata>,
+	pub docs: Vec<&'static str>,
+}
+
+/// An error of a pallet, generated by `#[macros::error]`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ErrorMetadata {
+	pub name: &'static str,
+	/// The index of the error in the pallet, which identifies it in a `support::ModuleError`.
+	pub index: u8,
+	pub docs: Vec<&'static str>,
+}
+
+/// The kind of a storage item, see the `storage` module.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StorageKind {
+	Value,
+	Map,
+	DoubleMap,
+}
+
+/// Describes a pallet for the metadata of the runtime. It is implemented by `#[macros::storage]`.
+pub trait PalletInfo {
+	/// The doc comments of the pallet, one line per item.
+	fn docs() -> Vec<&'static str>;
+	/// The storage items of the pallet.
+	fn storage() -> Vec<StorageMetadata>;
+}
+
+impl From<&RuntimeMetadata> for Value {
+	fn from(metadata: &RuntimeMetadata) -> Self {
+		let pallets = metadata.pallets.iter().map(Value::from).collect();
+		Value::object([("pallets", Value::Array(pallets))])
+	}
+}
+
+impl From<&PalletMetadata> for Value {
+	fn from(pallet: &PalletMetadata) -> Self {
+		Value::object([
+			("name", pallet.name.into()),
+			("index", pallet.index.into()),
+			("docs", pallet.docs.clone().into()),
+			("calls", Value::Array(pallet.calls.iter().map(Value::from).collect())),
+			("storage", Value::Array(pallet.storage.iter().map(Value::from).collect())),
+			("events", Value::Array(pallet.events.iter().map(Value::from).collect())),
+			("errors", Value::Array(pallet.errors.iter().map(Value::from).collect())),
+		])
+	}
+}
+
+impl From<&CallMetadata> for Value {
+	fn from(call: &CallMetadata) -> Self {
+		Value::object([
+			("name", call.name.into()),
+			("index", call.index.into()),
+			("args", Value::Array(call.args.iter().map(Value::from).collect())),
+			("docs", call.docs.clone().into()),
+		])
+	}
+}
+
+impl From<&ArgMetadata> for Value {
+	fn from(arg: &ArgMetadata) -> Self {
+		Value::object([("name", arg.name.into()), ("type", arg.ty.into())])
+	}
+}
+
+impl From<&EventMetadata> for Value {
+	fn from(event: &EventMetadata) -> Self {
+		Value::object([
+			("name", event.name.into()),
+			("fields", Value::Array(event.fields.iter().map(Value::from).collect())),
+			("docs", event.docs.clone().into()),
+		])
+	}
+}
+
+impl From<&ErrorMetadata> for Value {
+	fn from(error: &ErrorMetadata) -> Self {
+		Value::object([
+			("name", error.name.into()),
+			("index", error.index.into()),
+			("docs", error.docs.clone().into()),
+		])
+	}
+}
+
+impl From<&StorageMetadata> for Value {
+	fn from(storage: &StorageMetadata) -> Self {
+		let kind = match storage.kind {
+			StorageKind::Value => "value",
+			StorageKind::Map => "map",
+			StorageKind::DoubleMap => "doubleMap",
+		};
+		Value::object([
+			("name", storage.name.into()),
+			("kind", kind.into()),
+			("keys", storage.keys.clone().into()),
+			("value", storage.value.into()),
+			("docs", storage.docs.clone().into()),
+		])
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{ArgMetadata, ErrorMetadata, EventMetadata, StorageKind, StorageMetadata};
+	use crate::{json::Value, Runtime};
+
+	#[test]
+	fn metadata_describes_the_runtime() {
+		let metadata = Runtime::metadata();
+		let names = metadata.pallets.iter().map(|pallet| pallet.name).collect::<Vec<_>>();
+		assert_eq!(&names[..3], ["system", "balances", "proof_of_existence"]);
+		assert_eq!(metadata.pallets[0].index, None);
+		assert!(metadata.pallets[0].calls.is_empty());
+
+		let poe = &metadata.pallets[2];
+		assert_eq!(poe.index, Some(1));
+		assert_eq!(poe.docs[0], "This is the Proof of Existence Module.");
+		assert_eq!(
+			poe.storage,
+			vec![StorageMetadata {
+				name: "claims",
+				kind: StorageKind::Map,
+				keys: vec!["T::Content"],
+				value: "T::AccountId",
+				docs: vec![
+					"A simple storage map from content to the owner of that content.",
+					"Accounts can make multiple different claims, but each claim can only have one \
+					 owner.",
+				],
+			}]
+		);
+		let calls = poe.calls.iter().map(|call| (call.name, call.index)).collect::<Vec<_>>();
+		assert_eq!(calls, vec![("create_claim", 0), ("revoke_claim", 1)]);
+		assert_eq!(poe.calls[0].args, vec![ArgMetadata { name: "claim", ty: "T::Content" }]);
+		assert!(poe.events.is_empty());
+		assert_eq!(
+			poe.errors[0],
+			ErrorMetadata {
+				name: "AlreadyClaimed",
+				index: 0,
+				docs: vec!["This content is already claimed."],
+			}
+		);
+
+		let scheduler = metadata.pallets.iter().find(|pallet| pallet.name == "scheduler").unwrap();
+		assert_eq!(
+			scheduler.events,
+			vec![EventMetadata {
+				name: "Dispatched",
+				fields: vec![
+					ArgMetadata { name: "origin", ty: "T::AccountId" },
+					ArgMetadata { name: "result", ty: "DispatchResult" },
+				],
+				docs: vec!["A call scheduled by `origin` was dispatched on its behalf."],
+			}]
+		);
+
+		let json = Value::parse(&Value::from(&metadata).to_string()).unwrap();
+		let Some(Value::Array(pallets)) = json.get("pallets") else { panic!("no pallets") };
+		assert_eq!(pallets.len(), metadata.pallets.len());
+		assert_eq!(pallets[1].get("name"), Some(&"balances".into()));
+		assert_eq!(pallets[1].get("index"), Some(&0u8.into()));
+		let Some(Value::Array(errors)) = pallets[1].get("errors") else { panic!("no errors") };
+		assert_eq!(errors[1].get("name"), Some(&"InsufficientBalance".into()));
+	}
+}
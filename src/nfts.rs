@@ -0,0 +1,488 @@
+use core::fmt::Debug;
+
+use crate::{
+	codec::{Decode, Encode},
+	storage::{StorageDoubleMap, StorageMap},
+	support::DispatchResult,
+};
+
+pub trait Config: Sized + crate::system::Config<RuntimeEvent: From<Event<Self>>> {
+	/// The type used to identify a collection.
+	type CollectionId: Debug + Encode + Ord + Copy;
+	/// The type used to identify an item within a collection.
+	type ItemId: Debug + Encode + Ord + Copy;
+}
+
+/// Who is allowed to mint items in a collection.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MintType {
+	/// Only the owner of the collection can mint.
+	Issuer,
+	/// Anyone can mint.
+	Public,
+}
+
+impl Encode for MintType {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		(*self as u8).encode_to(dest);
+	}
+}
+
+impl Decode for MintType {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		match u8::decode(input)? {
+			0 => Ok(MintType::Issuer),
+			1 => Ok(MintType::Public),
+			_ => Err("invalid mint type"),
+		}
+	}
+}
+
+/// The details of a collection.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CollectionDetails<AccountId> {
+	/// The account which owns the collection, and manages its items' attributes and metadata.
+	pub owner: AccountId,
+	/// Who is allowed to mint items in the collection.
+	pub mint_type: MintType,
+	/// The number of items in the collection.
+	pub items: u32,
+}
+
+/// The details of an item.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ItemDetails<AccountId> {
+	/// The account which owns the item.
+	pub owner: AccountId,
+	/// The account which is allowed to transfer the item on behalf of its owner, if any.
+	pub approved: Option<AccountId>,
+}
+
+impl<AccountId: Encode> Encode for CollectionDetails<AccountId> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.owner.encode_to(dest);
+		self.mint_type.encode_to(dest);
+		self.items.encode_to(dest);
+	}
+}
+
+impl<AccountId: Decode> Decode for CollectionDetails<AccountId> {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(CollectionDetails {
+			owner: AccountId::decode(input)?,
+			mint_type: MintType::decode(input)?,
+			items: u32::decode(input)?,
+		})
+	}
+}
+
+impl<AccountId: Encode> Encode for ItemDetails<AccountId> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.owner.encode_to(dest);
+		self.approved.encode_to(dest);
+	}
+}
+
+impl<AccountId: Decode> Decode for ItemDetails<AccountId> {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(ItemDetails { owner: AccountId::decode(input)?, approved: Option::decode(input)? })
+	}
+}
+
+/// The key of an item: `(collection, item)`.
+type ItemKey<T> = (<T as Config>::CollectionId, <T as Config>::ItemId);
+
+/// This is the Non-Fungible Tokens Module.
+/// It manages unique items, grouped in collections.
+#[macros::storage]
+#[derive(Debug, macros::PalletInfo)]
+pub struct Pallet<T: Config> {
+	/// The details of each collection.
+	collections: StorageMap<T::CollectionId, CollectionDetails<T::AccountId>>,
+	/// The details of each item, by collection.
+	items: StorageMap<ItemKey<T>, ItemDetails<T::AccountId>>,
+	/// The attributes of each item, by key.
+	attributes: StorageDoubleMap<ItemKey<T>, String, String>,
+	/// The metadata of each item.
+	metadata: StorageMap<ItemKey<T>, String>,
+}
+
+/// The events of this pallet.
+#[macros::event]
+pub enum Event<T: Config> {
+	/// A collection was created, owned by `owner`.
+	Created { collection: T::CollectionId, owner: T::AccountId },
+	/// The owner of a collection set who is allowed to mint in it.
+	MintTypeSet { collection: T::CollectionId, mint_type: MintType },
+	/// An item was minted, owned by `owner`.
+	Issued { collection: T::CollectionId, item: T::ItemId, owner: T::AccountId },
+	/// An item was transferred from `from` to `to`.
+	Transferred {
+		collection: T::CollectionId,
+		item: T::ItemId,
+		from: T::AccountId,
+		to: T::AccountId,
+	},
+	/// An item owned by `owner` was destroyed.
+	Burned { collection: T::CollectionId, item: T::ItemId, owner: T::AccountId },
+	/// `delegate` can transfer an item on behalf of its owner.
+	ApprovedTransfer { collection: T::CollectionId, item: T::ItemId, delegate: T::AccountId },
+	/// The approval to transfer an item was removed.
+	ApprovalCancelled { collection: T::CollectionId, item: T::ItemId },
+	/// The attribute `key` of an item was set to `value`.
+	AttributeSet { collection: T::CollectionId, item: T::ItemId, key: String, value: String },
+	/// The attribute `key` of an item was removed.
+	AttributeCleared { collection: T::CollectionId, item: T::ItemId, key: String },
+	/// The metadata of an item was set.
+	MetadataSet { collection: T::CollectionId, item: T::ItemId, data: String },
+}
+
+/// The errors of this pallet.
+#[macros::error]
+pub enum Error<T: Config> {
+	/// The collection does not exist.
+	UnknownCollection,
+	/// The caller does not own this collection.
+	NotCollectionOwner,
+	/// The item does not exist.
+	UnknownItem,
+	/// The collection already exists.
+	CollectionExists,
+	/// The caller is not allowed to mint in this collection.
+	MintNotAllowed,
+	/// The item already exists.
+	ItemExists,
+	/// The caller is not allowed to transfer this item.
+	TransferNotAllowed,
+	/// The caller does not own this item.
+	NotItemOwner,
+	/// The attribute does not exist.
+	UnknownAttribute,
+	/// An arithmetic operation overflowed.
+	Overflow,
+}
+
+impl<T: Config> Pallet<T> {
+	/// Get the details of a collection, if it exists.
+	pub fn collection(&self, collection: &T::CollectionId) -> Option<CollectionDetails<T::AccountId>> {
+		self.collections.get(collection)
+	}
+
+	/// Get the owner (if any) of an item.
+	pub fn owner(&self, collection: &T::CollectionId, item: &T::ItemId) -> Option<T::AccountId> {
+		self.items.get(&(*collection, *item)).map(|details| details.owner)
+	}
+
+	/// Get the account (if any) approved to transfer an item.
+	pub fn approved(&self, collection: &T::CollectionId, item: &T::ItemId) -> Option<T::AccountId> {
+		self.items.get(&(*collection, *item)).and_then(|details| details.approved)
+	}
+
+	/// Get the value (if any) of the attribute `key` of an item.
+	pub fn attribute(&self, collection: &T::CollectionId, item: &T::ItemId, key: &str) -> Option<String> {
+		self.attributes.get(&(*collection, *item), key)
+	}
+
+	/// Get the metadata (if any) of an item.
+	pub fn metadata(&self, collection: &T::CollectionId, item: &T::ItemId) -> Option<String> {
+		self.metadata.get(&(*collection, *item))
+	}
+
+	/// Check that `who` owns `collection`.
+	fn ensure_collection_owner(&self, collection: &T::CollectionId, who: &T::AccountId) -> DispatchResult {
+		let details = self.collections.get(collection).ok_or(Error::<T>::UnknownCollection)?;
+		if details.owner != *who {
+			return Err(Error::<T>::NotCollectionOwner.into());
+		}
+		Ok(())
+	}
+
+	/// Check that an item exists, and that `who` owns its collection.
+	fn ensure_item_of_owner(
+		&self,
+		collection: &T::CollectionId,
+		item: &T::ItemId,
+		who: &T::AccountId,
+	) -> DispatchResult {
+		self.ensure_collection_owner(collection, who)?;
+		if !self.items.contains_key(&(*collection, *item)) {
+			return Err(Error::<T>::UnknownItem.into());
+		}
+		Ok(())
+	}
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	/// Create a new collection owned by the caller. Only the caller can mint in it, until
+	/// `set_mint_type` says otherwise.
+	#[call_index(0)]
+	pub fn create_collection(&mut self, caller: T::AccountId, collection: T::CollectionId) -> DispatchResult {
+		if self.collections.contains_key(&collection) {
+			return Err(Error::<T>::CollectionExists.into());
+		}
+		let details = CollectionDetails { owner: caller.clone(), mint_type: MintType::Issuer, items: 0 };
+		self.collections.insert(collection, details);
+		self.deposit_event(Event::Created { collection, owner: caller });
+		Ok(())
+	}
+
+	/// Set who is allowed to mint items in a collection.
+	/// Only the owner of the collection can change it.
+	#[call_index(1)]
+	pub fn set_mint_type(
+		&mut self,
+		caller: T::AccountId,
+		collection: T::CollectionId,
+		mint_type: MintType,
+	) -> DispatchResult {
+		self.ensure_collection_owner(&collection, &caller)?;
+		self.collections.mutate(collection, |details| {
+			if let Some(details) = details {
+				details.mint_type = mint_type;
+			}
+		});
+		self.deposit_event(Event::MintTypeSet { collection, mint_type });
+		Ok(())
+	}
+
+	/// Mint a new `item` in `collection`, owned by `owner`.
+	#[call_index(2)]
+	pub fn mint(
+		&mut self,
+		caller: T::AccountId,
+		collection: T::CollectionId,
+		item: T::ItemId,
+		owner: T::AccountId,
+	) -> DispatchResult {
+		let mut details = self.collections.get(&collection).ok_or(Error::<T>::UnknownCollection)?;
+		if details.mint_type == MintType::Issuer && details.owner != caller {
+			return Err(Error::<T>::MintNotAllowed.into());
+		}
+		if self.items.contains_key(&(collection, item)) {
+			return Err(Error::<T>::ItemExists.into());
+		}
+
+		details.items = details.items.checked_add(1).ok_or(Error::<T>::Overflow)?;
+		self.collections.insert(collection, details);
+		self.items.insert((collection, item), ItemDetails { owner: owner.clone(), approved: None });
+		self.deposit_event(Event::Issued { collection, item, owner });
+		Ok(())
+	}
+
+	/// Transfer an item to `dest`.
+	/// The caller must own the item, or be approved to transfer it. Any approval is cleared.
+	#[call_index(3)]
+	pub fn transfer(
+		&mut self,
+		caller: T::AccountId,
+		collection: T::CollectionId,
+		item: T::ItemId,
+		dest: T::AccountId,
+	) -> DispatchResult {
+		let mut details = self.items.get(&(collection, item)).ok_or(Error::<T>::UnknownItem)?;
+		if details.owner != caller && details.approved.as_ref() != Some(&caller) {
+			return Err(Error::<T>::TransferNotAllowed.into());
+		}
+
+		let from = core::mem::replace(&mut details.owner, dest.clone());
+		details.approved = None;
+		self.items.insert((collection, item), details);
+		self.deposit_event(Event::Transferred { collection, item, from, to: dest });
+		Ok(())
+	}
+
+	/// Destroy an item, with its attributes and metadata.
+	/// Only the owner of the item can burn it.
+	#[call_index(4)]
+	pub fn burn(&mut self, caller: T::AccountId, collection: T::CollectionId, item: T::ItemId) -> DispatchResult {
+		let owner = self.owner(&collection, &item).ok_or(Error::<T>::UnknownItem)?;
+		if owner != caller {
+			return Err(Error::<T>::NotItemOwner.into());
+		}
+
+		let mut details = self.collections.get(&collection).ok_or(Error::<T>::UnknownCollection)?;
+		details.items = details.items.checked_sub(1).ok_or(Error::<T>::Overflow)?;
+		self.collections.insert(collection, details);
+		self.items.remove(&(collection, item));
+		self.attributes.remove_prefix(&(collection, item));
+		self.metadata.remove(&(collection, item));
+		self.deposit_event(Event::Burned { collection, item, owner });
+		Ok(())
+	}
+
+	/// Allow `delegate` to transfer an item on behalf of the caller, replacing any previous
+	/// approval. Only the owner of the item can approve.
+	#[call_index(5)]
+	pub fn approve_transfer(
+		&mut self,
+		caller: T::AccountId,
+		collection: T::CollectionId,
+		item: T::ItemId,
+		delegate: T::AccountId,
+	) -> DispatchResult {
+		let mut details = self.items.get(&(collection, item)).ok_or(Error::<T>::UnknownItem)?;
+		if details.owner != caller {
+			return Err(Error::<T>::NotItemOwner.into());
+		}
+		details.approved = Some(delegate.clone());
+		self.items.insert((collection, item), details);
+		self.deposit_event(Event::ApprovedTransfer { collection, item, delegate });
+		Ok(())
+	}
+
+	/// Remove the approval to transfer an item.
+	/// Only the owner of the item can cancel the approval.
+	#[call_index(6)]
+	pub fn cancel_approval(
+		&mut self,
+		caller: T::AccountId,
+		collection: T::CollectionId,
+		item: T::ItemId,
+	) -> DispatchResult {
+		let mut details = self.items.get(&(collection, item)).ok_or(Error::<T>::UnknownItem)?;
+		if details.owner != caller {
+			return Err(Error::<T>::NotItemOwner.into());
+		}
+		details.approved = None;
+		self.items.insert((collection, item), details);
+		self.deposit_event(Event::ApprovalCancelled { collection, item });
+		Ok(())
+	}
+
+	/// Set the attribute `key` of an item to `value`.
+	/// Only the owner of the collection can set attributes.
+	#[call_index(7)]
+	pub fn set_attribute(
+		&mut self,
+		caller: T::AccountId,
+		collection: T::CollectionId,
+		item: T::ItemId,
+		key: String,
+		value: String,
+	) -> DispatchResult {
+		self.ensure_item_of_owner(&collection, &item, &caller)?;
+		self.attributes.insert((collection, item), key.clone(), value.clone());
+		self.deposit_event(Event::AttributeSet { collection, item, key, value });
+		Ok(())
+	}
+
+	/// Remove the attribute `key` of an item.
+	/// Only the owner of the collection can clear attributes.
+	#[call_index(8)]
+	pub fn clear_attribute(
+		&mut self,
+		caller: T::AccountId,
+		collection: T::CollectionId,
+		item: T::ItemId,
+		key: String,
+	) -> DispatchResult {
+		self.ensure_item_of_owner(&collection, &item, &caller)?;
+		self.attributes.remove(&(collection, item), &key).ok_or(Error::<T>::UnknownAttribute)?;
+		self.deposit_event(Event::AttributeCleared { collection, item, key });
+		Ok(())
+	}
+
+	/// Set the metadata of an item.
+	/// Only the owner of the collection can set metadata.
+	#[call_index(9)]
+	pub fn set_metadata(
+		&mut self,
+		caller: T::AccountId,
+		collection: T::CollectionId,
+		item: T::ItemId,
+		data: String,
+	) -> DispatchResult {
+		self.ensure_item_of_owner(&collection, &item, &caller)?;
+		self.metadata.insert((collection, item), data.clone());
+		self.deposit_event(Event::MetadataSet { collection, item, data });
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::MintType;
+
+	struct TestConfig;
+
+	impl crate::system::Config for TestConfig {
+		type AccountId = &'static str;
+		type BlockNumber = u32;
+		type Nonce = u32;
+		type RuntimeEvent = ();
+		type PalletIndex = ();
+		const MAXIMUM_BLOCK_WEIGHT: crate::support::Weight = u64::MAX;
+		const MAXIMUM_BLOCK_LENGTH: u32 = u32::MAX;
+		const SPEC_VERSION: u32 = 1;
+	}
+
+	impl super::Config for TestConfig {
+		type CollectionId = u32;
+		type ItemId = u32;
+	}
+
+	#[test]
+	fn collections_and_minting() {
+		let mut nfts = super::Pallet::<TestConfig>::new();
+		assert_eq!(nfts.mint("alice", 1, 1, "alice"), Err(super::Error::<TestConfig>::UnknownCollection.into()));
+		assert_eq!(nfts.create_collection("alice", 1), Ok(()));
+		assert_eq!(nfts.create_collection("bob", 1), Err(super::Error::<TestConfig>::CollectionExists.into()));
+
+		// Only the owner can mint, until minting is made public.
+		assert_eq!(nfts.mint("bob", 1, 1, "bob"), Err(super::Error::<TestConfig>::MintNotAllowed.into()));
+		assert_eq!(nfts.mint("alice", 1, 1, "bob"), Ok(()));
+		assert_eq!(nfts.mint("alice", 1, 1, "bob"), Err(super::Error::<TestConfig>::ItemExists.into()));
+		assert_eq!(nfts.set_mint_type("bob", 1, MintType::Public), Err(super::Error::<TestConfig>::NotCollectionOwner.into()));
+		assert_eq!(nfts.set_mint_type("alice", 1, MintType::Public), Ok(()));
+		assert_eq!(nfts.mint("bob", 1, 2, "bob"), Ok(()));
+
+		assert_eq!(nfts.owner(&1, &1), Some("bob"));
+		assert_eq!(nfts.collection(&1).map(|details| details.items), Some(2));
+	}
+
+	#[test]
+	fn transfers_and_approvals() {
+		let mut nfts = super::Pallet::<TestConfig>::new();
+		let _res = nfts.create_collection("alice", 1);
+		let _res = nfts.mint("alice", 1, 1, "bob");
+
+		assert_eq!(nfts.transfer("charlie", 1, 1, "charlie"), Err(super::Error::<TestConfig>::TransferNotAllowed.into()));
+		assert_eq!(nfts.approve_transfer("charlie", 1, 1, "charlie"), Err(super::Error::<TestConfig>::NotItemOwner.into()));
+		assert_eq!(nfts.approve_transfer("bob", 1, 1, "charlie"), Ok(()));
+		assert_eq!(nfts.approved(&1, &1), Some("charlie"));
+		assert_eq!(nfts.transfer("charlie", 1, 1, "dave"), Ok(()));
+		assert_eq!(nfts.owner(&1, &1), Some("dave"));
+		assert_eq!(nfts.approved(&1, &1), None);
+
+		assert_eq!(nfts.approve_transfer("dave", 1, 1, "bob"), Ok(()));
+		assert_eq!(nfts.cancel_approval("dave", 1, 1), Ok(()));
+		assert_eq!(nfts.transfer("bob", 1, 1, "bob"), Err(super::Error::<TestConfig>::TransferNotAllowed.into()));
+	}
+
+	#[test]
+	fn attributes_metadata_and_burn() {
+		let mut nfts = super::Pallet::<TestConfig>::new();
+		let _res = nfts.create_collection("alice", 1);
+		let _res = nfts.mint("alice", 1, 1, "bob");
+		let _res = nfts.mint("alice", 1, 2, "bob");
+
+		assert_eq!(nfts.set_attribute("bob", 1, 1, "color".into(), "red".into()), Err(super::Error::<TestConfig>::NotCollectionOwner.into()));
+		assert_eq!(nfts.set_attribute("alice", 1, 3, "color".into(), "red".into()), Err(super::Error::<TestConfig>::UnknownItem.into()));
+		assert_eq!(nfts.set_attribute("alice", 1, 1, "color".into(), "red".into()), Ok(()));
+		assert_eq!(nfts.set_attribute("alice", 1, 2, "color".into(), "blue".into()), Ok(()));
+		assert_eq!(nfts.attribute(&1, &1, "color"), Some("red".to_string()));
+		assert_eq!(nfts.set_metadata("alice", 1, 1, "ipfs://item".into()), Ok(()));
+		assert_eq!(nfts.metadata(&1, &1), Some("ipfs://item".to_string()));
+		assert_eq!(nfts.clear_attribute("alice", 1, 1, "size".into()), Err(super::Error::<TestConfig>::UnknownAttribute.into()));
+
+		assert_eq!(nfts.burn("alice", 1, 1), Err(super::Error::<TestConfig>::NotItemOwner.into()));
+		assert_eq!(nfts.burn("bob", 1, 1), Ok(()));
+		assert_eq!(nfts.owner(&1, &1), None);
+		assert_eq!(nfts.attribute(&1, &1, "color"), None);
+		assert_eq!(nfts.metadata(&1, &1), None);
+		// The attributes of the other items are kept.
+		assert_eq!(nfts.attribute(&1, &2, "color"), Some("blue".to_string()));
+		assert_eq!(nfts.collection(&1).map(|details| details.items), Some(1));
+	}
+}
@@ -0,0 +1,274 @@
+use std::{
+	fs, io,
+	path::{Path, PathBuf},
+};
+
+use crate::{
+	block_builder::BlockBuilder,
+	codec::{Decode, Encode},
+	database::{Database, DatabaseError},
+	genesis::GenesisConfig,
+	support, system,
+	transaction_pool::{PoolError, TransactionPool},
+	types, Runtime,
+};
+
+/// The file of the data directory holding the chain.
+const DATABASE_FILE: &str = "chain.db";
+/// The file of the data directory holding the transactions waiting in the pool.
+const POOL_FILE: &str = "pool";
+/// The maximum number of transactions waiting in the pool.
+const POOL_CAPACITY: usize = 1_000;
+
+/// Why the node could not do what was asked.
+#[derive(Debug)]
+pub enum NodeError {
+	/// The chain could not be stored or loaded.
+	Database(DatabaseError),
+	/// The pool refused a transaction.
+	Pool(PoolError),
+	/// The data directory could not be read or written.
+	Io(io::Error),
+	/// The block is not valid on top of the chain.
+	InvalidBlock(&'static str),
+}
+
+impl core::fmt::Display for NodeError {
+	fn fmt(&self, formatter: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		match self {
+			NodeError::Database(error) => write!(formatter, "{}", error),
+			NodeError::Pool(error) => write!(formatter, "transaction refused: {:?}", error),
+			NodeError::Io(error) => write!(formatter, "{}", error),
+			NodeError::InvalidBlock(error) => write!(formatter, "invalid block: {}", error),
+		}
+	}
+}
+
+impl From<DatabaseError> for NodeError {
+	fn from(error: DatabaseError) -> Self {
+		NodeError::Database(error)
+	}
+}
+
+impl From<io::Error> for NodeError {
+	fn from(error: io::Error) -> Self {
+		NodeError::Io(error)
+	}
+}
+
+/// A node keeping a chain in a data directory: the blocks of the chain, the state at its head, and
+/// the transactions waiting to be included in the next blocks.
+///
+/// The transactions in the pool are stored too, so they survive until the node is opened again.
+pub struct Node {
+	base_path: PathBuf,
+	database: Database,
+	/// The state at the head of the chain.
+	runtime: Runtime,
+	pool: TransactionPool,
+}
+
+impl Node {
+	/// Start a new chain from `genesis` in the data directory `base_path`, which is created if
+	/// needed. Fails if the directory already holds a chain.
+	pub fn init(base_path: &Path, genesis: GenesisConfig) -> Result<Self, NodeError> {
+		fs::create_dir_all(base_path)?;
+		let database = Database::create(&base_path.join(DATABASE_FILE), genesis)?;
+		let runtime = database.runtime()?;
+		let node = Node {
+			base_path: base_path.to_path_buf(),
+			database,
+			runtime,
+			pool: TransactionPool::new(POOL_CAPACITY),
+		};
+		node.save_pool()?;
+		Ok(node)
+	}
+
+	/// Open the chain of the data directory `base_path`. The transactions of the pool which are no
+	/// longer valid are dropped.
+	pub fn open(base_path: &Path) -> Result<Self, NodeError> {
+		let database = Database::open(&base_path.join(DATABASE_FILE))?;
+		let runtime = database.runtime()?;
+		let mut node = Node {
+			base_path: base_path.to_path_buf(),
+			database,
+			runtime,
+			pool: TransactionPool::new(POOL_CAPACITY),
+		};
+
+		let pool = match fs::read(node.base_path.join(POOL_FILE)) {
+			Ok(bytes) => Vec::<types::Extrinsic>::decode_all(&bytes)
+				.map_err(|e| NodeError::Database(DatabaseError::Corrupted(e)))?,
+			Err(error) if error.kind() == io::ErrorKind::NotFound => Vec::new(),
+			Err(error) => return Err(error.into()),
+		};
+		for extrinsic in pool {
+			let _res = node.pool.submit(&node.runtime, extrinsic);
+		}
+		Ok(node)
+	}
+
+	/// Get the state at the head of the chain.
+	pub fn runtime(&self) -> &Runtime {
+		&self.runtime
+	}
+
+	/// Get the stored blocks of the chain.
+	pub fn database(&self) -> &Database {
+		&self.database
+	}
+
+	/// Get the transactions waiting to be included in the next blocks.
+	pub fn pool(&self) -> &TransactionPool {
+		&self.pool
+	}
+
+	/// Get the nonce the next transaction of `who` should have, counting the transactions it has
+	/// waiting in the pool.
+	pub fn next_nonce(&self, who: &types::AccountId) -> types::Nonce {
+		self.pool
+			.transactions()
+			.filter(|extrinsic| &extrinsic.caller == who)
+			.map(|extrinsic| extrinsic.nonce + 1)
+			.fold(self.runtime.system.nonce(who), types::Nonce::max)
+	}
+
+	/// Validate `extrinsic` and add it to the pool, to be included in the next blocks.
+	pub fn submit(&mut self, extrinsic: types::Extrinsic) -> Result<(), NodeError> {
+		self.pool.submit(&self.runtime, extrinsic).map_err(NodeError::Pool)?;
+		self.save_pool()?;
+		Ok(())
+	}
+
+	/// Build the next block from the ready transactions of the pool, and import it.
+	pub fn produce_block(&mut self) -> Result<types::BlockReceipt, NodeError> {
+		let mut builder = BlockBuilder::new(&self.runtime).map_err(NodeError::InvalidBlock)?;
+		let max_weight = <Runtime as system::Config>::MAXIMUM_BLOCK_WEIGHT;
+		for extrinsic in self.pool.take_ready(max_weight) {
+			// Transactions which no longer fit or are no longer valid are dropped.
+			let _res = builder.push(extrinsic);
+		}
+		let block = builder.finalize();
+		self.import_block(block)?
+			.ok_or(NodeError::InvalidBlock("block is already known"))
+	}
+
+	/// Execute `block` and store it as the new head of the chain. Returns `None` if the block is
+	/// already part of the chain.
+	pub fn import_block(
+		&mut self,
+		block: types::Block,
+	) -> Result<Option<types::BlockReceipt>, NodeError> {
+		if let Some(known) = self.database.block(block.header.block_number) {
+			return if support::hash_of(&known.header) == support::hash_of(&block.header) {
+				Ok(None)
+			} else {
+				Err(NodeError::InvalidBlock("block conflicts with the chain"))
+			};
+		}
+
+		// The block is executed on a copy of the state, which is dropped if the block is invalid.
+		let mut runtime = self.runtime.clone();
+		let receipt = runtime.execute_block(block.clone()).map_err(NodeError::InvalidBlock)?;
+		self.database.import_block(block, &runtime)?;
+		self.runtime = runtime;
+		self.pool.prune(&self.runtime);
+		self.save_pool()?;
+		Ok(Some(receipt))
+	}
+
+	/// Store the transactions of the pool, replacing the ones stored before.
+	fn save_pool(&self) -> io::Result<()> {
+		let pool = self.pool.transactions().cloned().collect::<Vec<_>>();
+		// The pool is written next to the old one, then moved over it, so it is never left half
+		// written.
+		let path = self.base_path.join(POOL_FILE);
+		let new_path = path.with_extension("new");
+		fs::write(&new_path, pool.encode())?;
+		fs::rename(new_path, path)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::{fs, path::PathBuf};
+
+	use super::{Node, NodeError};
+	use crate::{
+		balances, genesis::GenesisConfig, support, transaction_pool::PoolError, types, RuntimeCall,
+	};
+
+	/// A data directory for a test, which does not exist yet.
+	fn base_path(name: &str) -> PathBuf {
+		let path = std::env::temp_dir().join(format!("node-{}-{}", name, std::process::id()));
+		let _ = fs::remove_dir_all(&path);
+		path
+	}
+
+	fn transfer(caller: &str, nonce: types::Nonce, amount: types::Balance) -> types::Extrinsic {
+		support::Extrinsic {
+			caller: caller.to_string(),
+			nonce,
+			tip: 0,
+			call: RuntimeCall::balances(balances::Call::transfer {
+				to: "dave".to_string(),
+				amount,
+			}),
+		}
+	}
+
+	#[test]
+	fn transactions_are_kept_until_included() {
+		let base_path = base_path("pool");
+		let mut node = Node::init(&base_path, GenesisConfig::dev()).unwrap();
+		assert!(matches!(
+			Node::init(&base_path, GenesisConfig::dev()),
+			Err(NodeError::Database(_))
+		));
+
+		node.submit(transfer("alice", 0, 10)).unwrap();
+		assert_eq!(node.next_nonce(&"alice".to_string()), 1);
+		node.submit(transfer("alice", 1, 10)).unwrap();
+		assert!(matches!(
+			node.submit(transfer("alice", 1, 10)),
+			Err(NodeError::Pool(PoolError::TooLowPriority))
+		));
+		drop(node);
+
+		// The pool survives the node.
+		let mut node = Node::open(&base_path).unwrap();
+		assert_eq!(node.next_nonce(&"alice".to_string()), 2);
+		let receipt = node.produce_block().unwrap();
+		assert_eq!(receipt.block_number, 1);
+		assert_eq!(receipt.extrinsics.len(), 2);
+		drop(node);
+
+		let node = Node::open(&base_path).unwrap();
+		assert_eq!(node.runtime().balances.balance(&"dave".to_string()), 20);
+		assert_eq!(node.next_nonce(&"alice".to_string()), 2);
+		fs::remove_dir_all(&base_path).unwrap();
+	}
+
+	#[test]
+	fn blocks_are_imported_from_other_nodes() {
+		let (producer_path, importer_path) = (base_path("producer"), base_path("importer"));
+		let mut producer = Node::init(&producer_path, GenesisConfig::dev()).unwrap();
+		let mut importer = Node::init(&importer_path, GenesisConfig::dev()).unwrap();
+
+		producer.submit(transfer("bob", 0, 10)).unwrap();
+		producer.produce_block().unwrap();
+		producer.produce_block().unwrap();
+
+		let block_1 = producer.database().block(1).unwrap().clone();
+		let block_2 = producer.database().block(2).unwrap().clone();
+		assert!(matches!(importer.import_block(block_2.clone()), Err(NodeError::InvalidBlock(_))));
+		assert!(importer.import_block(block_1.clone()).unwrap().is_some());
+		assert!(importer.import_block(block_1).unwrap().is_none());
+		assert!(importer.import_block(block_2).unwrap().is_some());
+		assert_eq!(support::hash_of(importer.runtime()), support::hash_of(producer.runtime()));
+
+		fs::remove_dir_all(&producer_path).unwrap();
+		fs::remove_dir_all(&importer_path).unwrap();
+	}
+}
@@ -1,31 +1,43 @@
-use core::fmt::Debug;
-use std::collections::BTreeMap;
+use core::{borrow::Borrow, fmt::Debug};
+
+use crate::{codec::Encode, storage::StorageMap};
 
 pub trait Config: crate::system::Config {
 	/// The type which represents the content that can be claimed using this pallet.
 	/// Could be the content directly as bytes, or better yet the hash of that content.
 	/// We leave that decision to the runtime developer.
-	type Content: Debug + Ord;
+	type Content: Debug + Encode + Ord + Clone;
 }
 
 /// This is the Proof of Existence Module.
 /// It is a simple module that allows accounts to claim existence of some data.
-#[derive(Debug)]
+#[macros::storage]
+#[derive(Debug, macros::PalletInfo)]
 pub struct Pallet<T: Config> {
 	/// A simple storage map from content to the owner of that content.
 	/// Accounts can make multiple different claims, but each claim can only have one owner.
 	/* TODO: Add a field `claims` which is a `BTreeMap` fom `T::Content` to `T::AccountId`. */
-    claims: BTreeMap<T::Content, T::AccountId>,
+    claims: StorageMap<T::Content, T::AccountId>,
+}
+
+/// The errors of this pallet.
+#[macros::error]
+pub enum Error<T: Config> {
+	/// This content is already claimed.
+	AlreadyClaimed,
+	/// The claim does not exist.
+	NoSuchClaim,
+	/// The caller does not own this content.
+	NotClaimOwner,
 }
 
 impl<T: Config> Pallet<T> {
-	/// Create a new instance of the Proof of Existence Module.
-	pub fn new() -> Self {
-		/* TODO: Return a new instance of the `Pallet` struct. */
-        Self {claims: BTreeMap::new()}
-	}
-	/// Get the owner (if any) of a claim.
-	pub fn get_claim(&self, claim: &T::Content) -> Option<&T::AccountId> {
+	/// Get the owner (if any) of a claim. The claim can be given in any form the content borrows as,
+	/// e.g. a `&str` for `&'static str` contents.
+	pub fn get_claim<Q: Encode + ?Sized>(&self, claim: &Q) -> Option<T::AccountId>
+	where
+		T::Content: Borrow<Q>,
+	{
 		/* TODO: `get` the `claim` */
 		self.claims.get(claim)
 	}
@@ -34,19 +46,21 @@ impl<T: Config> Pallet<T> {
 #[macros::call]
 impl<T: Config> Pallet<T> {
 
+	#[call_index(0)]
 	pub fn create_claim(&mut self, caller: T::AccountId, claim: T::Content) -> crate::support::DispatchResult {
         if self.claims.contains_key(&claim) {
-            return Err("this content is already claimed");
+            return Err(Error::<T>::AlreadyClaimed.into());
         }
         self.claims.insert(claim, caller);
 
 		Ok(())
 	}
 
+	#[call_index(1)]
 	pub fn revoke_claim(&mut self, caller: T::AccountId, claim: T::Content) -> crate::support::DispatchResult {
-        let owner = self.get_claim(&claim).ok_or("claim does not exist")?;
-        if *owner != caller {
-            return Err("caller does not own this content");
+        let owner = self.get_claim(&claim).ok_or(Error::<T>::NoSuchClaim)?;
+        if owner != caller {
+            return Err(Error::<T>::NotClaimOwner.into());
         }
         self.claims.remove(&claim);
 		Ok(())
@@ -55,8 +69,6 @@ impl<T: Config> Pallet<T> {
 
 #[cfg(test)]
 mod test {
-    use std::fmt::Error;
-
 	struct TestConfig;
 
 	impl super::Config for TestConfig {
@@ -67,6 +79,11 @@ mod test {
 		type AccountId = &'static str;
 		type BlockNumber = u32;
 		type Nonce = u32;
+		type RuntimeEvent = ();
+		type PalletIndex = ();
+		const MAXIMUM_BLOCK_WEIGHT: crate::support::Weight = u64::MAX;
+		const MAXIMUM_BLOCK_LENGTH: u32 = u32::MAX;
+		const SPEC_VERSION: u32 = 1;
 	}
 
 	#[test]
@@ -81,12 +98,12 @@ mod test {
         let mut claims = super::Pallet::<TestConfig>::new();
         assert_eq!(claims.get_claim(&"testcontent"), None);
         let _res = claims.create_claim("ziv", "this is ziv's first claim");
-        assert_eq!(claims.get_claim(&"this is ziv's first claim"), Some(&"ziv"));
+        assert_eq!(claims.get_claim(&"this is ziv's first claim"), Some("ziv"));
         let _res = claims.revoke_claim("ziv", "this is not ziv's claim");
-        assert_eq!(_res, Err("claim does not exist"));
+        assert_eq!(_res, Err(super::Error::<TestConfig>::NoSuchClaim.into()));
         let _res = claims.create_claim("someone", "this is someone's first claim");
         let _res = claims.revoke_claim("ziv", "this is someone's first claim");
-        assert_eq!(_res, Err("caller does not own this content"));
+        assert_eq!(_res, Err(super::Error::<TestConfig>::NotClaimOwner.into()));
         let _res = claims.revoke_claim("ziv", "this is ziv's first claim");
         assert_eq!(_res, Ok(()));
 
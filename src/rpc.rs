@@ -0,0 +1,643 @@
+use std::{
+	collections::{BTreeMap, VecDeque},
+	io::{self, BufRead, BufReader, Read, Write},
+	net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream},
+	time::Duration,
+};
+
+use crate::{
+	codec::Decode,
+	json::Value,
+	node::{Node, NodeError},
+	support::{self, Hash},
+	types, Runtime,
+};
+
+/// The port the server listens on when none is given.
+pub const DEFAULT_PORT: u16 = 9933;
+/// The largest request body the server accepts.
+const MAX_BODY_LEN: usize = 1 << 20;
+/// How long the server waits for a client to send its request.
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+/// The most notifications a subscription queues. Once it is full, the oldest notifications are
+/// dropped to make room for the new ones.
+const MAX_NOTIFICATIONS: usize = 1024;
+/// The most subscriptions the server keeps. Once there are as many, the subscription which was
+/// polled the longest ago is ended to make room for a new one.
+const MAX_SUBSCRIPTIONS: usize = 256;
+
+// The codes of the errors defined by JSON-RPC.
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+// The codes of the errors of the node.
+const NODE_ERROR: i64 = 1000;
+const INVALID_TRANSACTION: i64 = 1010;
+
+/// An error returned to the client, as defined by JSON-RPC.
+struct RpcError {
+	code: i64,
+	message: String,
+}
+
+impl RpcError {
+	fn new(code: i64, message: impl Into<String>) -> Self {
+		RpcError { code, message: message.into() }
+	}
+
+	fn invalid_params(message: &str) -> Self {
+		RpcError::new(INVALID_PARAMS, message)
+	}
+}
+
+impl From<NodeError> for RpcError {
+	fn from(error: NodeError) -> Self {
+		let code = match error {
+			NodeError::Pool(_) => INVALID_TRANSACTION,
+			_ => NODE_ERROR,
+		};
+		RpcError::new(code, error.to_string())
+	}
+}
+
+/// What a subscription is notified of.
+enum SubscriptionKind {
+	/// The header of each new block.
+	NewHeads,
+	/// The events of each new block.
+	Events,
+}
+
+struct Subscription {
+	kind: SubscriptionKind,
+	/// The notifications which were not polled yet, at most `MAX_NOTIFICATIONS`.
+	notifications: VecDeque<Value>,
+	/// The clock of the handler when the subscription was last polled, or created.
+	last_polled: u64,
+}
+
+impl Subscription {
+	/// Queue `notification`, dropping the oldest notification if the queue is full.
+	fn push(&mut self, notification: Value) {
+		if self.notifications.len() == MAX_NOTIFICATIONS {
+			self.notifications.pop_front();
+		}
+		self.notifications.push_back(notification);
+	}
+}
+
+/// Handles the JSON-RPC requests of clients on top of a node.
+///
+/// The available methods are:
+/// - `chain_getHeader([block])` and `chain_getBlock([block])` - where the block is given by its
+///   number, or by the hash of its header as a `0x` prefixed hexadecimal string. The head of the
+///   chain is used when no block is given.
+/// - `state_getBalance([account])`
+/// - `poe_getClaim([content])` - the owner of a claim, or `null`.
+/// - `system_accountNonce([account])` - the nonce of the next transaction of an account, counting
+///   its transactions waiting in the pool.
+/// - `author_submitExtrinsic([extrinsic])` - where the extrinsic is encoded with `codec::Encode`,
+///   as a `0x` prefixed hexadecimal string. Returns the hash of the extrinsic.
+/// - `state_getMetadata([])` - the metadata of the runtime, see `metadata::RuntimeMetadata`.
+/// - `engine_createBlock([])` - produce a block from the pool, and return its header.
+/// - `chain_subscribeNewHeads([])` and `state_subscribeEvents([])` - return the id of a new
+///   subscription. Its notifications are queued until they are taken with
+///   `subscription_poll([id])`, and `subscription_unsubscribe([id])` ends it. Only the latest
+///   `MAX_NOTIFICATIONS` notifications are kept, and at most `MAX_SUBSCRIPTIONS` subscriptions.
+pub struct RpcHandler {
+	node: Node,
+	subscriptions: BTreeMap<u64, Subscription>,
+	next_subscription: u64,
+	/// Counts the subscriptions and polls, to find the subscription polled the longest ago.
+	clock: u64,
+}
+
+impl RpcHandler {
+	pub fn new(node: Node) -> Self {
+		RpcHandler { node, subscriptions: BTreeMap::new(), next_subscription: 0, clock: 0 }
+	}
+
+	/// Handle the JSON text of a request, and return the JSON text of the response.
+	pub fn handle(&mut self, body: &str) -> String {
+		let (id, result) = match Value::parse(body) {
+			Ok(request) => {
+				let id = request.get("id").cloned().unwrap_or(Value::Null);
+				(id, self.call(&request))
+			},
+			Err(error) => (Value::Null, Err(RpcError::new(PARSE_ERROR, error))),
+		};
+		let response = match result {
+			Ok(result) => {
+				Value::object([("jsonrpc", "2.0".into()), ("id", id), ("result", result)])
+			},
+			Err(error) => Value::object([
+				("jsonrpc", "2.0".into()),
+				("id", id),
+				(
+					"error",
+					Value::object([("code", error.code.into()), ("message", error.message.into())]),
+				),
+			]),
+		};
+		response.to_string()
+	}
+
+	fn call(&mut self, request: &Value) -> Result<Value, RpcError> {
+		if request.get("jsonrpc").and_then(Value::as_str) != Some("2.0") {
+			return Err(RpcError::new(INVALID_REQUEST, "not a JSON-RPC 2.0 request"));
+		}
+		let method = request
+			.get("method")
+			.and_then(Value::as_str)
+			.ok_or(RpcError::new(INVALID_REQUEST, "missing method"))?;
+		let params = match request.get("params") {
+			None => &[][..],
+			Some(Value::Array(params)) => &params[..],
+			Some(_) => return Err(RpcError::invalid_params("params must be an array")),
+		};
+
+		match method {
+			"chain_getHeader" => Ok(self
+				.block(params.first())?
+				.map_or(Value::Null, |block| header_json(&block.header))),
+			"chain_getBlock" => Ok(self.block(params.first())?.map_or(Value::Null, block_json)),
+			"state_getBalance" => {
+				let who = account_param(params)?;
+				Ok(self.node.runtime().balances.balance(&who).into())
+			},
+			"poe_getClaim" => {
+				let claim = string_param(params)?;
+				Ok(self.node.runtime().proof_of_existence.get_claim(claim).into())
+			},
+			"system_accountNonce" => {
+				let who = account_param(params)?;
+				Ok(self.node.next_nonce(&who).into())
+			},
+			"author_submitExtrinsic" => {
+				let bytes = from_hex(string_param(params)?)
+					.ok_or(RpcError::invalid_params("invalid hexadecimal extrinsic"))?;
+				let extrinsic = types::Extrinsic::decode_all(&bytes)
+					.map_err(|e| RpcError::new(INVALID_TRANSACTION, e))?;
+				let hash = support::hash_of(&extrinsic);
+				self.node.submit(extrinsic)?;
+				Ok(hash_json(hash))
+			},
+			"engine_createBlock" => {
+				let receipt = self.node.produce_block()?;
+				let header = self
+					.node
+					.database()
+					.block(receipt.block_number)
+					.map(|block| header_json(&block.header))
+					.expect("the block was just imported");
+				self.notify(&header, &receipt);
+				Ok(header)
+			},
+			"state_getMetadata" => Ok(Value::from(&Runtime::metadata())),
+			"chain_subscribeNewHeads" => Ok(self.subscribe(SubscriptionKind::NewHeads)),
+			"state_subscribeEvents" => Ok(self.subscribe(SubscriptionKind::Events)),
+			"subscription_poll" => {
+				let id = subscription_param(params)?;
+				let subscription = self
+					.subscriptions
+					.get_mut(&id)
+					.ok_or(RpcError::invalid_params("unknown subscription"))?;
+				self.clock += 1;
+				subscription.last_polled = self.clock;
+				Ok(Value::Array(subscription.notifications.drain(..).collect()))
+			},
+			"subscription_unsubscribe" => {
+				let id = subscription_param(params)?;
+				Ok(self.subscriptions.remove(&id).is_some().into())
+			},
+			_ => Err(RpcError::new(METHOD_NOT_FOUND, "method not found")),
+		}
+	}
+
+	/// Find the block designated by `param`, or the head of the chain if there is none.
+	fn block(&self, param: Option<&Value>) -> Result<Option<&types::Block>, RpcError> {
+		let database = self.node.database();
+		Ok(match param {
+			None | Some(Value::Null) => {
+				database.head().and_then(|head| database.block(head.block_number))
+			},
+			Some(Value::Number(_)) => {
+				let number = param
+					.and_then(Value::as_u64)
+					.and_then(|number| types::BlockNumber::try_from(number).ok())
+					.ok_or(RpcError::invalid_params("invalid block number"))?;
+				database.block(number)
+			},
+			Some(Value::String(hash)) => {
+				let hash = hash
+					.strip_prefix("0x")
+					.and_then(|hash| Hash::from_str_radix(hash, 16).ok())
+					.ok_or(RpcError::invalid_params("invalid block hash"))?;
+				database.block_by_hash(hash)
+			},
+			Some(_) => return Err(RpcError::invalid_params("invalid block")),
+		})
+	}
+
+	/// Start a subscription, ending the one which was polled the longest ago if there are already
+	/// `MAX_SUBSCRIPTIONS`.
+	fn subscribe(&mut self, kind: SubscriptionKind) -> Value {
+		if self.subscriptions.len() >= MAX_SUBSCRIPTIONS {
+			let idle = self
+				.subscriptions
+				.iter()
+				.min_by_key(|(_, subscription)| subscription.last_polled)
+				.map(|(id, _)| *id);
+			if let Some(id) = idle {
+				self.subscriptions.remove(&id);
+			}
+		}
+
+		let id = self.next_subscription;
+		self.next_subscription += 1;
+		self.clock += 1;
+		let subscription =
+			Subscription { kind, notifications: VecDeque::new(), last_polled: self.clock };
+		self.subscriptions.insert(id, subscription);
+		id.into()
+	}
+
+	/// Queue the notifications of a new block for the subscriptions.
+	fn notify(&mut self, header: &Value, receipt: &types::BlockReceipt) {
+		let initialization_events = receipt.initialization_events.iter().map(|event| (None, event));
+		let extrinsic_events =
+			receipt.extrinsics.iter().enumerate().flat_map(|(i, extrinsic)| {
+				extrinsic.events.iter().map(move |event| (Some(i), event))
+			});
+		let events = initialization_events
+			.chain(extrinsic_events)
+			.map(|(extrinsic, event)| {
+				Value::object([
+					("blockNumber", receipt.block_number.into()),
+					("extrinsic", extrinsic.into()),
+					("event", format!("{:?}", event).into()),
+				])
+			})
+			.collect::<Vec<_>>();
+
+		for subscription in self.subscriptions.values_mut() {
+			match subscription.kind {
+				SubscriptionKind::NewHeads => subscription.push(header.clone()),
+				SubscriptionKind::Events => {
+					events.iter().cloned().for_each(|event| subscription.push(event))
+				},
+			}
+		}
+	}
+}
+
+fn string_param(params: &[Value]) -> Result<&str, RpcError> {
+	params
+		.first()
+		.and_then(Value::as_str)
+		.ok_or(RpcError::invalid_params("expected a string parameter"))
+}
+
+fn account_param(params: &[Value]) -> Result<types::AccountId, RpcError> {
+	string_param(params).map(str::to_string)
+}
+
+fn subscription_param(params: &[Value]) -> Result<u64, RpcError> {
+	params
+		.first()
+		.and_then(Value::as_u64)
+		.ok_or(RpcError::invalid_params("expected a subscription id"))
+}
+
+fn hash_json(hash: Hash) -> Value {
+	format!("0x{:016x}", hash).into()
+}
+
+fn header_json(header: &types::Header) -> Value {
+	Value::object([
+		("number", header.block_number.into()),
+		("hash", hash_json(support::hash_of(header))),
+		("parentHash", hash_json(header.parent_hash)),
+		("extrinsicsRoot", hash_json(header.extrinsics_root)),
+		("stateRoot", hash_json(header.state_root)),
+	])
+}
+
+fn block_json(block: &types::Block) -> Value {
+	let extrinsics = block
+		.extrinsics
+		.iter()
+		.map(|extrinsic| {
+			Value::object([
+				("caller", extrinsic.caller.as_str().into()),
+				("nonce", extrinsic.nonce.into()),
+				("tip", extrinsic.tip.into()),
+				("call", format!("{:?}", extrinsic.call).into()),
+			])
+		})
+		.collect();
+	Value::object([
+		("header", header_json(&block.header)),
+		("extrinsics", Value::Array(extrinsics)),
+	])
+}
+
+/// Decode a `0x` prefixed hexadecimal string.
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+	let hex = hex.strip_prefix("0x")?;
+	if hex.len() % 2 != 0 {
+		return None;
+	}
+	(0..hex.len())
+		.step_by(2)
+		.map(|i| hex.get(i..i + 2).and_then(|byte| u8::from_str_radix(byte, 16).ok()))
+		.collect()
+}
+
+/// A JSON-RPC server over HTTP, which only listens on localhost.
+///
+/// Requests are `POST`ed with a JSON-RPC request as their body, and handled one at a time.
+pub struct RpcServer {
+	listener: TcpListener,
+	handler: RpcHandler,
+}
+
+impl RpcServer {
+	/// Listen on `port` of localhost. A port of 0 lets the system pick a free port.
+	pub fn bind(node: Node, port: u16) -> io::Result<Self> {
+		let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+		Ok(RpcServer { listener, handler: RpcHandler::new(node) })
+	}
+
+	pub fn local_addr(&self) -> io::Result<SocketAddr> {
+		self.listener.local_addr()
+	}
+
+	/// Serve clients until the listener fails. A client which sends an invalid request only gets an
+	/// error response.
+	pub fn run(mut self) -> io::Result<()> {
+		loop {
+			let (stream, _) = self.listener.accept()?;
+			// The client may have gone away, which only concerns this client.
+			let _res = self.serve(stream);
+		}
+	}
+
+	/// Read a single HTTP request from `stream`, and write its response.
+	fn serve(&mut self, mut stream: TcpStream) -> io::Result<()> {
+		stream.set_read_timeout(Some(READ_TIMEOUT))?;
+		let (status, body) = match read_request(&mut stream)? {
+			Ok(body) => ("200 OK", self.handler.handle(&body)),
+			Err(status) => (status, String::new()),
+		};
+		write!(
+			stream,
+			"HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: \
+			 close\r\n\r\n{}",
+			status,
+			body.len(),
+			body
+		)?;
+		stream.flush()
+	}
+}
+
+/// Read an HTTP request, and return its body. A request which cannot be served is answered with the
+/// returned status instead.
+fn read_request(stream: &mut TcpStream) -> io::Result<Result<String, &'static str>> {
+	let mut reader = BufReader::new(stream).take(MAX_BODY_LEN as u64 * 2);
+	let mut request_line = String::new();
+	reader.read_line(&mut request_line)?;
+	let mut content_length = None;
+	loop {
+		let mut line = String::new();
+		if reader.read_line(&mut line)? == 0 {
+			return Ok(Err("400 Bad Request"));
+		}
+		let line = line.trim_end();
+		if line.is_empty() {
+			break;
+		}
+		if let Some((name, value)) = line.split_once(':')
+			&& name.eq_ignore_ascii_case("content-length")
+		{
+			content_length = value.trim().parse::<usize>().ok();
+		}
+	}
+
+	if !request_line.starts_with("POST ") {
+		return Ok(Err("405 Method Not Allowed"));
+	}
+	let Some(content_length) = content_length else { return Ok(Err("411 Length Required")) };
+	if content_length > MAX_BODY_LEN {
+		return Ok(Err("413 Payload Too Large"));
+	}
+	let mut body = vec![0; content_length];
+	reader.read_exact(&mut body)?;
+	Ok(String::from_utf8(body).map_err(|_| "400 Bad Request"))
+}
+
+#[cfg(test)]
+mod test {
+	use std::{
+		fs,
+		io::{Read, Write},
+		net::TcpStream,
+		path::PathBuf,
+	};
+
+	use super::{
+		RpcHandler, RpcServer, Subscription, SubscriptionKind, MAX_NOTIFICATIONS, MAX_SUBSCRIPTIONS,
+	};
+	use crate::{
+		balances, codec::Encode, genesis::GenesisConfig, json::Value, node::Node,
+		proof_of_existence, support, types, RuntimeCall,
+	};
+
+	/// A data directory for a test, which does not exist yet.
+	fn base_path(name: &str) -> PathBuf {
+		let path = std::env::temp_dir().join(format!("rpc-{}-{}", name, std::process::id()));
+		let _ = fs::remove_dir_all(&path);
+		path
+	}
+
+	/// Call `method` with `params`, and return the result, or the error.
+	fn call(handler: &mut RpcHandler, method: &str, params: &str) -> Value {
+		let request =
+			format!(r#"{{"jsonrpc":"2.0","id":7,"method":"{}","params":{}}}"#, method, params);
+		let response = Value::parse(&handler.handle(&request)).unwrap();
+		assert_eq!(response.get("id"), Some(&7u32.into()));
+		response.get("result").or(response.get("error")).unwrap().clone()
+	}
+
+	fn hex(bytes: &[u8]) -> String {
+		let digits = bytes.iter().map(|byte| format!("{:02x}", byte)).collect::<String>();
+		format!("\"0x{}\"", digits)
+	}
+
+	#[test]
+	fn chain_is_served() {
+		let base_path = base_path("handler");
+		let mut handler = RpcHandler::new(Node::init(&base_path, GenesisConfig::dev()).unwrap());
+		let heads = call(&mut handler, "chain_subscribeNewHeads", "[]");
+		let events = call(&mut handler, "state_subscribeEvents", "[]");
+
+		let claim: types::Extrinsic = support::Extrinsic {
+			caller: "alice".to_string(),
+			nonce: 0,
+			tip: 0,
+			call: RuntimeCall::proof_of_existence(proof_of_existence::Call::create_claim {
+				claim: "hello".to_string(),
+			}),
+		};
+		let hash =
+			call(&mut handler, "author_submitExtrinsic", &format!("[{}]", hex(&claim.encode())));
+		assert_eq!(hash, format!("0x{:016x}", support::hash_of(&claim)).as_str().into());
+		assert_eq!(call(&mut handler, "system_accountNonce", r#"["alice"]"#), 1u32.into());
+
+		let header = call(&mut handler, "engine_createBlock", "[]");
+		assert_eq!(header.get("number"), Some(&1u32.into()));
+		assert_eq!(call(&mut handler, "chain_getHeader", "[]"), header);
+		let hash = header.get("hash").unwrap().to_string();
+		let block = call(&mut handler, "chain_getBlock", &format!("[{}]", hash));
+		assert_eq!(call(&mut handler, "chain_getBlock", "[1]"), block);
+		assert_eq!(
+			block.get("extrinsics").unwrap().to_string(),
+			r#"[{"caller":"alice","nonce":0,"tip":0,"call":"proof_of_existence(create_claim { claim: \"hello\" })"}]"#
+		);
+		assert_eq!(call(&mut handler, "poe_getClaim", r#"["hello"]"#), "alice".into());
+		assert_eq!(call(&mut handler, "poe_getClaim", r#"["bye"]"#), Value::Null);
+		assert_eq!(call(&mut handler, "state_getBalance", r#"["alice"]"#), 999u32.into());
+		assert_eq!(call(&mut handler, "chain_getBlock", "[2]"), Value::Null);
+		let metadata = call(&mut handler, "state_getMetadata", "[]");
+		assert_eq!(metadata, Value::from(&crate::Runtime::metadata()));
+
+		// Every subscription is notified of the new block.
+		assert_eq!(
+			call(&mut handler, "subscription_poll", &format!("[{}]", heads)),
+			Value::Array(vec![header])
+		);
+		assert_eq!(
+			call(&mut handler, "subscription_poll", &format!("[{}]", heads)),
+			Value::Array(vec![])
+		);
+		assert_eq!(
+			call(&mut handler, "subscription_unsubscribe", &format!("[{}]", heads)),
+			true.into()
+		);
+		assert_eq!(
+			call(&mut handler, "subscription_poll", &format!("[{}]", events)),
+			Value::Array(vec![])
+		);
+
+		fs::remove_dir_all(&base_path).unwrap();
+	}
+
+	#[test]
+	fn subscriptions_keep_the_latest_notifications() {
+		let mut subscription = Subscription {
+			kind: SubscriptionKind::NewHeads,
+			notifications: Default::default(),
+			last_polled: 0,
+		};
+		for number in 0..MAX_NOTIFICATIONS + 2 {
+			subscription.push(number.into());
+		}
+		assert_eq!(subscription.notifications.len(), MAX_NOTIFICATIONS);
+		assert_eq!(subscription.notifications.front(), Some(&2u32.into()));
+	}
+
+	#[test]
+	fn idle_subscriptions_are_evicted() {
+		let base_path = base_path("subscriptions");
+		let mut handler = RpcHandler::new(Node::init(&base_path, GenesisConfig::dev()).unwrap());
+		let first = call(&mut handler, "chain_subscribeNewHeads", "[]");
+		let second = call(&mut handler, "chain_subscribeNewHeads", "[]");
+		for _ in 2..MAX_SUBSCRIPTIONS {
+			call(&mut handler, "chain_subscribeNewHeads", "[]");
+		}
+		call(&mut handler, "subscription_poll", &format!("[{}]", first));
+
+		// The second subscription was polled the longest ago, so it makes room for a new one.
+		call(&mut handler, "state_subscribeEvents", "[]");
+		assert_eq!(handler.subscriptions.len(), MAX_SUBSCRIPTIONS);
+		assert_eq!(
+			call(&mut handler, "subscription_poll", &format!("[{}]", first)),
+			Value::Array(vec![])
+		);
+		let error = call(&mut handler, "subscription_poll", &format!("[{}]", second));
+		assert_eq!(error.get("code"), Some(&(-32602i64).into()));
+
+		fs::remove_dir_all(&base_path).unwrap();
+	}
+
+	#[test]
+	fn invalid_requests_are_rejected() {
+		let base_path = base_path("errors");
+		let mut handler = RpcHandler::new(Node::init(&base_path, GenesisConfig::dev()).unwrap());
+		let code = |error: &Value| error.get("code").cloned().unwrap();
+
+		assert_eq!(code(&call(&mut handler, "chain_getThing", "[]")), (-32601i64).into());
+		assert_eq!(code(&call(&mut handler, "state_getBalance", "[1]")), (-32602i64).into());
+		assert_eq!(code(&call(&mut handler, "subscription_poll", "[5]")), (-32602i64).into());
+		assert_eq!(
+			code(&call(&mut handler, "author_submitExtrinsic", r#"["0x00"]"#)),
+			1010u32.into()
+		);
+
+		let transfer: types::Extrinsic = support::Extrinsic {
+			caller: "root".to_string(),
+			nonce: 0,
+			tip: 0,
+			call: RuntimeCall::balances(balances::Call::transfer {
+				to: "bob".to_string(),
+				amount: 1,
+			}),
+		};
+		let error =
+			call(&mut handler, "author_submitExtrinsic", &format!("[{}]", hex(&transfer.encode())));
+		assert_eq!(error.get("message"), Some(&"transaction refused: Invalid(BadSigner)".into()));
+
+		let response = Value::parse(&handler.handle("{")).unwrap();
+		assert_eq!(response.get("error").map(code), Some((-32700i64).into()));
+		fs::remove_dir_all(&base_path).unwrap();
+	}
+
+	#[test]
+	fn server_answers_over_http() {
+		let base_path = base_path("server");
+		// The state of a node is not shared between threads, so the server is made by the thread
+		// which runs it.
+		let (sender, receiver) = std::sync::mpsc::channel();
+		let node_path = base_path.clone();
+		std::thread::spawn(move || {
+			let server =
+				RpcServer::bind(Node::init(&node_path, GenesisConfig::dev()).unwrap(), 0).unwrap();
+			sender.send(server.local_addr().unwrap()).unwrap();
+			server.run()
+		});
+		let address = receiver.recv().unwrap();
+		assert!(address.ip().is_loopback());
+
+		let request = |request: &str| {
+			let mut stream = TcpStream::connect(address).unwrap();
+			stream.write_all(request.as_bytes()).unwrap();
+			let mut response = String::new();
+			stream.read_to_string(&mut response).unwrap();
+			response
+		};
+		let body = r#"{"jsonrpc":"2.0","id":1,"method":"state_getBalance","params":["bob"]}"#;
+		let response = request(&format!(
+			"POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{}",
+			body.len(),
+			body
+		));
+		assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+		assert!(response.ends_with(r#"{"jsonrpc":"2.0","id":1,"result":1000}"#));
+
+		let response = request("GET / HTTP/1.1\r\nHost: localhost\r\n\r\n");
+		assert!(response.starts_with("HTTP/1.1 405 Method Not Allowed\r\n"));
+		fs::remove_dir_all(&base_path).unwrap();
+	}
+}
@@ -0,0 +1,153 @@
+use crate::{
+	support::{self, CheckSignature, GetDispatchInfo, TransactionValidityError, ValidTransaction},
+	system, types, Runtime,
+};
+
+/// The number of blocks for which a transaction stays valid after it is validated.
+pub const TRANSACTION_LONGEVITY: u64 = 64;
+
+impl Runtime {
+	/// Check whether `extrinsic` could be included in a block on top of the current state, without
+	/// modifying the state.
+	///
+	/// An extrinsic whose nonce is ahead of the nonce of its caller is valid, but it requires the
+	/// extrinsics with the previous nonces of the caller to be included first. Otherwise, its call
+	/// is also executed, and it is invalid if the call fails. The changes it makes to the state are
+	/// rolled back.
+	pub fn validate_transaction(
+		&self,
+		extrinsic: &types::Extrinsic,
+	) -> Result<ValidTransaction<types::Tag>, TransactionValidityError> {
+		let support::Extrinsic { caller, nonce, tip, call } = extrinsic;
+		if !Runtime::check_signature(caller) {
+			return Err(TransactionValidityError::BadSigner);
+		}
+		let current_nonce = self.system.nonce(caller);
+		if *nonce < current_nonce {
+			return Err(TransactionValidityError::Stale);
+		}
+		if call.weight() > <Runtime as system::Config>::MAXIMUM_BLOCK_WEIGHT {
+			return Err(TransactionValidityError::ExhaustsResources);
+		}
+		let fee = support::TransactionPayment::compute_fee(self, call, *tip);
+		if fee > self.balances.balance(caller).saturating_sub(self.balances.locked(caller)) {
+			return Err(TransactionValidityError::Payment);
+		}
+
+		let requires = if *nonce > current_nonce {
+			vec![(caller.clone(), nonce - 1)]
+		} else {
+			// The state the call is executed on is only known for the next nonce of the caller. The
+			// call is executed through another runtime bound to the same backend, whose changes are
+			// undone.
+			let backend = self.system.storage_backend();
+			backend
+				.rollback(|| Runtime::new_in(backend).apply_extrinsic(extrinsic.clone()))?
+				.result
+				.map_err(TransactionValidityError::Call)?;
+			Vec::new()
+		};
+
+		Ok(ValidTransaction {
+			priority: u64::try_from(*tip).unwrap_or(u64::MAX),
+			requires,
+			provides: vec![(caller.clone(), *nonce)],
+			longevity: TRANSACTION_LONGEVITY,
+		})
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{
+		balances,
+		support::{self, TransactionValidityError, ValidTransaction},
+		tx_pause, types, Runtime, RuntimeCall, RuntimeError,
+	};
+
+	fn transfer(
+		caller: &str,
+		nonce: types::Nonce,
+		tip: types::Balance,
+		amount: types::Balance,
+	) -> types::Extrinsic {
+		support::Extrinsic {
+			caller: caller.to_string(),
+			nonce,
+			tip,
+			call: RuntimeCall::balances(balances::Call::transfer { to: "bob".to_string(), amount }),
+		}
+	}
+
+	#[test]
+	fn validate_transaction() {
+		let mut runtime = Runtime::new();
+		runtime.balances.set_balance(&"alice".to_string(), 100);
+		runtime.system.inc_nonce(&"alice".to_string());
+		let state_root = support::hash_of(&runtime);
+
+		assert_eq!(
+			runtime.validate_transaction(&transfer("alice", 1, 5, 10)),
+			Ok(ValidTransaction {
+				priority: 5,
+				requires: vec![],
+				provides: vec![("alice".to_string(), 1)],
+				longevity: super::TRANSACTION_LONGEVITY,
+			})
+		);
+		assert_eq!(
+			runtime
+				.validate_transaction(&transfer("alice", 3, 0, 10))
+				.map(|valid| valid.requires),
+			Ok(vec![("alice".to_string(), 2)])
+		);
+		assert_eq!(
+			runtime.validate_transaction(&transfer("root", 0, 0, 10)),
+			Err(TransactionValidityError::BadSigner)
+		);
+		assert_eq!(
+			runtime.validate_transaction(&transfer("alice", 0, 0, 10)),
+			Err(TransactionValidityError::Stale)
+		);
+		assert_eq!(
+			runtime.validate_transaction(&transfer("alice", 1, 100, 10)),
+			Err(TransactionValidityError::Payment)
+		);
+		// Errors are identified by the index of their pallet, and their index in the pallet.
+		let insufficient_balance = support::DispatchError::Module(support::ModuleError {
+			index: 0,
+			error: 1,
+			message: "InsufficientBalance",
+		});
+		assert_eq!(
+			runtime.validate_transaction(&transfer("alice", 1, 0, 1_000)),
+			Err(TransactionValidityError::Call(insufficient_balance))
+		);
+		assert_eq!(
+			RuntimeError::from_dispatch_error(&insufficient_balance),
+			Some(RuntimeError::balances(balances::Error::InsufficientBalance))
+		);
+		// The changes of the calls executed to validate them are rolled back.
+		assert_eq!(support::hash_of(&runtime), state_root);
+
+		// Paused calls are rejected before they reach their pallet.
+		let pause = RuntimeCall::tx_pause(tx_pause::Call::pause {
+			pallet: "balances".to_string(),
+			call: Some("transfer".to_string()),
+		});
+		assert_eq!(
+			support::Dispatch::dispatch(&mut runtime, crate::ROOT.to_string(), pause)
+				.map_err(|e| e.error),
+			Ok(Default::default())
+		);
+		assert_eq!(
+			runtime.validate_transaction(&transfer("alice", 1, 0, 10)),
+			Err(TransactionValidityError::Call(support::DispatchError::CallFiltered))
+		);
+		runtime.tx_pause = tx_pause::Pallet::new();
+
+		// Nothing was modified.
+		assert_eq!(runtime.balances.balance(&"alice".to_string()), 100);
+		assert_eq!(runtime.system.nonce(&"alice".to_string()), 1);
+	}
+}
@@ -0,0 +1,497 @@
+use core::fmt::Debug;
+use num::traits::{CheckedAdd, Zero};
+
+use crate::{
+	codec::{Decode, Encode},
+	storage::StorageMap,
+	support::{DispatchResult, GetDispatchInfo, Weight, DEFAULT_WEIGHT},
+	system,
+};
+
+/// The priority of a scheduled task. Tasks with a lower value are dispatched first.
+pub type Priority = u8;
+
+/// The period of a recurring task: the number of blocks between two executions, and the total
+/// number of times the task is executed.
+pub type Period<BlockNumber> = (BlockNumber, u32);
+
+pub trait Config:
+	system::Config<AccountId: Debug, BlockNumber: Debug>
+	+ Sized
+	+ AsRef<system::Pallet<Self>>
+	+ AsMut<Pallet<Self>>
+{
+	/// The aggregated call type of the runtime, which scheduled tasks dispatch.
+	type RuntimeCall: GetDispatchInfo + Clone + Debug;
+	/// The type used to name a task, so that it can be cancelled without knowing where it is
+	/// scheduled.
+	type TaskName: Debug + Encode + Ord + Clone;
+	/// The maximum total weight of the scheduled calls dispatched in a single block. Due calls
+	/// which do not fit are carried over to the following blocks.
+	const MAXIMUM_WEIGHT: Weight;
+}
+
+/// The weight of scheduling `call`, which pays in advance for every time it is dispatched, since
+/// the block which dispatches it does not charge anyone.
+fn schedule_weight<T: Config>(
+	maybe_periodic: &Option<Period<T::BlockNumber>>,
+	call: &T::RuntimeCall,
+) -> Weight {
+	let count = maybe_periodic.map_or(1, |(_, count)| count);
+	call.weight().saturating_mul(count as Weight).saturating_add(DEFAULT_WEIGHT)
+}
+
+/// A call scheduled to be dispatched at some future block.
+#[derive(Debug)]
+pub struct Scheduled<T: Config> {
+	/// The name of the task, if it was scheduled with `schedule_named`.
+	maybe_id: Option<T::TaskName>,
+	/// The priority of the task among the other tasks due in the same block.
+	priority: Priority,
+	/// The account which scheduled the task. The call is dispatched on behalf of this account.
+	origin: T::AccountId,
+	/// The call to dispatch.
+	call: T::RuntimeCall,
+	/// If the task is recurring, when and how many times it should be dispatched again.
+	maybe_periodic: Option<Period<T::BlockNumber>>,
+}
+
+// Deriving `Clone` would require the runtime itself to be `Clone`, rather than only its types.
+impl<T: Config> Clone for Scheduled<T> {
+	fn clone(&self) -> Self {
+		Scheduled {
+			maybe_id: self.maybe_id.clone(),
+			priority: self.priority,
+			origin: self.origin.clone(),
+			call: self.call.clone(),
+			maybe_periodic: self.maybe_periodic,
+		}
+	}
+}
+
+impl<T: Config> Encode for Scheduled<T>
+where
+	T::RuntimeCall: Encode,
+{
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.maybe_id.encode_to(dest);
+		self.priority.encode_to(dest);
+		self.origin.encode_to(dest);
+		self.call.encode_to(dest);
+		self.maybe_periodic.encode_to(dest);
+	}
+}
+
+impl<T: Config> Decode for Scheduled<T>
+where
+	T::TaskName: Decode,
+	T::AccountId: Decode,
+	T::RuntimeCall: Decode,
+	T::BlockNumber: Decode,
+{
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(Scheduled {
+			maybe_id: Option::decode(input)?,
+			priority: Priority::decode(input)?,
+			origin: T::AccountId::decode(input)?,
+			call: T::RuntimeCall::decode(input)?,
+			maybe_periodic: Option::decode(input)?,
+		})
+	}
+}
+
+/// This is the Scheduler Module.
+/// It allows accounts to schedule calls to be dispatched at a future block, once or periodically.
+#[macros::storage]
+#[derive(Debug, macros::PalletInfo)]
+pub struct Pallet<T: Config> {
+	/// The tasks scheduled for each block. Dispatched or cancelled tasks are replaced by `None`, so
+	/// the index of a task in its agenda never changes.
+	agenda: StorageMap<T::BlockNumber, Vec<Option<Scheduled<T>>>>,
+	/// The location `(block, index)` in `agenda` of each named task.
+	#[getter(lookup)]
+	lookup: StorageMap<T::TaskName, (T::BlockNumber, u32)>,
+}
+
+/// The events of this pallet.
+#[macros::event]
+pub enum Event<T: Config> {
+	/// A call scheduled by `origin` was dispatched on its behalf.
+	Dispatched { origin: T::AccountId, result: DispatchResult },
+}
+
+/// The errors of this pallet.
+#[macros::error]
+pub enum Error<T: Config> {
+	/// The call is too heavy to be dispatched within a block.
+	TooHeavy,
+	/// The period of a periodic task is invalid.
+	InvalidPeriod,
+	/// A task with this name is already scheduled.
+	NameAlreadyScheduled,
+	/// The task does not exist.
+	NotFound,
+	/// The caller did not schedule this task.
+	NotTaskOwner,
+	/// The task is scheduled at a block which is not after the current block.
+	TargetBlockNumberInPast,
+}
+
+impl<T: Config> Pallet<T> {
+	/// Take all the calls which are due at block `now`, in the order they should be dispatched.
+	///
+	/// Tasks are taken block by block, and by priority within a block, until the next task would
+	/// exceed `T::MAXIMUM_WEIGHT`. The remaining tasks stay in the agenda, and are taken first at
+	/// the next block. Periodic tasks are scheduled again relative to the block they were due at, so
+	/// a task which is carried over does not drift.
+	pub fn take_due(&mut self, now: T::BlockNumber) -> Vec<(T::AccountId, T::RuntimeCall)> {
+		let mut weight: Weight = 0;
+		let mut due = Vec::new();
+		let mut rescheduled = Vec::new();
+
+		'agendas: for (when, mut agenda) in self.agenda.range(..=now) {
+			let mut order = agenda
+				.iter()
+				.enumerate()
+				.filter_map(|(index, task)| task.as_ref().map(|task| (task.priority, index)))
+				.collect::<Vec<_>>();
+			order.sort();
+
+			for (_, index) in order {
+				let task_weight = agenda[index].as_ref().map_or(0, |task| task.call.weight());
+				if weight + task_weight > T::MAXIMUM_WEIGHT {
+					self.agenda.insert(when, agenda);
+					break 'agendas
+				}
+				weight += task_weight;
+
+				let Some(mut task) = agenda[index].take() else { continue };
+				if let Some(id) = &task.maybe_id {
+					self.lookup.remove(id);
+				}
+				due.push((task.origin.clone(), task.call.clone()));
+
+				if let Some((period, count)) = task.maybe_periodic &&
+					count > 1 && let Some(next) = when.checked_add(&period)
+				{
+					task.maybe_periodic = Some((period, count - 1));
+					rescheduled.push((next, task));
+				}
+			}
+			self.agenda.insert(when, agenda);
+		}
+
+		self.agenda.retain(|_, agenda| agenda.iter().any(Option::is_some));
+		for (when, task) in rescheduled {
+			self.place(when, task);
+		}
+
+		due
+	}
+
+	/// Add a task to the agenda of block `when`, and record where it is if it is named.
+	fn place(&mut self, when: T::BlockNumber, task: Scheduled<T>) {
+		let maybe_id = task.maybe_id.clone();
+		let index = self.agenda.mutate(when, |agenda| {
+			let agenda = agenda.get_or_insert_with(Vec::new);
+			agenda.push(Some(task));
+			agenda.len() as u32 - 1
+		});
+		if let Some(id) = maybe_id {
+			self.lookup.insert(id, (when, index));
+		}
+	}
+
+	/// Check that a task can be scheduled, and schedule it.
+	///
+	/// The call must fit within a block, since the calls due are dispatched when a block is
+	/// initialized, whatever the weight left in the block.
+	fn do_schedule(
+		runtime: &mut T,
+		maybe_id: Option<T::TaskName>,
+		origin: T::AccountId,
+		when: T::BlockNumber,
+		maybe_periodic: Option<Period<T::BlockNumber>>,
+		priority: Priority,
+		call: T::RuntimeCall,
+	) -> DispatchResult {
+		let now = AsRef::<system::Pallet<T>>::as_ref(runtime).block_number();
+		if when <= now {
+			return Err(Error::<T>::TargetBlockNumberInPast.into());
+		}
+		if call.weight() > T::MAXIMUM_WEIGHT.min(T::MAXIMUM_BLOCK_WEIGHT) {
+			return Err(Error::<T>::TooHeavy.into());
+		}
+		if let Some((period, count)) = maybe_periodic && (period.is_zero() || count == 0) {
+			return Err(Error::<T>::InvalidPeriod.into());
+		}
+		let scheduler: &mut Pallet<T> = runtime.as_mut();
+		if let Some(id) = &maybe_id && scheduler.lookup.contains_key(id) {
+			return Err(Error::<T>::NameAlreadyScheduled.into());
+		}
+
+		scheduler.place(when, Scheduled { maybe_id, priority, origin, call, maybe_periodic });
+		Ok(())
+	}
+
+	/// Remove a task from the agenda, checking that it was scheduled by `caller`.
+	fn do_cancel(
+		&mut self,
+		caller: &T::AccountId,
+		when: T::BlockNumber,
+		index: u32,
+	) -> DispatchResult {
+		let mut agenda = self.agenda.get(&when).ok_or(Error::<T>::NotFound)?;
+		let slot = agenda.get_mut(index as usize).ok_or(Error::<T>::NotFound)?;
+		match slot {
+			Some(task) if task.origin != *caller => return Err(Error::<T>::NotTaskOwner.into()),
+			Some(_) => {},
+			None => return Err(Error::<T>::NotFound.into()),
+		}
+
+		if let Some(id) = slot.take().and_then(|task| task.maybe_id) {
+			self.lookup.remove(&id);
+		}
+		if agenda.iter().all(Option::is_none) {
+			self.agenda.remove(&when);
+		} else {
+			self.agenda.insert(when, agenda);
+		}
+		Ok(())
+	}
+}
+
+// The calls are boxed, since `Call` would otherwise contain itself through `T::RuntimeCall`.
+#[allow(clippy::boxed_local)]
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	/// Schedule `call` to be dispatched on behalf of the caller at block `when`, which must be after
+	/// the current block.
+	/// If `maybe_periodic` is set, the call is dispatched again every `period` blocks, `count`
+	/// times in total.
+	#[call_index(0)]
+	#[weight(schedule_weight::<T>(maybe_periodic, call))]
+	pub fn schedule(
+		runtime: &mut T,
+		caller: T::AccountId,
+		when: T::BlockNumber,
+		maybe_periodic: Option<Period<T::BlockNumber>>,
+		priority: Priority,
+		call: Box<T::RuntimeCall>,
+	) -> DispatchResult {
+		Self::do_schedule(runtime, None, caller, when, maybe_periodic, priority, *call)
+	}
+
+	/// Cancel the task at position `index` of the agenda of block `when`.
+	/// Only the account which scheduled the task can cancel it.
+	#[call_index(1)]
+	pub fn cancel(&mut self, caller: T::AccountId, when: T::BlockNumber, index: u32) -> DispatchResult {
+		self.do_cancel(&caller, when, index)
+	}
+
+	/// Same as `schedule`, but the task is named `id` so it can be cancelled with `cancel_named`.
+	#[call_index(2)]
+	#[weight(schedule_weight::<T>(maybe_periodic, call))]
+	pub fn schedule_named(
+		runtime: &mut T,
+		caller: T::AccountId,
+		id: T::TaskName,
+		when: T::BlockNumber,
+		maybe_periodic: Option<Period<T::BlockNumber>>,
+		priority: Priority,
+		call: Box<T::RuntimeCall>,
+	) -> DispatchResult {
+		Self::do_schedule(runtime, Some(id), caller, when, maybe_periodic, priority, *call)
+	}
+
+	/// Cancel the task named `id`.
+	/// Only the account which scheduled the task can cancel it.
+	#[call_index(3)]
+	pub fn cancel_named(&mut self, caller: T::AccountId, id: T::TaskName) -> DispatchResult {
+		let (when, index) = self.lookup(&id).ok_or(Error::<T>::NotFound)?;
+		self.do_cancel(&caller, when, index)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{
+		support::{GetDispatchInfo, Weight},
+		system,
+	};
+
+	#[derive(Clone, Debug, PartialEq)]
+	enum TestCall {
+		Light(u32),
+		Heavy(u32),
+		Huge,
+	}
+
+	impl GetDispatchInfo for TestCall {
+		fn weight(&self) -> Weight {
+			match self {
+				TestCall::Light(_) => 10,
+				TestCall::Heavy(_) => 60,
+				TestCall::Huge => 1_000,
+			}
+		}
+	}
+
+	struct TestRuntime {
+		system: system::Pallet<Self>,
+		scheduler: super::Pallet<Self>,
+	}
+
+	impl system::Config for TestRuntime {
+		type AccountId = &'static str;
+		type BlockNumber = u32;
+		type Nonce = u32;
+		type RuntimeEvent = ();
+		type PalletIndex = ();
+		const MAXIMUM_BLOCK_WEIGHT: crate::support::Weight = u64::MAX;
+		const MAXIMUM_BLOCK_LENGTH: u32 = u32::MAX;
+		const SPEC_VERSION: u32 = 1;
+	}
+
+	impl super::Config for TestRuntime {
+		type RuntimeCall = TestCall;
+		type TaskName = &'static str;
+		const MAXIMUM_WEIGHT: Weight = 100;
+	}
+
+	impl AsRef<system::Pallet<Self>> for TestRuntime {
+		fn as_ref(&self) -> &system::Pallet<Self> {
+			&self.system
+		}
+	}
+
+	impl AsMut<super::Pallet<Self>> for TestRuntime {
+		fn as_mut(&mut self) -> &mut super::Pallet<Self> {
+			&mut self.scheduler
+		}
+	}
+
+	fn new_runtime() -> TestRuntime {
+		TestRuntime { system: system::Pallet::new(), scheduler: super::Pallet::new() }
+	}
+
+	/// Schedule `call` on behalf of `who` at block `when`.
+	fn schedule(
+		runtime: &mut TestRuntime,
+		who: &'static str,
+		when: u32,
+		maybe_periodic: Option<super::Period<u32>>,
+		priority: super::Priority,
+		call: TestCall,
+	) -> crate::support::DispatchResult {
+		super::Pallet::schedule(runtime, who, when, maybe_periodic, priority, Box::new(call))
+	}
+
+	#[test]
+	fn dispatches_at_scheduled_block() {
+		let mut runtime = new_runtime();
+		assert_eq!(schedule(&mut runtime, "alice", 3, None, 0, TestCall::Light(1)), Ok(()));
+
+		assert_eq!(runtime.scheduler.take_due(1), vec![]);
+		assert_eq!(runtime.scheduler.take_due(2), vec![]);
+		assert_eq!(runtime.scheduler.take_due(3), vec![("alice", TestCall::Light(1))]);
+		assert_eq!(runtime.scheduler.take_due(4), vec![]);
+
+		// Tasks can only be scheduled after the current block.
+		runtime.system.inc_block_number();
+		assert_eq!(
+			schedule(&mut runtime, "alice", 1, None, 0, TestCall::Light(2)),
+			Err(super::Error::<TestRuntime>::TargetBlockNumberInPast.into())
+		);
+		assert_eq!(schedule(&mut runtime, "alice", 2, None, 0, TestCall::Light(2)), Ok(()));
+	}
+
+	#[test]
+	fn orders_by_priority_and_carries_over_weight() {
+		let mut runtime = new_runtime();
+		let _res = schedule(&mut runtime, "alice", 1, None, 5, TestCall::Heavy(1));
+		let _res = schedule(&mut runtime, "bob", 1, None, 0, TestCall::Heavy(2));
+		let _res = schedule(&mut runtime, "alice", 1, None, 10, TestCall::Light(3));
+		let _res = schedule(&mut runtime, "bob", 2, None, 0, TestCall::Light(4));
+
+		// Only one heavy call fits in a block.
+		assert_eq!(runtime.scheduler.take_due(1), vec![("bob", TestCall::Heavy(2))]);
+		// Calls carried over from block 1 come before the calls of block 2.
+		assert_eq!(
+			runtime.scheduler.take_due(2),
+			vec![
+				("alice", TestCall::Heavy(1)),
+				("alice", TestCall::Light(3)),
+				("bob", TestCall::Light(4))
+			]
+		);
+		assert_eq!(runtime.scheduler.take_due(3), vec![]);
+
+		// Scheduling a call pays for every time it is dispatched.
+		let call = super::Call::<TestRuntime>::schedule {
+			when: 5,
+			maybe_periodic: Some((1, 3)),
+			priority: 0,
+			call: Box::new(TestCall::Heavy(5)),
+		};
+		assert_eq!(call.weight(), 3 * 60 + crate::support::DEFAULT_WEIGHT);
+
+		// A call which can never fit in a block is rejected.
+		assert_eq!(
+			schedule(&mut runtime, "alice", 5, None, 0, TestCall::Huge),
+			Err(super::Error::<TestRuntime>::TooHeavy.into())
+		);
+	}
+
+	#[test]
+	fn periodic_tasks() {
+		let mut runtime = new_runtime();
+		assert_eq!(
+			schedule(&mut runtime, "alice", 1, Some((0, 3)), 0, TestCall::Light(1)),
+			Err(super::Error::<TestRuntime>::InvalidPeriod.into())
+		);
+		assert_eq!(schedule(&mut runtime, "alice", 1, Some((2, 3)), 0, TestCall::Light(1)), Ok(()));
+
+		let dispatched =
+			(1..=8).filter(|n| !runtime.scheduler.take_due(*n).is_empty()).collect::<Vec<_>>();
+		assert_eq!(dispatched, vec![1, 3, 5]);
+
+		// A periodic task carried over to the next block keeps its period from the block it was due.
+		let _res = schedule(&mut runtime, "alice", 10, None, 0, TestCall::Heavy(1));
+		let _res = schedule(&mut runtime, "bob", 10, Some((3, 2)), 1, TestCall::Heavy(2));
+		assert_eq!(runtime.scheduler.take_due(10), vec![("alice", TestCall::Heavy(1))]);
+		assert_eq!(runtime.scheduler.take_due(11), vec![("bob", TestCall::Heavy(2))]);
+		assert_eq!(runtime.scheduler.take_due(12), vec![]);
+		assert_eq!(runtime.scheduler.take_due(13), vec![("bob", TestCall::Heavy(2))]);
+	}
+
+	#[test]
+	fn cancel_tasks() {
+		let mut runtime = new_runtime();
+		let _res = schedule(&mut runtime, "alice", 2, None, 0, TestCall::Light(1));
+		let _res = schedule(&mut runtime, "alice", 2, None, 0, TestCall::Light(2));
+
+		let scheduler = &mut runtime.scheduler;
+		assert_eq!(scheduler.cancel("bob", 2, 0), Err(super::Error::<TestRuntime>::NotTaskOwner.into()));
+		assert_eq!(scheduler.cancel("alice", 2, 5), Err(super::Error::<TestRuntime>::NotFound.into()));
+		assert_eq!(scheduler.cancel("alice", 2, 0), Ok(()));
+		assert_eq!(scheduler.cancel("alice", 2, 0), Err(super::Error::<TestRuntime>::NotFound.into()));
+
+		let call = Box::new(TestCall::Light(3));
+		assert_eq!(
+			super::Pallet::schedule_named(&mut runtime, "bob", "payout", 2, None, 0, call.clone()),
+			Ok(())
+		);
+		assert_eq!(
+			super::Pallet::schedule_named(&mut runtime, "bob", "payout", 3, None, 0, call),
+			Err(super::Error::<TestRuntime>::NameAlreadyScheduled.into())
+		);
+		let scheduler = &mut runtime.scheduler;
+		assert_eq!(scheduler.lookup(&"payout"), Some((2, 2)));
+		assert_eq!(scheduler.cancel_named("alice", "payout"), Err(super::Error::<TestRuntime>::NotTaskOwner.into()));
+		assert_eq!(scheduler.cancel_named("bob", "payout"), Ok(()));
+		assert_eq!(scheduler.lookup(&"payout"), None);
+
+		assert_eq!(scheduler.take_due(2), vec![("alice", TestCall::Light(2))]);
+	}
+}
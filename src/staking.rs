@@ -0,0 +1,747 @@
+use num::traits::{CheckedAdd, CheckedDiv, CheckedMul, CheckedSub, Zero};
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{
+	balances::{self, LockIdentifier},
+	codec::{Decode, Encode},
+	storage::{StorageMap, StorageValue},
+	support::{
+		DispatchError, DispatchResult, DispatchResultWithPostInfo, EnsureOrigin, PostDispatchInfo, Weight,
+		DEFAULT_WEIGHT,
+	},
+	system,
+};
+
+/// The identifier of the lock this pallet keeps on bonded funds.
+const STAKING_ID: LockIdentifier = *b"staking ";
+
+/// The weight of nominating, for each target.
+const NOMINATION_WEIGHT: Weight = DEFAULT_WEIGHT;
+
+/// The index of an era.
+pub type EraIndex = u32;
+
+/// The inflation curve of the chain: how much is paid out to stakers at the end of an era.
+pub trait EraPayout<Balance> {
+	/// The total reward for an era in which `total_staked` was exposed by the elected validators.
+	fn era_payout(total_staked: Balance) -> Balance;
+}
+
+/// `amount * numerator / denominator`, rounded down, where `numerator <= denominator`. When the
+/// product overflows, the fraction is first turned into parts per billion, and the result is exact
+/// to a billionth of `amount`. Returns `None` if `denominator` is zero.
+fn multiply_by_rational<Balance>(amount: Balance, numerator: Balance, denominator: Balance) -> Option<Balance>
+where
+	Balance: CheckedMul + CheckedDiv + CheckedSub + CheckedAdd + From<u32>,
+{
+	if let Some(product) = amount.checked_mul(&numerator) {
+		return product.checked_div(&denominator);
+	}
+	let billion = Balance::from(1_000_000_000);
+	let parts = match numerator.checked_mul(&billion) {
+		Some(numerator) => numerator.checked_div(&denominator)?,
+		// The denominator is at least as large as the numerator, so it is above a billion here.
+		None => numerator.checked_div(&denominator.checked_div(&billion)?)?,
+	};
+	// `amount` is split as `high * billion + low`, so that neither product overflows.
+	let high = amount.checked_div(&billion)?;
+	let low = amount.checked_sub(&high.checked_mul(&billion)?)?;
+	high.checked_mul(&parts)?.checked_add(&low.checked_mul(&parts)?.checked_div(&billion)?)
+}
+
+pub trait Config:
+	balances::Config<
+		Balance: CheckedMul + CheckedDiv + From<u32>,
+		RuntimeEvent: From<Event<Self>>,
+	>
+	+ Sized
+	+ AsRef<system::Pallet<Self>>
+	+ AsMut<balances::Pallet<Self>>
+	+ AsMut<Pallet<Self>>
+{
+	/// The number of blocks unbonded funds stay locked before they can be withdrawn.
+	const BONDING_DURATION: Self::BlockNumber;
+	/// The number of blocks in an era.
+	const ERA_LENGTH: Self::BlockNumber;
+	/// The number of validators elected at the start of each era.
+	const VALIDATOR_COUNT: u32;
+	/// The maximum number of validators a nominator can nominate.
+	const MAX_NOMINATIONS: u32;
+	/// The inflation curve used to reward stakers at the end of each era.
+	type EraPayout: EraPayout<Self::Balance>;
+	/// The origin allowed to slash validators and their nominators.
+	type SlashOrigin: EnsureOrigin<Self::AccountId>;
+}
+
+/// Funds which were unbonded, and can be withdrawn from block `unlock_at`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnlockChunk<Balance, BlockNumber> {
+	pub value: Balance,
+	pub unlock_at: BlockNumber,
+}
+
+impl<Balance: Encode, BlockNumber: Encode> Encode for UnlockChunk<Balance, BlockNumber> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.value.encode_to(dest);
+		self.unlock_at.encode_to(dest);
+	}
+}
+
+impl<Balance: Decode, BlockNumber: Decode> Decode for UnlockChunk<Balance, BlockNumber> {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(UnlockChunk { value: Balance::decode(input)?, unlock_at: BlockNumber::decode(input)? })
+	}
+}
+
+/// The bonded funds of a staker.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StakingLedger<Balance, BlockNumber> {
+	/// The funds at stake, which count towards elections and rewards.
+	pub active: Balance,
+	/// The funds being unbonded, which stay locked until they can be withdrawn.
+	pub unlocking: Vec<UnlockChunk<Balance, BlockNumber>>,
+}
+
+impl<Balance: CheckedAdd + Copy, BlockNumber> StakingLedger<Balance, BlockNumber> {
+	/// All the funds of the ledger, active or unlocking. They are all locked.
+	pub fn total(&self) -> Balance {
+		// This cannot overflow, since it is at most the balance of the staker.
+		self.unlocking
+			.iter()
+			.fold(self.active, |total, chunk| total.checked_add(&chunk.value).unwrap_or(total))
+	}
+}
+
+impl<Balance: Encode, BlockNumber: Encode> Encode for StakingLedger<Balance, BlockNumber> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.active.encode_to(dest);
+		self.unlocking.encode_to(dest);
+	}
+}
+
+impl<Balance: Decode, BlockNumber: Decode> Decode for StakingLedger<Balance, BlockNumber> {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(StakingLedger { active: Balance::decode(input)?, unlocking: Vec::decode(input)? })
+	}
+}
+
+/// The stake backing an elected validator during an era.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Exposure<AccountId, Balance> {
+	/// The total stake backing the validator.
+	pub total: Balance,
+	/// The stake of the validator itself.
+	pub own: Balance,
+	/// The stake of each nominator backing the validator.
+	pub others: Vec<(AccountId, Balance)>,
+}
+
+impl<AccountId: Encode, Balance: Encode> Encode for Exposure<AccountId, Balance> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.total.encode_to(dest);
+		self.own.encode_to(dest);
+		self.others.encode_to(dest);
+	}
+}
+
+impl<AccountId: Decode, Balance: Decode> Decode for Exposure<AccountId, Balance> {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(Exposure {
+			total: Balance::decode(input)?,
+			own: Balance::decode(input)?,
+			others: Vec::decode(input)?,
+		})
+	}
+}
+
+/// This is the Staking Module.
+/// It lets accounts bond funds to validate, or to nominate validators. Validators are elected at
+/// the start of each era, and stakers are rewarded at the end of the era.
+#[macros::storage]
+#[derive(Debug, macros::PalletInfo)]
+pub struct Pallet<T: Config> {
+	/// The bonded funds of each staker.
+	ledger: StorageMap<T::AccountId, StakingLedger<T::Balance, T::BlockNumber>>,
+	/// The stakers who want to be validators.
+	validators: StorageMap<T::AccountId, ()>,
+	/// The stakers who nominate validators, with the validators they nominate.
+	nominators: StorageMap<T::AccountId, Vec<T::AccountId>>,
+	/// The index of the current era.
+	#[getter(current_era)]
+	current_era: StorageValue<EraIndex>,
+	/// The block at which the next era starts.
+	#[default(T::ERA_LENGTH)]
+	next_era_start: StorageValue<T::BlockNumber>,
+	/// The validators elected for the current era, with the stake backing them.
+	exposures: StorageMap<T::AccountId, Exposure<T::AccountId, T::Balance>>,
+}
+
+/// The events of this pallet.
+#[macros::event]
+pub enum Event<T: Config> {
+	/// `amount` of the funds of `stash` were bonded.
+	Bonded { stash: T::AccountId, amount: T::Balance },
+	/// `amount` of the active funds of `stash` started unbonding.
+	Unbonded { stash: T::AccountId, amount: T::Balance },
+	/// `amount` of the funds of `stash` which finished unbonding were withdrawn.
+	Withdrawn { stash: T::AccountId, amount: T::Balance },
+	/// `amount` of the active funds of `staker` were slashed.
+	Slashed { staker: T::AccountId, amount: T::Balance },
+	/// `staker` was paid `amount` for the era which ended.
+	Rewarded { staker: T::AccountId, amount: T::Balance },
+	/// The reward of `staker` for the era which ended could not be paid.
+	RewardFailed { staker: T::AccountId, amount: T::Balance, error: DispatchError },
+}
+
+/// The errors of this pallet.
+#[macros::error]
+pub enum Error<T: Config> {
+	/// The validator is not elected.
+	NotElected,
+	/// The account does not have enough active bonded funds.
+	InsufficientBond,
+	/// The account is not bonded.
+	NotBonded,
+	/// Bonding zero funds is not allowed.
+	ZeroBond,
+	/// An arithmetic operation overflowed.
+	Overflow,
+	/// The account does not have enough funds.
+	InsufficientBalance,
+	/// There are no targets to nominate.
+	EmptyTargets,
+	/// There are too many targets to nominate.
+	TooManyTargets,
+	/// A target is not a validator.
+	NotValidator,
+	/// A target is nominated more than once.
+	DuplicateTarget,
+}
+
+impl<T: Config> Pallet<T> {
+	/// Get the bonded funds of `who`, if any.
+	pub fn ledger(&self, who: &T::AccountId) -> Option<StakingLedger<T::Balance, T::BlockNumber>> {
+		self.ledger.get(who)
+	}
+
+	/// Get the stake backing `validator`, if it is elected for the current era.
+	pub fn exposure(&self, validator: &T::AccountId) -> Option<Exposure<T::AccountId, T::Balance>> {
+		self.exposures.get(validator)
+	}
+
+	/// Get the validators elected for the current era.
+	pub fn elected(&self) -> Vec<T::AccountId> {
+		self.exposures.keys().collect()
+	}
+
+	/// Start a new era when `now` reaches the end of the current era: stakers are paid for the
+	/// era which ends, and a new set of validators is elected.
+	pub fn on_initialize(runtime: &mut T, now: T::BlockNumber) {
+		let staking: &mut Pallet<T> = runtime.as_mut();
+		if now < staking.next_era_start.get() {
+			return;
+		}
+
+		let rewards = staking.era_rewards();
+		staking.current_era.mutate(|era| *era += 1);
+		staking.next_era_start.put(now.checked_add(&T::ERA_LENGTH).unwrap_or(now));
+		staking.elect();
+
+		for (staker, amount) in rewards {
+			let balances: &mut balances::Pallet<T> = runtime.as_mut();
+			let event = match balances.mint(&staker, amount) {
+				Ok(()) => Event::Rewarded { staker, amount },
+				Err(error) => Event::RewardFailed { staker, amount, error },
+			};
+			AsMut::<Pallet<T>>::as_mut(runtime).deposit_event(event);
+		}
+	}
+
+	/// Burn up to `amount` of the active funds of `who`.
+	fn slash_staker(runtime: &mut T, who: &T::AccountId, amount: T::Balance) -> DispatchResult {
+		let staking: &mut Pallet<T> = runtime.as_mut();
+		let Some(mut ledger) = staking.ledger.get(who) else { return Ok(()) };
+		let amount = amount.min(ledger.active);
+		ledger.active = ledger.active.checked_sub(&amount).ok_or(Error::<T>::InsufficientBond)?;
+		staking.ledger.insert(who.clone(), ledger);
+
+		let balances: &mut balances::Pallet<T> = runtime.as_mut();
+		balances.burn(who, amount)?;
+		Self::update_lock(runtime, who);
+		let staking: &mut Pallet<T> = runtime.as_mut();
+		staking.deposit_event(Event::Slashed { staker: who.clone(), amount });
+		Ok(())
+	}
+
+	/// The rewards for the era which ends, based on the exposures of the elected validators. The
+	/// payout of `T::EraPayout` is shared between stakers, in proportion to their exposed stake.
+	fn era_rewards(&self) -> Vec<(T::AccountId, T::Balance)> {
+		let total_staked = self.exposures.values().fold(T::Balance::zero(), |total, exposure| {
+			total.checked_add(&exposure.total).unwrap_or(total)
+		});
+		if total_staked.is_zero() {
+			return Vec::new();
+		}
+		let payout = T::EraPayout::era_payout(total_staked);
+
+		self.exposures
+			.iter()
+			.flat_map(|(validator, exposure)| {
+				core::iter::once((validator, exposure.own)).chain(exposure.others)
+			})
+			.filter_map(|(who, stake)| Some((who, multiply_by_rational(payout, stake, total_staked)?)))
+			.collect()
+	}
+
+	/// Elect the validators of the new era.
+	///
+	/// Candidates are ranked by their approval stake: their own stake, plus the stake of every
+	/// nominator nominating them. The stake of a nominator is then split equally between the
+	/// elected validators it nominated.
+	fn elect(&mut self) {
+		let active = |who: &T::AccountId| {
+			self.ledger.get(who).map_or(T::Balance::zero(), |ledger| ledger.active)
+		};
+
+		let mut candidates = self
+			.validators
+			.keys()
+			.map(|validator| {
+				let approval = self
+					.nominators
+					.iter()
+					.filter(|(_, targets)| targets.contains(&validator))
+					.fold(active(&validator), |total, (nominator, _)| {
+						total.checked_add(&active(&nominator)).unwrap_or(total)
+					});
+				(approval, validator)
+			})
+			.collect::<Vec<_>>();
+		// Sort by approval stake, highest first.
+		candidates.sort_by_key(|(approval, _)| core::cmp::Reverse(*approval));
+
+		let mut exposures = candidates
+			.into_iter()
+			.take(T::VALIDATOR_COUNT as usize)
+			.map(|(_, validator)| {
+				let own = active(&validator);
+				(validator, Exposure { total: own, own, others: Vec::new() })
+			})
+			.collect::<BTreeMap<_, _>>();
+
+		for (nominator, targets) in self.nominators.iter() {
+			let elected_targets =
+				targets.iter().filter(|target| exposures.contains_key(*target)).collect::<Vec<_>>();
+			let Some(share) =
+				active(&nominator).checked_div(&T::Balance::from(elected_targets.len() as u32))
+			else {
+				continue
+			};
+			for target in elected_targets {
+				if let Some(exposure) = exposures.get_mut(target) {
+					exposure.total = exposure.total.checked_add(&share).unwrap_or(exposure.total);
+					exposure.others.push((nominator.clone(), share));
+				}
+			}
+		}
+
+		self.exposures.clear();
+		for (validator, exposure) in exposures {
+			self.exposures.insert(validator, exposure);
+		}
+	}
+
+	/// Lock all the funds in the ledger of `who`, or remove the lock if there are none.
+	fn update_lock(runtime: &mut T, who: &T::AccountId) {
+		let staking: &mut Pallet<T> = runtime.as_mut();
+		let total = staking.ledger.get(who).map_or(T::Balance::zero(), |ledger| ledger.total());
+
+		let balances: &mut balances::Pallet<T> = runtime.as_mut();
+		if total.is_zero() {
+			balances.remove_lock(STAKING_ID, who);
+		} else {
+			balances.set_lock(STAKING_ID, who, total);
+		}
+	}
+
+	/// Stop validating or nominating.
+	fn do_chill(&mut self, who: &T::AccountId) {
+		self.validators.remove(who);
+		self.nominators.remove(who);
+	}
+
+	/// Check that `who` has active bonded funds.
+	fn ensure_bonded(&self, who: &T::AccountId) -> DispatchResult {
+		match self.ledger.get(who) {
+			Some(ledger) if !ledger.active.is_zero() => Ok(()),
+			_ => Err(Error::<T>::NotBonded.into()),
+		}
+	}
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	/// Bond `value` of the caller's funds, adding to any funds already bonded.
+	#[call_index(0)]
+	pub fn bond(runtime: &mut T, caller: T::AccountId, value: T::Balance) -> DispatchResult {
+		if value.is_zero() {
+			return Err(Error::<T>::ZeroBond.into());
+		}
+		let staking: &mut Pallet<T> = runtime.as_mut();
+		let bonded = staking.ledger.get(&caller).map_or(T::Balance::zero(), |ledger| ledger.total());
+		let new_bonded = bonded.checked_add(&value).ok_or(Error::<T>::Overflow)?;
+
+		let balances: &mut balances::Pallet<T> = runtime.as_mut();
+		if new_bonded > balances.balance(&caller) {
+			return Err(Error::<T>::InsufficientBalance.into());
+		}
+
+		let staking: &mut Pallet<T> = runtime.as_mut();
+		let active = staking.ledger.get(&caller).map_or(T::Balance::zero(), |ledger| ledger.active);
+		let active = active.checked_add(&value).ok_or(Error::<T>::Overflow)?;
+		staking.ledger.mutate(caller.clone(), |ledger| {
+			ledger.get_or_insert_with(|| StakingLedger { active, unlocking: Vec::new() }).active = active;
+		});
+		staking.deposit_event(Event::Bonded { stash: caller.clone(), amount: value });
+		Self::update_lock(runtime, &caller);
+		Ok(())
+	}
+
+	/// Start unbonding `value` of the caller's active funds. They can be withdrawn with
+	/// `withdraw_unbonded` after `T::BONDING_DURATION` blocks. Unbonding all the active funds stops
+	/// validating or nominating.
+	#[call_index(1)]
+	pub fn unbond(runtime: &mut T, caller: T::AccountId, value: T::Balance) -> DispatchResult {
+		let now = AsRef::<system::Pallet<T>>::as_ref(runtime).block_number();
+		let unlock_at = now.checked_add(&T::BONDING_DURATION).ok_or(Error::<T>::Overflow)?;
+
+		let staking: &mut Pallet<T> = runtime.as_mut();
+		let mut ledger = staking.ledger.get(&caller).ok_or(Error::<T>::NotBonded)?;
+		ledger.active = ledger.active.checked_sub(&value).ok_or(Error::<T>::InsufficientBond)?;
+		ledger.unlocking.push(UnlockChunk { value, unlock_at });
+		if ledger.active.is_zero() {
+			staking.do_chill(&caller);
+		}
+		staking.ledger.insert(caller.clone(), ledger);
+		staking.deposit_event(Event::Unbonded { stash: caller, amount: value });
+		Ok(())
+	}
+
+	/// Withdraw the caller's funds which have finished unbonding.
+	#[call_index(2)]
+	pub fn withdraw_unbonded(runtime: &mut T, caller: T::AccountId) -> DispatchResult {
+		let now = AsRef::<system::Pallet<T>>::as_ref(runtime).block_number();
+
+		let staking: &mut Pallet<T> = runtime.as_mut();
+		let mut ledger = staking.ledger.get(&caller).ok_or(Error::<T>::NotBonded)?;
+		let bonded = ledger.total();
+		ledger.unlocking.retain(|chunk| chunk.unlock_at > now);
+		let amount = bonded.checked_sub(&ledger.total()).unwrap_or(T::Balance::zero());
+		if ledger.active.is_zero() && ledger.unlocking.is_empty() {
+			staking.ledger.remove(&caller);
+		} else {
+			staking.ledger.insert(caller.clone(), ledger);
+		}
+		staking.deposit_event(Event::Withdrawn { stash: caller.clone(), amount });
+		Self::update_lock(runtime, &caller);
+		Ok(())
+	}
+
+	/// Declare the wish to validate. Stops nominating.
+	#[call_index(3)]
+	pub fn validate(&mut self, caller: T::AccountId) -> DispatchResult {
+		self.ensure_bonded(&caller)?;
+		self.nominators.remove(&caller);
+		self.validators.insert(caller, ());
+		Ok(())
+	}
+
+	/// Declare the wish to nominate `targets`, which must want to validate. Stops validating.
+	///
+	/// The declared weight covers the maximum number of targets, but only the targets given are
+	/// actually consumed.
+	#[call_index(4)]
+	#[weight(NOMINATION_WEIGHT * T::MAX_NOMINATIONS as Weight)]
+	pub fn nominate(
+		&mut self,
+		caller: T::AccountId,
+		targets: Vec<T::AccountId>,
+	) -> DispatchResultWithPostInfo {
+		self.ensure_bonded(&caller)?;
+		if targets.is_empty() {
+			return Err(Error::<T>::EmptyTargets.into());
+		}
+		if targets.len() > T::MAX_NOMINATIONS as usize {
+			return Err(Error::<T>::TooManyTargets.into());
+		}
+		if targets.iter().any(|target| !self.validators.contains_key(target)) {
+			return Err(Error::<T>::NotValidator.into());
+		}
+		if targets.iter().collect::<BTreeSet<_>>().len() != targets.len() {
+			return Err(Error::<T>::DuplicateTarget.into());
+		}
+
+		let actual_weight = NOMINATION_WEIGHT * targets.len() as Weight;
+		self.validators.remove(&caller);
+		self.nominators.insert(caller, targets);
+		Ok(PostDispatchInfo { actual_weight: Some(actual_weight) })
+	}
+
+	/// Stop validating or nominating. The funds stay bonded.
+	#[call_index(5)]
+	pub fn chill(&mut self, caller: T::AccountId) -> DispatchResult {
+		self.do_chill(&caller);
+		Ok(())
+	}
+
+	/// Slash `percent` of the stake which `validator` and its nominators exposed in the current
+	/// era. The slashed funds are burned. Only `T::SlashOrigin` can slash.
+	#[call_index(6)]
+	pub fn slash(
+		runtime: &mut T,
+		caller: T::AccountId,
+		validator: T::AccountId,
+		percent: u8,
+	) -> DispatchResult {
+		T::SlashOrigin::ensure_origin(&caller)?;
+		let staking: &mut Pallet<T> = runtime.as_mut();
+		let exposure = staking.exposures.get(&validator).ok_or(Error::<T>::NotElected)?;
+		let percent = T::Balance::from(u32::from(percent.min(100)));
+
+		let stakers = core::iter::once((validator, exposure.own)).chain(exposure.others);
+		for (who, stake) in stakers {
+			let amount = stake
+				.checked_mul(&percent)
+				.and_then(|amount| amount.checked_div(&T::Balance::from(100)))
+				.unwrap_or(T::Balance::zero());
+			Self::slash_staker(runtime, &who, amount)?;
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{balances, support, system};
+
+	struct TestRuntime {
+		system: system::Pallet<Self>,
+		balances: balances::Pallet<Self>,
+		staking: super::Pallet<Self>,
+	}
+
+	impl TestRuntime {
+		fn new() -> Self {
+			Self {
+				system: system::Pallet::new(),
+				balances: balances::Pallet::new(),
+				staking: super::Pallet::new(),
+			}
+		}
+
+		fn run_to_block(&mut self, n: u32) {
+			while self.system.block_number() < n {
+				self.system.inc_block_number();
+				super::Pallet::on_initialize(self, self.system.block_number());
+			}
+		}
+	}
+
+	/// Pays out 10% of the total stake every era.
+	struct TenPercent;
+
+	impl super::EraPayout<u128> for TenPercent {
+		fn era_payout(total_staked: u128) -> u128 {
+			total_staked / 10
+		}
+	}
+
+	impl system::Config for TestRuntime {
+		type AccountId = &'static str;
+		type BlockNumber = u32;
+		type Nonce = u32;
+		type RuntimeEvent = ();
+		type PalletIndex = ();
+		const MAXIMUM_BLOCK_WEIGHT: crate::support::Weight = u64::MAX;
+		const MAXIMUM_BLOCK_LENGTH: u32 = u32::MAX;
+		const SPEC_VERSION: u32 = 1;
+	}
+
+	impl balances::Config for TestRuntime {
+		type Balance = u128;
+	}
+
+	struct EnsureRoot;
+
+	impl support::EnsureOrigin<&'static str> for EnsureRoot {
+		fn ensure_origin(caller: &&'static str) -> support::DispatchResult {
+			if *caller == "root" { Ok(()) } else { Err(support::DispatchError::BadOrigin) }
+		}
+	}
+
+	impl super::Config for TestRuntime {
+		const BONDING_DURATION: u32 = 3;
+		const ERA_LENGTH: u32 = 10;
+		const VALIDATOR_COUNT: u32 = 2;
+		const MAX_NOMINATIONS: u32 = 2;
+		type EraPayout = TenPercent;
+		type SlashOrigin = EnsureRoot;
+	}
+
+	impl AsRef<system::Pallet<Self>> for TestRuntime {
+		fn as_ref(&self) -> &system::Pallet<Self> {
+			&self.system
+		}
+	}
+
+	impl AsMut<balances::Pallet<Self>> for TestRuntime {
+		fn as_mut(&mut self) -> &mut balances::Pallet<Self> {
+			&mut self.balances
+		}
+	}
+
+	impl AsMut<super::Pallet<Self>> for TestRuntime {
+		fn as_mut(&mut self) -> &mut super::Pallet<Self> {
+			&mut self.staking
+		}
+	}
+
+	#[test]
+	fn bond_unbond_and_withdraw() {
+		let mut runtime = TestRuntime::new();
+		runtime.balances.set_balance(&"alice", 100);
+
+		assert_eq!(super::Pallet::bond(&mut runtime, "alice", 101), Err(super::Error::<TestRuntime>::InsufficientBalance.into()));
+		assert_eq!(super::Pallet::bond(&mut runtime, "alice", 60), Ok(()));
+		assert_eq!(super::Pallet::bond(&mut runtime, "alice", 50), Err(super::Error::<TestRuntime>::InsufficientBalance.into()));
+		assert_eq!(super::Pallet::bond(&mut runtime, "alice", 20), Ok(()));
+		assert_eq!(runtime.balances.locked(&"alice"), 80);
+		assert_eq!(runtime.balances.transfer("alice", "bob", 21), Err(crate::balances::Error::<TestRuntime>::FundsLocked.into()));
+
+		runtime.run_to_block(1);
+		assert_eq!(super::Pallet::unbond(&mut runtime, "alice", 90), Err(super::Error::<TestRuntime>::InsufficientBond.into()));
+		assert_eq!(super::Pallet::unbond(&mut runtime, "alice", 30), Ok(()));
+		assert_eq!(runtime.staking.ledger(&"alice").map(|ledger| ledger.active), Some(50));
+		// Unbonding funds stay locked until the bonding duration has passed.
+		assert_eq!(runtime.balances.locked(&"alice"), 80);
+		runtime.run_to_block(3);
+		assert_eq!(super::Pallet::withdraw_unbonded(&mut runtime, "alice"), Ok(()));
+		assert_eq!(runtime.balances.locked(&"alice"), 80);
+		runtime.run_to_block(4);
+		assert_eq!(super::Pallet::withdraw_unbonded(&mut runtime, "alice"), Ok(()));
+		assert_eq!(runtime.balances.locked(&"alice"), 50);
+
+		// Withdrawing everything removes the ledger and the lock.
+		assert_eq!(super::Pallet::unbond(&mut runtime, "alice", 50), Ok(()));
+		runtime.run_to_block(7);
+		assert_eq!(super::Pallet::withdraw_unbonded(&mut runtime, "alice"), Ok(()));
+		assert_eq!(runtime.staking.ledger(&"alice"), None);
+		assert_eq!(runtime.balances.locked(&"alice"), 0);
+	}
+
+	#[test]
+	fn validate_and_nominate() {
+		let mut runtime = TestRuntime::new();
+		runtime.balances.set_balance(&"alice", 100);
+		runtime.balances.set_balance(&"bob", 100);
+
+		assert_eq!(runtime.staking.validate("alice"), Err(super::Error::<TestRuntime>::NotBonded.into()));
+		let _res = super::Pallet::bond(&mut runtime, "alice", 100);
+		let _res = super::Pallet::bond(&mut runtime, "bob", 100);
+		assert_eq!(runtime.staking.validate("alice"), Ok(()));
+
+		assert_eq!(runtime.staking.nominate("bob", vec![]), Err(super::Error::<TestRuntime>::EmptyTargets.into()));
+		assert_eq!(runtime.staking.nominate("bob", vec!["charlie"]), Err(super::Error::<TestRuntime>::NotValidator.into()));
+		assert_eq!(
+			runtime.staking.nominate("bob", vec!["alice", "alice", "alice"]),
+			Err(super::Error::<TestRuntime>::TooManyTargets.into())
+		);
+		assert_eq!(
+			runtime.staking.nominate("bob", vec!["alice", "alice"]),
+			Err(super::Error::<TestRuntime>::DuplicateTarget.into())
+		);
+		assert_eq!(
+			runtime.staking.nominate("bob", vec!["alice"]),
+			Ok(crate::support::PostDispatchInfo { actual_weight: Some(super::NOMINATION_WEIGHT) })
+		);
+
+		// Unbonding all the active funds chills the staker.
+		assert_eq!(super::Pallet::unbond(&mut runtime, "alice", 100), Ok(()));
+		runtime.run_to_block(10);
+		assert_eq!(runtime.staking.elected(), Vec::<&str>::new());
+	}
+
+	#[test]
+	fn elections_and_rewards() {
+		let mut runtime = TestRuntime::new();
+		for (who, stake) in [("alice", 100), ("bob", 50), ("charlie", 10), ("dave", 200)] {
+			runtime.balances.set_balance(&who, 1_000);
+			let _res = super::Pallet::bond(&mut runtime, who, stake);
+		}
+		let _res = runtime.staking.validate("alice");
+		let _res = runtime.staking.validate("bob");
+		let _res = runtime.staking.validate("charlie");
+		// Dave's nominations make charlie win over bob.
+		let _res = runtime.staking.nominate("dave", vec!["alice", "charlie"]);
+
+		runtime.run_to_block(9);
+		assert_eq!(runtime.staking.current_era(), 0);
+		runtime.run_to_block(10);
+		assert_eq!(runtime.staking.current_era(), 1);
+		assert_eq!(runtime.staking.elected(), vec!["alice", "charlie"]);
+		assert_eq!(
+			runtime.staking.exposure(&"charlie"),
+			Some(super::Exposure { total: 110, own: 10, others: vec![("dave", 100)] })
+		);
+
+		// The era payout is 10% of the 310 exposed, shared in proportion to the stake.
+		runtime.run_to_block(20);
+		assert_eq!(runtime.staking.current_era(), 2);
+		assert_eq!(runtime.balances.balance(&"alice"), 1_010);
+		assert_eq!(runtime.balances.balance(&"bob"), 1_000);
+		assert_eq!(runtime.balances.balance(&"charlie"), 1_001);
+		assert_eq!(runtime.balances.balance(&"dave"), 1_020);
+	}
+
+	#[test]
+	fn rewards_of_large_stakes_do_not_overflow() {
+		// With 12 decimals: a payout of a thousand billion units, for a stake of 3 billion billion
+		// units out of 10 billion billion.
+		let payout = 1_000_000_000_000_000_000_000_000u128;
+		let stake = 3_000_000_000_000_000_000_000_000_000_000u128;
+		let total = 10_000_000_000_000_000_000_000_000_000_000u128;
+		assert!(payout.checked_mul(stake).is_none());
+		assert_eq!(super::multiply_by_rational(payout, stake, total), Some(payout / 10 * 3));
+		assert_eq!(super::multiply_by_rational(310u128, 100, 310), Some(100));
+		assert_eq!(super::multiply_by_rational(u128::MAX, 1, 1), Some(u128::MAX));
+		assert_eq!(super::multiply_by_rational(1u128, 1, 0), None);
+	}
+
+	#[test]
+	fn slashing() {
+		let mut runtime = TestRuntime::new();
+		for (who, stake) in [("alice", 100), ("dave", 200)] {
+			runtime.balances.set_balance(&who, 1_000);
+			let _res = super::Pallet::bond(&mut runtime, who, stake);
+		}
+		let _res = runtime.staking.validate("alice");
+		let _res = runtime.staking.nominate("dave", vec!["alice"]);
+
+		assert_eq!(
+			super::Pallet::slash(&mut runtime, "root", "alice", 10),
+			Err(super::Error::<TestRuntime>::NotElected.into())
+		);
+		runtime.run_to_block(10);
+		assert_eq!(
+			super::Pallet::slash(&mut runtime, "alice", "alice", 10),
+			Err(support::DispatchError::BadOrigin)
+		);
+		assert_eq!(super::Pallet::slash(&mut runtime, "root", "alice", 10), Ok(()));
+
+		assert_eq!(runtime.staking.ledger(&"alice").map(|ledger| ledger.active), Some(90));
+		assert_eq!(runtime.staking.ledger(&"dave").map(|ledger| ledger.active), Some(180));
+		assert_eq!(runtime.balances.balance(&"alice"), 990);
+		assert_eq!(runtime.balances.balance(&"dave"), 980);
+		assert_eq!(runtime.balances.locked(&"dave"), 180);
+	}
+}
@@ -0,0 +1,686 @@
+use core::{any::Any, borrow::Borrow, fmt, marker::PhantomData, ops::RangeBounds};
+use std::{
+	cell::{Ref, RefCell, RefMut},
+	collections::BTreeMap,
+	rc::Rc,
+};
+
+use crate::codec::{Decode, Encode};
+
+/// A value in the backend. Values are kept as they are, rather than encoded, so that only the keys
+/// of storage items need to be encodable.
+trait StoredValue: Any {
+	fn clone_value(&self) -> Box<dyn StoredValue>;
+	fn as_any(&self) -> &dyn Any;
+	fn into_any(self: Box<Self>) -> Box<dyn Any>;
+}
+
+impl<V: Any + Clone> StoredValue for V {
+	fn clone_value(&self) -> Box<dyn StoredValue> {
+		Box::new(self.clone())
+	}
+
+	fn as_any(&self) -> &dyn Any {
+		self
+	}
+
+	fn into_any(self: Box<Self>) -> Box<dyn Any> {
+		self
+	}
+}
+
+/// The entries of a backend, by key.
+type Entries = BTreeMap<Vec<u8>, Box<dyn StoredValue>>;
+
+/// The changes made to the entries of a backend, as the previous value of each entry which changed,
+/// or `None` if it did not exist, in the order of the changes.
+type Journal = Vec<(Vec<u8>, Option<Box<dyn StoredValue>>)>;
+
+/// The entries of a backend with their values encoded, under the same keys as in the backend. This
+/// is how the state of a runtime is stored on disk.
+pub type EncodedStorage = BTreeMap<Vec<u8>, Vec<u8>>;
+
+/// The entries of `storage` whose key starts with `prefix`, with the rest of their key.
+fn encoded_with_prefix<'a>(
+	storage: &'a EncodedStorage,
+	prefix: &'a [u8],
+) -> impl Iterator<Item = (&'a [u8], &'a [u8])> + 'a {
+	storage
+		.range(prefix.to_vec()..)
+		.take_while(move |(key, _)| key.starts_with(prefix))
+		.map(move |(key, value)| (&key[prefix.len()..], &value[..]))
+}
+
+/// The storage shared by the pallets of a runtime: a single map from keys to values.
+///
+/// Each storage item has its own prefix, made of the name of its pallet and its own name, and all
+/// its keys start with it. Prefixes are encoded with their length, so no prefix starts with another
+/// one, and items never see the keys of each other. Storage items are handles to their part of the
+/// backend: the backend is shared by all the items bound to it, and lives as long as they do.
+pub struct Backend {
+	entries: Rc<RefCell<Entries>>,
+	/// The journals of the changes to undo, one for each `rollback` in progress, innermost last.
+	journals: Rc<RefCell<Vec<Journal>>>,
+}
+
+impl Backend {
+	pub fn new() -> Self {
+		Backend::with_entries(BTreeMap::new())
+	}
+
+	fn with_entries(entries: Entries) -> Self {
+		Backend { entries: Rc::new(RefCell::new(entries)), journals: Rc::new(RefCell::new(Vec::new())) }
+	}
+
+	/// Copy the backend. Items bound to the copy do not see the changes made through the items
+	/// bound to `self`, and the other way around.
+	pub fn snapshot(&self) -> Self {
+		let entries = self.entries().iter().map(|(key, value)| (key.clone(), (**value).clone_value())).collect();
+		Backend::with_entries(entries)
+	}
+
+	/// Call `f`, then undo the changes it made to the entries, through any item bound to the
+	/// backend. Only the entries `f` changes are copied, unlike with `snapshot`.
+	pub fn rollback<R>(&self, f: impl FnOnce() -> R) -> R {
+		RefCell::borrow_mut(&self.journals).push(Journal::new());
+		let result = f();
+		let journal = RefCell::borrow_mut(&self.journals).pop().unwrap_or_default();
+		let mut entries = self.entries_mut();
+		for (key, value) in journal.into_iter().rev() {
+			match value {
+				Some(value) => entries.insert(key, value),
+				None => entries.remove(&key),
+			};
+		}
+		result
+	}
+
+	/// The keys of all the entries, in order.
+	pub fn keys(&self) -> Vec<Vec<u8>> {
+		self.entries().keys().cloned().collect()
+	}
+
+	fn entries(&self) -> Ref<'_, Entries> {
+		RefCell::borrow(&self.entries)
+	}
+
+	fn entries_mut(&self) -> RefMut<'_, Entries> {
+		RefCell::borrow_mut(&self.entries)
+	}
+
+	/// The prefix of the item `item` of the pallet `pallet`.
+	fn prefix(pallet: &str, item: &str) -> Vec<u8> {
+		(pallet, item).encode()
+	}
+
+	/// Another handle to the same entries.
+	fn handle(&self) -> Self {
+		Backend { entries: Rc::clone(&self.entries), journals: Rc::clone(&self.journals) }
+	}
+
+	/// Record the previous value of `key` in the journal of the innermost `rollback`, if any.
+	fn record(&self, key: &[u8], previous: Option<&dyn StoredValue>) {
+		if let Some(journal) = RefCell::borrow_mut(&self.journals).last_mut() {
+			journal.push((key.to_vec(), previous.map(StoredValue::clone_value)));
+		}
+	}
+
+	fn get<V: Clone + 'static>(&self, key: &[u8]) -> Option<V> {
+		self.entries().get(key).map(|value| downcast_ref::<V>(&**value).clone())
+	}
+
+	fn contains_key(&self, key: &[u8]) -> bool {
+		self.entries().contains_key(key)
+	}
+
+	fn insert<V: Clone + 'static>(&self, key: Vec<u8>, value: V) -> Option<V> {
+		let previous = self.entries_mut().insert(key.clone(), Box::new(value));
+		self.record(&key, previous.as_deref());
+		previous.map(downcast::<V>)
+	}
+
+	fn remove<V: 'static>(&self, key: &[u8]) -> Option<V> {
+		let previous = self.entries_mut().remove(key);
+		if previous.is_some() {
+			self.record(key, previous.as_deref());
+		}
+		previous.map(downcast::<V>)
+	}
+
+	/// Copy the values of all the entries whose key starts with `prefix`, in the order of their keys.
+	fn values_with_prefix<V: Clone + 'static>(&self, prefix: &[u8]) -> Vec<V> {
+		self.entries()
+			.range(prefix.to_vec()..)
+			.take_while(|(key, _)| key.starts_with(prefix))
+			.map(|(_, value)| downcast_ref::<V>(&**value).clone())
+			.collect()
+	}
+
+	/// Remove all the entries whose key starts with `prefix`.
+	fn clear_prefix(&self, prefix: &[u8]) {
+		self.entries_mut().retain(|key, value| {
+			let keep = !key.starts_with(prefix);
+			if !keep {
+				self.record(key, Some(&**value));
+			}
+			keep
+		});
+	}
+}
+
+impl Default for Backend {
+	fn default() -> Self {
+		Backend::new()
+	}
+}
+
+// Each item has its own prefix, and only keeps values of its own type under it, so the values
+// under the prefix of an item always have the type of the item.
+fn downcast_ref<V: 'static>(value: &dyn StoredValue) -> &V {
+	value.as_any().downcast_ref().expect("the values of a storage item have its type")
+}
+
+fn downcast<V: 'static>(value: Box<dyn StoredValue>) -> V {
+	*value.into_any().downcast().expect("the values of a storage item have its type")
+}
+
+/// A single value in the storage of a pallet.
+///
+/// The value always exists: it starts as the default value of the storage item, as given by
+/// `#[default(..)]` in `#[macros::storage]`.
+pub struct StorageValue<V> {
+	backend: Backend,
+	prefix: Vec<u8>,
+	_value: PhantomData<V>,
+}
+
+impl<V: Clone + 'static> StorageValue<V> {
+	/// Bind the item `item` of the pallet `pallet` to `backend`. The value is set to `default`,
+	/// unless the backend already has one.
+	pub fn new(backend: &Backend, pallet: &str, item: &str, default: V) -> Self {
+		let prefix = Backend::prefix(pallet, item);
+		if !backend.contains_key(&prefix) {
+			backend.insert(prefix.clone(), default);
+		}
+		StorageValue { backend: backend.handle(), prefix, _value: PhantomData }
+	}
+
+	/// The backend the item is bound to.
+	pub fn backend(&self) -> &Backend {
+		&self.backend
+	}
+
+	pub fn get(&self) -> V {
+		self.backend.get(&self.prefix).expect("storage values always exist")
+	}
+
+	/// Replace the value, and return the previous one.
+	pub fn put(&mut self, value: V) -> V {
+		self.backend.insert(self.prefix.clone(), value).expect("storage values always exist")
+	}
+
+	/// Change the value, and return what `f` returns.
+	pub fn mutate<R>(&mut self, f: impl FnOnce(&mut V) -> R) -> R {
+		let mut value = self.get();
+		let result = f(&mut value);
+		self.put(value);
+		result
+	}
+
+	/// Add the value, encoded, to `storage`.
+	pub fn encode_into(&self, storage: &mut EncodedStorage)
+	where
+		V: Encode,
+	{
+		storage.insert(self.prefix.clone(), self.get().encode());
+	}
+
+	/// Set the value from `storage`. The value is left as it is if `storage` has none.
+	pub fn decode_from(&mut self, storage: &EncodedStorage) -> Result<(), &'static str>
+	where
+		V: Decode,
+	{
+		if let Some(value) = storage.get(&self.prefix) {
+			self.put(V::decode_all(value)?);
+		}
+		Ok(())
+	}
+}
+
+/// A map from keys to values in the storage of a pallet.
+///
+/// Keys which are not in the map have no value, and the getters generated by `#[macros::storage]`
+/// return the `#[default(..)]` of the item for them. Entries are iterated in the order of their keys.
+/// Reading the map gives copies of its values: changes go through `insert`, `mutate` or `retain`.
+pub struct StorageMap<K, V> {
+	backend: Backend,
+	prefix: Vec<u8>,
+	_entries: PhantomData<(K, V)>,
+}
+
+impl<K: Encode + Ord + Clone + 'static, V: Clone + 'static> StorageMap<K, V> {
+	/// Bind the item `item` of the pallet `pallet` to `backend`.
+	pub fn new(backend: &Backend, pallet: &str, item: &str) -> Self {
+		StorageMap {
+			backend: backend.handle(),
+			prefix: Backend::prefix(pallet, item),
+			_entries: PhantomData,
+		}
+	}
+
+	/// The key of the entry for `key` in the backend.
+	fn key<Q: Encode + ?Sized>(&self, key: &Q) -> Vec<u8> {
+		let mut prefixed = self.prefix.clone();
+		key.encode_to(&mut prefixed);
+		prefixed
+	}
+
+	pub fn get<Q: Encode + ?Sized>(&self, key: &Q) -> Option<V>
+	where
+		K: Borrow<Q>,
+	{
+		self.backend.get::<(K, V)>(&self.key(key)).map(|(_, value)| value)
+	}
+
+	pub fn contains_key<Q: Encode + ?Sized>(&self, key: &Q) -> bool
+	where
+		K: Borrow<Q>,
+	{
+		self.backend.contains_key(&self.key(key))
+	}
+
+	/// Set the value of `key`, and return its previous value.
+	pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+		let prefixed = self.key(&key);
+		self.backend.insert(prefixed, (key, value)).map(|(_, value)| value)
+	}
+
+	pub fn remove<Q: Encode + ?Sized>(&mut self, key: &Q) -> Option<V>
+	where
+		K: Borrow<Q>,
+	{
+		self.backend.remove::<(K, V)>(&self.key(key)).map(|(_, value)| value)
+	}
+
+	/// Change the value of `key`, and return what `f` returns. The value is `None` if `key` is not
+	/// in the map, and `key` is removed from the map if `f` leaves `None`.
+	pub fn mutate<R>(&mut self, key: K, f: impl FnOnce(&mut Option<V>) -> R) -> R {
+		let mut value = self.get(&key);
+		let result = f(&mut value);
+		match value {
+			Some(value) => self.insert(key, value),
+			None => self.remove(&key),
+		};
+		result
+	}
+
+	/// Keep only the entries for which `f` returns `true`. The changes `f` makes to the values it
+	/// keeps are saved.
+	pub fn retain(&mut self, mut f: impl FnMut(&K, &mut V) -> bool) {
+		for (key, mut value) in self.iter() {
+			if f(&key, &mut value) {
+				self.insert(key, value);
+			} else {
+				self.remove(&key);
+			}
+		}
+	}
+
+	/// Remove all the entries.
+	pub fn clear(&mut self) {
+		self.backend.clear_prefix(&self.prefix);
+	}
+
+	pub fn iter(&self) -> impl Iterator<Item = (K, V)> + use<K, V> {
+		let mut entries = self.backend.values_with_prefix::<(K, V)>(&self.prefix);
+		// Encoded keys are not ordered like the keys themselves.
+		entries.sort_by(|(key1, _), (key2, _)| key1.cmp(key2));
+		entries.into_iter()
+	}
+
+	/// Iterate over the entries with keys in `range`.
+	pub fn range<R: RangeBounds<K>>(&self, range: R) -> impl Iterator<Item = (K, V)> + use<K, V, R> {
+		self.iter().filter(move |(key, _)| range.contains(key))
+	}
+
+	pub fn keys(&self) -> impl Iterator<Item = K> + use<K, V> {
+		self.iter().map(|(key, _)| key)
+	}
+
+	pub fn values(&self) -> impl Iterator<Item = V> + use<K, V> {
+		self.iter().map(|(_, value)| value)
+	}
+
+	/// Add the entries, encoded, to `storage`.
+	pub fn encode_into(&self, storage: &mut EncodedStorage)
+	where
+		V: Encode,
+	{
+		for (key, value) in self.iter() {
+			storage.insert(self.key(&key), value.encode());
+		}
+	}
+
+	/// Replace the entries with the ones in `storage`.
+	pub fn decode_from(&mut self, storage: &EncodedStorage) -> Result<(), &'static str>
+	where
+		K: Decode,
+		V: Decode,
+	{
+		self.clear();
+		let prefix = self.prefix.clone();
+		for (key, value) in encoded_with_prefix(storage, &prefix) {
+			self.insert(K::decode_all(key)?, V::decode_all(value)?);
+		}
+		Ok(())
+	}
+}
+
+/// A map from pairs of keys to values in the storage of a pallet. The entries sharing their first
+/// key can be iterated together.
+///
+/// Keys which are not in the map have no value, and the getters generated by `#[macros::storage]`
+/// return the `#[default(..)]` of the item for them. Entries are iterated in the order of their keys.
+pub struct StorageDoubleMap<K1, K2, V> {
+	backend: Backend,
+	prefix: Vec<u8>,
+	_entries: PhantomData<(K1, K2, V)>,
+}
+
+impl<K1, K2, V> StorageDoubleMap<K1, K2, V>
+where
+	K1: Encode + Ord + Clone + 'static,
+	K2: Encode + Ord + Clone + 'static,
+	V: Clone + 'static,
+{
+	/// Bind the item `item` of the pallet `pallet` to `backend`.
+	pub fn new(backend: &Backend, pallet: &str, item: &str) -> Self {
+		StorageDoubleMap {
+			backend: backend.handle(),
+			prefix: Backend::prefix(pallet, item),
+			_entries: PhantomData,
+		}
+	}
+
+	/// The key of the entry for `(key1, key2)` in the backend. The encoding of `key1` is followed by
+	/// the encoding of `key2`, so the entries sharing their first key also share a prefix.
+	fn key<Q1: Encode + ?Sized, Q2: Encode + ?Sized>(&self, key1: &Q1, key2: &Q2) -> Vec<u8> {
+		let mut prefixed = self.prefix.clone();
+		key1.encode_to(&mut prefixed);
+		key2.encode_to(&mut prefixed);
+		prefixed
+	}
+
+	pub fn get<Q1: Encode + ?Sized, Q2: Encode + ?Sized>(&self, key1: &Q1, key2: &Q2) -> Option<V>
+	where
+		K1: Borrow<Q1>,
+		K2: Borrow<Q2>,
+	{
+		self.backend.get::<(K1, K2, V)>(&self.key(key1, key2)).map(|(_, _, value)| value)
+	}
+
+	pub fn contains_key<Q1: Encode + ?Sized, Q2: Encode + ?Sized>(&self, key1: &Q1, key2: &Q2) -> bool
+	where
+		K1: Borrow<Q1>,
+		K2: Borrow<Q2>,
+	{
+		self.backend.contains_key(&self.key(key1, key2))
+	}
+
+	/// Set the value of `(key1, key2)`, and return its previous value.
+	pub fn insert(&mut self, key1: K1, key2: K2, value: V) -> Option<V> {
+		let prefixed = self.key(&key1, &key2);
+		self.backend.insert(prefixed, (key1, key2, value)).map(|(_, _, value)| value)
+	}
+
+	pub fn remove<Q1: Encode + ?Sized, Q2: Encode + ?Sized>(&mut self, key1: &Q1, key2: &Q2) -> Option<V>
+	where
+		K1: Borrow<Q1>,
+		K2: Borrow<Q2>,
+	{
+		self.backend.remove::<(K1, K2, V)>(&self.key(key1, key2)).map(|(_, _, value)| value)
+	}
+
+	/// Remove all the entries with the first key `key1`.
+	pub fn remove_prefix<Q1: Encode + ?Sized>(&mut self, key1: &Q1)
+	where
+		K1: Borrow<Q1>,
+	{
+		let mut prefix = self.prefix.clone();
+		key1.encode_to(&mut prefix);
+		self.backend.clear_prefix(&prefix);
+	}
+
+	/// Iterate over the entries with the first key `key1`, in the order of their second key.
+	pub fn iter_prefix<Q1: Encode + ?Sized>(&self, key1: &Q1) -> impl Iterator<Item = (K2, V)> + use<K1, K2, V, Q1>
+	where
+		K1: Borrow<Q1>,
+	{
+		let mut prefix = self.prefix.clone();
+		key1.encode_to(&mut prefix);
+		let mut entries = self.backend.values_with_prefix::<(K1, K2, V)>(&prefix);
+		entries.sort_by(|(_, key1, _), (_, key2, _)| key1.cmp(key2));
+		entries.into_iter().map(|(_, key2, value)| (key2, value))
+	}
+
+	/// Iterate over all the entries, in the order of their keys.
+	fn iter(&self) -> impl Iterator<Item = (K1, K2, V)> + use<K1, K2, V> {
+		let mut entries = self.backend.values_with_prefix::<(K1, K2, V)>(&self.prefix);
+		entries.sort_by(|(key1, key2, _), (other1, other2, _)| (key1, key2).cmp(&(other1, other2)));
+		entries.into_iter()
+	}
+
+	/// Add the entries, encoded, to `storage`.
+	pub fn encode_into(&self, storage: &mut EncodedStorage)
+	where
+		V: Encode,
+	{
+		for (key1, key2, value) in self.iter() {
+			storage.insert(self.key(&key1, &key2), value.encode());
+		}
+	}
+
+	/// Replace the entries with the ones in `storage`.
+	pub fn decode_from(&mut self, storage: &EncodedStorage) -> Result<(), &'static str>
+	where
+		K1: Decode,
+		K2: Decode,
+		V: Decode,
+	{
+		let prefix = self.prefix.clone();
+		self.backend.clear_prefix(&prefix);
+		for (mut key, value) in encoded_with_prefix(storage, &prefix) {
+			let key1 = K1::decode(&mut key)?;
+			self.insert(key1, K2::decode_all(key)?, V::decode_all(value)?);
+		}
+		Ok(())
+	}
+}
+
+// Storage items are printed like the value or map they hold, which keeps the state of the runtime
+// readable when it is printed, and the state root independent of how storage is implemented.
+impl<V: Clone + fmt::Debug + 'static> fmt::Debug for StorageValue<V> {
+	fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+		self.get().fmt(formatter)
+	}
+}
+
+impl<K, V> fmt::Debug for StorageMap<K, V>
+where
+	K: Encode + Ord + Clone + fmt::Debug + 'static,
+	V: Clone + fmt::Debug + 'static,
+{
+	fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+		formatter.debug_map().entries(self.iter()).finish()
+	}
+}
+
+impl<K1, K2, V> fmt::Debug for StorageDoubleMap<K1, K2, V>
+where
+	K1: Encode + Ord + Clone + fmt::Debug + 'static,
+	K2: Encode + Ord + Clone + fmt::Debug + 'static,
+	V: Clone + fmt::Debug + 'static,
+{
+	fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let mut groups = BTreeMap::<K1, BTreeMap<K2, V>>::new();
+		for (key1, key2, value) in self.iter() {
+			groups.entry(key1).or_default().insert(key2, value);
+		}
+		groups.fmt(formatter)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{Backend, EncodedStorage, StorageDoubleMap, StorageMap, StorageValue};
+
+	#[test]
+	fn storage_value_works() {
+		let backend = Backend::new();
+		let mut value = StorageValue::new(&backend, "pallet", "value", 1u32);
+		assert_eq!(value.put(2), 1);
+		assert_eq!(
+			value.mutate(|value| {
+				*value += 1;
+				*value
+			}),
+			3
+		);
+		assert_eq!(value.get(), 3);
+		assert_eq!(format!("{:?}", value), "3");
+
+		// Binding the item again keeps its value.
+		let value = StorageValue::new(&backend, "pallet", "value", 1u32);
+		assert_eq!(value.get(), 3);
+	}
+
+	#[test]
+	fn storage_map_works() {
+		let backend = Backend::new();
+		let mut map = StorageMap::<String, u32>::new(&backend, "pallet", "map");
+		map.insert("alice".to_string(), 1);
+		map.mutate("bob".to_string(), |value| *value = Some(value.unwrap_or(0) + 2));
+		assert_eq!(map.get("bob"), Some(2));
+		// Leaving `None` removes the entry.
+		map.mutate("alice".to_string(), |value| *value = None);
+		assert!(!map.contains_key("alice"));
+		assert_eq!(map.iter().collect::<Vec<_>>(), vec![("bob".to_string(), 2)]);
+		assert_eq!(format!("{:?}", map), r#"{"bob": 2}"#);
+
+		// Entries are in the order of their keys, not of their encoding.
+		let mut map = StorageMap::<u32, ()>::new(&backend, "pallet", "numbers");
+		map.insert(256, ());
+		map.insert(1, ());
+		assert_eq!(map.keys().collect::<Vec<_>>(), vec![1, 256]);
+		assert_eq!(map.range(2..).collect::<Vec<_>>(), vec![(256, ())]);
+		map.clear();
+		assert_eq!(map.iter().count(), 0);
+	}
+
+	#[test]
+	fn storage_double_map_works() {
+		let backend = Backend::new();
+		let mut map = StorageDoubleMap::<u32, u32, &str>::new(&backend, "pallet", "double_map");
+		map.insert(1, 2, "a");
+		map.insert(1, 1, "b");
+		map.insert(2, 1, "c");
+		assert_eq!(map.get(&1, &2), Some("a"));
+		assert_eq!(map.iter_prefix(&1).collect::<Vec<_>>(), vec![(1, "b"), (2, "a")]);
+
+		assert_eq!(map.remove(&2, &1), Some("c"));
+		assert_eq!(map.iter_prefix(&2).count(), 0);
+		assert_eq!(format!("{:?}", map), r#"{1: {1: "b", 2: "a"}}"#);
+
+		map.insert(2, 1, "c");
+		map.remove_prefix(&1);
+		assert_eq!(format!("{:?}", map), r#"{2: {1: "c"}}"#);
+	}
+
+	#[test]
+	fn items_share_the_backend_under_their_prefix() {
+		let backend = Backend::new();
+		let mut first = StorageMap::<String, u32>::new(&backend, "pallet", "item");
+		let mut second = StorageMap::<String, u32>::new(&backend, "pallet", "item2");
+		let mut other = StorageMap::<String, u32>::new(&backend, "other", "item");
+		first.insert("alice".to_string(), 1);
+		second.insert("alice".to_string(), 2);
+		other.insert("alice".to_string(), 3);
+		assert_eq!(backend.keys().len(), 3);
+		assert_eq!(first.iter().collect::<Vec<_>>(), vec![("alice".to_string(), 1)]);
+		first.clear();
+		assert_eq!(second.get("alice"), Some(2));
+		assert_eq!(other.get("alice"), Some(3));
+
+		// Items bound to the same backend see the same entries, but not the ones of a snapshot.
+		let snapshot = backend.snapshot();
+		let mut copy = StorageMap::<String, u32>::new(&snapshot, "pallet", "item2");
+		copy.insert("alice".to_string(), 4);
+		let same = StorageMap::<String, u32>::new(&backend, "pallet", "item2");
+		assert_eq!(same.get("alice"), Some(2));
+		assert_eq!(copy.get("alice"), Some(4));
+	}
+
+	#[test]
+	fn changes_are_rolled_back() {
+		let backend = Backend::new();
+		let mut value = StorageValue::new(&backend, "pallet", "value", 1u32);
+		let mut map = StorageMap::<String, u32>::new(&backend, "pallet", "map");
+		map.insert("alice".to_string(), 1);
+		map.insert("bob".to_string(), 2);
+		let keys = backend.keys();
+
+		let result = backend.rollback(|| {
+			value.put(2);
+			map.insert("alice".to_string(), 3);
+			map.insert("charlie".to_string(), 4);
+			// Nested rollbacks only undo their own changes.
+			backend.rollback(|| map.clear());
+			assert_eq!(map.get("bob"), Some(2));
+			map.remove("bob");
+			map.get("alice")
+		});
+		assert_eq!(result, Some(3));
+		assert_eq!(value.get(), 1);
+		assert_eq!(format!("{:?}", map), r#"{"alice": 1, "bob": 2}"#);
+		assert_eq!(backend.keys(), keys);
+
+		// Changes made outside of a rollback are kept.
+		value.put(5);
+		assert_eq!(value.get(), 5);
+	}
+
+	#[test]
+	fn items_are_encoded_and_decoded() {
+		let backend = Backend::new();
+		let mut value = StorageValue::new(&backend, "pallet", "value", 0u32);
+		let mut map = StorageMap::<String, u32>::new(&backend, "pallet", "map");
+		let mut double_map = StorageDoubleMap::<u32, String, u64>::new(&backend, "pallet", "double_map");
+		value.put(1);
+		map.insert("alice".to_string(), 2);
+		map.insert("bob".to_string(), 3);
+		double_map.insert(4, "alice".to_string(), 5);
+
+		let mut storage = EncodedStorage::new();
+		value.encode_into(&mut storage);
+		map.encode_into(&mut storage);
+		double_map.encode_into(&mut storage);
+		// The encoded entries have the keys of the backend.
+		assert_eq!(storage.keys().cloned().collect::<Vec<_>>(), backend.keys());
+
+		let other = Backend::new();
+		let mut value = StorageValue::new(&other, "pallet", "value", 0u32);
+		let mut map = StorageMap::<String, u32>::new(&other, "pallet", "map");
+		let mut double_map = StorageDoubleMap::<u32, String, u64>::new(&other, "pallet", "double_map");
+		value.decode_from(&storage).unwrap();
+		map.decode_from(&storage).unwrap();
+		double_map.decode_from(&storage).unwrap();
+		assert_eq!(value.get(), 1);
+		assert_eq!(format!("{:?}", map), r#"{"alice": 2, "bob": 3}"#);
+		assert_eq!(double_map.get(&4, "alice"), Some(5));
+
+		// Values missing from the encoded entries keep their default.
+		let mut missing = StorageValue::new(&other, "pallet", "missing", 6u32);
+		missing.decode_from(&storage).unwrap();
+		assert_eq!(missing.get(), 6);
+	}
+}
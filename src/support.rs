@@ -1,4 +1,7 @@
+use crate::codec::{Decode, Encode};
+
 /// The most primitive representation of a Blockchain block.
+#[derive(Clone, Debug)]
 pub struct Block<Header, Extrinsic> {
 	/// The block header contains metadata about the block.
 	pub header: Header,
@@ -6,27 +9,341 @@ pub struct Block<Header, Extrinsic> {
 	pub extrinsics: Vec<Extrinsic>,
 }
 
-/// We are using an extremely simplified header which only contains the current block number.
-/// On a real blockchain, you would expect to also find:
-/// - parent block hash
-/// - state root
-/// - extrinsics root
-/// - etc...
+/// We are using a simplified header, which links the block to its parent, and commits to its
+/// extrinsics and to the state after executing them. On a real blockchain, you would expect to also
+/// find a digest, and the roots would be cryptographic hashes.
+#[derive(Clone, Debug)]
 pub struct Header<BlockNumber> {
 	pub block_number: BlockNumber,
+	/// The hash of the header of the parent block.
+	pub parent_hash: Hash,
+	/// The hash of the extrinsics of the block.
+	pub extrinsics_root: Hash,
+	/// The hash of the state of the runtime after executing the block.
+	pub state_root: Hash,
 }
 
 /// This is an "extrinsic": literally an external message from outside of the blockchain.
 /// This simplified version of an extrinsic tells us who is making the call, and which call they are
-/// making.
-pub struct Extrinsic<Caller, Call> {
+/// making. The `nonce` must match the nonce of the caller for the extrinsic to be executed, and the
+/// `tip` is paid on top of the fee to get the extrinsic included sooner.
+#[derive(Clone, Debug)]
+pub struct Extrinsic<Caller, Nonce, Balance, Call> {
 	pub caller: Caller,
+	pub nonce: Nonce,
+	pub tip: Balance,
 	pub call: Call,
 }
 
+/// The types a block is made of. `#[macros::runtime]` is only given the block type of the runtime,
+/// and finds the types of its header and extrinsics through this trait.
+pub trait BlockFormat {
+	/// The header of the block.
+	type Header;
+	/// The extrinsics of the block.
+	type Extrinsic;
+	/// The type the tips and fees of the extrinsics are paid in.
+	type Balance;
+}
+
+impl<BlockNumber, Caller, Nonce, Balance, Call> BlockFormat
+	for Block<Header<BlockNumber>, Extrinsic<Caller, Nonce, Balance, Call>>
+{
+	type Header = Header<BlockNumber>;
+	type Extrinsic = Extrinsic<Caller, Nonce, Balance, Call>;
+	type Balance = Balance;
+}
+
+impl<Header: Encode, Extrinsic: Encode> Encode for Block<Header, Extrinsic> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.header.encode_to(dest);
+		self.extrinsics.encode_to(dest);
+	}
+}
+
+impl<Header: Decode, Extrinsic: Decode> Decode for Block<Header, Extrinsic> {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(Block { header: Header::decode(input)?, extrinsics: Vec::decode(input)? })
+	}
+}
+
+impl<BlockNumber: Encode> Encode for Header<BlockNumber> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.block_number.encode_to(dest);
+		self.parent_hash.encode_to(dest);
+		self.extrinsics_root.encode_to(dest);
+		self.state_root.encode_to(dest);
+	}
+}
+
+impl<BlockNumber: Decode> Decode for Header<BlockNumber> {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(Header {
+			block_number: BlockNumber::decode(input)?,
+			parent_hash: Hash::decode(input)?,
+			extrinsics_root: Hash::decode(input)?,
+			state_root: Hash::decode(input)?,
+		})
+	}
+}
+
+impl<Caller, Nonce, Balance, Call> Encode for Extrinsic<Caller, Nonce, Balance, Call>
+where
+	Caller: Encode,
+	Nonce: Encode,
+	Balance: Encode,
+	Call: Encode,
+{
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.caller.encode_to(dest);
+		self.nonce.encode_to(dest);
+		self.tip.encode_to(dest);
+		self.call.encode_to(dest);
+	}
+}
+
+impl<Caller, Nonce, Balance, Call> Decode for Extrinsic<Caller, Nonce, Balance, Call>
+where
+	Caller: Decode,
+	Nonce: Decode,
+	Balance: Decode,
+	Call: Decode,
+{
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(Extrinsic {
+			caller: Caller::decode(input)?,
+			nonce: Nonce::decode(input)?,
+			tip: Balance::decode(input)?,
+			call: Call::decode(input)?,
+		})
+	}
+}
+
 /// The Result type for our runtime. When everything completes successfully, we return `Ok(())`,
-/// otherwise return a static error message.
-pub type DispatchResult = Result<(), &'static str>;
+/// otherwise return a `DispatchError`.
+pub type DispatchResult = Result<(), DispatchError>;
+
+/// Why the dispatch of a call failed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DispatchError {
+	/// The caller is not allowed to make the call.
+	BadOrigin,
+	/// An error declared by a pallet with `#[macros::error]`.
+	Module(ModuleError),
+	/// The call is not allowed by the `CallFilter` of the runtime, e.g. because it is paused.
+	CallFiltered,
+	/// Any other error, with a static error message.
+	Other(&'static str),
+}
+
+/// An error declared by a pallet, identified by the index of the pallet in the runtime and the
+/// index of the error in the pallet.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ModuleError {
+	/// The index of the pallet in the runtime.
+	pub index: u8,
+	/// The index of the error in the pallet.
+	pub error: u8,
+	/// The name of the error, which is only informative.
+	pub message: &'static str,
+}
+
+impl From<&'static str> for DispatchError {
+	fn from(message: &'static str) -> Self {
+		DispatchError::Other(message)
+	}
+}
+
+impl core::fmt::Display for DispatchError {
+	fn fmt(&self, formatter: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		match self {
+			DispatchError::BadOrigin => write!(formatter, "bad origin"),
+			DispatchError::Module(error) => write!(formatter, "{}", error.message),
+			DispatchError::CallFiltered => write!(formatter, "call filtered"),
+			DispatchError::Other(message) => write!(formatter, "{}", message),
+		}
+	}
+}
+
+impl Encode for DispatchError {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		match self {
+			DispatchError::BadOrigin => 0u8.encode_to(dest),
+			DispatchError::Module(error) => {
+				1u8.encode_to(dest);
+				error.encode_to(dest);
+			},
+			DispatchError::CallFiltered => 2u8.encode_to(dest),
+			DispatchError::Other(message) => {
+				3u8.encode_to(dest);
+				message.encode_to(dest);
+			},
+		}
+	}
+}
+
+impl Decode for DispatchError {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		match u8::decode(input)? {
+			0 => Ok(DispatchError::BadOrigin),
+			1 => Ok(DispatchError::Module(ModuleError::decode(input)?)),
+			2 => Ok(DispatchError::CallFiltered),
+			3 => Ok(DispatchError::Other(intern(String::decode(input)?))),
+			_ => Err("invalid dispatch error"),
+		}
+	}
+}
+
+impl Encode for ModuleError {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.index.encode_to(dest);
+		self.error.encode_to(dest);
+		self.message.encode_to(dest);
+	}
+}
+
+impl Decode for ModuleError {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(ModuleError {
+			index: u8::decode(input)?,
+			error: u8::decode(input)?,
+			message: intern(String::decode(input)?),
+		})
+	}
+}
+
+/// Turn a decoded error message back into a static string. Error messages are a small fixed set
+/// of strings of the runtime, so each distinct message is leaked once and then reused.
+fn intern(message: String) -> &'static str {
+	static MESSAGES: std::sync::Mutex<std::collections::BTreeSet<&'static str>> =
+		std::sync::Mutex::new(std::collections::BTreeSet::new());
+	let mut messages = MESSAGES.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+	match messages.get(message.as_str()) {
+		Some(message) => message,
+		None => {
+			let message: &'static str = Box::leak(message.into_boxed_str());
+			messages.insert(message);
+			message
+		},
+	}
+}
+
+/// The information a call gives back once dispatched.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PostDispatchInfo {
+	/// The weight the call actually consumed, if it is known to be less than its declared weight.
+	pub actual_weight: Option<Weight>,
+}
+
+impl PostDispatchInfo {
+	/// The weight the call consumed, which is never more than its `declared` weight.
+	pub fn calc_actual_weight(&self, declared: Weight) -> Weight {
+		self.actual_weight.map_or(declared, |actual| actual.min(declared))
+	}
+}
+
+/// Calls which return `DispatchResult` give no information back.
+impl From<()> for PostDispatchInfo {
+	fn from(_: ()) -> Self {
+		Self::default()
+	}
+}
+
+/// Why the dispatch of a call failed, with the information it gives back.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DispatchErrorWithPostInfo {
+	/// The information the call gives back, even though it failed.
+	pub post_info: PostDispatchInfo,
+	/// Why the call failed.
+	pub error: DispatchError,
+}
+
+impl<E: Into<DispatchError>> From<E> for DispatchErrorWithPostInfo {
+	fn from(error: E) -> Self {
+		Self { post_info: PostDispatchInfo::default(), error: error.into() }
+	}
+}
+
+/// The Result type of calls which give information back once dispatched, like the weight they
+/// actually consumed.
+pub type DispatchResultWithPostInfo = Result<PostDispatchInfo, DispatchErrorWithPostInfo>;
+
+/// Gives the index of each pallet in the runtime, which the errors of the pallet are identified
+/// with. It is implemented by `#[macros::runtime]`.
+pub trait PalletIndex {
+	/// The index of the pallet `P`, or `None` if it is not part of the runtime.
+	fn index<P: 'static>() -> Option<u8>;
+}
+
+/// Pallets tested on their own are not part of a runtime, so their errors are reported as
+/// `DispatchError::Other`.
+impl PalletIndex for () {
+	fn index<P: 'static>() -> Option<u8> {
+		None
+	}
+}
+
+/// The outcome of applying an extrinsic. The error means the extrinsic is invalid, e.g. its nonce
+/// is wrong or its caller cannot pay the fee, and it cannot be included in a block. Otherwise, the
+/// extrinsic is included, and its receipt is returned.
+pub type ApplyExtrinsicResult<Balance, Event> =
+	Result<ExtrinsicReceipt<Balance, Event>, TransactionValidityError>;
+
+/// What happened when an extrinsic included in a block was executed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExtrinsicReceipt<Balance, Event> {
+	/// The result of the dispatch of the call. The fee is paid even if it failed.
+	pub result: DispatchResult,
+	/// The weight consumed by the call.
+	pub weight: Weight,
+	/// The fee paid by the caller, including the tip.
+	pub fee: Balance,
+	/// The events deposited while the call was dispatched, in order.
+	pub events: Vec<Event>,
+}
+
+/// What happened when a block was executed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlockReceipt<BlockNumber, Balance, Event> {
+	pub block_number: BlockNumber,
+	/// The events deposited when the block was initialized, before any extrinsic.
+	pub initialization_events: Vec<Event>,
+	/// The receipts of the extrinsics of the block, in order.
+	pub extrinsics: Vec<ExtrinsicReceipt<Balance, Event>>,
+}
+
+/// Why a transaction cannot be included in a block.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransactionValidityError {
+	/// The caller of the transaction cannot sign it.
+	BadSigner,
+	/// The nonce of the transaction was already used by its caller.
+	Stale,
+	/// The nonce of the transaction is ahead of the nonce of its caller.
+	Future,
+	/// The caller cannot pay the fee of the transaction.
+	Payment,
+	/// The transaction is too heavy to fit in a block.
+	ExhaustsResources,
+	/// The call of the transaction would fail.
+	Call(DispatchError),
+}
+
+/// The priority of a transaction. Transactions with a higher priority are included first.
+pub type TransactionPriority = u64;
+
+/// What is known about a valid transaction, to decide when it can be included in a block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidTransaction<Tag> {
+	pub priority: TransactionPriority,
+	/// The tags which must be provided by other transactions before this one can be included.
+	pub requires: Vec<Tag>,
+	/// The tags this transaction provides once it is included. No two transactions providing the
+	/// same tag can be included.
+	pub provides: Vec<Tag>,
+	/// The number of blocks for which the transaction stays valid.
+	pub longevity: u64,
+}
 
 /// A trait which allows us to dispatch an incoming extrinsic to the appropriate state transition
 /// function call.
@@ -37,6 +354,150 @@ pub trait Dispatch {
 	type Call;
 
 	/// This function takes a `caller` and the `call` they want to make, and returns a `Result`
-	/// based on the outcome of that function call.
-	fn dispatch(&mut self, caller: Self::Caller, call: Self::Call) -> DispatchResult;
+	/// based on the outcome of that function call, with the information the call gives back.
+	fn dispatch(&mut self, caller: Self::Caller, call: Self::Call) -> DispatchResultWithPostInfo;
+}
+
+/// Decides which calls the runtime dispatches. It is consulted before a call is routed to its
+/// pallet, so calls can be disabled without changing the pallets which expose them.
+pub trait CallFilter<Call> {
+	/// Return `true` if `call` can be dispatched.
+	fn contains(&self, call: &Call) -> bool;
+}
+
+/// The weight of a call: a measure of the resources it consumes when it is executed.
+pub type Weight = u64;
+
+/// The weight of a callable function which does not declare one with `#[weight(..)]`.
+pub const DEFAULT_WEIGHT: Weight = 1_000;
+
+/// A trait which allows us to know the weight of a call before dispatching it.
+pub trait GetDispatchInfo {
+	/// The weight of this call.
+	fn weight(&self) -> Weight;
+}
+
+/// A hook the runtime executes at the start of every block, before any of its extrinsics.
+pub trait OnInitialize<BlockNumber> {
+	/// Execute the logic which must run at the start of `block_number`, and return the weight it
+	/// consumed. It counts toward the weight of the block, leaving less to its extrinsics.
+	fn on_initialize(&mut self, block_number: BlockNumber) -> Weight;
+}
+
+/// A hook the runtime executes once, at the first block after its `SPEC_VERSION` is increased,
+/// before `OnInitialize`. It migrates the state left by the previous version of the runtime to what
+/// the new version expects.
+///
+/// The state may have been left by any older version, so the migrations are kept by the spec
+/// version which added them: upgrading from `from` to `to` only runs the migrations added after
+/// `from`.
+pub trait OnRuntimeUpgrade {
+	/// Check the state before the migrations, and return what `post_upgrade` needs to check the
+	/// state after them, e.g. the number of entries in a migrated map.
+	fn pre_upgrade(&self) -> Result<Vec<u8>, &'static str> {
+		Ok(Vec::new())
+	}
+
+	/// Migrate the state from the spec version `from` to the spec version `to`.
+	fn on_runtime_upgrade(&mut self, from: u32, to: u32);
+
+	/// Check the state after the migrations, given what `pre_upgrade` returned.
+	fn post_upgrade(&self, _state: Vec<u8>) -> Result<(), &'static str> {
+		Ok(())
+	}
+
+	/// Run the migrations, between their `pre_upgrade` and `post_upgrade` checks.
+	fn try_on_runtime_upgrade(&mut self, from: u32, to: u32) -> Result<(), &'static str> {
+		let state = self.pre_upgrade()?;
+		self.on_runtime_upgrade(from, to);
+		self.post_upgrade(state)
+	}
+}
+
+/// The version of the storage of a pallet. A pallet bumps it with
+/// `#[macros::storage(version = ..)]` whenever it changes its storage in a way which needs a
+/// migration.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct StorageVersion(pub u16);
+
+impl Encode for StorageVersion {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.0.encode_to(dest);
+	}
+}
+
+impl Decode for StorageVersion {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(StorageVersion(u16::decode(input)?))
+	}
+}
+
+/// Gives the storage version of a pallet, which is implemented by `#[macros::storage]`.
+pub trait GetStorageVersion {
+	/// The storage version the code of the pallet expects.
+	const IN_CODE_STORAGE_VERSION: StorageVersion;
+
+	/// The storage version the state of the pallet is in. It is behind the in-code storage version
+	/// until the migrations of the pallet are run.
+	fn on_chain_storage_version(&self) -> StorageVersion;
+
+	/// Record the storage version of the state, once a migration brought it to `version`.
+	fn set_on_chain_storage_version(&mut self, version: StorageVersion);
+}
+
+/// A check that the caller of a privileged call is allowed to make it.
+pub trait EnsureOrigin<AccountId> {
+	/// Return `Ok(())` if `caller` is allowed to make the call.
+	fn ensure_origin(caller: &AccountId) -> DispatchResult;
+}
+
+/// How the runtime charges the callers of the extrinsics it executes.
+pub trait TransactionPayment<AccountId, Balance, Call> {
+	/// The fee paid to execute `call`, including `tip`.
+	fn compute_fee(&self, call: &Call, tip: Balance) -> Balance;
+
+	/// Withdraw `fee` from `who`, before its extrinsic is dispatched.
+	fn withdraw_fee(&mut self, who: &AccountId, fee: Balance) -> DispatchResult;
+}
+
+/// The hash of a header, of the extrinsics of a block, or of the state of the runtime.
+pub type Hash = u64;
+
+/// Hash `value` through its `Debug` representation, with 64 bits FNV-1a. This is not a
+/// cryptographic hash: it only lets us detect when a block or a state differs from what is
+/// expected. The algorithm is fixed, so hashes stay the same across builds and toolchains.
+pub fn hash_of<T: core::fmt::Debug + ?Sized>(value: &T) -> Hash {
+	use core::fmt::Write;
+
+	struct Fnv1a(Hash);
+
+	impl Write for Fnv1a {
+		fn write_str(&mut self, s: &str) -> core::fmt::Result {
+			for byte in s.bytes() {
+				self.0 = (self.0 ^ Hash::from(byte)).wrapping_mul(0x0100_0000_01b3);
+			}
+			Ok(())
+		}
+	}
+
+	let mut hasher = Fnv1a(0xcbf2_9ce4_8422_2325);
+	// Writing to the hasher never fails.
+	let _ = write!(hasher, "{:?}", value);
+	hasher.0
+}
+
+/// A check that extrinsics of an account can be trusted to come from it. Extrinsics carry no
+/// signature here, so this can only rule out the accounts no one can sign for, like Root.
+pub trait CheckSignature<AccountId> {
+	/// Whether `who` can sign extrinsics.
+	fn check_signature(who: &AccountId) -> bool;
+}
+
+#[cfg(test)]
+mod test {
+	#[test]
+	fn hash_of_is_fnv1a() {
+		assert_eq!(super::hash_of(&""), 0x07cc_7607_b494_9e25);
+		assert_eq!(super::hash_of(&42), 0x07ee_7e07_b4b1_9223);
+	}
 }
@@ -1,37 +1,98 @@
 /* TODO: You might need to update your imports. */
 use core::ops::AddAssign;
-use num::traits::{One, Zero};
-use std::collections::BTreeMap;
+use num::traits::{CheckedAdd, One, Zero};
 
-pub trait Config {
-	type AccountId: Ord + Clone;
-	type BlockNumber: Zero + One + AddAssign + Copy;
-	type Nonce: Zero + One + Copy;
+use crate::{
+	codec::Encode,
+	storage::{Backend, StorageMap, StorageValue},
+	support::{Hash, PalletIndex, Weight},
+};
+
+/// The name the system pallet is bound to its backend under, so that the other pallets sharing the
+/// backend find it.
+pub const PALLET_NAME: &str = "system";
+
+pub trait Config: 'static {
+	type AccountId: Encode + Ord + Clone;
+	type BlockNumber: Encode + Zero + One + AddAssign + CheckedAdd + Ord + Copy;
+	type Nonce: Zero + One + Ord + Copy;
+	/// The events deposited by the runtime.
+	type RuntimeEvent: Clone + core::fmt::Debug;
+	/// The index of each pallet in the runtime, which identifies the errors of the pallets.
+	type PalletIndex: PalletIndex;
+	/// The maximum total weight of the extrinsics of a block.
+	const MAXIMUM_BLOCK_WEIGHT: Weight;
+	/// The maximum number of extrinsics in a block.
+	const MAXIMUM_BLOCK_LENGTH: u32;
+	/// The version of the runtime. Increasing it runs the migrations of the runtime at the next
+	/// block.
+	const SPEC_VERSION: u32;
 }
 
-#[derive(Debug)]
+/// This is the System Module.
+/// It keeps the state every block relies on: the current block number and the nonce of each account.
+#[macros::storage]
+#[derive(Debug, macros::PalletInfo)]
 pub struct Pallet<T: Config> {
-	block_number: T::BlockNumber,
-	nonce: BTreeMap<T::AccountId, T::Nonce>,
+	/// The number of the current block.
+	#[getter(block_number)]
+	#[default(T::BlockNumber::zero())]
+	block_number: StorageValue<T::BlockNumber>,
+	/// The number of transactions sent by each account.
+	#[getter(nonce)]
+	#[default(T::Nonce::zero())]
+	nonce: StorageMap<T::AccountId, T::Nonce>,
+	/// The hash of the header of the last block, which is the parent of the next block.
+	#[getter(parent_hash)]
+	parent_hash: StorageValue<Hash>,
+	/// The events deposited during the current block.
+	events: StorageValue<Vec<T::RuntimeEvent>>,
+	/// The spec version of the runtime the state was last upgraded to. A state which was never
+	/// upgraded is at 0.
+	#[getter(spec_version)]
+	spec_version: StorageValue<u32>,
 }
 
 impl<T: Config> Pallet<T> {
-	
-	pub fn new() -> Self {
-        Self {block_number: T::BlockNumber::zero(),nonce: BTreeMap::new()}
+	pub fn inc_block_number(&mut self){
+		self.block_number.mutate(|number| *number += T::BlockNumber::one());
+	}
+
+	/// Record the hash of the header of the block which was just executed.
+	pub fn set_parent_hash(&mut self, hash: Hash) {
+		self.parent_hash.put(hash);
 	}
-	
-	pub fn block_number(&self) -> T::BlockNumber {
-		self.block_number
+
+	/// Get the events deposited during the current block, in order.
+	pub fn events(&self) -> Vec<T::RuntimeEvent> {
+		self.events.get()
 	}
 
-	pub fn inc_block_number(&mut self){
-		self.block_number += T::BlockNumber::one();
+	/// Deposit an event, which is kept until the next block is initialized.
+	pub fn deposit_event(&mut self, event: T::RuntimeEvent) {
+		self.events.mutate(|events| events.push(event));
+	}
+
+	/// Deposit an event in the system pallet bound to `backend`. This is how the other pallets
+	/// deposit their events, since they share the backend of the system pallet.
+	pub fn deposit_event_in(backend: &Backend, event: T::RuntimeEvent) {
+		let mut events = StorageValue::new(backend, PALLET_NAME, "events", Vec::new());
+		events.mutate(|events: &mut Vec<T::RuntimeEvent>| events.push(event));
+	}
+
+	/// Clear the events of the previous block.
+	pub fn reset_events(&mut self) {
+		self.events.put(Vec::new());
+	}
+
+	/// Record that the state was migrated to the spec version `version`.
+	pub fn set_spec_version(&mut self, version: u32) {
+		self.spec_version.put(version);
 	}
 
 	pub fn inc_nonce(&mut self, who: &T::AccountId){
 		/* TODO: Get the current nonce of `who`, and increment it by one. */
-		let old_nonce: T::Nonce = *self.nonce.get(who).unwrap_or(&T::Nonce::zero());
+		let old_nonce: T::Nonce = self.nonce(who);
         let new_nonce: T::Nonce = old_nonce + T::Nonce::one();
 		self.nonce.insert(who.clone(), new_nonce);
 	}
@@ -40,11 +101,18 @@ impl<T: Config> Pallet<T> {
 
 #[cfg(test)]
 mod test {
+	use crate::support::{GetStorageVersion, StorageVersion};
+
     struct TestConfig;
 	impl super::Config for TestConfig {
 		type AccountId = String;
 		type BlockNumber = u32;
 		type Nonce = u32;
+		type RuntimeEvent = ();
+		type PalletIndex = ();
+		const MAXIMUM_BLOCK_WEIGHT: crate::support::Weight = u64::MAX;
+		const MAXIMUM_BLOCK_LENGTH: u32 = u32::MAX;
+		const SPEC_VERSION: u32 = 1;
 	}
 
 	#[test]
@@ -62,8 +130,17 @@ mod test {
         system.inc_nonce(&"alice".to_string());
 
         assert_eq!(system.block_number(), 1);
-        assert_eq!(system.nonce.get(&"alice".to_string()), Some(&1));
+        assert_eq!(system.nonce.get(&"alice".to_string()), Some(1));
         assert_eq!(system.nonce.get(&"bob".to_string()), None);
 
+		// A new pallet is at the storage version of its code, since there is nothing to migrate. The
+		// spec version is set by the runtime.
+		assert_eq!(system.spec_version(), 0);
+		system.set_spec_version(1);
+		assert_eq!(system.spec_version(), 1);
+		assert_eq!(system.on_chain_storage_version(), StorageVersion(0));
+		system.set_on_chain_storage_version(StorageVersion(1));
+		assert_eq!(system.on_chain_storage_version(), StorageVersion(1));
+
 	}
 }
@@ -0,0 +1,283 @@
+use core::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap};
+
+use crate::{
+	support::{self, GetDispatchInfo, TransactionValidityError, ValidTransaction},
+	types, Runtime,
+};
+
+/// Why the pool refused a transaction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PoolError {
+	/// The transaction is not valid on top of the current state.
+	Invalid(TransactionValidityError),
+	/// A transaction providing the same tag, with a priority at least as high, is already in the
+	/// pool.
+	TooLowPriority,
+	/// The pool has no room for more transactions.
+	Full,
+}
+
+/// A transaction waiting in the pool.
+struct PooledTransaction {
+	extrinsic: types::Extrinsic,
+	/// The outcome of the last validation of the transaction.
+	valid: ValidTransaction<types::Tag>,
+	/// The last block at which the transaction is valid.
+	valid_until: types::BlockNumber,
+}
+
+/// The pool of transactions waiting to be included in a block.
+///
+/// Transactions are validated with `Runtime::validate_transaction`. A transaction is ready once the
+/// tags it requires are provided by ready transactions, e.g. the previous nonces of its caller. The
+/// others wait in the pool until they are. Ready transactions are ordered by priority.
+pub struct TransactionPool {
+	/// The transactions in the pool, by the order in which they were submitted.
+	transactions: BTreeMap<u64, PooledTransaction>,
+	/// The number of transactions submitted so far.
+	insertions: u64,
+	/// The maximum number of transactions in the pool.
+	capacity: usize,
+}
+
+impl TransactionPool {
+	/// Create an empty pool, holding up to `capacity` transactions.
+	pub fn new(capacity: usize) -> Self {
+		Self { transactions: BTreeMap::new(), insertions: 0, capacity }
+	}
+
+	/// The number of transactions in the pool, ready or not.
+	pub fn len(&self) -> usize {
+		self.transactions.len()
+	}
+
+	/// Whether the pool has no transactions.
+	pub fn is_empty(&self) -> bool {
+		self.transactions.is_empty()
+	}
+
+	/// The transactions in the pool, ready or not, in the order they were submitted.
+	pub fn transactions(&self) -> impl Iterator<Item = &types::Extrinsic> {
+		self.transactions.values().map(|transaction| &transaction.extrinsic)
+	}
+
+	/// Validate `extrinsic` against the current state of `runtime`, and add it to the pool.
+	///
+	/// A transaction providing the same tag as one already in the pool replaces it only if it has a
+	/// higher priority.
+	pub fn submit(
+		&mut self,
+		runtime: &Runtime,
+		extrinsic: types::Extrinsic,
+	) -> Result<(), PoolError> {
+		let valid = runtime.validate_transaction(&extrinsic).map_err(PoolError::Invalid)?;
+
+		let replaced = self.transactions.iter().find(|(_, transaction)| {
+			transaction.valid.provides.iter().any(|tag| valid.provides.contains(tag))
+		});
+		match replaced {
+			Some((_, replaced)) if replaced.valid.priority >= valid.priority => {
+				return Err(PoolError::TooLowPriority)
+			},
+			Some((key, _)) => {
+				let key = *key;
+				self.transactions.remove(&key);
+			},
+			None if self.len() >= self.capacity => return Err(PoolError::Full),
+			None => {},
+		}
+
+		let longevity =
+			types::BlockNumber::try_from(valid.longevity).unwrap_or(types::BlockNumber::MAX);
+		let valid_until = runtime.system.block_number().saturating_add(longevity);
+		self.transactions
+			.insert(self.insertions, PooledTransaction { extrinsic, valid, valid_until });
+		self.insertions += 1;
+		Ok(())
+	}
+
+	/// Select the ready transactions, in the order they should be included in a block, as long as
+	/// their total weight does not exceed `max_weight`. Transactions which do not fit are skipped,
+	/// along with the transactions which require them.
+	fn select(&self, max_weight: support::Weight) -> Vec<u64> {
+		let mut provided = BTreeSet::new();
+		let mut queued = BTreeSet::new();
+		let mut ready = BinaryHeap::new();
+		let mut weight: support::Weight = 0;
+		let mut selected = Vec::new();
+
+		loop {
+			// Queue the transactions whose requirements are now all provided.
+			for (key, transaction) in &self.transactions {
+				if !queued.contains(key)
+					&& transaction.valid.requires.iter().all(|tag| provided.contains(tag))
+				{
+					queued.insert(*key);
+					ready.push((transaction.valid.priority, Reverse(*key)));
+				}
+			}
+			let Some((_, Reverse(key))) = ready.pop() else { break };
+
+			let transaction = &self.transactions[&key];
+			match weight.checked_add(transaction.extrinsic.call.weight()) {
+				Some(total) if total <= max_weight => weight = total,
+				_ => continue,
+			}
+			provided.extend(transaction.valid.provides.iter());
+			selected.push(key);
+		}
+		selected
+	}
+
+	/// Take the ready transactions which fit in a block of `max_weight`, in the order they should
+	/// be included.
+	pub fn take_ready(&mut self, max_weight: support::Weight) -> Vec<types::Extrinsic> {
+		self.select(max_weight)
+			.into_iter()
+			.filter_map(|key| self.transactions.remove(&key))
+			.map(|transaction| transaction.extrinsic)
+			.collect()
+	}
+
+	/// Validate the transactions in the pool again, against the new state of `runtime`. The
+	/// transactions which are no longer valid, or which outlived their longevity, are evicted.
+	pub fn prune(&mut self, runtime: &Runtime) {
+		let now = runtime.system.block_number();
+		self.transactions.retain(|_, transaction| {
+			if now > transaction.valid_until {
+				return false;
+			}
+			match runtime.validate_transaction(&transaction.extrinsic) {
+				Ok(valid) => {
+					transaction.valid = valid;
+					true
+				},
+				Err(_) => false,
+			}
+		});
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::{PoolError, TransactionPool};
+	use crate::{
+		balances, support, support::TransactionValidityError, types, Runtime, RuntimeCall,
+	};
+
+	fn transfer(caller: &str, nonce: types::Nonce, tip: types::Balance) -> types::Extrinsic {
+		support::Extrinsic {
+			caller: caller.to_string(),
+			nonce,
+			tip,
+			call: RuntimeCall::balances(balances::Call::transfer {
+				to: "charlie".to_string(),
+				amount: 1,
+			}),
+		}
+	}
+
+	fn new_runtime() -> Runtime {
+		let mut runtime = Runtime::new();
+		runtime.balances.set_balance(&"alice".to_string(), 100);
+		runtime.balances.set_balance(&"bob".to_string(), 100);
+		runtime
+	}
+
+	/// The `(caller, nonce)` of the ready transactions, in the order they should be included.
+	fn ready(pool: &TransactionPool) -> Vec<(types::AccountId, types::Nonce)> {
+		pool.select(support::Weight::MAX)
+			.into_iter()
+			.map(|key| {
+				let extrinsic = &pool.transactions[&key].extrinsic;
+				(extrinsic.caller.clone(), extrinsic.nonce)
+			})
+			.collect()
+	}
+
+	fn ids(ids: &[(&str, types::Nonce)]) -> Vec<(types::AccountId, types::Nonce)> {
+		ids.iter().map(|(caller, nonce)| (caller.to_string(), *nonce)).collect()
+	}
+
+	#[test]
+	fn submit_validates_transactions() {
+		let mut runtime = new_runtime();
+		let mut pool = TransactionPool::new(2);
+
+		assert_eq!(
+			pool.submit(&runtime, transfer("root", 0, 0)),
+			Err(PoolError::Invalid(TransactionValidityError::BadSigner))
+		);
+		assert_eq!(
+			pool.submit(&runtime, transfer("dave", 0, 0)),
+			Err(PoolError::Invalid(TransactionValidityError::Payment))
+		);
+		assert_eq!(pool.submit(&runtime, transfer("alice", 0, 1)), Ok(()));
+		assert_eq!(pool.submit(&runtime, transfer("alice", 0, 1)), Err(PoolError::TooLowPriority));
+		assert_eq!(pool.submit(&runtime, transfer("alice", 0, 2)), Ok(()));
+		assert_eq!(pool.submit(&runtime, transfer("alice", 1, 0)), Ok(()));
+		assert_eq!(pool.submit(&runtime, transfer("bob", 0, 0)), Err(PoolError::Full));
+		assert_eq!(pool.len(), 2);
+
+		runtime.system.inc_nonce(&"alice".to_string());
+		assert_eq!(
+			pool.submit(&runtime, transfer("alice", 0, 5)),
+			Err(PoolError::Invalid(TransactionValidityError::Stale))
+		);
+		pool.prune(&runtime);
+		assert_eq!(pool.len(), 1);
+	}
+
+	#[test]
+	fn ready_transactions_are_ordered() {
+		let mut runtime = new_runtime();
+		let mut pool = TransactionPool::new(10);
+
+		// Alice's nonce 2 waits until nonce 1 is submitted.
+		assert_eq!(pool.submit(&runtime, transfer("alice", 2, 9)), Ok(()));
+		assert_eq!(pool.submit(&runtime, transfer("alice", 0, 1)), Ok(()));
+		assert_eq!(ready(&pool), ids(&[("alice", 0)]));
+
+		assert_eq!(pool.submit(&runtime, transfer("bob", 0, 3)), Ok(()));
+		assert_eq!(pool.submit(&runtime, transfer("bob", 1, 0)), Ok(()));
+		assert_eq!(pool.submit(&runtime, transfer("alice", 1, 2)), Ok(()));
+		assert_eq!(
+			ready(&pool),
+			ids(&[("bob", 0), ("alice", 0), ("alice", 1), ("alice", 2), ("bob", 1)])
+		);
+
+		// Only three transactions fit in the block.
+		let taken = pool.take_ready(3 * support::DEFAULT_WEIGHT);
+		let taken = taken
+			.into_iter()
+			.map(|extrinsic| (extrinsic.caller, extrinsic.nonce))
+			.collect::<Vec<_>>();
+		assert_eq!(taken, ids(&[("bob", 0), ("alice", 0), ("alice", 1)]));
+		assert_eq!(pool.len(), 2);
+
+		// Once the taken transactions are executed, the others are ready on top of the new state.
+		for (caller, _) in taken {
+			runtime.system.inc_nonce(&caller);
+		}
+		pool.prune(&runtime);
+		assert_eq!(ready(&pool), ids(&[("alice", 2), ("bob", 1)]));
+	}
+
+	#[test]
+	fn old_transactions_are_evicted() {
+		let mut runtime = new_runtime();
+		let mut pool = TransactionPool::new(10);
+		assert_eq!(pool.submit(&runtime, transfer("alice", 1, 0)), Ok(()));
+
+		for _ in 0..crate::runtime_api::TRANSACTION_LONGEVITY {
+			runtime.system.inc_block_number();
+		}
+		pool.prune(&runtime);
+		assert_eq!(pool.len(), 1);
+
+		runtime.system.inc_block_number();
+		pool.prune(&runtime);
+		assert!(pool.is_empty());
+	}
+}
@@ -0,0 +1,363 @@
+use num::traits::{CheckedAdd, CheckedDiv, CheckedMul, Zero};
+
+use crate::{
+	balances,
+	codec::{Decode, Encode},
+	storage::{StorageMap, StorageValue},
+	support::{DispatchResult, EnsureOrigin},
+};
+
+/// The index of a spend proposal.
+pub type ProposalIndex = u32;
+
+pub trait Config:
+	balances::Config<
+		Balance: CheckedMul + CheckedDiv + From<u32>,
+		RuntimeEvent: From<Event<Self>>,
+	>
+	+ Sized
+	+ AsMut<balances::Pallet<Self>>
+	+ AsMut<Pallet<Self>>
+{
+	/// The origin allowed to approve spend proposals.
+	type ApproveOrigin: EnsureOrigin<Self::AccountId>;
+	/// The origin allowed to reject spend proposals.
+	type RejectOrigin: EnsureOrigin<Self::AccountId>;
+	/// The percentage of the value of a proposal reserved from the proposer as a bond.
+	const PROPOSAL_BOND: u8;
+	/// The minimum bond of a proposal.
+	const PROPOSAL_BOND_MINIMUM: Self::Balance;
+	/// The number of blocks between two payouts of the approved proposals.
+	const SPEND_PERIOD: Self::BlockNumber;
+	/// The percentage of the funds left in the pot which is burned at the end of a spend period, if
+	/// every approved proposal was paid out.
+	const BURN: u8;
+
+	/// The account holding the funds of the treasury. Anyone can fund the treasury by transferring
+	/// to it.
+	fn pot_account() -> Self::AccountId;
+}
+
+/// A proposal to spend funds from the treasury.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Proposal<AccountId, Balance> {
+	/// The account which made the proposal, and reserved the bond.
+	pub proposer: AccountId,
+	/// The amount to pay out.
+	pub value: Balance,
+	/// The account which receives the funds.
+	pub beneficiary: AccountId,
+	/// The amount reserved from the proposer. It is returned once the proposal is paid out, and
+	/// goes to the treasury if the proposal is rejected.
+	pub bond: Balance,
+}
+
+impl<AccountId: Encode, Balance: Encode> Encode for Proposal<AccountId, Balance> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.proposer.encode_to(dest);
+		self.value.encode_to(dest);
+		self.beneficiary.encode_to(dest);
+		self.bond.encode_to(dest);
+	}
+}
+
+impl<AccountId: Decode, Balance: Decode> Decode for Proposal<AccountId, Balance> {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(Proposal {
+			proposer: AccountId::decode(input)?,
+			value: Balance::decode(input)?,
+			beneficiary: AccountId::decode(input)?,
+			bond: Balance::decode(input)?,
+		})
+	}
+}
+
+/// This is the Treasury Module.
+/// It keeps a pot of funds, which pays out the spend proposals approved by `T::ApproveOrigin` at the
+/// end of each spend period.
+#[macros::storage]
+#[derive(Debug, macros::PalletInfo)]
+pub struct Pallet<T: Config> {
+	/// The spend proposals which are not paid out or rejected yet.
+	proposals: StorageMap<ProposalIndex, Proposal<T::AccountId, T::Balance>>,
+	/// The index of the next proposal.
+	proposal_count: StorageValue<ProposalIndex>,
+	/// The proposals which are approved, in the order they are paid out.
+	approvals: StorageValue<Vec<ProposalIndex>>,
+	/// The block at which the next spend period ends.
+	#[default(T::SPEND_PERIOD)]
+	next_spend: StorageValue<T::BlockNumber>,
+}
+
+/// The events of this pallet.
+#[macros::event]
+pub enum Event<T: Config> {
+	/// `proposer` proposed to pay `value` from the treasury to `beneficiary`.
+	Proposed {
+		index: ProposalIndex,
+		proposer: T::AccountId,
+		value: T::Balance,
+		beneficiary: T::AccountId,
+	},
+	/// A proposal was approved, to be paid out at the end of the spend period.
+	Approved { index: ProposalIndex },
+	/// A proposal was rejected, and `slashed` of the bond of its proposer went to the treasury.
+	Rejected { index: ProposalIndex, slashed: T::Balance },
+	/// An approved proposal paid `value` to `beneficiary`.
+	Awarded { index: ProposalIndex, value: T::Balance, beneficiary: T::AccountId },
+	/// `amount` of the funds left in the pot were burned at the end of a spend period.
+	Burnt { amount: T::Balance },
+}
+
+/// The errors of this pallet.
+#[macros::error]
+pub enum Error<T: Config> {
+	/// The proposal does not exist.
+	UnknownProposal,
+	/// The proposal is already approved.
+	AlreadyApproved,
+}
+
+impl<T: Config> Pallet<T> {
+	/// Get a spend proposal, if it is not paid out or rejected yet.
+	pub fn proposal(&self, index: ProposalIndex) -> Option<Proposal<T::AccountId, T::Balance>> {
+		self.proposals.get(&index)
+	}
+
+	/// Get the proposals which are approved, in the order they are paid out.
+	pub fn approvals(&self) -> Vec<ProposalIndex> {
+		self.approvals.get()
+	}
+
+	/// `percent` of `amount`, rounded down.
+	fn percent_of(amount: T::Balance, percent: u8) -> T::Balance {
+		amount
+			.checked_mul(&T::Balance::from(u32::from(percent.min(100))))
+			.and_then(|amount| amount.checked_div(&T::Balance::from(100)))
+			.unwrap_or(T::Balance::zero())
+	}
+
+	/// End the spend period when `now` reaches its end. The approved proposals are paid out in
+	/// order; the ones the pot cannot pay stay approved until the next period. Then, if every
+	/// proposal was paid out, `T::BURN` percent of the funds left in the pot are burned.
+	pub fn on_initialize(runtime: &mut T, now: T::BlockNumber) {
+		let treasury: &mut Pallet<T> = runtime.as_mut();
+		if now < treasury.next_spend.get() {
+			return;
+		}
+		treasury.next_spend.put(now.checked_add(&T::SPEND_PERIOD).unwrap_or(now));
+		let approvals = treasury.approvals.put(Vec::new());
+
+		let pot = T::pot_account();
+		let mut missed = Vec::new();
+		for index in approvals {
+			let treasury: &mut Pallet<T> = runtime.as_mut();
+			let Some(proposal) = treasury.proposals.get(&index) else { continue };
+
+			let balances: &mut balances::Pallet<T> = runtime.as_mut();
+			if balances.transfer(pot.clone(), proposal.beneficiary.clone(), proposal.value).is_err() {
+				missed.push(index);
+				continue;
+			}
+			balances.unreserve(&proposal.proposer, proposal.bond);
+			let treasury: &mut Pallet<T> = runtime.as_mut();
+			treasury.proposals.remove(&index);
+			let Proposal { value, beneficiary, .. } = proposal;
+			treasury.deposit_event(Event::Awarded { index, value, beneficiary });
+		}
+		// The funds left are kept for the missed proposals.
+		if !missed.is_empty() {
+			AsMut::<Pallet<T>>::as_mut(runtime).approvals.put(missed);
+			return;
+		}
+
+		let balances: &mut balances::Pallet<T> = runtime.as_mut();
+		let burn = Self::percent_of(balances.balance(&pot), T::BURN);
+		if balances.burn(&pot, burn).is_ok() {
+			AsMut::<Pallet<T>>::as_mut(runtime).deposit_event(Event::Burnt { amount: burn });
+		}
+	}
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	/// Propose to pay `value` from the treasury to `beneficiary`. A bond of `T::PROPOSAL_BOND`
+	/// percent of `value`, and at least `T::PROPOSAL_BOND_MINIMUM`, is reserved from the caller.
+	#[call_index(0)]
+	pub fn propose_spend(
+		runtime: &mut T,
+		caller: T::AccountId,
+		value: T::Balance,
+		beneficiary: T::AccountId,
+	) -> DispatchResult {
+		let bond = Self::percent_of(value, T::PROPOSAL_BOND).max(T::PROPOSAL_BOND_MINIMUM);
+		let balances: &mut balances::Pallet<T> = runtime.as_mut();
+		balances.reserve(&caller, bond)?;
+
+		let treasury: &mut Pallet<T> = runtime.as_mut();
+		let index = treasury.proposal_count.get();
+		treasury.proposal_count.mutate(|count| *count += 1);
+		let proposal = Proposal { proposer: caller.clone(), value, beneficiary: beneficiary.clone(), bond };
+		treasury.proposals.insert(index, proposal);
+		treasury.deposit_event(Event::Proposed { index, proposer: caller, value, beneficiary });
+		Ok(())
+	}
+
+	/// Approve a proposal, to be paid out at the end of the spend period.
+	#[call_index(1)]
+	pub fn approve_proposal(&mut self, caller: T::AccountId, proposal_id: ProposalIndex) -> DispatchResult {
+		T::ApproveOrigin::ensure_origin(&caller)?;
+		if !self.proposals.contains_key(&proposal_id) {
+			return Err(Error::<T>::UnknownProposal.into());
+		}
+		if self.approvals.get().contains(&proposal_id) {
+			return Err(Error::<T>::AlreadyApproved.into());
+		}
+		self.approvals.mutate(|approvals| approvals.push(proposal_id));
+		self.deposit_event(Event::Approved { index: proposal_id });
+		Ok(())
+	}
+
+	/// Reject a proposal. The bond of the proposer goes to the treasury.
+	#[call_index(2)]
+	pub fn reject_proposal(runtime: &mut T, caller: T::AccountId, proposal_id: ProposalIndex) -> DispatchResult {
+		T::RejectOrigin::ensure_origin(&caller)?;
+		let treasury: &mut Pallet<T> = runtime.as_mut();
+		let proposal = treasury.proposals.remove(&proposal_id).ok_or(Error::<T>::UnknownProposal)?;
+		treasury.approvals.mutate(|approvals| approvals.retain(|index| *index != proposal_id));
+
+		let balances: &mut balances::Pallet<T> = runtime.as_mut();
+		let slashed = balances.slash_reserved(&proposal.proposer, proposal.bond);
+		balances.mint(&T::pot_account(), slashed)?;
+		let treasury: &mut Pallet<T> = runtime.as_mut();
+		treasury.deposit_event(Event::Rejected { index: proposal_id, slashed });
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::Proposal;
+	use crate::{balances, support};
+
+	struct TestRuntime {
+		balances: balances::Pallet<Self>,
+		treasury: super::Pallet<Self>,
+	}
+
+	impl crate::system::Config for TestRuntime {
+		type AccountId = &'static str;
+		type BlockNumber = u32;
+		type Nonce = u32;
+		type RuntimeEvent = ();
+		type PalletIndex = ();
+		const MAXIMUM_BLOCK_WEIGHT: crate::support::Weight = u64::MAX;
+		const MAXIMUM_BLOCK_LENGTH: u32 = u32::MAX;
+		const SPEC_VERSION: u32 = 1;
+	}
+
+	impl balances::Config for TestRuntime {
+		type Balance = u128;
+	}
+
+	/// Only "council" can approve or reject proposals.
+	struct EnsureCouncil;
+
+	impl support::EnsureOrigin<&'static str> for EnsureCouncil {
+		fn ensure_origin(caller: &&'static str) -> support::DispatchResult {
+			if *caller == "council" { Ok(()) } else { Err(support::DispatchError::BadOrigin) }
+		}
+	}
+
+	impl super::Config for TestRuntime {
+		type ApproveOrigin = EnsureCouncil;
+		type RejectOrigin = EnsureCouncil;
+		const PROPOSAL_BOND: u8 = 5;
+		const PROPOSAL_BOND_MINIMUM: u128 = 2;
+		const SPEND_PERIOD: u32 = 4;
+		const BURN: u8 = 50;
+
+		fn pot_account() -> &'static str {
+			"treasury"
+		}
+	}
+
+	impl AsMut<balances::Pallet<Self>> for TestRuntime {
+		fn as_mut(&mut self) -> &mut balances::Pallet<Self> {
+			&mut self.balances
+		}
+	}
+
+	impl AsMut<super::Pallet<Self>> for TestRuntime {
+		fn as_mut(&mut self) -> &mut super::Pallet<Self> {
+			&mut self.treasury
+		}
+	}
+
+	fn new_runtime() -> TestRuntime {
+		let mut runtime = TestRuntime { balances: balances::Pallet::new(), treasury: super::Pallet::new() };
+		runtime.balances.set_balance(&"alice", 100);
+		runtime.balances.set_balance(&"treasury", 100);
+		runtime
+	}
+
+	#[test]
+	fn propose_and_reject() {
+		let mut runtime = new_runtime();
+		assert_eq!(super::Pallet::propose_spend(&mut runtime, "alice", 20, "bob"), Ok(()));
+		assert_eq!(super::Pallet::propose_spend(&mut runtime, "alice", 100, "bob"), Ok(()));
+		assert_eq!(
+			runtime.treasury.proposal(0),
+			Some(Proposal { proposer: "alice", value: 20, beneficiary: "bob", bond: 2 })
+		);
+		assert_eq!(runtime.balances.reserved_balance(&"alice"), 7);
+
+		assert_eq!(super::Pallet::reject_proposal(&mut runtime, "alice", 1), Err(support::DispatchError::BadOrigin));
+		assert_eq!(super::Pallet::reject_proposal(&mut runtime, "council", 1), Ok(()));
+		assert_eq!(super::Pallet::reject_proposal(&mut runtime, "council", 1), Err(super::Error::<TestRuntime>::UnknownProposal.into()));
+		assert_eq!(runtime.balances.reserved_balance(&"alice"), 2);
+		assert_eq!(runtime.balances.balance(&"alice"), 93);
+		assert_eq!(runtime.balances.balance(&"treasury"), 105);
+	}
+
+	#[test]
+	fn spend_period_pays_out_and_burns() {
+		let mut runtime = new_runtime();
+		assert_eq!(super::Pallet::propose_spend(&mut runtime, "alice", 60, "bob"), Ok(()));
+		assert_eq!(super::Pallet::propose_spend(&mut runtime, "alice", 50, "charlie"), Ok(()));
+		assert_eq!(runtime.treasury.approve_proposal("alice", 0), Err(support::DispatchError::BadOrigin));
+		assert_eq!(runtime.treasury.approve_proposal("council", 0), Ok(()));
+		assert_eq!(runtime.treasury.approve_proposal("council", 0), Err(super::Error::<TestRuntime>::AlreadyApproved.into()));
+		assert_eq!(runtime.treasury.approve_proposal("council", 1), Ok(()));
+
+		// Nothing happens before the end of the spend period.
+		super::Pallet::on_initialize(&mut runtime, 3);
+		assert_eq!(runtime.balances.balance(&"treasury"), 100);
+
+		// Proposal 0 is paid out. The pot cannot afford proposal 1, which waits for the next period,
+		// so nothing is burned.
+		super::Pallet::on_initialize(&mut runtime, 4);
+		assert_eq!(runtime.balances.balance(&"bob"), 60);
+		assert_eq!(runtime.balances.reserved_balance(&"alice"), 2);
+		assert_eq!(runtime.treasury.proposal(0), None);
+		assert_eq!(runtime.treasury.approvals(), &[1]);
+		assert_eq!(runtime.balances.balance(&"treasury"), 40);
+
+		// The pot has enough funds, but they are locked, so the proposal is still not paid out.
+		runtime.balances.set_balance(&"treasury", 70);
+		runtime.balances.set_lock(*b"lock_one", &"treasury", 30);
+		super::Pallet::on_initialize(&mut runtime, 8);
+		assert_eq!(runtime.balances.balance(&"charlie"), 0);
+		assert_eq!(runtime.balances.reserved_balance(&"alice"), 2);
+		assert_eq!(runtime.treasury.approvals(), &[1]);
+		assert_eq!(runtime.balances.balance(&"treasury"), 70);
+
+		// Once every proposal is paid out, half of what is left is burned.
+		runtime.balances.remove_lock(*b"lock_one", &"treasury");
+		super::Pallet::on_initialize(&mut runtime, 12);
+		assert_eq!(runtime.balances.balance(&"charlie"), 50);
+		assert_eq!(runtime.balances.reserved_balance(&"alice"), 0);
+		assert_eq!(runtime.treasury.approvals(), &[] as &[u32]);
+		assert_eq!(runtime.balances.balance(&"treasury"), 10);
+	}
+}
@@ -0,0 +1,203 @@
+use crate::{
+	storage::{StorageDoubleMap, StorageMap},
+	support::{DispatchResult, EnsureOrigin},
+	system,
+};
+
+pub trait Config:
+	system::Config<RuntimeEvent: From<Event<Self>>>
+	+ Sized
+	+ AsRef<system::Pallet<Self>>
+	+ AsMut<Pallet<Self>>
+{
+	/// The origin allowed to pause and unpause calls.
+	type PauseOrigin: EnsureOrigin<Self::AccountId>;
+}
+
+/// This is the Transaction Pause Module.
+/// It keeps the pallets and the calls which are paused, by name, so that the runtime can stop
+/// dispatching them until they are unpaused. Filtering the calls is left to the `CallFilter` of the
+/// runtime.
+#[macros::storage]
+#[derive(Debug, macros::PalletInfo)]
+pub struct Pallet<T: Config> {
+	/// The pallets whose calls are all paused, with the block at which they were paused.
+	paused_pallets: StorageMap<String, T::BlockNumber>,
+	/// The single calls which are paused, by pallet and call name, with the block at which they
+	/// were paused.
+	paused_calls: StorageDoubleMap<String, String, T::BlockNumber>,
+}
+
+/// The events of this pallet.
+#[macros::event]
+pub enum Event<T: Config> {
+	/// The call `call` of the pallet `pallet`, or the whole pallet if `call` is `None`, was paused.
+	CallPaused { pallet: String, call: Option<String> },
+	/// The call `call` of the pallet `pallet`, or the whole pallet if `call` is `None`, was
+	/// unpaused.
+	CallUnpaused { pallet: String, call: Option<String> },
+}
+
+/// The errors of this pallet.
+#[macros::error]
+pub enum Error<T: Config> {
+	/// The pallet or call is already paused.
+	AlreadyPaused,
+	/// The pallet or call is not paused.
+	NotPaused,
+}
+
+impl<T: Config> Pallet<T> {
+	/// Whether the call `call` of the pallet `pallet` is paused, either on its own or because its
+	/// whole pallet is.
+	pub fn is_paused(&self, pallet: &str, call: &str) -> bool {
+		self.paused_pallets.contains_key(pallet) || self.paused_calls.contains_key(pallet, call)
+	}
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	/// Pause the call `call` of the pallet `pallet`, or all the calls of the pallet if `call` is
+	/// `None`. Names are the ones of `RuntimeCall::pallet_name()` and `RuntimeCall::call_name()`.
+	#[call_index(0)]
+	pub fn pause(
+		runtime: &mut T,
+		caller: T::AccountId,
+		pallet: String,
+		call: Option<String>,
+	) -> DispatchResult {
+		T::PauseOrigin::ensure_origin(&caller)?;
+		let now = AsRef::<system::Pallet<T>>::as_ref(runtime).block_number();
+		let tx_pause: &mut Pallet<T> = runtime.as_mut();
+		let paused = match &call {
+			Some(call) => tx_pause.paused_calls.contains_key(&pallet, call),
+			None => tx_pause.paused_pallets.contains_key(&pallet),
+		};
+		if paused {
+			return Err(Error::<T>::AlreadyPaused.into());
+		}
+		match &call {
+			Some(call) => tx_pause.paused_calls.insert(pallet.clone(), call.clone(), now),
+			None => tx_pause.paused_pallets.insert(pallet.clone(), now),
+		};
+		tx_pause.deposit_event(Event::CallPaused { pallet, call });
+		Ok(())
+	}
+
+	/// Unpause the call `call` of the pallet `pallet`, or the pallet if `call` is `None`. Unpausing
+	/// a pallet does not unpause the calls of the pallet which were paused on their own.
+	#[call_index(1)]
+	pub fn unpause(
+		&mut self,
+		caller: T::AccountId,
+		pallet: String,
+		call: Option<String>,
+	) -> DispatchResult {
+		T::PauseOrigin::ensure_origin(&caller)?;
+		let previous = match &call {
+			Some(call) => self.paused_calls.remove(&pallet, call),
+			None => self.paused_pallets.remove(&pallet),
+		};
+		if previous.is_none() {
+			return Err(Error::<T>::NotPaused.into());
+		}
+		self.deposit_event(Event::CallUnpaused { pallet, call });
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use crate::{support, system};
+
+	struct TestRuntime {
+		system: system::Pallet<Self>,
+		tx_pause: super::Pallet<Self>,
+	}
+
+	impl system::Config for TestRuntime {
+		type AccountId = &'static str;
+		type BlockNumber = u32;
+		type Nonce = u32;
+		type RuntimeEvent = ();
+		type PalletIndex = ();
+		const MAXIMUM_BLOCK_WEIGHT: crate::support::Weight = u64::MAX;
+		const MAXIMUM_BLOCK_LENGTH: u32 = u32::MAX;
+		const SPEC_VERSION: u32 = 1;
+	}
+
+	struct EnsureRoot;
+
+	impl support::EnsureOrigin<&'static str> for EnsureRoot {
+		fn ensure_origin(caller: &&'static str) -> support::DispatchResult {
+			if *caller == "root" { Ok(()) } else { Err(support::DispatchError::BadOrigin) }
+		}
+	}
+
+	impl super::Config for TestRuntime {
+		type PauseOrigin = EnsureRoot;
+	}
+
+	impl AsRef<system::Pallet<Self>> for TestRuntime {
+		fn as_ref(&self) -> &system::Pallet<Self> {
+			&self.system
+		}
+	}
+
+	impl AsMut<super::Pallet<Self>> for TestRuntime {
+		fn as_mut(&mut self) -> &mut super::Pallet<Self> {
+			&mut self.tx_pause
+		}
+	}
+
+	#[test]
+	fn pause_and_unpause_calls() {
+		let mut runtime =
+			TestRuntime { system: system::Pallet::new(), tx_pause: super::Pallet::new() };
+		let pause = |runtime: &mut TestRuntime, caller, call: Option<&str>| {
+			super::Pallet::pause(runtime, caller, "balances".to_string(), call.map(str::to_string))
+		};
+
+		assert_eq!(
+			pause(&mut runtime, "alice", Some("transfer")),
+			Err(support::DispatchError::BadOrigin)
+		);
+		assert_eq!(pause(&mut runtime, "root", Some("transfer")), Ok(()));
+		assert_eq!(
+			pause(&mut runtime, "root", Some("transfer")),
+			Err(super::Error::<TestRuntime>::AlreadyPaused.into())
+		);
+		assert!(runtime.tx_pause.is_paused("balances", "transfer"));
+		assert!(!runtime.tx_pause.is_paused("balances", "approve"));
+		assert!(!runtime.tx_pause.is_paused("assets", "transfer"));
+
+		// Pausing the pallet pauses all its calls.
+		assert_eq!(pause(&mut runtime, "root", None), Ok(()));
+		assert!(runtime.tx_pause.is_paused("balances", "approve"));
+
+		// Calls paused on their own stay paused when their pallet is unpaused.
+		assert_eq!(runtime.tx_pause.unpause("root", "balances".to_string(), None), Ok(()));
+		assert!(!runtime.tx_pause.is_paused("balances", "approve"));
+		assert!(runtime.tx_pause.is_paused("balances", "transfer"));
+
+		assert_eq!(
+			runtime
+				.tx_pause
+				.unpause("alice", "balances".to_string(), Some("transfer".to_string())),
+			Err(support::DispatchError::BadOrigin)
+		);
+		assert_eq!(
+			runtime
+				.tx_pause
+				.unpause("root", "balances".to_string(), Some("transfer".to_string())),
+			Ok(())
+		);
+		assert_eq!(
+			runtime
+				.tx_pause
+				.unpause("root", "balances".to_string(), Some("transfer".to_string())),
+			Err(super::Error::<TestRuntime>::NotPaused.into())
+		);
+		assert!(!runtime.tx_pause.is_paused("balances", "transfer"));
+	}
+}
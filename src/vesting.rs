@@ -0,0 +1,319 @@
+use num::traits::{CheckedAdd, CheckedMul, CheckedSub, Zero};
+
+use crate::{
+	balances::{self, LockIdentifier},
+	codec::{Decode, Encode},
+	storage::StorageMap,
+	support::DispatchResult,
+	system,
+};
+
+/// The identifier of the lock this pallet keeps on vesting funds.
+const VESTING_ID: LockIdentifier = *b"vesting ";
+
+pub trait Config:
+	balances::Config<Balance: CheckedMul + From<Self::BlockNumber>, RuntimeEvent: From<Event<Self>>>
+	+ Sized
+	+ AsRef<system::Pallet<Self>>
+	+ AsMut<balances::Pallet<Self>>
+	+ AsMut<Pallet<Self>>
+{
+	/// The minimum amount which can be transferred with `vested_transfer`.
+	const MIN_VESTED_TRANSFER: Self::Balance;
+	/// The maximum number of vesting schedules an account can have at once.
+	const MAX_VESTING_SCHEDULES: u32;
+}
+
+/// A schedule which unlocks funds linearly, block by block.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VestingInfo<Balance, BlockNumber> {
+	/// The amount locked when the schedule was created.
+	pub locked: Balance,
+	/// The amount unlocked every block after `starting_block`.
+	pub per_block: Balance,
+	/// The block at which funds start to unlock.
+	pub starting_block: BlockNumber,
+}
+
+/// The vesting schedules of an account.
+pub type VestingSchedules<Balance, BlockNumber> = Vec<VestingInfo<Balance, BlockNumber>>;
+
+impl<Balance: Encode, BlockNumber: Encode> Encode for VestingInfo<Balance, BlockNumber> {
+	fn encode_to(&self, dest: &mut Vec<u8>) {
+		self.locked.encode_to(dest);
+		self.per_block.encode_to(dest);
+		self.starting_block.encode_to(dest);
+	}
+}
+
+impl<Balance: Decode, BlockNumber: Decode> Decode for VestingInfo<Balance, BlockNumber> {
+	fn decode(input: &mut &[u8]) -> Result<Self, &'static str> {
+		Ok(VestingInfo {
+			locked: Balance::decode(input)?,
+			per_block: Balance::decode(input)?,
+			starting_block: BlockNumber::decode(input)?,
+		})
+	}
+}
+
+impl<Balance, BlockNumber> VestingInfo<Balance, BlockNumber>
+where
+	Balance: Zero + CheckedSub + CheckedMul + From<BlockNumber> + Copy,
+	BlockNumber: Copy,
+{
+	/// The amount which is still locked at block `n`.
+	pub fn locked_at(&self, n: BlockNumber) -> Balance {
+		let vested_blocks = Balance::from(n)
+			.checked_sub(&Balance::from(self.starting_block))
+			.unwrap_or(Balance::zero());
+		let vested = self.per_block.checked_mul(&vested_blocks).unwrap_or(self.locked);
+		self.locked.checked_sub(&vested).unwrap_or(Balance::zero())
+	}
+}
+
+/// This is the Vesting Module.
+/// It locks funds in the balances pallet, and unlocks them linearly over time.
+#[macros::storage]
+#[derive(Debug, macros::PalletInfo)]
+pub struct Pallet<T: Config> {
+	/// The vesting schedules of each account, which are never empty. Each account has at most
+	/// `T::MAX_VESTING_SCHEDULES` schedules.
+	vesting: StorageMap<T::AccountId, VestingSchedules<T::Balance, T::BlockNumber>>,
+}
+
+/// The events of this pallet.
+#[macros::event]
+pub enum Event<T: Config> {
+	/// The funds of `account` which are still locked by its vesting schedules changed to
+	/// `unvested`.
+	VestingUpdated { account: T::AccountId, unvested: T::Balance },
+	/// All the funds of `account` have vested, and its vesting lock was removed.
+	VestingCompleted { account: T::AccountId },
+}
+
+/// The errors of this pallet.
+#[macros::error]
+pub enum Error<T: Config> {
+	/// The account has no vesting schedule.
+	NotVesting,
+	/// The amount is below the minimum vested transfer.
+	AmountLow,
+	/// The schedule does not unlock any funds.
+	InvalidSchedule,
+	/// The account already has the maximum number of vesting schedules.
+	AtMaxVestingSchedules,
+}
+
+impl<T: Config> Pallet<T> {
+	/// Get the vesting schedules of `who`, in the order they were added.
+	pub fn vesting(&self, who: &T::AccountId) -> Vec<VestingInfo<T::Balance, T::BlockNumber>> {
+		self.vesting.get(who).unwrap_or_default()
+	}
+
+	/// Update the lock on the funds of `who` to what is still locked by its schedules at the
+	/// current block. Schedules which unlocked everything are removed, and so is the lock once
+	/// nothing is locked anymore.
+	fn update_lock(runtime: &mut T, who: &T::AccountId) -> DispatchResult {
+		let now = AsRef::<system::Pallet<T>>::as_ref(runtime).block_number();
+		let vesting: &mut Pallet<T> = runtime.as_mut();
+		let mut schedules = vesting.vesting.get(who).ok_or(Error::<T>::NotVesting)?;
+		let locked = schedules
+			.iter()
+			.try_fold(T::Balance::zero(), |locked, info| locked.checked_add(&info.locked_at(now)))
+			.ok_or(balances::Error::<T>::Overflow)?;
+		schedules.retain(|info| !info.locked_at(now).is_zero());
+		if schedules.is_empty() {
+			vesting.vesting.remove(who);
+		} else {
+			vesting.vesting.insert(who.clone(), schedules);
+		}
+
+		let balances: &mut balances::Pallet<T> = runtime.as_mut();
+		let event = if locked.is_zero() {
+			balances.remove_lock(VESTING_ID, who);
+			Event::VestingCompleted { account: who.clone() }
+		} else {
+			balances.set_lock(VESTING_ID, who, locked);
+			Event::VestingUpdated { account: who.clone(), unvested: locked }
+		};
+		AsMut::<Pallet<T>>::as_mut(runtime).deposit_event(event);
+		Ok(())
+	}
+}
+
+#[macros::call]
+impl<T: Config> Pallet<T> {
+	/// Unlock the funds of the caller which have vested so far.
+	#[call_index(0)]
+	pub fn vest(runtime: &mut T, caller: T::AccountId) -> DispatchResult {
+		Self::update_lock(runtime, &caller)
+	}
+
+	/// Unlock the funds of `target` which have vested so far.
+	#[call_index(1)]
+	pub fn vest_other(runtime: &mut T, _caller: T::AccountId, target: T::AccountId) -> DispatchResult {
+		Self::update_lock(runtime, &target)
+	}
+
+	/// Transfer `schedule.locked` from the caller to `target`, locked under `schedule`. The schedule
+	/// is added to the ones `target` already has, up to `T::MAX_VESTING_SCHEDULES`.
+	#[call_index(2)]
+	pub fn vested_transfer(
+		runtime: &mut T,
+		caller: T::AccountId,
+		target: T::AccountId,
+		schedule: VestingInfo<T::Balance, T::BlockNumber>,
+	) -> DispatchResult {
+		if schedule.locked < T::MIN_VESTED_TRANSFER {
+			return Err(Error::<T>::AmountLow.into());
+		}
+		if schedule.per_block.is_zero() {
+			return Err(Error::<T>::InvalidSchedule.into());
+		}
+		let vesting: &mut Pallet<T> = runtime.as_mut();
+		if vesting.vesting(&target).len() as u32 >= T::MAX_VESTING_SCHEDULES {
+			return Err(Error::<T>::AtMaxVestingSchedules.into());
+		}
+
+		let balances: &mut balances::Pallet<T> = runtime.as_mut();
+		balances.transfer(caller, target.clone(), schedule.locked)?;
+
+		AsMut::<Pallet<T>>::as_mut(runtime).vesting.mutate(target.clone(), |schedules| {
+			schedules.get_or_insert_with(Vec::new).push(schedule);
+		});
+		Self::update_lock(runtime, &target)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::VestingInfo;
+	use crate::{balances, system};
+
+	struct TestRuntime {
+		system: system::Pallet<Self>,
+		balances: balances::Pallet<Self>,
+		vesting: super::Pallet<Self>,
+	}
+
+	impl system::Config for TestRuntime {
+		type AccountId = &'static str;
+		type BlockNumber = u32;
+		type Nonce = u32;
+		type RuntimeEvent = ();
+		type PalletIndex = ();
+		const MAXIMUM_BLOCK_WEIGHT: crate::support::Weight = u64::MAX;
+		const MAXIMUM_BLOCK_LENGTH: u32 = u32::MAX;
+		const SPEC_VERSION: u32 = 1;
+	}
+
+	impl balances::Config for TestRuntime {
+		type Balance = u128;
+	}
+
+	impl super::Config for TestRuntime {
+		const MIN_VESTED_TRANSFER: u128 = 10;
+		const MAX_VESTING_SCHEDULES: u32 = 2;
+	}
+
+	impl AsRef<system::Pallet<Self>> for TestRuntime {
+		fn as_ref(&self) -> &system::Pallet<Self> {
+			&self.system
+		}
+	}
+
+	impl AsMut<balances::Pallet<Self>> for TestRuntime {
+		fn as_mut(&mut self) -> &mut balances::Pallet<Self> {
+			&mut self.balances
+		}
+	}
+
+	impl AsMut<super::Pallet<Self>> for TestRuntime {
+		fn as_mut(&mut self) -> &mut super::Pallet<Self> {
+			&mut self.vesting
+		}
+	}
+
+	#[test]
+	fn locked_at() {
+		let info = VestingInfo::<u128, u32> { locked: 100, per_block: 10, starting_block: 5 };
+		assert_eq!(info.locked_at(0), 100);
+		assert_eq!(info.locked_at(5), 100);
+		assert_eq!(info.locked_at(8), 70);
+		assert_eq!(info.locked_at(15), 0);
+		assert_eq!(info.locked_at(100), 0);
+	}
+
+	#[test]
+	fn vested_transfer_and_vest() {
+		let mut runtime = TestRuntime {
+			system: system::Pallet::new(),
+			balances: balances::Pallet::new(),
+			vesting: super::Pallet::new(),
+		};
+		runtime.balances.set_balance(&"alice", 200);
+
+		let schedule = VestingInfo { locked: 100, per_block: 25, starting_block: 2 };
+		assert_eq!(
+			super::Pallet::vested_transfer(&mut runtime, "alice", "bob", VestingInfo { locked: 5, ..schedule }),
+			Err(super::Error::<TestRuntime>::AmountLow.into())
+		);
+		assert_eq!(super::Pallet::vested_transfer(&mut runtime, "alice", "bob", schedule), Ok(()));
+		assert_eq!(runtime.vesting.vesting(&"bob"), &[schedule]);
+		assert_eq!(runtime.balances.balance(&"bob"), 100);
+		assert_eq!(runtime.balances.locked(&"bob"), 100);
+		assert_eq!(runtime.balances.transfer("bob", "charlie", 1), Err(crate::balances::Error::<TestRuntime>::FundsLocked.into()));
+
+		// Two blocks after the start of the schedule, half of the funds are unlocked.
+		for _ in 0..4 {
+			runtime.system.inc_block_number();
+		}
+		assert_eq!(runtime.balances.locked(&"bob"), 100);
+		assert_eq!(super::Pallet::vest(&mut runtime, "bob"), Ok(()));
+		assert_eq!(runtime.balances.locked(&"bob"), 50);
+		assert_eq!(runtime.balances.transfer("bob", "charlie", 60), Err(crate::balances::Error::<TestRuntime>::FundsLocked.into()));
+		assert_eq!(runtime.balances.transfer("bob", "charlie", 50), Ok(()));
+
+		// Anyone can unlock the vested funds of someone else.
+		runtime.system.inc_block_number();
+		assert_eq!(super::Pallet::vest_other(&mut runtime, "charlie", "bob"), Ok(()));
+		assert_eq!(runtime.balances.locked(&"bob"), 25);
+
+		// Once everything is unlocked, the schedule is removed.
+		runtime.system.inc_block_number();
+		assert_eq!(super::Pallet::vest(&mut runtime, "bob"), Ok(()));
+		assert_eq!(runtime.balances.locked(&"bob"), 0);
+		assert_eq!(runtime.vesting.vesting(&"bob"), &[]);
+		assert_eq!(super::Pallet::vest(&mut runtime, "bob"), Err(super::Error::<TestRuntime>::NotVesting.into()));
+	}
+
+	#[test]
+	fn multiple_schedules() {
+		let mut runtime = TestRuntime {
+			system: system::Pallet::new(),
+			balances: balances::Pallet::new(),
+			vesting: super::Pallet::new(),
+		};
+		runtime.balances.set_balance(&"alice", 200);
+		runtime.balances.set_balance(&"charlie", 200);
+
+		// A transfer from someone else does not prevent the next ones, up to the maximum.
+		let first = VestingInfo { locked: 10, per_block: 10, starting_block: 0 };
+		let second = VestingInfo { locked: 100, per_block: 10, starting_block: 0 };
+		assert_eq!(super::Pallet::vested_transfer(&mut runtime, "charlie", "bob", first), Ok(()));
+		assert_eq!(super::Pallet::vested_transfer(&mut runtime, "alice", "bob", second), Ok(()));
+		assert_eq!(
+			super::Pallet::vested_transfer(&mut runtime, "alice", "bob", second),
+			Err(super::Error::<TestRuntime>::AtMaxVestingSchedules.into())
+		);
+		assert_eq!(runtime.vesting.vesting(&"bob"), &[first, second]);
+		assert_eq!(runtime.balances.locked(&"bob"), 110);
+
+		// The lock covers what all the schedules still lock, and finished schedules are removed.
+		runtime.system.inc_block_number();
+		assert_eq!(super::Pallet::vest(&mut runtime, "bob"), Ok(()));
+		assert_eq!(runtime.balances.locked(&"bob"), 90);
+		assert_eq!(runtime.vesting.vesting(&"bob"), &[second]);
+		assert_eq!(super::Pallet::vested_transfer(&mut runtime, "charlie", "bob", first), Ok(()));
+	}
+}